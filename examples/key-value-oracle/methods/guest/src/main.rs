@@ -0,0 +1,28 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use key_value_oracle_core::SYS_KV_LOOKUP;
+use risc0_zkvm::guest::env;
+
+fn main() {
+    // Read the key to look up from the host.
+    let key: String = env::read();
+
+    // Ask the host for the value stored under the key.
+    let value: &[u8] = env::send_recv_slice(SYS_KV_LOOKUP, key.as_bytes());
+    let value = core::str::from_utf8(value).expect("Value should be valid UTF-8");
+
+    // Commit the key and its value to the journal.
+    env::commit(&(key, value));
+}
@@ -0,0 +1,80 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![doc = include_str!("../README.md")]
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use key_value_oracle_core::SYS_KV_LOOKUP;
+use key_value_oracle_methods::LOOKUP_ELF;
+use risc0_zkvm::{default_prover, Bytes, ExecutorEnv, Receipt};
+
+// This example shows how a guest can call back into the host.
+// The guest sends a key to the host over the SYS_KV_LOOKUP syscall, the host
+// replies with the value it has stored under that key, and the guest commits
+// both to the journal.
+
+/// Prove a lookup of `key` in `store`, returning the receipt and the value found.
+pub fn lookup(store: &HashMap<String, String>, key: &str) -> Result<(Receipt, String)> {
+    let env = ExecutorEnv::builder()
+        // Send the key to the guest
+        .write(&key)?
+        // Answer the guest's lookup requests from the store
+        .io_callback(SYS_KV_LOOKUP, |from_guest: Bytes| {
+            let key = std::str::from_utf8(&from_guest)?;
+            let value = store
+                .get(key)
+                .ok_or_else(|| anyhow!("no value stored for key {key:?}"))?;
+            Ok(Bytes::copy_from_slice(value.as_bytes()))
+        })
+        .build()?;
+
+    // Obtain the default prover.
+    let prover = default_prover();
+
+    // Produce a receipt by proving the specified ELF binary.
+    let receipt = prover.prove(env, LOOKUP_ELF)?.receipt;
+
+    // Extract the key and value committed by the guest.
+    let (committed_key, value): (String, String) = receipt.journal.decode()?;
+    assert_eq!(committed_key, key);
+
+    Ok((receipt, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::lookup;
+
+    fn store() -> HashMap<String, String> {
+        HashMap::from([
+            ("alice".to_string(), "42".to_string()),
+            ("bob".to_string(), "7".to_string()),
+        ])
+    }
+
+    #[test]
+    fn lookup_existing_key() {
+        let (_, value) = lookup(&store(), "alice").unwrap();
+        assert_eq!(value, "42");
+    }
+
+    #[test]
+    fn lookup_missing_key() {
+        assert!(lookup(&store(), "carol").is_err());
+    }
+}
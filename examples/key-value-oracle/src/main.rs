@@ -0,0 +1,38 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use key_value_oracle::lookup;
+use key_value_oracle_methods::LOOKUP_ID;
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    // The host-side store the guest can query.
+    let store = HashMap::from([
+        ("alice".to_string(), "42".to_string()),
+        ("bob".to_string(), "7".to_string()),
+    ]);
+
+    let (receipt, value) = lookup(&store, "alice").unwrap();
+    println!("The value stored under \"alice\" is {value}");
+
+    // Verify receipt, panic if it's wrong
+    receipt.verify(LOOKUP_ID).expect(
+        "Code you have proven should successfully verify; did you specify the correct image ID?",
+    );
+}
@@ -0,0 +1,190 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::Hash,
+};
+
+/// A least-recently-used cache whose entries can be pinned to protect them from eviction.
+///
+/// The cache holds at most `capacity` entries. Once it is full, inserting an entry evicts the least
+/// recently used unpinned entry, or the least recently used pinned entry if every entry is pinned.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    // Keys by the tick at which they were last used, least recently used first.
+    unpinned: BTreeMap<u64, K>,
+    pinned: BTreeMap<u64, K>,
+    pins: HashSet<K>,
+    tick: u64,
+}
+
+impl<K: Clone + Eq + Hash, V> LruCache<K, V> {
+    /// Create an empty cache that holds up to `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            unpinned: BTreeMap::new(),
+            pinned: BTreeMap::new(),
+            pins: HashSet::new(),
+            tick: 0,
+        }
+    }
+
+    /// Returns true if `key` is in the cache, without marking it as used.
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Look up `key`, marking it as the most recently used entry.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let (_, tick) = self.entries.get(key)?;
+        let tick = *tick;
+        self.order(key).remove(&tick);
+        let tick = self.next_tick(key.clone());
+        let (value, last_used) = self.entries.get_mut(key).unwrap();
+        *last_used = tick;
+        Some(value)
+    }
+
+    /// Insert `value` as the most recently used entry, evicting entries as needed.
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some((_, tick)) = self.entries.remove(&key) {
+            self.order(&key).remove(&tick);
+        }
+        let tick = self.next_tick(key.clone());
+        self.entries.insert(key, (value, tick));
+        while self.entries.len() > self.capacity {
+            let Some((_, key)) = self
+                .unpinned
+                .pop_first()
+                .or_else(|| self.pinned.pop_first())
+            else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+    }
+
+    /// Prefer to keep `key` until it is [unpinned](Self::unpin), evicting it only once every
+    /// unpinned entry has been.
+    ///
+    /// A key may be pinned before it is inserted.
+    pub fn pin(&mut self, key: K) {
+        if self.pins.insert(key.clone()) {
+            if let Some((_, tick)) = self.entries.get(&key) {
+                let tick = *tick;
+                self.unpinned.remove(&tick);
+                self.pinned.insert(tick, key);
+            }
+        }
+    }
+
+    /// Allow `key` to be evicted like any other entry again.
+    pub fn unpin(&mut self, key: &K) {
+        if self.pins.remove(key) {
+            if let Some((_, tick)) = self.entries.get(key) {
+                let tick = *tick;
+                self.pinned.remove(&tick);
+                self.unpinned.insert(tick, key.clone());
+            }
+        }
+    }
+
+    fn order(&mut self, key: &K) -> &mut BTreeMap<u64, K> {
+        if self.pins.contains(key) {
+            &mut self.pinned
+        } else {
+            &mut self.unpinned
+        }
+    }
+
+    fn next_tick(&mut self, key: K) -> u64 {
+        self.tick += 1;
+        let tick = self.tick;
+        self.order(&key).insert(tick, key);
+        tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LruCache;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        assert_eq!(cache.get(&1), Some(&"one"));
+
+        // 2 is now the least recently used entry.
+        cache.insert(3, "three");
+        assert!(cache.contains(&1));
+        assert!(!cache.contains(&2));
+        assert!(cache.contains(&3));
+    }
+
+    #[test]
+    fn overfill() {
+        let mut cache = LruCache::new(3);
+        for key in 0..10 {
+            cache.insert(key, key);
+            assert!(cache.entries.len() <= 3);
+        }
+        for key in 0..7 {
+            assert!(!cache.contains(&key));
+        }
+        for key in 7..10 {
+            assert!(cache.contains(&key));
+        }
+
+        // Replacing an entry does not grow the cache, and marks it as used.
+        cache.insert(7, 70);
+        cache.insert(10, 10);
+        assert_eq!(cache.entries.len(), 3);
+        assert!(!cache.contains(&8));
+        assert_eq!(cache.get(&7), Some(&70));
+    }
+
+    #[test]
+    fn pinned_entries_are_evicted_last() {
+        let mut cache = LruCache::new(2);
+        cache.pin(1);
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        cache.insert(3, "three");
+        assert!(cache.contains(&1));
+        assert!(!cache.contains(&2));
+        assert!(cache.contains(&3));
+
+        // Once every entry is pinned, the least recently used pinned entry is evicted.
+        cache.pin(3);
+        cache.pin(4);
+        cache.insert(4, "four");
+        assert_eq!(cache.entries.len(), 2);
+        assert!(!cache.contains(&1));
+        assert!(cache.contains(&3));
+        assert!(cache.contains(&4));
+
+        // Once unpinned, the entry is evicted before pinned ones.
+        cache.unpin(&4);
+        cache.insert(5, "five");
+        assert!(cache.contains(&3));
+        assert!(!cache.contains(&4));
+        assert!(cache.contains(&5));
+    }
+}
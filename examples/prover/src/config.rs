@@ -0,0 +1,126 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use anyhow::{Context, Result};
+
+/// Configuration of the example, read from `PROVER_*` environment variables.
+///
+/// Every variable is optional. Flags are enabled by setting the variable to any value.
+#[derive(Debug)]
+pub struct Config {
+    /// `PROVER_MAX_CONCURRENCY`: how many proving jobs are sent to the backend at once.
+    /// Defaults to 1.
+    pub max_concurrency: usize,
+
+    /// `PROVER_EXPECTED_CYCLES`: the number of user cycles the guest is expected to run, e.g.
+    /// from an earlier run, so that progress is also reported while it is executing.
+    pub expected_cycles: Option<u64>,
+
+    /// `PROVER_VERIFY_SEGMENTS`: verify each segment receipt as soon as it is proven, stopping at
+    /// the first one that is invalid.
+    pub verify_segments: bool,
+
+    /// `PROVER_CRITICAL_PATH_FIRST`: when there are more segments ready than workers, prove those
+    /// feeding the deepest part of the join tree first rather than in the order they were
+    /// executed.
+    pub critical_path_first: bool,
+
+    /// `PROVER_MAX_UNPROVEN_SEGMENTS`: pause execution while this many segments are waiting to be
+    /// proven, bounding the memory they use.
+    pub max_unproven_segments: Option<usize>,
+
+    /// `PROVER_JOIN_ARITY`: how many receipts are joined at once. Defaults to 2.
+    pub join_arity: usize,
+
+    /// `PROVER_PLAN_STATE`: save the state of the planner to this path as it changes, so that a
+    /// restarted coordinator can continue from where it left off. If the file exists at startup,
+    /// the plan is loaded from it.
+    pub plan_state: Option<PathBuf>,
+
+    /// `PROVER_PLAN_DOT`: write a Graphviz DOT rendering of the proving plan to this path.
+    pub plan_dot: Option<PathBuf>,
+
+    /// `PROVER_DEADLINE_SECS`: cancel execution and proving once this much time has passed.
+    pub deadline: Option<Duration>,
+
+    /// `PROVER_KECCAK_SERVER_PATH`: the `r0vm` server that keccak coprocessor proofs are sent to.
+    /// By default they are proven by the same backend as everything else.
+    pub keccak_server_path: Option<PathBuf>,
+
+    /// `PROVER_RECEIPT_STORE_DIR`: persist coprocessor receipts to this directory, where they are
+    /// kept across runs and verified as they are read back.
+    pub receipt_store_dir: Option<PathBuf>,
+
+    /// `PROVER_RECEIPT_CACHE_CAPACITY`: how many coprocessor receipts are kept in memory when no
+    /// store directory is given; evicted receipts are proven again when needed. Unbounded by
+    /// default.
+    pub receipt_cache_capacity: usize,
+
+    /// `PROVER_ASSUMPTIONS_DIR`: a directory of serialized `SuccinctReceipt`s proven ahead of
+    /// time, used instead of asking the coprocessor to prove the same claims.
+    pub assumptions_dir: Option<PathBuf>,
+
+    /// `PROVER_GROTH16`: compress the final receipt into a Groth16 receipt. This requires Docker
+    /// on an x86 host.
+    pub groth16: bool,
+}
+
+impl Config {
+    /// Read the configuration from the environment.
+    ///
+    /// Returns an error if a variable is set to a value that cannot be parsed.
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            max_concurrency: parse("PROVER_MAX_CONCURRENCY")?.unwrap_or(1),
+            expected_cycles: parse("PROVER_EXPECTED_CYCLES")?,
+            verify_segments: flag("PROVER_VERIFY_SEGMENTS"),
+            critical_path_first: flag("PROVER_CRITICAL_PATH_FIRST"),
+            max_unproven_segments: parse("PROVER_MAX_UNPROVEN_SEGMENTS")?,
+            join_arity: parse("PROVER_JOIN_ARITY")?.unwrap_or(2),
+            plan_state: path("PROVER_PLAN_STATE"),
+            plan_dot: path("PROVER_PLAN_DOT"),
+            deadline: parse("PROVER_DEADLINE_SECS")?.map(Duration::from_secs),
+            keccak_server_path: path("PROVER_KECCAK_SERVER_PATH"),
+            receipt_store_dir: path("PROVER_RECEIPT_STORE_DIR"),
+            receipt_cache_capacity: parse("PROVER_RECEIPT_CACHE_CAPACITY")?.unwrap_or(usize::MAX),
+            assumptions_dir: path("PROVER_ASSUMPTIONS_DIR"),
+            groth16: flag("PROVER_GROTH16"),
+        })
+    }
+}
+
+fn flag(name: &str) -> bool {
+    std::env::var_os(name).is_some()
+}
+
+fn path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).map(PathBuf::from)
+}
+
+fn parse<T>(name: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    std::env::var(name)
+        .ok()
+        .map(|value| {
+            value
+                .parse()
+                .with_context(|| format!("invalid {name}: {value}"))
+        })
+        .transpose()
+}
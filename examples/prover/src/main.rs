@@ -15,149 +15,671 @@
 //! This is an example of how the public 1.0 API can be used to build a proving service.
 //! It's not meant to be used in production since it doesn't handle failures.
 //! This is also not an optimal implementation; many performance improvements could be made.
+//!
+//! For the common case of proving a single guest on one machine, `ApiClient::prove` executes,
+//! proves, joins and resolves assumptions in a single call. This example instead shows how those
+//! steps can be split up and distributed across workers.
+//!
+//! The guest is executed, each segment is proven and lifted, the lifted receipts are joined, and
+//! any assumptions are resolved. Proving is done by an implementation of the `Worker` trait, which
+//! the `TaskManager` calls as the plan allows; another implementation can prove on other hardware
+//! or with a remote service while reusing the same scheduling.
+//!
+//! Requests are sent to the `r0vm` server given by `RISC0_SERVER_PATH`, or found on the `PATH`.
+//! Building with the `prove` feature executes and proves in-process when neither is available.
+//! The example is otherwise configured through the `PROVER_*` environment variables documented on
+//! `Config`. Building with the `tracing` feature logs a span for each task and backend request,
+//! filtered by `RUST_LOG`.
 
+mod cache;
+mod config;
+#[cfg(test)]
+mod mock;
 mod plan;
+mod progress;
+mod retry;
+mod store;
 mod task_mgr;
 mod worker;
 
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use anyhow::Result;
-use risc0_circuit_keccak_methods::{KECCAK_ELF, KECCAK_ID};
+use anyhow::{ensure, Result};
+use risc0_circuit_keccak_methods::KECCAK_PATH;
 use risc0_zkp::digest;
 use risc0_zkvm::{
-    sha::Digest, ApiClient, Asset, AssetRequest, CoprocessorCallback, ExecutorEnv, InnerReceipt,
-    MaybePruned, ProveKeccakRequest, ProveZkrRequest, ProverOpts, Receipt, SuccinctReceipt,
+    sha::{Digest, Digestible},
+    ApiClient, Asset, AssetRequest, CoprocessorCallback, ExecutorEnv, GuestArtifact, InnerReceipt,
+    ProveKeccakRequest, ProveZkrRequest, ProverOpts, Receipt, ReceiptClaim, SuccinctReceipt,
     Unknown,
 };
 
-use self::{plan::Planner, task_mgr::TaskManager};
+use self::{
+    cache::LruCache,
+    config::Config,
+    plan::{Planner, Task},
+    progress::ProgressTracker,
+    retry::RetryingCoprocessor,
+    store::{FileReceiptStore, ReceiptStore},
+    task_mgr::{RunResult, Schedule, TaskManager},
+};
+
+/// How many times the tasks that failed are run again before giving up.
+const MAX_RETRIES: usize = 3;
 
 fn main() {
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .init();
+
     prover_example();
 }
 
-struct Coprocessor {
-    pub(crate) receipts: HashMap<Digest, SuccinctReceipt<Unknown>>,
+/// A proof request made by the guest, kept so that an evicted receipt can be proven again.
+#[derive(Clone)]
+enum ProofRequest {
+    Zkr(ProveZkrRequest),
+    Keccak(ProveKeccakRequest),
 }
 
-impl Coprocessor {
-    fn new() -> Self {
+struct Coprocessor<S> {
+    zkr_client: ApiClient,
+    keccak_client: ApiClient,
+    receipts: S,
+    supplied: HashMap<Digest, SuccinctReceipt<Unknown>>,
+    requests: HashMap<Digest, ProofRequest>,
+    pending_zkr: Vec<ProveZkrRequest>,
+}
+
+impl<S: ReceiptStore> Coprocessor<S> {
+    /// Create a coprocessor that proves ZKR requests with `zkr_client` and keccak requests with
+    /// `keccak_client`, keeping the receipts in `receipts`.
+    ///
+    /// Receipts are pinned in the store until their assumptions are [released](Self::release), so
+    /// that the store drops them last. A receipt which the store no longer has is proven again if
+    /// it is needed later.
+    fn new(zkr_client: ApiClient, keccak_client: ApiClient, receipts: S) -> Self {
         Self {
-            receipts: HashMap::new(),
+            zkr_client,
+            keccak_client,
+            receipts,
+            supplied: HashMap::new(),
+            requests: HashMap::new(),
+            pending_zkr: Vec::new(),
+        }
+    }
+
+    /// Add a receipt that was proven ahead of time, e.g. by another party.
+    ///
+    /// Supplied receipts are used in preference to proving, and are never evicted. They should also
+    /// be added to the [ExecutorEnv] with `add_assumption` so the guest does not request them.
+    fn supply(&mut self, receipt: SuccinctReceipt<Unknown>) {
+        self.supplied.insert(receipt.claim_digest(), receipt);
+    }
+
+    /// Record a request made by the guest. The guest will hold an assumption on the claim until
+    /// it is resolved, so its receipt is pinned until then.
+    fn track(&mut self, claim_digest: Digest, proof_request: ProofRequest) {
+        self.receipts.pin(claim_digest);
+        self.requests.insert(claim_digest, proof_request);
+    }
+
+    /// Forget `claim_digest` once the assumption on it has been resolved, allowing its receipt to
+    /// be evicted.
+    fn release(&mut self, claim_digest: &Digest) {
+        self.receipts.unpin(claim_digest);
+        self.requests.remove(claim_digest);
+    }
+
+    /// Returns the receipt for `claim_digest`, proving it again if it has been evicted.
+    fn receipt(&mut self, claim_digest: &Digest) -> Result<SuccinctReceipt<Unknown>> {
+        if let Some(receipt) = self.supplied.get(claim_digest) {
+            return Ok(receipt.clone());
+        }
+        if let Some(receipt) = self.receipts.get(claim_digest)? {
+            return Ok(receipt);
         }
+        let receipt: SuccinctReceipt<Unknown> = match self.requests.get(claim_digest) {
+            Some(ProofRequest::Zkr(proof_request)) => self
+                .zkr_client
+                .prove_zkr(proof_request.clone(), AssetRequest::Inline)?,
+            Some(ProofRequest::Keccak(proof_request)) => self
+                .keccak_client
+                .prove_keccak(proof_request.clone(), AssetRequest::Inline)?,
+            None => anyhow::bail!("no proof request for claim {claim_digest}"),
+        };
+        self.receipts.put(receipt.clone())?;
+        Ok(receipt)
+    }
+
+    /// Prove all pending ZKR requests in a single batch.
+    ///
+    /// Requests are only removed once the batch succeeds, so a failed flush can be retried.
+    fn flush(&mut self) -> Result<()> {
+        if self.pending_zkr.is_empty() {
+            return Ok(());
+        }
+        let receipts = self
+            .zkr_client
+            .prove_zkr_batch(self.pending_zkr.clone(), AssetRequest::Inline)?;
+        self.pending_zkr.clear();
+        for receipt in receipts {
+            self.receipts.put(receipt)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if a receipt for `claim_digest` has been supplied, proven or is pending, or can
+    /// be proven again from the request the guest made for it.
+    fn can_provide(&self, claim_digest: &Digest) -> bool {
+        self.contains(claim_digest) || self.requests.contains_key(claim_digest)
+    }
+
+    /// Returns true if a receipt for `claim_digest` has been supplied, proven or is pending.
+    fn contains(&self, claim_digest: &Digest) -> bool {
+        self.supplied.contains_key(claim_digest)
+            || self.receipts.contains(claim_digest)
+            || self
+                .pending_zkr
+                .iter()
+                .any(|proof_request| &proof_request.claim_digest == claim_digest)
     }
 }
 
-impl CoprocessorCallback for Coprocessor {
+impl<S: ReceiptStore> CoprocessorCallback for Coprocessor<S> {
     fn prove_zkr(&mut self, proof_request: ProveZkrRequest) -> Result<()> {
-        let client = ApiClient::from_env().unwrap();
-        let claim_digest = proof_request.claim_digest;
-        let receipt = client.prove_zkr(proof_request, AssetRequest::Inline)?;
-        self.receipts.insert(claim_digest, receipt);
+        self.track(
+            proof_request.claim_digest,
+            ProofRequest::Zkr(proof_request.clone()),
+        );
+        // A guest may request the same proof more than once; only prove it the first time.
+        if self.contains(&proof_request.claim_digest) {
+            return Ok(());
+        }
+        // The receipt is not needed until assumptions are resolved, so proving is deferred until
+        // the next segment boundary to avoid a server round-trip per request.
+        self.pending_zkr.push(proof_request);
         Ok(())
     }
 
     fn prove_keccak(&mut self, proof_request: ProveKeccakRequest) -> Result<()> {
-        let client = ApiClient::from_env().unwrap();
-        let receipt = client.prove_keccak(proof_request, AssetRequest::Inline)?;
-        let claim_digest = match receipt.claim {
-            // unknown is always pruned so if we get to this branch, something went wrong...
-            MaybePruned::Value(_) => unimplemented!(),
-            MaybePruned::Pruned(claim_digest) => claim_digest,
-        };
-        self.receipts.insert(claim_digest, receipt);
-        Ok(())
+        let claim_digest = proof_request.claim_digest;
+        self.track(claim_digest, ProofRequest::Keccak(proof_request.clone()));
+        if self.contains(&claim_digest) {
+            return Ok(());
+        }
+        let receipt: SuccinctReceipt<Unknown> = self
+            .keccak_client
+            .prove_keccak(proof_request, AssetRequest::Inline)?;
+        ensure!(
+            receipt.claim_digest() == claim_digest,
+            "keccak receipt claims {}, expected {claim_digest}",
+            receipt.claim_digest()
+        );
+        self.receipts.put(receipt)
     }
 }
 
+/// An assumption of the session which cannot be resolved, as found by [validate_assumptions].
+#[derive(Debug, PartialEq, Eq)]
+enum AssumptionError {
+    /// The output of the session, or the assumption with this digest, was pruned from its claim.
+    Pruned { digest: Digest },
+    /// No receipt has been supplied, proven or requested for the assumption on `claim_digest`.
+    MissingAssumption { claim_digest: Digest },
+}
+
+impl std::fmt::Display for AssumptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pruned { digest } => write!(f, "{digest} is pruned and cannot be resolved"),
+            Self::MissingAssumption { claim_digest } => {
+                write!(f, "no receipt for the assumption on claim {claim_digest}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssumptionError {}
+
+/// Check that the coprocessor can provide a receipt for every assumption in `receipt_claim`,
+/// before any of them are resolved.
+///
+/// Coprocessor receipts are unconditional, so resolving them cannot introduce further assumptions
+/// and the assumptions cannot depend on each other; only the receipts themselves need checking.
+fn validate_assumptions<S: ReceiptStore>(
+    receipt_claim: &ReceiptClaim,
+    coprocessor: &Coprocessor<S>,
+) -> Result<(), AssumptionError> {
+    let pruned = |digest| AssumptionError::Pruned { digest };
+    let output = receipt_claim
+        .output
+        .as_value()
+        .map_err(|_| pruned(receipt_claim.output.digest()))?;
+    let Some(output) = output else {
+        return Ok(());
+    };
+    let assumptions = output
+        .assumptions
+        .as_value()
+        .map_err(|_| pruned(output.assumptions.digest()))?;
+    for assumption in assumptions.iter() {
+        let assumption = assumption
+            .as_value()
+            .map_err(|_| pruned(assumption.digest()))?;
+        if !coprocessor.can_provide(&assumption.claim) {
+            return Err(AssumptionError::MissingAssumption {
+                claim_digest: assumption.claim,
+            });
+        }
+    }
+    Ok(())
+}
+
 fn prover_example() {
+    let config = Config::from_env().unwrap();
     println!("Submitting proof request...");
 
-    let mut task_manager = TaskManager::new();
-    let mut planner = Planner::default();
+    let mut progress = ProgressTracker::default();
+    if let Some(cycles) = config.expected_cycles {
+        progress = progress.with_expected_cycles(cycles);
+    }
+    let progress = Arc::new(Mutex::new(progress));
+    let task_progress = progress.clone();
+    let mut task_manager = TaskManager::with_max_concurrency(config.max_concurrency)
+        .verify_segments(config.verify_segments)
+        .on_join(|event| {
+            println!(
+                "Joined tasks {} and {} into {}, {} join(s) remaining",
+                event.left_task, event.right_task, event.result_task, event.remaining_joins
+            )
+        })
+        .on_task_complete(move |_| {
+            let mut progress = task_progress.lock().unwrap();
+            progress.on_task_complete();
+            println!("Progress: {:.1}%", 100.0 * progress.fraction_complete());
+        });
+    if config.critical_path_first {
+        task_manager = task_manager.with_schedule(Schedule::CriticalPathFirst);
+    }
+    if let Some(high_water_mark) = config.max_unproven_segments {
+        task_manager = task_manager.with_high_water_mark(high_water_mark);
+    }
+    let mut planner = match config.plan_state.as_deref().filter(|path| path.exists()) {
+        Some(path) => {
+            println!("Resuming plan from {}", path.display());
+            Planner::load(path).unwrap()
+        }
+        None => Planner::default().with_join_arity(config.join_arity),
+    };
+
+    // A resumed plan already covers the segments planned before the restart, but the receipts
+    // proven since were lost with the old task manager. The guest is executed again, and the tasks
+    // handed out before the restart are given to the new task manager once their segments are
+    // available.
+    let planned_segments: usize = planner.po2_histogram().values().sum();
+    let mut replay: VecDeque<Task> = planner.dispatched_tasks().iter().cloned().collect();
+
+    // Execution and proving share one token, so the deadline stops whichever is in progress.
+    let cancel = task_manager.cancellation_token();
+    if let Some(deadline) = config.deadline {
+        let cancel = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(deadline);
+            println!("Deadline of {deadline:?} reached, cancelling...");
+            cancel.cancel();
+        });
+    }
 
     let po2 = 16;
     let claim_digest = digest!("b83c10da0c23587bf318cbcec2c2ac0260dbd6c0fa6905df639f8f6056f0d56c");
     let to_guest: (Digest, u32) = (claim_digest, po2);
 
-    let coprocessor = Rc::new(RefCell::new(Coprocessor::new()));
-    let env = ExecutorEnv::builder()
+    let keccak_client = match &config.keccak_server_path {
+        Some(path) => ApiClient::new_sub_process(path).unwrap(),
+        None => ApiClient::from_env_or_local().unwrap(),
+    };
+    let receipts: Box<dyn ReceiptStore> = match &config.receipt_store_dir {
+        Some(dir) => Box::new(FileReceiptStore::open(dir).unwrap().verify_on_read(true)),
+        None => Box::new(LruCache::new(config.receipt_cache_capacity)),
+    };
+    let mut coprocessor = Coprocessor::new(
+        ApiClient::from_env_or_local().unwrap(),
+        keccak_client,
+        receipts,
+    );
+
+    // Receipts proven ahead of time are given to the executor so that the guest does not request
+    // them, and to the coprocessor so that they are used when resolving assumptions.
+    let mut env = ExecutorEnv::builder();
+    if let Some(dir) = &config.assumptions_dir {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let receipt: SuccinctReceipt<Unknown> =
+                Asset::Path(entry.unwrap().path()).try_into().unwrap();
+            env.add_assumption(receipt.clone());
+            coprocessor.supply(receipt);
+        }
+    }
+
+    let coprocessor = RetryingCoprocessor::new(coprocessor)
+        .with_max_attempts(5)
+        .with_base_delay(Duration::from_secs(1));
+    let coprocessor = Rc::new(RefCell::new(coprocessor));
+    // Segments are written to disk by the server and only loaded when they are proven.
+    let segments_dir = tempfile::tempdir().unwrap();
+    let env = env
         .write(&to_guest)
         .unwrap()
         .coprocessor_callback_ref(coprocessor.clone())
+        .segment_path(segments_dir.path())
         .build()
         .unwrap();
 
-    let client = ApiClient::from_env().unwrap();
+    // The image ID is computed from the ELF so that the receipt is verified against the same guest
+    // that was executed.
+    let guest = GuestArtifact::load(KECCAK_PATH).unwrap();
+
+    let client = ApiClient::from_env_or_local().unwrap();
     let mut segment_idx = 0;
     let session = client
-        .execute(
+        .execute_with_cancellation(
             &env,
-            Asset::Inline(KECCAK_ELF.into()),
+            &guest,
             AssetRequest::Inline,
+            &cancel,
             |info, segment| {
                 println!("{info:?}");
-                planner.enqueue_segment(segment_idx).unwrap();
+                let mut progress = progress.lock().unwrap();
+                progress.on_segment(info.cycles as u64);
+                println!("Progress: {:.1}%", 100.0 * progress.fraction_complete());
+                coprocessor
+                    .borrow_mut()
+                    .retry("zkr batch", Coprocessor::flush)?;
+                if segment_idx as usize >= planned_segments {
+                    planner.enqueue_segment(segment_idx, info.po2).unwrap();
+                }
+                task_manager.wait_for_capacity()?;
                 task_manager.add_segment(segment_idx, segment);
+                while replay
+                    .front()
+                    .is_some_and(|task| task.segment_idx.map_or(true, |idx| idx <= segment_idx))
+                {
+                    task_manager.add_task(replay.pop_front().unwrap());
+                }
                 while let Some(task) = planner.next_task() {
                     task_manager.add_task(task.clone());
                 }
+                if let Some(path) = &config.plan_state {
+                    planner.save(path)?;
+                }
                 segment_idx += 1;
                 Ok(())
             },
         )
         .unwrap();
 
+    println!("{:?}", session.stats());
+
+    // Each assumption made by the guest is resolved once all of the segments have been joined.
+    let receipt_claim = session.receipt_claim.as_ref().unwrap();
+    let mut coprocessor = coprocessor.borrow_mut();
+    validate_assumptions(receipt_claim, coprocessor.inner_mut()).unwrap();
+    let output = receipt_claim.output.as_value().unwrap().as_ref().unwrap();
+    for assumption in output.assumptions.as_value().unwrap().iter() {
+        let assumption = assumption.as_value().unwrap();
+        println!("{assumption:?}");
+        let description = format!("claim {}", assumption.claim);
+        let assumption_receipt = coprocessor
+            .retry(description, |inner| inner.receipt(&assumption.claim))
+            .unwrap();
+        // A plan resumed after it was finished already resolves its assumptions.
+        if !planner.is_finished() {
+            planner.enqueue_assumption(assumption.claim).unwrap();
+        }
+        task_manager.add_assumption(assumption.claim, assumption_receipt);
+        coprocessor.inner_mut().release(&assumption.claim);
+    }
+
     planner.finish().unwrap();
+    if let Some(path) = &config.plan_state {
+        planner.save(path).unwrap();
+    }
 
-    println!("Plan:");
+    let graph = planner.task_graph();
+    progress
+        .lock()
+        .unwrap()
+        .on_plan_finished(graph.tasks().len());
+    println!(
+        "Plan ({} tasks, depth {}):",
+        graph.tasks().len(),
+        graph.depth()
+    );
     println!("{planner:?}");
+    for (po2, count) in planner.po2_histogram() {
+        println!("po2 {po2}: {count} segment(s)");
+    }
+    if let Some(path) = &config.plan_dot {
+        std::fs::write(path, planner.to_dot()).unwrap();
+        println!("Plan written to {}", path.display());
+    }
 
     while let Some(task) = planner.next_task() {
         task_manager.add_task(task.clone());
     }
 
-    let conditional_receipt = task_manager.run();
-
-    let output = conditional_receipt
-        .claim
-        .as_value()
-        .unwrap()
-        .output
-        .as_value()
-        .unwrap()
-        .as_ref()
-        .unwrap();
-    let assumptions = output.assumptions.as_value().unwrap();
-
-    let coprocessor = coprocessor.borrow();
-    let mut succinct_receipt = conditional_receipt.clone();
-    for assumption in assumptions.iter() {
-        let assumption = assumption.as_value().unwrap();
-        println!("{assumption:?}");
-        let assumption_receipt = coprocessor.receipts.get(&assumption.claim).unwrap().clone();
-        let opts = ProverOpts::default();
-        succinct_receipt = client
-            .resolve(
-                &opts,
-                succinct_receipt.try_into().unwrap(),
-                assumption_receipt.try_into().unwrap(),
-                AssetRequest::Inline,
-            )
-            .unwrap();
+    // Only the tasks that failed are run again; the receipts of the others are kept.
+    let mut result = task_manager.run().unwrap();
+    for _ in 0..MAX_RETRIES {
+        let RunResult::Failed(failures) = &result else {
+            break;
+        };
+        println!(
+            "{} task(s) failed (segments {:?}), retrying...",
+            failures.len(),
+            result.failed_segments()
+        );
+        result = task_manager.retry_failed().unwrap();
     }
+    let succinct_receipt = result.into_receipt().unwrap();
+    task_manager.shutdown().unwrap();
 
     let receipt = Receipt::new(
         InnerReceipt::Succinct(succinct_receipt),
         session.journal.bytes.clone(),
     );
-    let asset = receipt.try_into().unwrap();
-    client.verify(asset, KECCAK_ID).unwrap();
+    let asset: Asset = receipt.try_into().unwrap();
+    client.verify(asset.clone(), &guest).unwrap();
     println!("Receipt verified!");
+
+    if config.groth16 {
+        let receipt = client
+            .compress_to_groth16(&ProverOpts::groth16(), asset, AssetRequest::Inline)
+            .unwrap();
+        receipt.verify(guest.image_id()).unwrap();
+        println!("Groth16 receipt verified!");
+    }
 }
 
+// Without `RISC0_SERVER_PATH` or an installed `r0vm`, this needs the `prove` feature to prove
+// in-process.
 #[test]
 fn smoke_test() {
     prover_example();
 }
+
+#[test]
+fn deduplicate_requests() {
+    let claim_digest = Digest::new([1; 8]);
+    let zkr_request = ProveZkrRequest {
+        claim_digest,
+        control_id: Digest::ZERO,
+        input: vec![],
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut coprocessor = Coprocessor::new(
+        recording_server(dir.path(), "zkr"),
+        recording_server(dir.path(), "keccak"),
+        LruCache::new(usize::MAX),
+    );
+    coprocessor.prove_zkr(zkr_request.clone()).unwrap();
+    coprocessor.prove_zkr(zkr_request).unwrap();
+    assert_eq!(coprocessor.pending_zkr.len(), 1);
+
+    // A keccak request for a claim that is already pending returns without contacting the server.
+    let keccak_request = ProveKeccakRequest {
+        claim_digest,
+        po2: 15,
+        control_root: Digest::ZERO,
+        input: vec![],
+    };
+    coprocessor.prove_keccak(keccak_request).unwrap();
+    assert!(!coprocessor.receipts.contains(&claim_digest));
+    assert!(!dir.path().join("requests.log").exists());
+}
+
+#[test]
+fn missing_assumption() {
+    use risc0_zkvm::{Assumption, Assumptions, Output};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut coprocessor = Coprocessor::new(
+        recording_server(dir.path(), "zkr"),
+        recording_server(dir.path(), "keccak"),
+        LruCache::new(usize::MAX),
+    );
+    let requested = Digest::new([1; 8]);
+    coprocessor
+        .prove_zkr(ProveZkrRequest {
+            claim_digest: requested,
+            control_id: Digest::ZERO,
+            input: vec![],
+        })
+        .unwrap();
+
+    let claim_with = |claims: &[Digest]| {
+        let assumptions = claims.iter().map(|claim| {
+            Assumption {
+                claim: *claim,
+                control_root: Digest::ZERO,
+            }
+            .into()
+        });
+        let mut receipt_claim = ReceiptClaim::ok(Digest::ZERO, vec![]);
+        receipt_claim.output = Some(Output {
+            journal: vec![].into(),
+            assumptions: Assumptions(assumptions.collect()).into(),
+        })
+        .into();
+        receipt_claim
+    };
+    validate_assumptions(&claim_with(&[requested]), &coprocessor).unwrap();
+
+    // The guest never requested a proof of this claim, and none was supplied.
+    let missing = Digest::new([2; 8]);
+    assert_eq!(
+        validate_assumptions(&claim_with(&[requested, missing]), &coprocessor),
+        Err(AssumptionError::MissingAssumption {
+            claim_digest: missing
+        })
+    );
+}
+
+#[cfg(test)]
+fn recording_server(dir: &std::path::Path, name: &str) -> ApiClient {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    // A stand-in for r0vm that records which backend was used and then drops the connection.
+    let path = dir.join(name);
+    let version = risc0_zkvm::get_version().unwrap();
+    let script = format!(
+        r#"#!/bin/bash
+if [ "$1" = "--version" ]; then
+  echo "r0vm {version}"
+  exit 0
+fi
+echo {name} >> "{log}"
+exec 3<>/dev/tcp/127.0.0.1/$2
+"#,
+        log = dir.join("requests.log").display()
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    ApiClient::new_sub_process(path).unwrap()
+}
+
+#[test]
+fn route_requests_by_type() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("requests.log");
+    let read_log = || std::fs::read_to_string(&log).unwrap_or_default();
+
+    let mut coprocessor = Coprocessor::new(
+        recording_server(dir.path(), "zkr"),
+        recording_server(dir.path(), "keccak"),
+        LruCache::new(usize::MAX),
+    );
+
+    let keccak_request = ProveKeccakRequest {
+        claim_digest: Digest::new([1; 8]),
+        po2: 15,
+        control_root: Digest::ZERO,
+        input: vec![],
+    };
+    assert!(coprocessor.prove_keccak(keccak_request).is_err());
+    assert_eq!(read_log(), "keccak\n");
+
+    let zkr_request = ProveZkrRequest {
+        claim_digest: Digest::new([2; 8]),
+        control_id: Digest::ZERO,
+        input: vec![],
+    };
+    coprocessor.prove_zkr(zkr_request).unwrap();
+    assert!(coprocessor.flush().is_err());
+    assert_eq!(read_log(), "keccak\nzkr\n");
+}
+
+#[test]
+fn reprove_evicted_receipt() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("requests.log");
+    let read_log = || std::fs::read_to_string(&log).unwrap_or_default();
+
+    let mut coprocessor = Coprocessor::new(
+        recording_server(dir.path(), "zkr"),
+        recording_server(dir.path(), "keccak"),
+        LruCache::new(1),
+    );
+
+    // Claims that the guest never requested cannot be proven.
+    assert!(coprocessor.receipt(&Digest::new([3; 8])).is_err());
+    assert_eq!(read_log(), "");
+
+    let keccak_request = ProveKeccakRequest {
+        claim_digest: Digest::new([1; 8]),
+        po2: 15,
+        control_root: Digest::ZERO,
+        input: vec![],
+    };
+    assert!(coprocessor.prove_keccak(keccak_request).is_err());
+    assert_eq!(read_log(), "keccak\n");
+
+    // The receipt is not cached, as if it had been evicted, so it is proven again on demand
+    // using the backend that handled the original request.
+    assert!(coprocessor.receipt(&Digest::new([1; 8])).is_err());
+    assert_eq!(read_log(), "keccak\nkeccak\n");
+
+    // Once released, the request is forgotten.
+    coprocessor.release(&Digest::new([1; 8]));
+    assert!(coprocessor.receipt(&Digest::new([1; 8])).is_err());
+    assert_eq!(read_log(), "keccak\nkeccak\n");
+}
@@ -0,0 +1,185 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use risc0_zkvm::{
+    sha::Digest, Asset, MaybePruned, ReceiptClaim, SegmentReceipt, SuccinctReceipt, Unknown,
+};
+use serde_json::json;
+
+use crate::worker::Worker;
+
+/// A segment receipt which records its index in its seal.
+fn segment_receipt(index: u32) -> SegmentReceipt {
+    serde_json::from_value(json!({
+        "seal": [index],
+        "index": index,
+        "hashfn": "poseidon2",
+        "verifier_parameters": Digest::ZERO,
+        "claim": ReceiptClaim::ok(Digest::ZERO, vec![]),
+    }))
+    .unwrap()
+}
+
+/// A receipt which records the segments it covers in its seal.
+pub fn succinct_receipt(seal: Vec<u32>, claim: ReceiptClaim) -> SuccinctReceipt<ReceiptClaim> {
+    serde_json::from_value(json!({
+        "seal": seal,
+        "control_id": Digest::ZERO,
+        "claim": MaybePruned::Value(claim),
+        "hashfn": "poseidon2",
+        "verifier_parameters": Digest::ZERO,
+        "control_inclusion_proof": { "index": 0, "digests": [] },
+    }))
+    .unwrap()
+}
+
+/// A [Worker] which proves nothing and records the calls made to it.
+///
+/// Each segment is identified by the first byte of its asset, or 0 if the asset is empty. Its
+/// receipt records that index in its seal, and joining or resolving receipts concatenates their
+/// seals, so the seal of the final receipt lists the segments and assumptions it covers in order.
+/// A segment receipt is valid if its seal is exactly its index.
+#[derive(Default)]
+pub struct MockWorker {
+    hook: Option<Box<dyn Fn() + Send + Sync>>,
+    fail_once: Option<u32>,
+    corrupt: Option<u32>,
+    hold: Option<u32>,
+    failed: AtomicBool,
+    released: AtomicBool,
+    /// The name of each method called, in order.
+    pub calls: Mutex<Vec<&'static str>>,
+    /// The index of each segment proving was attempted for, in order.
+    pub proven: Mutex<Vec<u32>>,
+    /// The address of the buffer backing each inline segment, in order.
+    pub addresses: Mutex<Vec<usize>>,
+}
+
+impl MockWorker {
+    /// Run `hook` in place of proving each segment and joining each pair.
+    pub fn with_hook(self, hook: impl Fn() + Send + Sync + 'static) -> Self {
+        Self {
+            hook: Some(Box::new(hook)),
+            ..self
+        }
+    }
+
+    /// Sleep for `delay` in place of proving each segment and joining each pair.
+    pub fn with_delay(self, delay: Duration) -> Self {
+        self.with_hook(move || thread::sleep(delay))
+    }
+
+    /// Fail the first attempt to prove segment `idx`.
+    pub fn fail_once(self, idx: u32) -> Self {
+        Self {
+            fail_once: Some(idx),
+            ..self
+        }
+    }
+
+    /// Prove segment `idx` with a corrupted seal, which fails [Worker::verify_segment].
+    pub fn corrupt(self, idx: u32) -> Self {
+        Self {
+            corrupt: Some(idx),
+            ..self
+        }
+    }
+
+    /// Wait to prove segment `idx` until [MockWorker::release] is called.
+    pub fn hold(self, idx: u32) -> Self {
+        Self {
+            hold: Some(idx),
+            ..self
+        }
+    }
+
+    /// Let the segment given to [MockWorker::hold] be proven.
+    pub fn release(&self) {
+        self.released.store(true, Ordering::SeqCst);
+    }
+
+    fn record(&self, call: &'static str) {
+        self.calls.lock().unwrap().push(call);
+        if let Some(hook) = &self.hook {
+            if call == "prove_segment" || call == "join" {
+                hook();
+            }
+        }
+    }
+}
+
+impl Worker for MockWorker {
+    fn prove_segment(&self, segment: Asset) -> Result<SegmentReceipt> {
+        let bytes = segment.as_bytes()?;
+        if let Asset::Inline(_) = segment {
+            self.addresses.lock().unwrap().push(bytes.as_ptr() as usize);
+        }
+        let idx = bytes.first().copied().unwrap_or_default() as u32;
+        while self.hold == Some(idx) && !self.released.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        self.record("prove_segment");
+        self.proven.lock().unwrap().push(idx);
+        if self.fail_once == Some(idx) && !self.failed.swap(true, Ordering::SeqCst) {
+            bail!("transient failure");
+        }
+        let mut receipt = segment_receipt(idx);
+        if self.corrupt == Some(idx) {
+            receipt.seal = vec![u32::MAX];
+        }
+        Ok(receipt)
+    }
+
+    fn verify_segment(&self, receipt: &SegmentReceipt) -> Result<()> {
+        if receipt.seal != [receipt.index] {
+            bail!("invalid seal");
+        }
+        Ok(())
+    }
+
+    fn lift(&self, receipt: SegmentReceipt) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        self.record("lift");
+        Ok(succinct_receipt(receipt.seal, receipt.claim))
+    }
+
+    fn join(
+        &self,
+        mut left: SuccinctReceipt<ReceiptClaim>,
+        right: SuccinctReceipt<ReceiptClaim>,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        self.record("join");
+        left.seal.extend(right.seal);
+        Ok(left)
+    }
+
+    fn resolve(
+        &self,
+        mut conditional: SuccinctReceipt<ReceiptClaim>,
+        assumption: SuccinctReceipt<Unknown>,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        self.record("resolve");
+        conditional.seal.extend(assumption.seal);
+        Ok(conditional)
+    }
+}
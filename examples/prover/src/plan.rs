@@ -12,7 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use risc0_zkvm::sha::Digest;
+use serde::{Deserialize, Serialize};
+
+pub type TaskId = usize;
 
 #[derive(Debug)]
 pub enum PlannerErr {
@@ -20,20 +30,51 @@ pub enum PlannerErr {
     PlanFinalized,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub enum Command {
     Finalize,
     Join,
+    Resolve,
     Segment,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Task {
-    pub task_number: usize,
+    pub task_number: TaskId,
     pub task_height: u32,
     pub command: Command,
-    pub depends_on: Vec<usize>,
+    pub depends_on: Vec<TaskId>,
     pub segment_idx: Option<u32>,
+
+    /// The claim digest of the assumption removed by a Resolve task. Its receipt is produced by
+    /// the coprocessor during execution rather than by another task in the plan.
+    pub assumption: Option<Digest>,
+}
+
+/// A step of the work done by a [Task], as returned by [Task::steps], for schedulers which route
+/// different kinds of work to different workers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PlanTask {
+    /// Prove segment `idx`.
+    ProveSegment { idx: u32 },
+
+    /// Lift the receipt of segment `idx` into a succinct receipt.
+    Lift { idx: u32 },
+
+    /// Join the receipts of tasks `left` through `right`.
+    ///
+    /// For joins wider than two, the receipts of the other tasks the join depends on, which lie
+    /// between them in the session, are joined as well.
+    Join { left: TaskId, right: TaskId },
+
+    /// Remove `assumption` from the receipt of task `conditional`.
+    Resolve {
+        conditional: TaskId,
+        assumption: Digest,
+    },
+
+    /// Produce the final receipt from the receipt of task `receipt`.
+    Finalize { receipt: TaskId },
 }
 
 impl Task {
@@ -44,16 +85,34 @@ impl Task {
             command: Command::Segment,
             depends_on: Vec::new(),
             segment_idx: Some(segment_idx),
+            assumption: None,
         }
     }
 
-    pub fn new_join(task_number: usize, task_height: u32, left: usize, right: usize) -> Self {
+    pub fn new_join(task_number: usize, task_height: u32, children: Vec<usize>) -> Self {
         Task {
             task_number,
             task_height,
             command: Command::Join,
-            depends_on: vec![left, right],
+            depends_on: children,
             segment_idx: None,
+            assumption: None,
+        }
+    }
+
+    pub fn new_resolve(
+        task_number: usize,
+        task_height: u32,
+        conditional: usize,
+        assumption: Digest,
+    ) -> Self {
+        Task {
+            task_number,
+            task_height,
+            command: Command::Resolve,
+            depends_on: vec![conditional],
+            segment_idx: None,
+            assumption: Some(assumption),
         }
     }
 
@@ -64,11 +123,106 @@ impl Task {
             command: Command::Finalize,
             depends_on: vec![depends_on],
             segment_idx: None,
+            assumption: None,
         }
     }
+
+    /// The steps of this task, in the order they are run.
+    ///
+    /// A segment task both proves and lifts its segment, so it has two steps.
+    pub fn steps(&self) -> Vec<PlanTask> {
+        match self.command {
+            Command::Segment => {
+                let idx = self.segment_idx.unwrap();
+                vec![PlanTask::ProveSegment { idx }, PlanTask::Lift { idx }]
+            }
+            Command::Join => vec![PlanTask::Join {
+                left: self.depends_on[0],
+                right: *self.depends_on.last().unwrap(),
+            }],
+            Command::Resolve => vec![PlanTask::Resolve {
+                conditional: self.depends_on[0],
+                assumption: self.assumption.unwrap(),
+            }],
+            Command::Finalize => vec![PlanTask::Finalize {
+                receipt: self.depends_on[0],
+            }],
+        }
+    }
+
+    /// Returns true if every task this task depends on has completed.
+    pub fn is_ready(&self, completed: &HashSet<TaskId>) -> bool {
+        self.depends_on.iter().all(|dep| completed.contains(dep))
+    }
 }
 
-#[derive(Clone, Default)]
+/// The dependency structure of a plan.
+///
+/// Segment tasks (prove and lift) have no dependencies, joins depend on their children, and
+/// resolves depend on the receipt they make unconditional. Any task whose dependencies are
+/// complete may be run concurrently with the others.
+#[derive(Clone, Debug, Default)]
+pub struct TaskGraph {
+    tasks: Vec<Task>,
+    /// The position of each task in `tasks`.
+    index: HashMap<TaskId, usize>,
+    /// See [TaskGraph::critical_path_lengths].
+    critical_path_lengths: HashMap<TaskId, u32>,
+}
+
+impl TaskGraph {
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    /// Add a task, after the tasks it depends on.
+    pub fn insert(&mut self, task: Task) {
+        // The tasks this one depends on, and their own dependencies in turn, are now on a chain
+        // one task longer. Propagation stops at tasks already on a chain at least as long.
+        let mut stack: Vec<(TaskId, u32)> = task.depends_on.iter().map(|&dep| (dep, 1)).collect();
+        while let Some((id, length)) = stack.pop() {
+            let Some(current) = self.critical_path_lengths.get_mut(&id) else {
+                continue;
+            };
+            if *current >= length {
+                continue;
+            }
+            *current = length;
+            let deps = &self.tasks[self.index[&id]].depends_on;
+            stack.extend(deps.iter().map(|&dep| (dep, length + 1)));
+        }
+        self.critical_path_lengths.insert(task.task_number, 0);
+        self.index.insert(task.task_number, self.tasks.len());
+        self.tasks.push(task);
+    }
+
+    /// Tasks that have not completed and whose dependencies have all completed.
+    pub fn ready<'a>(&'a self, completed: &'a HashSet<TaskId>) -> impl Iterator<Item = &'a Task> {
+        self.tasks
+            .iter()
+            .filter(|task| !completed.contains(&task.task_number) && task.is_ready(completed))
+    }
+
+    /// For each task, the number of tasks in the longest chain of tasks which depend on it.
+    ///
+    /// The tasks with the longest chains are on the critical path of the plan: the final receipt
+    /// cannot be produced until each task in the chain has run in turn. The lengths are kept up
+    /// to date as tasks are inserted.
+    pub fn critical_path_lengths(&self) -> &HashMap<TaskId, u32> {
+        &self.critical_path_lengths
+    }
+
+    /// The length of the longest chain of tasks in the graph.
+    pub fn depth(&self) -> u32 {
+        self.tasks
+            .iter()
+            .map(|task| task.task_height + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Planner {
     /// All of the tasks in this plan
     tasks: Vec<Task>,
@@ -83,36 +237,110 @@ pub struct Planner {
 
     /// Last task in the plan. Set by `self.finish()`.
     last_task: Option<usize>,
+
+    /// Assumptions to resolve, in the order they must be removed from the claim.
+    assumptions: Vec<Digest>,
+
+    /// Number of enqueued segments at each po2.
+    po2_histogram: BTreeMap<u32, usize>,
+
+    /// Maximum number of children folded by each Join task.
+    join_arity: usize,
+}
+
+impl Default for Planner {
+    fn default() -> Self {
+        Self {
+            tasks: Vec::new(),
+            peaks: Vec::new(),
+            consumer_position: 0,
+            last_task: None,
+            assumptions: Vec::new(),
+            po2_histogram: BTreeMap::new(),
+            join_arity: 2,
+        }
+    }
 }
 
 impl Planner {
-    pub fn enqueue_segment(&mut self, segment_idx: u32) -> Result<usize, PlannerErr> {
+    /// Plan joins that each fold up to `join_arity` receipts, rather than two.
+    ///
+    /// A k-ary join tree is shallower than a binary one, which is cheaper on backends that can
+    /// join several receipts at once. The joined receipt is the same regardless of arity.
+    pub fn with_join_arity(mut self, join_arity: usize) -> Self {
+        self.join_arity = join_arity.max(2);
+        self
+    }
+
+    /// Write the full state of the plan to `path` as JSON, including the position of
+    /// [Planner::next_task], so that a restarted coordinator can [load](Planner::load) it and
+    /// continue planning where this one left off.
+    ///
+    /// The state is written to a temporary file which is then renamed over `path`, so a crash
+    /// while saving leaves the previous state intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(self)?)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("failed to rename {}", tmp_path.display()))?;
+        Ok(())
+    }
+
+    /// Read a plan written by [Planner::save].
+    ///
+    /// The loaded planner hands out the same sequence of tasks from [Planner::next_task] as the
+    /// one that was saved would have.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_slice(&bytes)
+            .with_context(|| format!("failed to parse plan from {}", path.display()))
+    }
+
+    pub fn enqueue_segment(&mut self, segment_idx: u32, po2: u32) -> Result<usize, PlannerErr> {
         if self.last_task.is_some() {
             return Err(PlannerErr::PlanFinalized);
         }
 
         let task_number = self.next_task_number();
         self.tasks.push(Task::new_segment(task_number, segment_idx));
+        *self.po2_histogram.entry(po2).or_default() += 1;
 
-        let mut new_peak = task_number;
-        while let Some(smallest_peak) = self.peaks.last().copied() {
-            let new_height = self.get_task(new_peak).task_height;
-            let smallest_peak_height = self.get_task(smallest_peak).task_height;
+        // Join the smallest peaks once there are `join_arity` of them at the same height.
+        self.peaks.push(task_number);
+        while self.peaks.len() >= self.join_arity {
+            let children = &self.peaks[self.peaks.len() - self.join_arity..];
+            let highest = self.get_task(children[0]).task_height;
+            let lowest = self.get_task(*children.last().unwrap()).task_height;
 
-            match new_height.cmp(&smallest_peak_height) {
-                Ordering::Less => break,
+            match highest.cmp(&lowest) {
+                Ordering::Greater => break,
                 Ordering::Equal => {
-                    self.peaks.pop();
-                    new_peak = self.enqueue_join(smallest_peak, new_peak);
+                    let children = self.peaks.split_off(self.peaks.len() - self.join_arity);
+                    let new_peak = self.enqueue_join(children);
+                    self.peaks.push(new_peak);
                 }
-                Ordering::Greater => unreachable!(),
+                Ordering::Less => unreachable!(),
             }
         }
-        self.peaks.push(new_peak);
 
         Ok(task_number)
     }
 
+    /// Add an assumption to be resolved once all segments have been joined.
+    ///
+    /// Assumptions must be added in the order they appear in the session's claim.
+    pub fn enqueue_assumption(&mut self, claim_digest: Digest) -> Result<(), PlannerErr> {
+        if self.last_task.is_some() {
+            return Err(PlannerErr::PlanFinalized);
+        }
+        self.assumptions.push(claim_digest);
+        Ok(())
+    }
+
     pub fn finish(&mut self) -> Result<usize, PlannerErr> {
         // Return error if plan has not yet started
         if self.peaks.is_empty() {
@@ -121,22 +349,51 @@ impl Planner {
 
         // Finish the plan (if it's not yet finished)
         if self.last_task.is_none() {
-            // Join remaining peaks
+            // Join remaining peaks, smallest first. Only as many are joined as needed for the
+            // rest to fit in a single join at the root.
             while 2 <= self.peaks.len() {
-                let peak_0 = self.peaks.pop().unwrap();
-                let peak_1 = self.peaks.pop().unwrap();
+                let count = if self.peaks.len() <= self.join_arity {
+                    self.peaks.len()
+                } else {
+                    self.join_arity.min(self.peaks.len() - self.join_arity + 1)
+                };
+                let children = self.peaks.split_off(self.peaks.len() - count);
+
+                let peak = self.enqueue_join(children);
+                self.peaks.push(peak);
+            }
 
-                let peak_3 = self.enqueue_join(peak_1, peak_0);
-                self.peaks.push(peak_3);
+            // Resolve each assumption in turn, starting from the root join
+            let mut root = self.peaks[0];
+            for assumption in std::mem::take(&mut self.assumptions) {
+                root = self.enqueue_resolve(root, assumption);
             }
 
             // Add the Finalize task
-            self.last_task = Some(self.enqueue_finalize(self.peaks[0]));
+            self.last_task = Some(self.enqueue_finalize(root));
         }
 
         Ok(self.last_task.unwrap())
     }
 
+    /// The number of enqueued segments at each po2.
+    ///
+    /// Segments can only be enqueued before [Planner::finish], so once the plan is finished this
+    /// covers every segment in it.
+    pub fn po2_histogram(&self) -> BTreeMap<u32, usize> {
+        self.po2_histogram.clone()
+    }
+
+    /// Returns true once [Planner::finish] has been called.
+    pub fn is_finished(&self) -> bool {
+        self.last_task.is_some()
+    }
+
+    /// The tasks already handed out by [Planner::next_task], in order.
+    pub fn dispatched_tasks(&self) -> &[Task] {
+        &self.tasks[..self.consumer_position]
+    }
+
     pub fn task_count(&self) -> usize {
         self.tasks.len()
     }
@@ -149,6 +406,42 @@ impl Planner {
         }
     }
 
+    /// The dependency graph of all tasks planned so far.
+    pub fn task_graph(&self) -> TaskGraph {
+        let mut graph = TaskGraph::default();
+        for task in self.tasks.iter() {
+            graph.insert(task.clone());
+        }
+        graph
+    }
+
+    /// Render the plan as a Graphviz DOT digraph, with an edge from each task to the tasks that
+    /// depend on it.
+    ///
+    /// Tasks are emitted in the order they were planned, so the output is deterministic.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph plan {\n");
+        for task in self.tasks.iter() {
+            let label = match task.command {
+                Command::Finalize => "Finalize".to_string(),
+                Command::Join => "Join".to_string(),
+                Command::Resolve => format!("Resolve {}", task.assumption.unwrap()),
+                Command::Segment => format!("Segment {}", task.segment_idx.unwrap()),
+            };
+            dot.push_str(&format!(
+                "  {} [label=\"{} {label}\"];\n",
+                task.task_number, task.task_number
+            ));
+        }
+        for task in self.tasks.iter() {
+            for dep in task.depends_on.iter() {
+                dot.push_str(&format!("  {dep} -> {};\n", task.task_number));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
     pub fn next_task(&mut self) -> Option<&Task> {
         if self.consumer_position < self.task_count() {
             let out = &self.tasks[self.consumer_position];
@@ -159,14 +452,27 @@ impl Planner {
         }
     }
 
-    fn enqueue_join(&mut self, left: usize, right: usize) -> usize {
+    fn enqueue_join(&mut self, children: Vec<usize>) -> usize {
         let task_number = self.next_task_number();
-        let task_height = 1 + u32::max(
-            self.get_task(left).task_height,
-            self.get_task(right).task_height,
-        );
+        let task_height = 1 + children
+            .iter()
+            .map(|&child| self.get_task(child).task_height)
+            .max()
+            .unwrap();
         self.tasks
-            .push(Task::new_join(task_number, task_height, left, right));
+            .push(Task::new_join(task_number, task_height, children));
+        task_number
+    }
+
+    fn enqueue_resolve(&mut self, conditional: usize, assumption: Digest) -> usize {
+        let task_number = self.next_task_number();
+        let task_height = 1 + self.get_task(conditional).task_height;
+        self.tasks.push(Task::new_resolve(
+            task_number,
+            task_height,
+            conditional,
+            assumption,
+        ));
         task_number
     }
 
@@ -207,8 +513,13 @@ impl std::fmt::Debug for Planner {
                 }
                 Command::Join => {
                     write!(f, "{:?} Join", task.task_number)?;
+                    for child in task.depends_on.iter() {
+                        stack.push((indent + 2, *child));
+                    }
+                }
+                Command::Resolve => {
+                    write!(f, "{:?} Resolve", task.task_number)?;
                     stack.push((indent + 2, task.depends_on[0]));
-                    stack.push((indent + 2, task.depends_on[1]));
                 }
                 Command::Segment => {
                     write!(f, "{:?} Segment", task.task_number)?;
@@ -219,3 +530,274 @@ impl std::fmt::Debug for Planner {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use risc0_zkvm::sha::Digest;
+
+    use super::{Command, PlanTask, Planner};
+
+    fn plan_with_arity(segments: u32, join_arity: usize) -> Planner {
+        let mut planner = Planner::default().with_join_arity(join_arity);
+        for idx in 0..segments {
+            planner.enqueue_segment(idx, 20).unwrap();
+        }
+        planner.finish().unwrap();
+        planner
+    }
+
+    fn plan(segments: u32, assumptions: usize) -> Planner {
+        let mut planner = Planner::default();
+        for idx in 0..segments {
+            planner.enqueue_segment(idx, 20).unwrap();
+        }
+        for idx in 0..assumptions {
+            planner
+                .enqueue_assumption(Digest::new([idx as u32; 8]))
+                .unwrap();
+        }
+        planner.finish().unwrap();
+        planner
+    }
+
+    #[test]
+    fn critical_path_lengths() {
+        let graph = plan(5, 2).task_graph();
+        let lengths = graph.critical_path_lengths();
+        assert_eq!(lengths.len(), graph.tasks().len());
+
+        // Each task is one longer than the longest chain of any task that depends on it.
+        for task in graph.tasks() {
+            let expected = graph
+                .tasks()
+                .iter()
+                .filter(|dependent| dependent.depends_on.contains(&task.task_number))
+                .map(|dependent| lengths[&dependent.task_number] + 1)
+                .max()
+                .unwrap_or(0);
+            assert_eq!(lengths[&task.task_number], expected);
+        }
+        assert_eq!(lengths[&graph.tasks().last().unwrap().task_number], 0);
+    }
+
+    #[test]
+    fn join_tree_depth() {
+        for segments in 1..=33u32 {
+            let graph = plan(segments, 0).task_graph();
+            let joins: Vec<_> = graph
+                .tasks()
+                .iter()
+                .filter(|task| task.command == Command::Join)
+                .collect();
+            assert_eq!(joins.len(), segments as usize - 1);
+
+            let expected_height = segments.next_power_of_two().trailing_zeros();
+            let root_height = joins.iter().map(|task| task.task_height).max().unwrap_or(0);
+            assert_eq!(root_height, expected_height, "segments: {segments}");
+
+            // The join tree plus the Finalize task.
+            assert_eq!(graph.depth(), expected_height + 2);
+        }
+    }
+
+    #[test]
+    fn join_arity() {
+        let join_depth = |join_arity| {
+            let mut planner = Planner::default().with_join_arity(join_arity);
+            for idx in 0..16 {
+                planner.enqueue_segment(idx, 20).unwrap();
+            }
+            planner.finish().unwrap();
+            let graph = planner.task_graph();
+            let joins: Vec<_> = graph
+                .tasks()
+                .iter()
+                .filter(|task| task.command == Command::Join)
+                .cloned()
+                .collect();
+            assert!(joins.iter().all(|task| task.depends_on.len() <= join_arity));
+
+            // Every segment is folded into the root exactly once, in order.
+            let mut leaves = Vec::new();
+            let mut stack = vec![joins.last().unwrap().task_number];
+            while let Some(task) = stack.pop() {
+                let task = &graph.tasks()[task];
+                match task.command {
+                    Command::Segment => leaves.push(task.segment_idx.unwrap()),
+                    _ => stack.extend(task.depends_on.iter().rev()),
+                }
+            }
+            assert_eq!(leaves, (0..16).collect::<Vec<_>>());
+
+            joins.last().unwrap().task_height
+        };
+        assert_eq!(join_depth(2), 4);
+        assert_eq!(join_depth(4), 2);
+
+        // Segment counts that are not a power of the arity leave a partial join at the root.
+        let graph = plan_with_arity(11, 4).task_graph();
+        let root = &graph.tasks()[graph.tasks().last().unwrap().depends_on[0]];
+        assert_eq!(root.command, Command::Join);
+        assert_eq!(root.task_height, 2);
+    }
+
+    #[test]
+    fn plan_task_steps() {
+        let mut planner = Planner::default();
+        for idx in 0..3 {
+            planner.enqueue_segment(idx, 20).unwrap();
+        }
+        planner.enqueue_assumption(Digest::new([7; 8])).unwrap();
+        planner.finish().unwrap();
+
+        let mut steps = Vec::new();
+        while let Some(task) = planner.next_task() {
+            steps.push(task.steps());
+        }
+        assert_eq!(
+            steps,
+            vec![
+                vec![PlanTask::ProveSegment { idx: 0 }, PlanTask::Lift { idx: 0 }],
+                vec![PlanTask::ProveSegment { idx: 1 }, PlanTask::Lift { idx: 1 }],
+                vec![PlanTask::Join { left: 0, right: 1 }],
+                vec![PlanTask::ProveSegment { idx: 2 }, PlanTask::Lift { idx: 2 }],
+                vec![PlanTask::Join { left: 2, right: 3 }],
+                vec![PlanTask::Resolve {
+                    conditional: 4,
+                    assumption: Digest::new([7; 8]),
+                }],
+                vec![PlanTask::Finalize { receipt: 5 }],
+            ]
+        );
+
+        // Wider joins name their first and last children.
+        let graph = plan_with_arity(3, 3).task_graph();
+        assert_eq!(
+            graph.tasks()[3].steps(),
+            vec![PlanTask::Join { left: 0, right: 2 }]
+        );
+    }
+
+    #[test]
+    fn dot_export() {
+        let dot = plan(3, 1).to_dot();
+        let expected = concat!(
+            "digraph plan {\n",
+            "  0 [label=\"0 Segment 0\"];\n",
+            "  1 [label=\"1 Segment 1\"];\n",
+            "  2 [label=\"2 Join\"];\n",
+            "  3 [label=\"3 Segment 2\"];\n",
+            "  4 [label=\"4 Join\"];\n",
+            "  5 [label=\"5 Resolve 0000000000000000000000000000000000000000000000000000000000000000\"];\n",
+            "  6 [label=\"6 Finalize\"];\n",
+            "  0 -> 2;\n",
+            "  1 -> 2;\n",
+            "  2 -> 4;\n",
+            "  3 -> 4;\n",
+            "  4 -> 5;\n",
+            "  5 -> 6;\n",
+            "}\n",
+        );
+        assert_eq!(dot, expected);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let enqueue = |planner: &mut Planner, segments: std::ops::Range<u32>| {
+            for idx in segments {
+                planner.enqueue_segment(idx, 20 - idx % 2).unwrap();
+            }
+        };
+
+        let mut planner = Planner::default().with_join_arity(3);
+        enqueue(&mut planner, 0..5);
+        planner.enqueue_assumption(Digest::new([1; 8])).unwrap();
+        for _ in 0..3 {
+            planner.next_task().unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plan.json");
+        planner.save(&path).unwrap();
+        let mut loaded = Planner::load(&path).unwrap();
+        assert_eq!(loaded.dispatched_tasks(), planner.dispatched_tasks());
+
+        // Both planners continue with the same remaining segments and hand out the same tasks.
+        for planner in [&mut planner, &mut loaded] {
+            enqueue(planner, 5..11);
+            planner.enqueue_assumption(Digest::new([2; 8])).unwrap();
+            planner.finish().unwrap();
+        }
+        let mut expected = Vec::new();
+        while let Some(task) = planner.next_task() {
+            expected.push(task.clone());
+        }
+        let mut actual = Vec::new();
+        while let Some(task) = loaded.next_task() {
+            actual.push(task.clone());
+        }
+        assert_eq!(actual, expected);
+        assert_eq!(loaded.po2_histogram(), planner.po2_histogram());
+        assert_eq!(loaded.to_dot(), planner.to_dot());
+
+        // A finished plan stays finished once reloaded.
+        loaded.save(&path).unwrap();
+        let mut loaded = Planner::load(&path).unwrap();
+        assert!(loaded.is_finished());
+        assert!(loaded.enqueue_segment(11, 20).is_err());
+        assert!(loaded.next_task().is_none());
+    }
+
+    #[test]
+    fn po2_histogram() {
+        let mut planner = Planner::default();
+        for (idx, po2) in [20, 18, 20, 16, 20, 18].into_iter().enumerate() {
+            planner.enqueue_segment(idx as u32, po2).unwrap();
+        }
+        planner.finish().unwrap();
+        assert!(planner.enqueue_segment(6, 20).is_err());
+        assert_eq!(
+            planner.po2_histogram().into_iter().collect::<Vec<_>>(),
+            vec![(16, 1), (18, 2), (20, 3)]
+        );
+    }
+
+    #[test]
+    fn schedule_respects_dependencies() {
+        let graph = plan(11, 2).task_graph();
+        let mut completed = HashSet::new();
+        let mut waves = 0;
+        while completed.len() < graph.tasks().len() {
+            let ready: Vec<_> = graph.ready(&completed).cloned().collect();
+            assert!(!ready.is_empty(), "plan is stuck");
+            for task in ready.iter() {
+                for dep in task.depends_on.iter() {
+                    assert!(completed.contains(dep));
+                }
+                if task.command == Command::Join {
+                    assert_eq!(task.depends_on.len(), 2);
+                }
+            }
+            completed.extend(ready.iter().map(|task| task.task_number));
+            waves += 1;
+        }
+        assert_eq!(waves, graph.depth());
+
+        let resolves: Vec<_> = graph
+            .tasks()
+            .iter()
+            .filter(|task| task.command == Command::Resolve)
+            .collect();
+        assert_eq!(resolves.len(), 2);
+        assert_eq!(
+            graph.tasks()[resolves[0].depends_on[0]].command,
+            Command::Join
+        );
+        assert_eq!(resolves[1].depends_on, vec![resolves[0].task_number]);
+        let finalize = graph.tasks().last().unwrap();
+        assert_eq!(finalize.command, Command::Finalize);
+        assert_eq!(finalize.depends_on, vec![resolves[1].task_number]);
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// The share of the progress bar given to execution. Proving takes far longer than executing, so
+/// most of the bar is spent on the tasks of the plan.
+const EXECUTION_WEIGHT: f64 = 0.1;
+
+/// Tracks the progress of a session across execution and proving, for a single progress bar.
+///
+/// The coordinator reports each segment from the execute callback, the size of the plan once it
+/// is finished, and each task as the [TaskManager](crate::task_mgr::TaskManager) completes it.
+/// Until the plan is finished the number of tasks is unknown, so only execution progress is
+/// reported.
+#[derive(Clone, Debug, Default)]
+pub struct ProgressTracker {
+    expected_cycles: Option<u64>,
+    executed_cycles: u64,
+    total_tasks: Option<usize>,
+    completed_tasks: usize,
+}
+
+impl ProgressTracker {
+    /// Measure execution progress against `expected_cycles` user cycles, e.g. from an earlier run
+    /// of the same guest.
+    ///
+    /// Without an estimate, execution progress is only reported once execution has finished.
+    pub fn with_expected_cycles(mut self, expected_cycles: u64) -> Self {
+        self.expected_cycles = Some(expected_cycles);
+        self
+    }
+
+    /// Record a segment of `cycles` user cycles, as reported by the execute callback.
+    pub fn on_segment(&mut self, cycles: u64) {
+        self.executed_cycles += cycles;
+    }
+
+    /// Record that execution has finished and the plan holds `total_tasks` tasks.
+    pub fn on_plan_finished(&mut self, total_tasks: usize) {
+        self.total_tasks = Some(total_tasks);
+    }
+
+    /// Record that a task of the plan has completed.
+    pub fn on_task_complete(&mut self) {
+        self.completed_tasks += 1;
+    }
+
+    /// The fraction of execution that is complete, between 0 and 1.
+    pub fn execution_fraction(&self) -> f64 {
+        if self.total_tasks.is_some() {
+            return 1.0;
+        }
+        match self.expected_cycles {
+            Some(expected) if expected > 0 => {
+                (self.executed_cycles as f64 / expected as f64).min(1.0)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// The fraction of the tasks of the plan that are complete, or `None` until the plan is
+    /// finished.
+    pub fn proving_fraction(&self) -> Option<f64> {
+        self.total_tasks.map(|total| match total {
+            0 => 1.0,
+            total => (self.completed_tasks as f64 / total as f64).min(1.0),
+        })
+    }
+
+    /// The fraction of the whole session that is complete, between 0 and 1.
+    ///
+    /// This never decreases as progress is reported, and reaches 1 once every task of the plan
+    /// has completed.
+    pub fn fraction_complete(&self) -> f64 {
+        match self.proving_fraction() {
+            Some(proving) => EXECUTION_WEIGHT + (1.0 - EXECUTION_WEIGHT) * proving,
+            None => EXECUTION_WEIGHT * self.execution_fraction(),
+        }
+    }
+}
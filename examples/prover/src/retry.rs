@@ -0,0 +1,224 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fmt::Display, io::ErrorKind, time::Duration};
+
+use anyhow::Result;
+use risc0_zkvm::{CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest, ProverError};
+
+/// A [CoprocessorCallback] that retries requests which fail with a transient
+/// error, waiting with exponential backoff between attempts.
+pub struct RetryingCoprocessor<C> {
+    inner: C,
+    max_attempts: u32,
+    base_delay: Duration,
+}
+
+impl<C: CoprocessorCallback> RetryingCoprocessor<C> {
+    /// Wrap `inner`, making up to 3 attempts per request by default.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+
+    /// Set the maximum number of attempts made for each request.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set the delay before the first retry; each later retry doubles it.
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Access the wrapped coprocessor.
+    pub fn inner_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Run `f` against the wrapped coprocessor, retrying it on transient errors.
+    ///
+    /// This can be used for operations beyond [CoprocessorCallback], such as flushing a batch of
+    /// pending requests.
+    pub fn retry<T, F>(&mut self, description: impl Display, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut C) -> Result<T>,
+    {
+        let mut attempt = 1;
+        loop {
+            match f(&mut self.inner) {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt < self.max_attempts && is_transient(&err) => {
+                    let delay = self.delay(attempt);
+                    println!(
+                        "Attempt {attempt}/{} for {description} failed: {err:#}, retrying in {delay:?}",
+                        self.max_attempts
+                    );
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        self.base_delay.saturating_mul(factor)
+    }
+}
+
+impl<C: CoprocessorCallback> CoprocessorCallback for RetryingCoprocessor<C> {
+    fn prove_zkr(&mut self, proof_request: ProveZkrRequest) -> Result<()> {
+        let description = format!("claim {}", proof_request.claim_digest);
+        self.retry(description, |inner| inner.prove_zkr(proof_request.clone()))
+    }
+
+    fn prove_keccak(&mut self, proof_request: ProveKeccakRequest) -> Result<()> {
+        let description = format!("claim {}", proof_request.claim_digest);
+        self.retry(description, |inner| {
+            inner.prove_keccak(proof_request.clone())
+        })
+    }
+}
+
+/// Returns true if `err` was caused by a failure that may succeed when retried,
+/// such as a dropped connection or a timeout.
+///
+/// Errors reported by the server about the request itself (e.g. a malformed
+/// claim) are not transient, and neither is a server process that exited with
+/// an error, since running the same request again fails the same way.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(err) = cause.downcast_ref::<ProverError>() {
+            return matches!(err, ProverError::Connection(_) | ProverError::Timeout(_));
+        }
+        cause.downcast_ref::<std::io::Error>().is_some_and(|err| {
+            matches!(
+                err.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::TimedOut
+                    | ErrorKind::UnexpectedEof
+            )
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::{anyhow, Result};
+    use risc0_zkvm::{
+        sha::Digest, CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest, ProverError,
+        TimedOut,
+    };
+
+    use super::RetryingCoprocessor;
+
+    struct Flaky {
+        failures: Vec<anyhow::Error>,
+        calls: usize,
+    }
+
+    impl CoprocessorCallback for Flaky {
+        fn prove_zkr(&mut self, _proof_request: ProveZkrRequest) -> Result<()> {
+            self.calls += 1;
+            match self.failures.pop() {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        }
+
+        fn prove_keccak(&mut self, _proof_request: ProveKeccakRequest) -> Result<()> {
+            unimplemented!()
+        }
+    }
+
+    fn request() -> ProveZkrRequest {
+        ProveZkrRequest {
+            claim_digest: Digest::ZERO,
+            control_id: Digest::ZERO,
+            input: vec![],
+        }
+    }
+
+    fn reset() -> anyhow::Error {
+        std::io::Error::from(std::io::ErrorKind::ConnectionReset).into()
+    }
+
+    #[test]
+    fn retries_transient_errors() {
+        let flaky = Flaky {
+            failures: vec![
+                reset(),
+                ProverError::Connection(reset()).into(),
+                ProverError::Timeout(TimedOut {
+                    timeout: Duration::from_secs(1),
+                })
+                .into(),
+            ],
+            calls: 0,
+        };
+        let mut coprocessor = RetryingCoprocessor::new(flaky)
+            .with_max_attempts(5)
+            .with_base_delay(Duration::ZERO);
+        coprocessor.prove_zkr(request()).unwrap();
+        assert_eq!(coprocessor.inner_mut().calls, 4);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let flaky = Flaky {
+            failures: vec![reset(), reset()],
+            calls: 0,
+        };
+        let mut coprocessor = RetryingCoprocessor::new(flaky)
+            .with_max_attempts(2)
+            .with_base_delay(Duration::ZERO);
+        assert!(coprocessor.prove_zkr(request()).is_err());
+        assert_eq!(coprocessor.inner_mut().calls, 2);
+    }
+
+    #[test]
+    fn does_not_retry_request_errors() {
+        for err in [
+            anyhow!("claim digest mismatch"),
+            // Only the typed error counts, not one that merely reads like a connection failure.
+            anyhow!("server connection failed: Child finished with: 1"),
+            ProverError::VerificationFailed(anyhow!("invalid proof")).into(),
+            ProverError::ProcessFailed {
+                code: 1,
+                stderr: "out of GPU memory".into(),
+            }
+            .into(),
+        ] {
+            let flaky = Flaky {
+                failures: vec![err],
+                calls: 0,
+            };
+            let mut coprocessor = RetryingCoprocessor::new(flaky).with_base_delay(Duration::ZERO);
+            assert!(coprocessor.prove_zkr(request()).is_err());
+            assert_eq!(coprocessor.inner_mut().calls, 1);
+        }
+    }
+}
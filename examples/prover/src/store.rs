@@ -0,0 +1,224 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{ensure, Context, Result};
+use memmap2::Mmap;
+use risc0_zkvm::{sha::Digest, SuccinctReceipt, Unknown};
+
+use crate::cache::LruCache;
+
+/// Holds the receipts proven by a coprocessor, keyed by the digest of their claim.
+pub trait ReceiptStore {
+    /// Returns the receipt for `claim_digest`, if the store has one.
+    fn get(&mut self, claim_digest: &Digest) -> Result<Option<SuccinctReceipt<Unknown>>>;
+
+    /// Add `receipt`, keyed by the digest of its claim.
+    fn put(&mut self, receipt: SuccinctReceipt<Unknown>) -> Result<()>;
+
+    /// Returns true if the store has a receipt for `claim_digest`, without loading it.
+    fn contains(&self, claim_digest: &Digest) -> bool;
+
+    /// Prefer to keep the receipt for `claim_digest` until it is [unpinned](Self::unpin).
+    ///
+    /// Stores which never drop receipts need not do anything.
+    fn pin(&mut self, _claim_digest: Digest) {}
+
+    /// Allow the receipt for `claim_digest` to be dropped again.
+    fn unpin(&mut self, _claim_digest: &Digest) {}
+}
+
+impl<S: ReceiptStore + ?Sized> ReceiptStore for Box<S> {
+    fn get(&mut self, claim_digest: &Digest) -> Result<Option<SuccinctReceipt<Unknown>>> {
+        (**self).get(claim_digest)
+    }
+
+    fn put(&mut self, receipt: SuccinctReceipt<Unknown>) -> Result<()> {
+        (**self).put(receipt)
+    }
+
+    fn contains(&self, claim_digest: &Digest) -> bool {
+        (**self).contains(claim_digest)
+    }
+
+    fn pin(&mut self, claim_digest: Digest) {
+        (**self).pin(claim_digest)
+    }
+
+    fn unpin(&mut self, claim_digest: &Digest) {
+        (**self).unpin(claim_digest)
+    }
+}
+
+/// Receipts held in memory, evicting the least recently used ones, unpinned first, beyond the
+/// capacity of the cache.
+impl ReceiptStore for LruCache<Digest, SuccinctReceipt<Unknown>> {
+    fn get(&mut self, claim_digest: &Digest) -> Result<Option<SuccinctReceipt<Unknown>>> {
+        Ok(LruCache::get(self, claim_digest).cloned())
+    }
+
+    fn put(&mut self, receipt: SuccinctReceipt<Unknown>) -> Result<()> {
+        self.insert(receipt.claim_digest(), receipt);
+        Ok(())
+    }
+
+    fn contains(&self, claim_digest: &Digest) -> bool {
+        LruCache::contains(self, claim_digest)
+    }
+
+    fn pin(&mut self, claim_digest: Digest) {
+        LruCache::pin(self, claim_digest)
+    }
+
+    fn unpin(&mut self, claim_digest: &Digest) {
+        LruCache::unpin(self, claim_digest)
+    }
+}
+
+/// Receipts persisted as files in a directory, so that they survive restarts without being held in
+/// memory.
+///
+/// Each receipt is written to a file named after its claim digest. Files are only read when a
+/// receipt is requested, by mapping them into memory.
+pub struct FileReceiptStore {
+    dir: PathBuf,
+    verify_on_read: bool,
+}
+
+impl FileReceiptStore {
+    /// Open the store in `dir`, creating the directory if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create receipt store {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            verify_on_read: false,
+        })
+    }
+
+    /// Verify the integrity of each receipt as it is read, e.g. when the directory is shared with
+    /// other processes.
+    pub fn verify_on_read(mut self, verify_on_read: bool) -> Self {
+        self.verify_on_read = verify_on_read;
+        self
+    }
+
+    fn path(&self, claim_digest: &Digest) -> PathBuf {
+        self.dir.join(format!("{claim_digest}.receipt"))
+    }
+}
+
+impl ReceiptStore for FileReceiptStore {
+    fn get(&mut self, claim_digest: &Digest) -> Result<Option<SuccinctReceipt<Unknown>>> {
+        let path = self.path(claim_digest);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(&path)?;
+        // SAFETY: receipts are written to a temporary file and renamed into place, so a mapped
+        // file is never modified.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let receipt: SuccinctReceipt<Unknown> = bincode::deserialize(&mmap)
+            .with_context(|| format!("failed to read receipt {}", path.display()))?;
+        ensure!(
+            receipt.claim_digest() == *claim_digest,
+            "receipt {} is for claim {}",
+            path.display(),
+            receipt.claim_digest()
+        );
+        if self.verify_on_read {
+            receipt
+                .verify_integrity()
+                .with_context(|| format!("receipt {} failed to verify", path.display()))?;
+        }
+        Ok(Some(receipt))
+    }
+
+    fn put(&mut self, receipt: SuccinctReceipt<Unknown>) -> Result<()> {
+        let path = self.path(&receipt.claim_digest());
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, bincode::serialize(&receipt)?)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    fn contains(&self, claim_digest: &Digest) -> bool {
+        self.path(claim_digest).exists()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm::{sha::Digest, MaybePruned, ReceiptClaim, SuccinctReceipt, Unknown};
+    use serde_json::json;
+
+    use super::{FileReceiptStore, ReceiptStore};
+
+    /// A receipt naming a hash function that no verifier supports, so it fails to verify.
+    fn fake_receipt(seal: u32) -> SuccinctReceipt<Unknown> {
+        let receipt: SuccinctReceipt<ReceiptClaim> = serde_json::from_value(json!({
+            "seal": [seal],
+            "control_id": Digest::ZERO,
+            "claim": MaybePruned::Value(ReceiptClaim::ok(Digest::ZERO, vec![seal as u8])),
+            "hashfn": "none",
+            "verifier_parameters": Digest::ZERO,
+            "control_inclusion_proof": { "index": 0, "digests": [] },
+        }))
+        .unwrap();
+        receipt.into_unknown()
+    }
+
+    #[test]
+    fn persists_across_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let receipt = fake_receipt(1);
+        let claim_digest = receipt.claim_digest();
+
+        let mut store = FileReceiptStore::open(dir.path()).unwrap();
+        assert!(!store.contains(&claim_digest));
+        assert!(store.get(&claim_digest).unwrap().is_none());
+        store.put(receipt.clone()).unwrap();
+        drop(store);
+
+        let mut store = FileReceiptStore::open(dir.path()).unwrap();
+        assert!(store.contains(&claim_digest));
+        let loaded = store.get(&claim_digest).unwrap().unwrap();
+        assert_eq!(loaded.seal, receipt.seal);
+        assert_eq!(loaded.claim_digest(), claim_digest);
+        assert!(!store.contains(&fake_receipt(2).claim_digest()));
+    }
+
+    #[test]
+    fn verify_on_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let receipt = fake_receipt(1);
+        let claim_digest = receipt.claim_digest();
+        FileReceiptStore::open(dir.path())
+            .unwrap()
+            .put(receipt)
+            .unwrap();
+
+        // The receipt is only rejected when verifying.
+        let mut store = FileReceiptStore::open(dir.path()).unwrap();
+        assert!(store.get(&claim_digest).unwrap().is_some());
+        let mut store = store.verify_on_read(true);
+        let err = store.get(&claim_digest).unwrap_err();
+        assert!(err.to_string().contains("failed to verify"));
+    }
+}
@@ -13,24 +13,34 @@
 // limitations under the License.
 
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
-    sync::mpsc::{Receiver, Sender},
+    collections::{HashMap, HashSet},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
-use risc0_zkvm::{Asset, ReceiptClaim, SuccinctReceipt};
-use workerpool::Pool;
+use anyhow::{bail, Result};
+use risc0_zkvm::{
+    sha::Digest, Asset, CancellationToken, Cancelled, ReceiptClaim, SuccinctReceipt, Unknown,
+};
+use workerpool::{
+    thunk::{Thunk, ThunkWorker},
+    Pool,
+};
 
 use crate::{
-    plan::{Command, Task},
-    worker::Worker,
+    plan::{Command, PlanTask, Task, TaskGraph, TaskId},
+    worker::{self, ApiWorker, Worker},
 };
 
-type TaskNumber = usize;
-
 pub enum JobKind {
     Segment(Asset),
-    Join(Box<(SuccinctReceipt<ReceiptClaim>, SuccinctReceipt<ReceiptClaim>)>),
+    Join(Vec<SuccinctReceipt<ReceiptClaim>>),
+    Resolve(Box<(SuccinctReceipt<ReceiptClaim>, SuccinctReceipt<Unknown>)>),
     Receipt(Box<SuccinctReceipt<ReceiptClaim>>),
+    Failed(anyhow::Error),
 }
 
 pub struct Job {
@@ -38,102 +48,913 @@ pub struct Job {
     pub kind: JobKind,
 }
 
-pub struct TaskManager {
+/// A task whose job returned an error.
+#[derive(Debug)]
+pub struct TaskFailure {
+    pub task: Task,
+    pub error: anyhow::Error,
+}
+
+/// A segment receipt which failed to verify when [TaskManager::verify_segments] is enabled.
+///
+/// [TaskManager::run] returns this as its error, rather than reporting it in
+/// [RunResult::Failed], since proving the segment again is not expected to help.
+#[derive(Debug)]
+pub struct InvalidSegment {
+    pub segment_idx: u32,
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for InvalidSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "segment {} receipt failed to verify: {:#}",
+            self.segment_idx, self.error
+        )
+    }
+}
+
+impl std::error::Error for InvalidSegment {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// The outcome of [TaskManager::run].
+#[derive(Debug)]
+pub enum RunResult {
+    /// Every task succeeded, producing a receipt for the whole session.
+    Complete(Box<SuccinctReceipt<ReceiptClaim>>),
+
+    /// Some tasks failed, so the tasks depending on them were not run. The receipts of the tasks
+    /// that succeeded are kept by the manager, so [TaskManager::retry_failed] only runs the failed
+    /// tasks again.
+    Failed(Vec<TaskFailure>),
+}
+
+impl RunResult {
+    /// Indices of the segments whose proofs failed.
+    pub fn failed_segments(&self) -> Vec<u32> {
+        match self {
+            Self::Complete(_) => Vec::new(),
+            Self::Failed(failures) => failures
+                .iter()
+                .filter_map(|failure| failure.task.segment_idx)
+                .collect(),
+        }
+    }
+
+    /// Returns the receipt for the session, or an error describing the tasks that failed.
+    pub fn into_receipt(self) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        match self {
+            Self::Complete(receipt) => Ok(*receipt),
+            Self::Failed(failures) => {
+                let errors: Vec<_> = failures
+                    .iter()
+                    .map(|failure| {
+                        format!("task {}: {:#}", failure.task.task_number, failure.error)
+                    })
+                    .collect();
+                bail!("{} task(s) failed: {}", failures.len(), errors.join("; "))
+            }
+        }
+    }
+}
+
+/// A join completed by a [TaskManager], as reported to the hook set with [TaskManager::on_join].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct JoinEvent {
+    /// The task whose receipt was joined on the left. When joining more than two receipts, this
+    /// is the first of them.
+    pub left_task: TaskId,
+
+    /// The task whose receipt was joined on the right. When joining more than two receipts, this
+    /// is the last of them.
+    pub right_task: TaskId,
+
+    /// The join task itself, whose receipt covers the segments of both sides.
+    pub result_task: TaskId,
+
+    /// How many join tasks of the plan, as known to the manager, are yet to complete. This is
+    /// zero for the join producing the root of the tree once the whole plan has been added.
+    pub remaining_joins: usize,
+}
+
+/// The hook set with [TaskManager::on_join].
+type JoinHook = Box<dyn FnMut(&JoinEvent) + Send>;
+
+/// The hook set with [TaskManager::on_task_complete].
+type TaskHook = Box<dyn FnMut(&Task) + Send>;
+
+/// The order in which a [TaskManager] runs the tasks that are ready.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Schedule {
+    /// Run tasks in the order they became ready.
+    #[default]
+    Fifo,
+
+    /// Run the tasks with the longest chain of dependent tasks first, as given by
+    /// [TaskGraph::critical_path_lengths]. Segments feeding the deepest join subtree are proven
+    /// first, so the final receipt is produced sooner when there are more ready tasks than
+    /// workers.
+    CriticalPathFirst,
+}
+
+/// Jobs waiting for a thread in the pool.
+///
+/// Each job dispatched to the pool is added here, and the thread that runs it takes whichever
+/// queued job the [Schedule] prefers. The order is therefore decided once a thread is free, using
+/// the plan as it is known at that point.
+struct JobQueue {
+    schedule: Schedule,
+    graph: TaskGraph,
+    jobs: Vec<Job>,
+}
+
+impl JobQueue {
+    fn push(&mut self, job: Job) {
+        self.jobs.push(job);
+    }
+
+    fn pop(&mut self) -> Job {
+        let idx = match self.schedule {
+            Schedule::Fifo => 0,
+            Schedule::CriticalPathFirst => {
+                let lengths = self.graph.critical_path_lengths();
+                // Ties are broken in the order the jobs were queued.
+                (0..self.jobs.len())
+                    .rev()
+                    .max_by_key(|&idx| lengths.get(&self.jobs[idx].task.task_number))
+                    .unwrap()
+            }
+        };
+        self.jobs.remove(idx)
+    }
+}
+
+/// How long [TaskManager::shutdown] waits for in-flight jobs to complete.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Schedules the tasks of a plan onto a pool of threads as their dependencies complete, proving
+/// each with a [Worker].
+///
+/// At most `max_concurrency` jobs are run at once; any further ready tasks are queued until a
+/// worker becomes available, then run in the order given by the [Schedule].
+///
+/// A task whose job fails does not stop the run; the tasks that do not depend on it still run,
+/// and the failure is reported once they have completed. See [TaskManager::retry_failed].
+///
+/// The run can be stopped with the token returned by [TaskManager::cancellation_token]. Jobs that
+/// are already running are allowed to finish, but no new ones are started.
+///
+/// Stopping the manager, either by calling [TaskManager::shutdown] or by dropping it, stops
+/// dispatching new tasks and waits for the jobs in flight to complete. Dropping it waits with the
+/// same timeout as [TaskManager::shutdown] and logs a warning if jobs are still running.
+pub struct TaskManager<W: Worker = ApiWorker> {
     segments: HashMap<u32, Asset>,
-    receipts: HashMap<TaskNumber, SuccinctReceipt<ReceiptClaim>>,
-    pending_tasks: BTreeMap<TaskNumber, Task>,
-    completed: HashSet<TaskNumber>,
-    pool: Pool<Worker>,
+    assumptions: HashMap<Digest, SuccinctReceipt<Unknown>>,
+    receipts: HashMap<TaskId, SuccinctReceipt<ReceiptClaim>>,
+    queue: Arc<Mutex<JobQueue>>,
+    dispatched: HashSet<TaskId>,
+    completed: HashSet<TaskId>,
+    failed: Vec<Task>,
+    failures: Vec<TaskFailure>,
+    invalid: Option<anyhow::Error>,
+    resolved: usize,
+    worker: Arc<W>,
+    pool: Pool<ThunkWorker<Job>>,
     job_tx: Sender<Job>,
     job_rx: Receiver<Job>,
+    cancel: CancellationToken,
+    verify_segments: bool,
+    high_water_mark: Option<usize>,
+    on_join: Option<JoinHook>,
+    on_task_complete: Option<TaskHook>,
+    shut_down: bool,
 }
 
 impl TaskManager {
-    pub fn new() -> Self {
+    pub fn with_max_concurrency(max_concurrency: usize) -> Self {
+        Self::new(ApiWorker, max_concurrency)
+    }
+}
+
+impl<W: Worker> TaskManager<W> {
+    /// Create a manager which proves with `worker`, running at most `max_concurrency` jobs at once.
+    pub fn new(worker: W, max_concurrency: usize) -> Self {
         let (job_tx, job_rx) = std::sync::mpsc::channel();
         Self {
+            worker: Arc::new(worker),
+            pool: Pool::new(max_concurrency.max(1)),
             segments: HashMap::new(),
+            assumptions: HashMap::new(),
             receipts: HashMap::new(),
-            pending_tasks: BTreeMap::new(),
+            queue: Arc::new(Mutex::new(JobQueue {
+                schedule: Schedule::default(),
+                graph: TaskGraph::default(),
+                jobs: Vec::new(),
+            })),
+            dispatched: HashSet::new(),
             completed: HashSet::new(),
-            pool: Pool::new(1),
+            failed: Vec::new(),
+            failures: Vec::new(),
+            invalid: None,
+            resolved: 0,
             job_tx,
             job_rx,
+            cancel: CancellationToken::new(),
+            verify_segments: false,
+            high_water_mark: None,
+            on_join: None,
+            on_task_complete: None,
+            shut_down: false,
         }
     }
 
+    /// Verify each segment receipt as soon as it is proven, before it is lifted.
+    ///
+    /// The first receipt that fails to verify stops the run: no further tasks are started, and
+    /// once the jobs already running complete, [TaskManager::run] returns an [InvalidSegment]
+    /// identifying the segment.
+    pub fn verify_segments(mut self, verify_segments: bool) -> Self {
+        self.verify_segments = verify_segments;
+        self
+    }
+
+    /// Hold at most `high_water_mark` segments that have not yet been proven, so that execution is
+    /// paced by proving rather than buffering every segment in memory.
+    ///
+    /// The limit is applied by [TaskManager::wait_for_capacity], which the execute callback should
+    /// call before each [TaskManager::add_segment].
+    pub fn with_high_water_mark(mut self, high_water_mark: usize) -> Self {
+        self.high_water_mark = Some(high_water_mark.max(1));
+        self
+    }
+
+    /// Choose the order in which ready tasks are run. The default is [Schedule::Fifo].
+    pub fn with_schedule(self, schedule: Schedule) -> Self {
+        self.queue.lock().unwrap().schedule = schedule;
+        self
+    }
+
+    /// Call `on_join` each time a join task completes, e.g. to show progress towards the final
+    /// receipt.
+    pub fn on_join(mut self, on_join: impl FnMut(&JoinEvent) + Send + 'static) -> Self {
+        self.on_join = Some(Box::new(on_join));
+        self
+    }
+
+    /// Call `on_task_complete` each time a task of the plan completes, e.g. to update a
+    /// [ProgressTracker](crate::progress::ProgressTracker).
+    pub fn on_task_complete(
+        mut self,
+        on_task_complete: impl FnMut(&Task) + Send + 'static,
+    ) -> Self {
+        self.on_task_complete = Some(Box::new(on_task_complete));
+        self
+    }
+
+    /// Add the segment with index `idx`, as handed to the execute callback.
+    ///
+    /// The asset is stored as is. An [Asset::Inline] segment shares its buffer with the callback
+    /// and with each job that proves it, so it is never copied by the manager.
     pub fn add_segment(&mut self, idx: u32, segment: Asset) {
         self.segments.insert(idx, segment);
     }
 
+    /// Block until there is room for another segment below the high-water mark set with
+    /// [TaskManager::with_high_water_mark], completing proving jobs in the meantime.
+    ///
+    /// Called from the execute callback, this blocks execution until proving catches up. Returns
+    /// immediately if no high-water mark was set, or if no running job could make room, e.g.
+    /// because the remaining segments failed or the run was cancelled.
+    pub fn wait_for_capacity(&mut self) -> Result<()> {
+        let Some(high_water_mark) = self.high_water_mark else {
+            return Ok(());
+        };
+        while self.unproven_segments() >= high_water_mark
+            && self.in_flight() > 0
+            && !self.cancel.is_cancelled()
+        {
+            let job = self.job_rx.recv()?;
+            self.complete_job(job)?;
+        }
+        Ok(())
+    }
+
+    /// The number of segments added which have not yet been proven.
+    pub fn unproven_segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn add_assumption(&mut self, claim_digest: Digest, receipt: SuccinctReceipt<Unknown>) {
+        self.assumptions.insert(claim_digest, receipt);
+    }
+
+    /// A handle which stops [TaskManager::run] when cancelled.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
     pub fn add_task(&mut self, task: Task) {
-        let deps = HashSet::from_iter(task.depends_on.iter().cloned());
-        if self.completed.is_superset(&deps) {
+        self.queue.lock().unwrap().graph.insert(task.clone());
+        if task.is_ready(&self.completed) && !self.cancel.is_cancelled() {
             self.run_task(task);
-        } else {
-            self.pending_tasks.insert(task.task_number, task);
         }
     }
 
-    pub fn run(&mut self) -> SuccinctReceipt<ReceiptClaim> {
-        let mut root_receipt = None;
-        for job in self.job_rx.iter() {
-            let job_id = job.task.task_number;
-            let receipt = match job.kind {
-                JobKind::Receipt(receipt) => receipt.clone(),
-                _ => unreachable!(),
+    /// Run the plan until the receipt for the whole session is produced, or until every task that
+    /// can run has completed after some have failed.
+    ///
+    /// Returns an [InvalidSegment] error if a segment receipt fails to verify; see
+    /// [TaskManager::verify_segments].
+    pub fn run(&mut self) -> Result<RunResult> {
+        loop {
+            // Once cancelled or failed, wait for the jobs still running so that none of them is
+            // interrupted part way.
+            if self.in_flight() == 0 {
+                if self.cancel.is_cancelled() {
+                    return Err(Cancelled.into());
+                }
+                if let Some(error) = self.invalid.take() {
+                    return Err(error);
+                }
+                if !self.failures.is_empty() {
+                    return Ok(RunResult::Failed(std::mem::take(&mut self.failures)));
+                }
+            }
+            let job = self.job_rx.recv()?;
+            if let Some(receipt) = self.complete_job(job)? {
+                return Ok(RunResult::Complete(receipt));
+            }
+        }
+    }
+
+    /// Run the tasks that failed during the last [TaskManager::run] again, then continue running
+    /// the plan as [TaskManager::run] does.
+    ///
+    /// Tasks that succeeded are not run again; their receipts are used as the plan resumes.
+    pub fn retry_failed(&mut self) -> Result<RunResult> {
+        if !self.cancel.is_cancelled() {
+            for task in std::mem::take(&mut self.failed) {
+                self.dispatched.remove(&task.task_number);
+                self.run_task(task);
+            }
+        }
+        self.run()
+    }
+
+    /// Stop dispatching new tasks and wait for the jobs already handed to the pool to complete.
+    ///
+    /// Each job waits for the server processes it starts to exit, so once every job has completed
+    /// none of them are left running. Returns an error if jobs are still running after the timeout;
+    /// those jobs are left to finish in the background.
+    pub fn shutdown(self) -> Result<()> {
+        self.shutdown_timeout(SHUTDOWN_TIMEOUT)
+    }
+
+    /// Like [TaskManager::shutdown], but waits at most `timeout` for in-flight jobs.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Result<()> {
+        self.shut_down = true;
+        self.drain(timeout)
+    }
+
+    fn drain(&mut self, timeout: Duration) -> Result<()> {
+        self.cancel.cancel();
+        let deadline = Instant::now() + timeout;
+        while self.in_flight() > 0 {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Ok(job) = self.job_rx.recv_timeout(remaining) else {
+                bail!(
+                    "{} job(s) still running after {timeout:?}",
+                    self.in_flight()
+                );
             };
-            self.receipts.insert(job_id, *receipt.clone());
-            self.completed.insert(job_id);
-            let ready_tasks = self.collect_ready_tasks();
-            for next_task in ready_tasks {
-                self.pending_tasks.remove(&next_task.task_number);
-                self.run_task(next_task);
+            self.completed.insert(job.task.task_number);
+        }
+        Ok(())
+    }
+
+    /// Record the outcome of a job returned by the pool, and run the tasks that it made ready.
+    ///
+    /// Returns the receipt for the whole session once the finalize task completes.
+    fn complete_job(&mut self, job: Job) -> Result<Option<Box<SuccinctReceipt<ReceiptClaim>>>> {
+        let task = job.task;
+        let receipt = match job.kind {
+            JobKind::Receipt(receipt) => receipt,
+            JobKind::Failed(error) => {
+                self.failed.push(task.clone());
+                if error.is::<InvalidSegment>() {
+                    tracing::error!("Task {} failed: {error:#}", task.task_number);
+                    self.invalid.get_or_insert(error);
+                } else {
+                    tracing::warn!("Task {} failed: {error:#}", task.task_number);
+                    self.failures.push(TaskFailure { task, error });
+                }
+                return Ok(None);
             }
-            if job.task.command == Command::Finalize {
-                root_receipt = Some(receipt);
-                break;
+            _ => unreachable!(),
+        };
+        for step in task.steps() {
+            match step {
+                PlanTask::ProveSegment { idx } => {
+                    // Each segment is only proven once, so the manager can release it now.
+                    // Segments written to disk by the server are no longer needed either.
+                    if let Some(Asset::Path(path)) = self.segments.remove(&idx) {
+                        std::fs::remove_file(path)?;
+                    }
+                }
+                PlanTask::Resolve { assumption, .. } => {
+                    self.resolved += 1;
+                    tracing::info!(
+                        "Resolved assumption {}/{}: {assumption}",
+                        self.resolved,
+                        self.assumptions.len(),
+                    );
+                }
+                _ => {}
             }
         }
-        *root_receipt.unwrap()
+        self.receipts.insert(task.task_number, *receipt.clone());
+        self.completed.insert(task.task_number);
+        if task.command == Command::Join {
+            self.report_join(&task);
+        }
+        if let Some(on_task_complete) = self.on_task_complete.as_mut() {
+            on_task_complete(&task);
+        }
+        if self.cancel.is_cancelled() || self.invalid.is_some() {
+            return Ok(None);
+        }
+        let ready_tasks = self.collect_ready_tasks();
+        for next_task in ready_tasks {
+            self.run_task(next_task);
+        }
+        Ok((task.command == Command::Finalize).then_some(receipt))
     }
 
-    fn collect_ready_tasks(&self) -> Vec<Task> {
-        self.pending_tasks
-            .values()
+    fn in_flight(&self) -> usize {
+        self.dispatched.len() - self.completed.len() - self.failed.len()
+    }
+
+    fn report_join(&mut self, task: &Task) {
+        let Some(on_join) = self.on_join.as_mut() else {
+            return;
+        };
+        let remaining_joins = self
+            .queue
+            .lock()
+            .unwrap()
+            .graph
+            .tasks()
+            .iter()
             .filter(|task| {
-                let deps = HashSet::from_iter(task.depends_on.iter().cloned());
-                self.completed.is_superset(&deps)
+                task.command == Command::Join && !self.completed.contains(&task.task_number)
             })
+            .count();
+        on_join(&JoinEvent {
+            left_task: task.depends_on[0],
+            right_task: *task.depends_on.last().unwrap(),
+            result_task: task.task_number,
+            remaining_joins,
+        });
+    }
+
+    fn collect_ready_tasks(&self) -> Vec<Task> {
+        self.queue
+            .lock()
+            .unwrap()
+            .graph
+            .ready(&self.completed)
+            .filter(|task| !self.dispatched.contains(&task.task_number))
             .cloned()
             .collect()
     }
 
-    fn run_task(&self, task: Task) {
-        let job = match task.command {
-            Command::Segment => {
-                let segment = self.segments.get(&task.segment_idx.unwrap()).unwrap();
-                Job {
-                    task,
-                    kind: JobKind::Segment(segment.clone()),
-                }
+    fn run_task(&mut self, task: Task) {
+        self.dispatched.insert(task.task_number);
+        // The inputs of a task are those of its first step; a segment is lifted as soon as it has
+        // been proven, by the same job.
+        let kind = match task.steps()[0] {
+            PlanTask::ProveSegment { idx } => {
+                // The segment is kept until it has been proven, in case the job fails.
+                JobKind::Segment(self.segments.get(&idx).unwrap().clone())
             }
-            Command::Join => {
-                let left = self.receipts.get(&task.depends_on[0]).unwrap();
-                let right = self.receipts.get(&task.depends_on[1]).unwrap();
-                Job {
-                    task,
-                    kind: JobKind::Join(Box::new((left.clone(), right.clone()))),
-                }
+            PlanTask::Join { .. } => JobKind::Join(
+                task.depends_on
+                    .iter()
+                    .map(|child| self.receipts.get(child).unwrap().clone())
+                    .collect(),
+            ),
+            PlanTask::Resolve {
+                conditional,
+                assumption,
+            } => {
+                let conditional = self.receipts.get(&conditional).unwrap();
+                let assumption = self.assumptions.get(&assumption).unwrap();
+                JobKind::Resolve(Box::new((conditional.clone(), assumption.clone())))
+            }
+            PlanTask::Finalize { receipt } => {
+                JobKind::Receipt(Box::new(self.receipts.get(&receipt).unwrap().clone()))
+            }
+            PlanTask::Lift { .. } => unreachable!("a segment is lifted after it is proven"),
+        };
+        let job = Job { task, kind };
+        self.queue.lock().unwrap().push(job);
+        let queue = self.queue.clone();
+        let worker = self.worker.clone();
+        let verify_segments = self.verify_segments;
+        self.pool.execute_to(
+            self.job_tx.clone(),
+            Thunk::of(move || {
+                let job = queue.lock().unwrap().pop();
+                worker::execute(worker.as_ref(), job, verify_segments)
+            }),
+        );
+    }
+}
+
+impl<W: Worker> Drop for TaskManager<W> {
+    fn drop(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        if let Err(err) = self.drain(SHUTDOWN_TIMEOUT) {
+            tracing::warn!("TaskManager dropped without shutdown: {err:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use risc0_zkvm::{sha::Digest, Asset, Bytes, Cancelled, ReceiptClaim};
+
+    use super::{InvalidSegment, Job, JobKind, JoinEvent, RunResult, Schedule, TaskManager};
+    use crate::{
+        mock::{succinct_receipt, MockWorker},
+        plan::{Planner, Task},
+        progress::ProgressTracker,
+    };
+
+    /// A worker which sleeps for a while in place of each job, recording the most jobs it ran at
+    /// once in `max_in_flight`.
+    fn counting_worker(max_in_flight: Arc<AtomicUsize>) -> MockWorker {
+        let in_flight = AtomicUsize::new(0);
+        MockWorker::default().with_hook(move || {
+            let count = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(count, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+        })
+    }
+
+    /// Returns the order in which the segments of an unbalanced plan are proven with `schedule`.
+    ///
+    /// Segments 2 and 3 are at the bottom of the deepest join subtree, while segment 1 is joined
+    /// one level higher and segment 0 only at the root.
+    fn proving_order(schedule: Schedule) -> Vec<u32> {
+        let mut task_manager =
+            TaskManager::new(MockWorker::default().hold(0), 1).with_schedule(schedule);
+        for idx in 0..4 {
+            task_manager.add_segment(idx, Asset::Inline(vec![idx as u8].into()));
+            task_manager.add_task(Task::new_segment(idx as usize, idx));
+        }
+        task_manager.add_task(Task::new_join(4, 1, vec![2, 3]));
+        task_manager.add_task(Task::new_join(5, 2, vec![1, 4]));
+        task_manager.add_task(Task::new_join(6, 3, vec![0, 5]));
+        task_manager.add_task(Task::new_finalize(7, 4, 6));
+        task_manager.worker.release();
+        task_manager.run().unwrap().into_receipt().unwrap();
+        let proven = task_manager.worker.proven.lock().unwrap().clone();
+        proven
+    }
+
+    #[test]
+    fn max_concurrency() {
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let mut task_manager = TaskManager::new(counting_worker(max_in_flight.clone()), 2);
+        for idx in 0..8 {
+            task_manager.add_segment(idx, Asset::Inline(Default::default()));
+            task_manager.add_task(Task::new_segment(idx as usize, idx));
+        }
+        task_manager.shutdown().unwrap();
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn with_max_concurrency() {
+        // The limit on jobs in flight is checked by `max_concurrency`; this only covers the pool
+        // size of a manager proving with the API worker.
+        assert_eq!(TaskManager::with_max_concurrency(0).pool.max_count(), 1);
+        assert_eq!(TaskManager::with_max_concurrency(2).pool.max_count(), 2);
+    }
+
+    #[test]
+    fn cancel_before_run() {
+        let mut task_manager = TaskManager::new(MockWorker::default(), 2);
+        task_manager.cancellation_token().cancel();
+        for idx in 0..8 {
+            task_manager.add_segment(idx, Asset::Inline(Default::default()));
+            task_manager.add_task(Task::new_segment(idx as usize, idx));
+        }
+        let err = task_manager.run().unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert!(task_manager.dispatched.is_empty());
+    }
+
+    #[test]
+    fn shutdown_drains_jobs() {
+        // A worker which counts the jobs it has finished.
+        let finished_worker = |finished: Arc<AtomicUsize>| {
+            MockWorker::default().with_hook(move || {
+                thread::sleep(Duration::from_millis(50));
+                finished.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let add_segments = |task_manager: &mut TaskManager<MockWorker>| {
+            for idx in 0..4 {
+                task_manager.add_segment(idx, Asset::Inline(Default::default()));
+                task_manager.add_task(Task::new_segment(idx as usize, idx));
+            }
+            assert_eq!(task_manager.dispatched.len(), 4);
+        };
+
+        // Every job handed to the pool has finished once shutdown returns.
+        let finished = Arc::new(AtomicUsize::new(0));
+        let mut task_manager = TaskManager::new(finished_worker(finished.clone()), 2);
+        add_segments(&mut task_manager);
+        task_manager.shutdown().unwrap();
+        assert_eq!(finished.load(Ordering::SeqCst), 4);
+
+        // Dropping the manager waits for them as well.
+        let finished = Arc::new(AtomicUsize::new(0));
+        let mut task_manager = TaskManager::new(finished_worker(finished.clone()), 2);
+        add_segments(&mut task_manager);
+        drop(task_manager);
+        assert_eq!(finished.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn shutdown_timeout() {
+        let mut task_manager = TaskManager::new(
+            MockWorker::default().with_delay(Duration::from_millis(50)),
+            1,
+        );
+        for idx in 0..4 {
+            task_manager.add_segment(idx, Asset::Inline(Default::default()));
+            task_manager.add_task(Task::new_segment(idx as usize, idx));
+        }
+        let err = task_manager.shutdown_timeout(Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("still running"));
+    }
+
+    #[test]
+    fn join_children_concurrently() {
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let worker = counting_worker(max_in_flight.clone());
+        let children = (0..5)
+            .map(|idx| succinct_receipt(vec![idx], ReceiptClaim::ok(Digest::ZERO, vec![])))
+            .collect();
+        let job = Job {
+            task: Task::new_segment(0, 0),
+            kind: JobKind::Join(children),
+        };
+        let job = crate::worker::execute(&worker, job, false);
+        assert!(matches!(job.kind, JobKind::Receipt(_)));
+
+        // The first level of the tree joins two independent pairs at once.
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn custom_worker() {
+        let mut task_manager = TaskManager::new(MockWorker::default(), 2);
+        let mut planner = Planner::default();
+        for idx in 0..4 {
+            planner.enqueue_segment(idx, 20).unwrap();
+            task_manager.add_segment(idx, Asset::Inline(vec![idx as u8].into()));
+        }
+        let assumption = succinct_receipt(vec![4], ReceiptClaim::ok(Digest::ZERO, vec![4]));
+        let claim_digest = assumption.claim_digest();
+        planner.enqueue_assumption(claim_digest).unwrap();
+        task_manager.add_assumption(claim_digest, assumption.into_unknown());
+        planner.finish().unwrap();
+        while let Some(task) = planner.next_task() {
+            task_manager.add_task(task.clone());
+        }
+
+        // The segments are joined in order, then the assumption is resolved.
+        let receipt = task_manager.run().unwrap().into_receipt().unwrap();
+        assert_eq!(receipt.seal, [0, 1, 2, 3, 4]);
+        let mut calls = task_manager.worker.calls.lock().unwrap().clone();
+        calls.sort();
+        let expected = [
+            ("join", 3),
+            ("lift", 4),
+            ("prove_segment", 4),
+            ("resolve", 1),
+        ]
+        .into_iter()
+        .flat_map(|(call, count)| std::iter::repeat(call).take(count))
+        .collect::<Vec<_>>();
+        assert_eq!(calls, expected);
+    }
+
+    #[test]
+    fn join_events() {
+        for segments in [1, 2, 5, 8] {
+            let events = Arc::new(Mutex::new(Vec::<JoinEvent>::new()));
+            let recorded = events.clone();
+            let mut task_manager = TaskManager::new(MockWorker::default(), 2)
+                .on_join(move |event| recorded.lock().unwrap().push(*event));
+            let mut planner = Planner::default();
+            for idx in 0..segments {
+                planner.enqueue_segment(idx, 20).unwrap();
+                task_manager.add_segment(idx, Asset::Inline(vec![idx as u8].into()));
+            }
+            planner.finish().unwrap();
+            while let Some(task) = planner.next_task() {
+                task_manager.add_task(task.clone());
+            }
+            task_manager.run().unwrap().into_receipt().unwrap();
+
+            // A binary join tree over N segments has N - 1 joins, counting down to the root.
+            let events = events.lock().unwrap();
+            assert_eq!(events.len(), segments as usize - 1);
+            let remaining: Vec<_> = events.iter().map(|event| event.remaining_joins).collect();
+            assert_eq!(remaining, (0..events.len()).rev().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn progress_reaches_complete() {
+        let progress = Arc::new(Mutex::new(
+            ProgressTracker::default().with_expected_cycles(5 * 1000),
+        ));
+        let fractions = Arc::new(Mutex::new(vec![0.0]));
+        let (task_progress, recorded) = (progress.clone(), fractions.clone());
+        let mut task_manager =
+            TaskManager::new(MockWorker::default(), 2).on_task_complete(move |_| {
+                let mut progress = task_progress.lock().unwrap();
+                progress.on_task_complete();
+                recorded.lock().unwrap().push(progress.fraction_complete());
+            });
+        let mut planner = Planner::default();
+        for idx in 0..5 {
+            planner.enqueue_segment(idx, 20).unwrap();
+            task_manager.add_segment(idx, Asset::Inline(vec![idx as u8].into()));
+            let mut progress = progress.lock().unwrap();
+            progress.on_segment(1000);
+            assert!(progress.proving_fraction().is_none());
+            fractions.lock().unwrap().push(progress.fraction_complete());
+        }
+        planner.finish().unwrap();
+        while let Some(task) = planner.next_task() {
+            task_manager.add_task(task.clone());
+        }
+        {
+            let mut progress = progress.lock().unwrap();
+            assert_eq!(progress.execution_fraction(), 1.0);
+            progress.on_plan_finished(planner.task_graph().tasks().len());
+            fractions.lock().unwrap().push(progress.fraction_complete());
+        }
+        task_manager.run().unwrap().into_receipt().unwrap();
+
+        let fractions = fractions.lock().unwrap();
+        assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(fractions[1] > 0.0);
+        assert_eq!(*fractions.last().unwrap(), 1.0);
+    }
+
+    #[test]
+    fn high_water_mark() {
+        // Segments are added faster than a single worker proves them. Returns the most segments
+        // the manager held unproven at once.
+        let max_unproven = |high_water_mark: Option<usize>| {
+            let worker = MockWorker::default().with_delay(Duration::from_millis(10));
+            let mut task_manager = TaskManager::new(worker, 1);
+            if let Some(high_water_mark) = high_water_mark {
+                task_manager = task_manager.with_high_water_mark(high_water_mark);
             }
-            Command::Finalize => {
-                let receipt = self.receipts.get(&task.depends_on[0]).unwrap();
-                Job {
-                    task,
-                    kind: JobKind::Receipt(Box::new(receipt.clone())),
+            let mut planner = Planner::default();
+            let mut max_unproven = 0;
+            for idx in 0..8 {
+                planner.enqueue_segment(idx, 20).unwrap();
+                task_manager.wait_for_capacity().unwrap();
+                task_manager.add_segment(idx, Asset::Inline(vec![idx as u8].into()));
+                while let Some(task) = planner.next_task() {
+                    task_manager.add_task(task.clone());
                 }
+                max_unproven = max_unproven.max(task_manager.unproven_segments());
+            }
+            planner.finish().unwrap();
+            while let Some(task) = planner.next_task() {
+                task_manager.add_task(task.clone());
             }
+            task_manager.run().unwrap().into_receipt().unwrap();
+            assert_eq!(task_manager.unproven_segments(), 0);
+            max_unproven
+        };
+        assert!(max_unproven(None) > 2);
+        assert_eq!(max_unproven(Some(2)), 2);
+    }
+
+    #[test]
+    fn retry_failed_segment() {
+        let mut task_manager = TaskManager::new(MockWorker::default().fail_once(2), 2);
+        let mut planner = Planner::default();
+        for idx in 0..4 {
+            planner.enqueue_segment(idx, 20).unwrap();
+            task_manager.add_segment(idx, Asset::Inline(vec![idx as u8].into()));
+        }
+        planner.finish().unwrap();
+        while let Some(task) = planner.next_task() {
+            task_manager.add_task(task.clone());
+        }
+
+        let result = task_manager.run().unwrap();
+        assert_eq!(result.failed_segments(), [2]);
+        let RunResult::Failed(failures) = result else {
+            panic!("expected the run to fail");
         };
-        self.pool.execute_to(self.job_tx.clone(), job);
+        assert_eq!(failures[0].error.to_string(), "transient failure");
+
+        // Only the failed segment is proven again, and joining resumes from the kept receipts.
+        let receipt = task_manager.retry_failed().unwrap().into_receipt().unwrap();
+        assert_eq!(receipt.seal, [0, 1, 2, 3]);
+        let mut proven = task_manager.worker.proven.lock().unwrap().clone();
+        proven.sort();
+        assert_eq!(proven, [0, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn verify_segments_fails_fast() {
+        let mut task_manager =
+            TaskManager::new(MockWorker::default().corrupt(1), 1).verify_segments(true);
+        let mut planner = Planner::default();
+        for idx in 0..4 {
+            planner.enqueue_segment(idx, 20).unwrap();
+            task_manager.add_segment(idx, Asset::Inline(vec![idx as u8].into()));
+        }
+        planner.finish().unwrap();
+        while let Some(task) = planner.next_task() {
+            task_manager.add_task(task.clone());
+        }
+
+        let err = task_manager.run().unwrap_err();
+        let invalid = err.downcast_ref::<InvalidSegment>().unwrap();
+        assert_eq!(invalid.segment_idx, 1);
+        assert_eq!(invalid.error.to_string(), "invalid seal");
+        assert!(!task_manager.worker.calls.lock().unwrap().contains(&"join"));
+    }
+
+    #[test]
+    fn critical_path_first() {
+        let position = |order: &[u32], idx| order.iter().position(|&x| x == idx).unwrap();
+
+        let fifo = proving_order(Schedule::Fifo);
+        assert_eq!(fifo, [0, 1, 2, 3]);
+
+        // Segment 2 feeds the deepest subtree, so it is proven before the segments queued ahead of
+        // it. Segment 0 may already have been taken by the worker when the others were queued.
+        let critical_path = proving_order(Schedule::CriticalPathFirst);
+        assert!(position(&critical_path, 2) < position(&fifo, 2));
+        assert!(position(&critical_path, 2) < position(&critical_path, 1));
+        assert!(position(&critical_path, 3) < position(&critical_path, 1));
+    }
+
+    #[test]
+    fn inline_segments_are_not_copied() {
+        let mut task_manager = TaskManager::new(MockWorker::default(), 2);
+        let mut planner = Planner::default();
+        let mut addresses = Vec::new();
+        for idx in 0..4 {
+            // Stands in for the segment handed to the execute callback.
+            let segment = Bytes::from(vec![idx as u8; 1 << 16]);
+            addresses.push(segment.as_ptr() as usize);
+            planner.enqueue_segment(idx, 20).unwrap();
+            task_manager.add_segment(idx, Asset::Inline(segment));
+        }
+        planner.finish().unwrap();
+        while let Some(task) = planner.next_task() {
+            task_manager.add_task(task.clone());
+        }
+
+        // Each job is handed the same buffer that the callback saw.
+        task_manager.run().unwrap().into_receipt().unwrap();
+        let mut proven = task_manager.worker.addresses.lock().unwrap().clone();
+        proven.sort();
+        addresses.sort();
+        assert_eq!(proven, addresses);
     }
 }
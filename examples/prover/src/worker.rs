@@ -12,62 +12,173 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use risc0_zkvm::{ApiClient, Asset, AssetRequest, ProverOpts, ReceiptClaim, SuccinctReceipt};
+use std::{panic::AssertUnwindSafe, thread};
 
-use crate::task_mgr::{Job, JobKind};
+use anyhow::Result;
+use risc0_zkvm::{
+    ApiClient, Asset, AssetRequest, ProverOpts, ReceiptClaim, SegmentReceipt, SuccinctReceipt,
+    Unknown, VerifierContext,
+};
 
-#[derive(Default)]
-pub struct Worker;
+use crate::task_mgr::{InvalidSegment, Job, JobKind};
 
-impl workerpool::Worker for Worker {
-    type Input = Job;
-    type Output = Job;
+/// A backend which proves the jobs scheduled by a [TaskManager](crate::task_mgr::TaskManager).
+///
+/// Implement this to prove on other hardware or with a remote service while reusing the
+/// scheduling of the task manager. Methods are called concurrently from the manager's pool of
+/// threads. A job whose method returns an error is reported as failed by
+/// [TaskManager::run](crate::task_mgr::TaskManager::run), and can be attempted again with
+/// [TaskManager::retry_failed](crate::task_mgr::TaskManager::retry_failed).
+pub trait Worker: Send + Sync + 'static {
+    /// Prove a segment written by the executor.
+    fn prove_segment(&self, segment: Asset) -> Result<SegmentReceipt>;
 
-    fn execute(&mut self, job: Job) -> Job {
-        println!("{:?}", job.task);
-        std::panic::catch_unwind(|| {
-            let receipt = match job.kind {
-                JobKind::Segment(segment) => self.prove_and_lift(segment),
-                JobKind::Join(pair) => self.join(pair.0, pair.1),
-                JobKind::Receipt(receipt) => *receipt,
-            };
-            Job {
-                task: job.task,
-                kind: JobKind::Receipt(Box::new(receipt)),
-            }
-        })
-        .unwrap_or_else(|_| {
-            std::process::abort();
-        })
+    /// Verify a receipt returned by [Worker::prove_segment].
+    ///
+    /// Only called when [TaskManager::verify_segments](crate::task_mgr::TaskManager::verify_segments)
+    /// is enabled. The default verifies the receipt against the default [VerifierContext].
+    fn verify_segment(&self, receipt: &SegmentReceipt) -> Result<()> {
+        Ok(receipt.verify(&VerifierContext::default())?)
     }
+
+    /// Lift a [SegmentReceipt] into a [SuccinctReceipt].
+    fn lift(&self, receipt: SegmentReceipt) -> Result<SuccinctReceipt<ReceiptClaim>>;
+
+    /// Join two consecutive receipts of the same session into one.
+    fn join(
+        &self,
+        left: SuccinctReceipt<ReceiptClaim>,
+        right: SuccinctReceipt<ReceiptClaim>,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>>;
+
+    /// Remove an assumption from a conditional receipt.
+    fn resolve(
+        &self,
+        conditional: SuccinctReceipt<ReceiptClaim>,
+        assumption: SuccinctReceipt<Unknown>,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>>;
 }
 
-impl Worker {
-    fn prove_and_lift(&self, segment: Asset) -> SuccinctReceipt<ReceiptClaim> {
-        let opts = ProverOpts::default();
-        let client = ApiClient::from_env().unwrap();
+/// A [Worker] which proves with an [ApiClient], starting a server for each request.
+#[derive(Default)]
+pub struct ApiWorker;
 
-        let segment_receipt = client
-            .prove_segment(&opts, segment, AssetRequest::Inline)
-            .unwrap();
+impl Worker for ApiWorker {
+    fn prove_segment(&self, segment: Asset) -> Result<SegmentReceipt> {
+        let client = ApiClient::from_env_or_local()?;
+        Ok(client.prove_segment(&ProverOpts::default(), segment, AssetRequest::Inline)?)
+    }
 
-        let segment_receipt_asset = segment_receipt.try_into().unwrap();
-        client
-            .lift(&opts, segment_receipt_asset, AssetRequest::Inline)
-            .unwrap()
+    fn lift(&self, receipt: SegmentReceipt) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        let client = ApiClient::from_env_or_local()?;
+        Ok(client.lift(
+            &ProverOpts::default(),
+            receipt.try_into()?,
+            AssetRequest::Inline,
+        )?)
     }
 
     fn join(
         &self,
         left: SuccinctReceipt<ReceiptClaim>,
         right: SuccinctReceipt<ReceiptClaim>,
-    ) -> SuccinctReceipt<ReceiptClaim> {
-        let opts = ProverOpts::default();
-        let client = ApiClient::from_env().unwrap();
-        let left_asset = left.try_into().unwrap();
-        let right_asset = right.try_into().unwrap();
-        client
-            .join(&opts, left_asset, right_asset, AssetRequest::Inline)
-            .unwrap()
+    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        let client = ApiClient::from_env_or_local()?;
+        Ok(client.join(
+            &ProverOpts::default(),
+            left.try_into()?,
+            right.try_into()?,
+            AssetRequest::Inline,
+        )?)
+    }
+
+    fn resolve(
+        &self,
+        conditional: SuccinctReceipt<ReceiptClaim>,
+        assumption: SuccinctReceipt<Unknown>,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        let client = ApiClient::from_env_or_local()?;
+        Ok(client.resolve(
+            &ProverOpts::default(),
+            conditional.try_into()?,
+            assumption.try_into()?,
+            AssetRequest::Inline,
+        )?)
+    }
+}
+
+/// Run `job` with `worker`, returning a job holding the resulting receipt or error.
+///
+/// When `verify_segments` is set, each segment receipt is verified before it is lifted, and a
+/// receipt that fails is reported as an [InvalidSegment].
+pub fn execute(worker: &impl Worker, job: Job, verify_segments: bool) -> Job {
+    println!("{:?}", job.task);
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!(
+        "task",
+        task_number = job.task.task_number,
+        command = ?job.task.command,
+        segment_idx = job.task.segment_idx,
+        claim_digest = job.task.assumption.map(tracing::field::display),
+    )
+    .entered();
+    std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let result = match job.kind {
+            JobKind::Segment(segment) => worker.prove_segment(segment).and_then(|receipt| {
+                if verify_segments {
+                    worker
+                        .verify_segment(&receipt)
+                        .map_err(|error| InvalidSegment {
+                            segment_idx: job.task.segment_idx.unwrap(),
+                            error,
+                        })?;
+                }
+                worker.lift(receipt)
+            }),
+            JobKind::Join(children) => join(worker, children),
+            JobKind::Resolve(pair) => worker.resolve(pair.0, pair.1),
+            JobKind::Receipt(receipt) => Ok(*receipt),
+            JobKind::Failed(_) => unreachable!(),
+        };
+        let kind = match result {
+            Ok(receipt) => JobKind::Receipt(Box::new(receipt)),
+            Err(err) => JobKind::Failed(err),
+        };
+        Job {
+            task: job.task,
+            kind,
+        }
+    }))
+    .unwrap_or_else(|_| {
+        std::process::abort();
+    })
+}
+
+/// Join consecutive receipts pairwise in a binary tree, issuing the independent joins of each
+/// level of the tree concurrently.
+fn join(
+    worker: &impl Worker,
+    mut children: Vec<SuccinctReceipt<ReceiptClaim>>,
+) -> Result<SuccinctReceipt<ReceiptClaim>> {
+    while children.len() > 1 {
+        children = thread::scope(|scope| {
+            let mut joins = Vec::new();
+            let mut level = children.into_iter();
+            while let Some(left) = level.next() {
+                let right = level.next();
+                joins.push(scope.spawn(move || match right {
+                    Some(right) => worker.join(left, right),
+                    None => Ok(left),
+                }));
+            }
+            joins
+                .into_iter()
+                .map(|join| {
+                    join.join()
+                        .unwrap_or_else(|err| std::panic::resume_unwind(err))
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
     }
+    Ok(children.pop().unwrap())
 }
@@ -112,9 +112,9 @@ impl Datasheet {
     /// `$PATH`.
     fn connect_to_server(&self) -> Result<ApiClient> {
         if let Some(server_path) = &self.r0vm {
-            ApiClient::new_sub_process(server_path)
+            Ok(ApiClient::new_sub_process(server_path)?)
         } else {
-            ApiClient::from_env()
+            Ok(ApiClient::from_env()?)
         }
     }
 
@@ -216,7 +216,7 @@ mod util {
     {
         let binary = Asset::Inline(elf.to_vec().into());
         let segments_out = AssetRequest::Inline;
-        client.execute(env, binary, segments_out, segment_callback)
+        Ok(client.execute(env, binary, segments_out, segment_callback)?)
     }
 
     pub fn prove(
@@ -374,7 +374,7 @@ mod benches {
 
         let prove_segment = |segment| -> Result<SuccinctReceipt<ReceiptClaim>> {
             let receipt = client.prove_segment(&opts, segment, AssetRequest::Inline)?;
-            client.lift(&opts, receipt.try_into()?, AssetRequest::Inline)
+            Ok(client.lift(&opts, receipt.try_into()?, AssetRequest::Inline)?)
         };
 
         let [left_segment, right_segment]: [Asset; 2] = {
@@ -455,9 +455,12 @@ mod benches {
     }
 
     /// Measures the duration for executing a fallible `operation` once.
-    fn try_time<T>(operation: impl FnOnce() -> Result<T>) -> Result<(T, Duration)> {
+    fn try_time<T, E>(operation: impl FnOnce() -> Result<T, E>) -> Result<(T, Duration)>
+    where
+        E: Into<anyhow::Error>,
+    {
         let (result, duration) = time(operation);
-        Ok((result?, duration))
+        Ok((result.map_err(Into::into)?, duration))
     }
 }
 
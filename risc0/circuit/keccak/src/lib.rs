@@ -26,6 +26,9 @@ pub const KECCAK_DEFAULT_PO2: usize = 17;
 
 pub const KECCAK_PO2_RANGE: core::ops::RangeInclusive<usize> = 14..=18;
 
+/// Approximate number of cycles used by the keccak circuit for each permutation.
+pub const KECCAK_PERMUTE_CYCLES: usize = 200;
+
 pub const RECURSION_PO2: usize = 18;
 
 pub type KeccakState = [u64; 25];
@@ -36,12 +36,13 @@ use risc0_zkvm_platform::{
     syscall::{bigint, ecall, halt, reg_abi::*, IO_CHUNK_WORDS},
     PAGE_SIZE, WORD_SIZE,
 };
+use serde::{Deserialize, Serialize};
 use sha2::digest::generic_array::GenericArray;
 
 use super::{
     addr::{ByteAddr, WordAddr},
     bibc,
-    pager::PagedMemory,
+    pager::{PageFaults, PagedMemory},
     rv32im::{DecodedInstruction, EmuContext, Emulator, Instruction, TrapCause},
     BIGINT2_WIDTH_BYTES, BIGINT_CYCLES, SYSTEM_START,
 };
@@ -65,6 +66,12 @@ pub trait Syscall {
         ctx: &mut dyn SyscallContext,
         into_guest: &mut [u32],
     ) -> Result<(u32, u32)>;
+
+    /// Returns true if a system call asked for the current segment to end after the instruction
+    /// that made it, clearing the request.
+    fn take_segment_flush(&self) -> bool {
+        false
+    }
 }
 
 /// Access to memory and machine state for syscalls.
@@ -114,6 +121,9 @@ pub struct ExecutorResult {
     pub post_state: SystemState,
     pub output_digest: Option<Digest>,
     pub ecall_metrics: Vec<(String, EcallMetric)>,
+    pub page_in_count: u64,
+    pub page_out_count: u64,
+    pub unique_pages_touched: u64,
 }
 
 #[derive(Clone, Copy, Debug, Enum)]
@@ -142,6 +152,71 @@ struct SessionCycles {
     reserved: u64,
 }
 
+/// The executor state at a segment boundary, from which a session can be
+/// resumed with [Executor::resume].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExecutorCheckpoint {
+    /// The memory image (including registers and pc) at the boundary.
+    pub image: MemoryImage,
+    /// The index of the next segment to be produced.
+    pub segment_index: usize,
+    /// The system state at the start of the session.
+    pub pre_state: SystemState,
+    /// A syscall made by the instruction that was split across segments,
+    /// which must be replayed rather than re-run.
+    pub pending_syscall: Option<SyscallRecord>,
+    pub output_digest: Option<Digest>,
+    pub user_cycles: u64,
+    pub paging_cycles: u64,
+    pub reserved_cycles: u64,
+    pub total_cycles: u64,
+}
+
+/// A segment that has outgrown a power of two, passed to the split policy set with
+/// [Executor::with_split_policy].
+#[derive(Clone, Debug)]
+pub struct SplitPoint {
+    /// The index of the segment.
+    pub index: usize,
+    /// The po2 the segment would have if it were split now.
+    pub po2: usize,
+    /// The instruction and paging cycles used by the segment so far.
+    pub cycles: usize,
+    /// The user cycles executed by the session so far.
+    pub user_cycles: u64,
+}
+
+/// The error returned when a session runs past its limit of user cycles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CycleLimitExceeded {
+    /// The limit of user cycles for the session.
+    pub limit: u64,
+    /// The user cycles executed when execution was stopped.
+    pub cycles: u64,
+}
+
+impl std::fmt::Display for CycleLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Session limit exceeded: {} user cycles executed, limit is {}",
+            self.cycles, self.limit
+        )
+    }
+}
+
+impl std::error::Error for CycleLimitExceeded {}
+
+type SplitPolicy<'a> = Box<dyn FnMut(&SplitPoint) -> bool + 'a>;
+
+struct ResumeState {
+    segment_index: usize,
+    pre_state: SystemState,
+    pending_syscall: Option<SyscallRecord>,
+    output_digest: Option<Digest>,
+    cycles: SessionCycles,
+}
+
 pub struct SimpleSession {
     pub segments: Vec<Segment>,
     pub result: ExecutorResult,
@@ -172,6 +247,10 @@ pub struct Executor<'a, 'b, S: Syscall> {
     trace: Vec<Rc<RefCell<dyn TraceCallback + 'b>>>,
     cycles: SessionCycles,
     ecall_metrics: EcallMetrics,
+    resume: Option<ResumeState>,
+    split_policy: Option<SplitPolicy<'b>>,
+    min_segment_po2: usize,
+    coalesce_po2: Option<usize>,
 }
 
 impl PendingState {
@@ -215,15 +294,85 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             trace,
             cycles: SessionCycles::default(),
             ecall_metrics: Default::default(),
+            resume: None,
+            split_policy: None,
+            min_segment_po2: MIN_CYCLES_PO2,
+            coalesce_po2: None,
         }
     }
 
+    /// Consult `policy` each time a segment outgrows a power of two that is smaller than the
+    /// segment limit, splitting the segment at that po2 if it returns true.
+    pub fn with_split_policy(mut self, policy: impl FnMut(&SplitPoint) -> bool + 'b) -> Self {
+        self.split_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Pad the final segment to at least `po2`, so that it is the same size as the other
+    /// segments when `po2` is the segment limit.
+    pub fn with_min_segment_po2(mut self, po2: usize) -> Self {
+        self.min_segment_po2 = po2;
+        self
+    }
+
+    /// Merge a final segment smaller than `po2` into the segment before it, rather than ending
+    /// the session with a segment that costs little more than the fixed overhead of a proof.
+    ///
+    /// Only a segment that a split policy ended below the segment limit is merged into, and only
+    /// when the merged segment still fits within the segment limit.
+    pub fn with_coalesce_po2(mut self, po2: usize) -> Self {
+        self.coalesce_po2 = Some(po2);
+        self
+    }
+
+    /// Construct an [Executor] that continues a session from a checkpoint
+    /// produced by [Executor::run_with_checkpoints].
+    pub fn resume(
+        checkpoint: ExecutorCheckpoint,
+        syscall_handler: &'a S,
+        input_digest: Option<Digest>,
+        trace: Vec<Rc<RefCell<dyn TraceCallback + 'b>>>,
+    ) -> Self {
+        let mut this = Self::new(checkpoint.image, syscall_handler, input_digest, trace);
+        this.resume = Some(ResumeState {
+            segment_index: checkpoint.segment_index,
+            pre_state: checkpoint.pre_state,
+            pending_syscall: checkpoint.pending_syscall,
+            output_digest: checkpoint.output_digest,
+            cycles: SessionCycles {
+                total: checkpoint.total_cycles,
+                user: checkpoint.user_cycles,
+                paging: checkpoint.paging_cycles,
+                reserved: checkpoint.reserved_cycles,
+            },
+        });
+        this
+    }
+
     pub fn run<F: FnMut(Segment) -> Result<()>>(
         &mut self,
         segment_po2: usize,
         max_cycles: Option<u64>,
         mut callback: F,
     ) -> Result<ExecutorResult> {
+        self.run_with_checkpoints(segment_po2, max_cycles, None, |segment, _| {
+            callback(segment)
+        })
+    }
+
+    /// Run the executor, producing an [ExecutorCheckpoint] alongside the first
+    /// segment that ends at least `checkpoint_interval` user cycles after the
+    /// previous checkpoint.
+    pub fn run_with_checkpoints<F>(
+        &mut self,
+        segment_po2: usize,
+        max_cycles: Option<u64>,
+        checkpoint_interval: Option<u64>,
+        mut callback: F,
+    ) -> Result<ExecutorResult>
+    where
+        F: FnMut(Segment, Option<ExecutorCheckpoint>) -> Result<()>,
+    {
         // at least one HaltCycle needs to appear in the body
         const MIN_HALT_CYCLES: usize = 1;
         // a final "is_done" PageFault cycle is required when a split occurs
@@ -231,13 +380,36 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         // leave room for reserved cycles
         const RESERVED_CYCLES: usize =
             INIT_CYCLES + MIN_HALT_CYCLES + PAGE_FINI_CYCLES + FINI_CYCLES + ZK_CYCLES;
-        let segment_limit = (1 << segment_po2) - RESERVED_CYCLES;
-
         self.reset();
 
         let mut emu = Emulator::new();
         let mut segments = 0;
-        let initial_state = self.pager.image.get_system_state();
+        let mut initial_state = self.pager.image.get_system_state();
+        if let Some(resume) = self.resume.take() {
+            segments = resume.segment_index;
+            initial_state = resume.pre_state;
+            self.pending.syscall = resume.pending_syscall;
+            self.output_digest = resume.output_digest;
+            self.cycles = resume.cycles;
+        }
+        let mut last_checkpoint = self.cycles.user;
+
+        // The po2 at which the current segment is split, and the next smaller po2 to offer to the
+        // split policy.
+        let mut split_po2 = segment_po2;
+        let mut probe_po2 = MIN_CYCLES_PO2;
+
+        // Whether the guest asked for the current segment to end before the next instruction.
+        let mut flush = false;
+
+        // Pages touched by any segment, and the running page-in/page-out totals.
+        let mut pages_touched = BTreeSet::new();
+        let mut page_in_count = 0;
+        let mut page_out_count = 0;
+
+        // The last segment to end below the segment limit, held back while coalescing so that the
+        // final segment can be merged into it.
+        let mut held: Option<(Segment, PageFaults)> = None;
 
         loop {
             if self.exit_code.is_some() {
@@ -246,15 +418,44 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
             if let Some(max_cycles) = max_cycles {
                 if self.cycles.user >= max_cycles {
-                    bail!("Session limit exceeded");
+                    return Err(CycleLimitExceeded {
+                        limit: max_cycles,
+                        cycles: self.cycles.user,
+                    }
+                    .into());
                 }
             }
 
             emu.step(self)?;
 
             let segment_cycles = self.insn_cycles + self.pager.cycles + self.pending.cycles;
-            if segment_cycles < segment_limit {
+            if let Some(policy) = self.split_policy.as_mut() {
+                while split_po2 == segment_po2
+                    && probe_po2 < segment_po2
+                    && segment_cycles >= (1 << probe_po2) - RESERVED_CYCLES
+                {
+                    let point = SplitPoint {
+                        index: segments,
+                        po2: probe_po2,
+                        cycles: self.insn_cycles + self.pager.cycles,
+                        user_cycles: self.cycles.user,
+                    };
+                    // A segment can only be split once it holds at least one instruction.
+                    if self.insn_cycles > 0 && policy(&point) {
+                        split_po2 = probe_po2;
+                    }
+                    probe_po2 += 1;
+                }
+            }
+
+            let segment_limit = (1 << split_po2) - RESERVED_CYCLES;
+            if segment_cycles < segment_limit && !flush {
                 self.advance()?;
+                if self.syscall_handler.take_segment_flush() {
+                    // A segment which has not yet run as many instructions as the smallest
+                    // segment holds is not split.
+                    flush = self.insn_cycles >= 1 << self.min_segment_po2;
+                }
             } else if self.insn_cycles == 0 {
                 bail!(
                     "segment limit ({segment_limit}) too small for instruction at pc: {:?}",
@@ -263,7 +464,12 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             } else {
                 self.pager.undo();
                 let used_cycles = self.insn_cycles + self.pager.cycles + RESERVED_CYCLES;
-                let po2_padding = (1 << segment_po2) - used_cycles;
+                if flush {
+                    // End a flushed segment at the smallest po2 that holds it.
+                    let po2 = log2_ceil(used_cycles.next_power_of_two()).max(self.min_segment_po2);
+                    split_po2 = split_po2.min(po2);
+                }
+                let po2_padding = (1 << split_po2) - used_cycles;
                 tracing::debug!(
                     "split: {} + {} + {RESERVED_CYCLES} = {used_cycles}, padding: {po2_padding}, pending: {:?}",
                     self.insn_cycles,
@@ -272,55 +478,161 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
                 );
 
                 // split
+                let faults = self.pager.get_faults();
+                page_in_count += faults.reads.len() as u64;
+                page_out_count += faults.writes.len() as u64;
                 let (pre_state, partial_image, post_state) = self.pager.commit(self.pc);
-                callback(Segment {
+                self.cycles.total += 1 << split_po2;
+                self.cycles.paging += self.pager.cycles as u64;
+                self.cycles.reserved += (po2_padding + RESERVED_CYCLES) as u64;
+
+                let checkpoint = checkpoint_interval
+                    .filter(|interval| self.cycles.user - last_checkpoint >= *interval)
+                    .map(|_| {
+                        last_checkpoint = self.cycles.user;
+                        ExecutorCheckpoint {
+                            image: self.pager.image.clone(),
+                            segment_index: segments + 1,
+                            pre_state: initial_state.clone(),
+                            pending_syscall: self.pending.syscall.clone(),
+                            output_digest: self.output_digest,
+                            user_cycles: self.cycles.user,
+                            paging_cycles: self.cycles.paging,
+                            reserved_cycles: self.cycles.reserved,
+                            total_cycles: self.cycles.total,
+                        }
+                    });
+
+                let segment = Segment {
                     partial_image,
                     pre_state,
                     post_state,
                     syscalls: mem::take(&mut self.syscalls),
                     insn_cycles: self.insn_cycles,
-                    po2: segment_po2,
+                    paging_cycles: self.pager.cycles,
+                    po2: split_po2,
                     exit_code: ExitCode::SystemSplit,
                     index: segments,
                     input_digest: self.input_digest,
                     output_digest: self.output_digest,
-                })?;
+                    page_in_count: faults.reads.len(),
+                    page_out_count: faults.writes.len(),
+                };
+                pages_touched.extend(faults.reads.iter().copied());
+                if let Some((prev, _)) = held.take() {
+                    callback(prev, None)?;
+                }
+                // A segment that a checkpoint resumes after must be kept as it is.
+                let hold = self.coalesce_po2.is_some()
+                    && !flush
+                    && split_po2 < segment_po2
+                    && checkpoint.is_none();
+                if hold {
+                    held = Some((segment, faults));
+                } else {
+                    callback(segment, checkpoint)?;
+                }
                 segments += 1;
-                self.cycles.total += 1 << segment_po2;
-                self.cycles.paging += self.pager.cycles as u64;
-                self.cycles.reserved += (po2_padding + RESERVED_CYCLES) as u64;
                 self.pager.clear();
                 self.insn_cycles = 0;
 
                 // replay the current instruction in a new segment
                 self.pending.pc = self.pc;
                 self.pending.cycles = 0;
+                split_po2 = segment_po2;
+                probe_po2 = MIN_CYCLES_PO2;
+                flush = false;
             }
         }
 
+        let faults = self.pager.get_faults();
+        page_in_count += faults.reads.len() as u64;
+        page_out_count += faults.writes.len() as u64;
         let (pre_state, partial_image, post_state) = self.pager.commit(self.pc);
         let segment_cycles = self.insn_cycles + self.pager.cycles + RESERVED_CYCLES;
-        let po2 = log2_ceil(segment_cycles.next_power_of_two());
+        let po2 = log2_ceil(segment_cycles.next_power_of_two()).max(self.min_segment_po2);
         let po2_padding = (1 << po2) - segment_cycles;
         let exit_code = self.exit_code.unwrap();
 
-        callback(Segment {
+        let mut last = Segment {
             partial_image,
             pre_state: pre_state.clone(),
             post_state: post_state.clone(),
             syscalls: mem::take(&mut self.syscalls),
             insn_cycles: self.insn_cycles,
+            paging_cycles: self.pager.cycles,
             po2,
             exit_code,
             index: segments,
             input_digest: self.input_digest,
             output_digest: self.output_digest,
-        })?;
+            page_in_count: faults.reads.len(),
+            page_out_count: faults.writes.len(),
+        };
+        pages_touched.extend(faults.reads.iter().copied());
         segments += 1;
         self.cycles.total += 1 << po2;
         self.cycles.paging += self.pager.cycles as u64;
         self.cycles.reserved += (po2_padding + RESERVED_CYCLES) as u64;
 
+        if let Some((prev, prev_faults)) = held.take() {
+            let faults = PageFaults {
+                reads: &prev_faults.reads | &faults.reads,
+                writes: &prev_faults.writes | &faults.writes,
+            };
+            let insn_cycles = prev.insn_cycles + last.insn_cycles;
+            let paging_cycles = self.pager.paging_cycles(&faults);
+            let used_cycles = insn_cycles + paging_cycles + RESERVED_CYCLES;
+            let merged_po2 = log2_ceil(used_cycles.next_power_of_two()).max(self.min_segment_po2);
+            let coalesce_po2 = self.coalesce_po2.unwrap_or_default();
+            if last.po2 < coalesce_po2 && merged_po2 <= segment_po2 {
+                tracing::debug!(
+                    "coalescing final segment (po2 {}) into segment {}: po2 {merged_po2}",
+                    last.po2,
+                    prev.index
+                );
+                for segment in [&prev, &last] {
+                    let total: usize = 1 << segment.po2;
+                    self.cycles.total -= total as u64;
+                    self.cycles.paging -= segment.paging_cycles as u64;
+                    self.cycles.reserved -=
+                        (total - segment.insn_cycles - segment.paging_cycles) as u64;
+                    page_in_count -= segment.page_in_count as u64;
+                    page_out_count -= segment.page_out_count as u64;
+                }
+                self.cycles.total += 1 << merged_po2;
+                self.cycles.paging += paging_cycles as u64;
+                self.cycles.reserved += ((1 << merged_po2) - insn_cycles - paging_cycles) as u64;
+                page_in_count += faults.reads.len() as u64;
+                page_out_count += faults.writes.len() as u64;
+                segments -= 1;
+
+                // Pages the previous segment did not touch hold the same data at the start of
+                // either segment.
+                let mut partial_image = prev.partial_image;
+                let pages = mem::replace(&mut partial_image.pages, last.partial_image.pages);
+                partial_image.pages.extend(pages);
+                last = Segment {
+                    partial_image,
+                    pre_state: prev.pre_state,
+                    post_state: last.post_state,
+                    syscalls: [prev.syscalls, last.syscalls].concat(),
+                    insn_cycles,
+                    paging_cycles,
+                    po2: merged_po2,
+                    exit_code: last.exit_code,
+                    index: prev.index,
+                    input_digest: last.input_digest,
+                    output_digest: last.output_digest,
+                    page_in_count: faults.reads.len(),
+                    page_out_count: faults.writes.len(),
+                };
+            } else {
+                callback(prev, None)?;
+            }
+        }
+        callback(last, None)?;
+
         // NOTE: When a segment ends in a Halted(_) state, the post_state will be null.
         let post_state = match exit_code {
             ExitCode::Halted(_) => SystemState {
@@ -344,6 +656,9 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             post_state,
             output_digest: self.output_digest,
             ecall_metrics: ecall_metrics.into(),
+            page_in_count,
+            page_out_count,
+            unique_pages_touched: pages_touched.len() as u64,
         })
     }
 
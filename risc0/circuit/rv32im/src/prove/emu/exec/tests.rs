@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use anyhow::Result;
 use risc0_binfmt::{Digestible, ExitCode, MemoryImage};
@@ -23,7 +23,7 @@ use risc0_zkvm_platform::{
 };
 use test_log::test;
 
-use super::{Syscall, SyscallContext};
+use super::{Executor, Syscall, SyscallContext};
 use crate::prove::emu::{
     addr::ByteAddr,
     exec::DEFAULT_SEGMENT_LIMIT_PO2,
@@ -129,3 +129,210 @@ fn system_split() {
         segments[0].post_state.digest::<ShaImpl>()
     );
 }
+
+#[test]
+fn split_policy() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    let mut segments = Vec::new();
+    Executor::new(image, &syscall, None, vec![])
+        .with_split_policy(|point| point.po2 == 14)
+        .with_min_segment_po2(14)
+        .run(16, DEFAULT_SESSION_LIMIT, |segment| {
+            segments.push(segment);
+            Ok(())
+        })
+        .unwrap();
+
+    assert!(segments.len() > 1);
+    for segment in segments.iter() {
+        assert_eq!(segment.po2, 14);
+    }
+    assert_eq!(segments.last().unwrap().exit_code, ExitCode::Halted(0));
+}
+
+#[test]
+fn coalesce_small_segments() {
+    let program = testutil::simple_loop();
+    let syscall = BasicSyscall::default();
+    let run = |coalesce: bool| {
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let mut exec =
+            Executor::new(image, &syscall, None, vec![]).with_split_policy(|point| point.po2 == 14);
+        if coalesce {
+            exec = exec.with_coalesce_po2(15);
+        }
+        let mut segments = Vec::new();
+        let result = exec
+            .run(16, DEFAULT_SESSION_LIMIT, |segment| {
+                segments.push(segment);
+                Ok(())
+            })
+            .unwrap();
+        (segments, result)
+    };
+
+    // The policy ends the first segment below the segment limit, and the loop ends one instruction
+    // into the second segment, which holds little more than paging.
+    let (split, split_result) = run(false);
+    assert_eq!(split.len(), 2);
+    assert_eq!(split[0].po2, 14);
+    assert!(split[1].po2 < 15);
+
+    let (coalesced, result) = run(true);
+    assert_eq!(coalesced.len(), split.len() - 1);
+    assert_eq!(result.segments, split_result.segments - 1);
+    let segment = &coalesced[0];
+    assert_eq!(segment.index, 0);
+    assert_eq!(segment.po2, 15);
+    assert_eq!(segment.exit_code, ExitCode::Halted(0));
+    segment.preflight().unwrap();
+    assert_eq!(
+        segment.pre_state.digest::<ShaImpl>(),
+        split[0].pre_state.digest::<ShaImpl>()
+    );
+    assert_eq!(
+        segment.post_state.digest::<ShaImpl>(),
+        split[1].post_state.digest::<ShaImpl>()
+    );
+    assert_eq!(
+        segment.insn_cycles,
+        split[0].insn_cycles + split[1].insn_cycles
+    );
+    assert_eq!(result.user_cycles, split_result.user_cycles);
+    assert_eq!(result.total_cycles, 1 << 15);
+    assert_eq!(
+        result.total_cycles,
+        result.user_cycles + result.paging_cycles + result.reserved_cycles
+    );
+    assert_eq!(
+        result.post_image.compute_id(),
+        split_result.post_image.compute_id()
+    );
+}
+
+#[test]
+fn coalesce_within_segment_limit() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    // The first segment ends at the segment limit, so merging into it would exceed the limit.
+    let mut segments = Vec::new();
+    Executor::new(image, &syscall, None, vec![])
+        .with_coalesce_po2(15)
+        .run(14, DEFAULT_SESSION_LIMIT, |segment| {
+            segments.push(segment);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(segments.len(), 2);
+    for segment in segments.iter() {
+        assert!(segment.po2 <= 14);
+    }
+}
+
+/// Requests a segment flush after each of the given instructions, counting from one.
+struct FlushSyscall {
+    flush_after: Vec<usize>,
+    steps: Cell<usize>,
+}
+
+impl Syscall for FlushSyscall {
+    fn syscall(
+        &self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        _guest_buf: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        unimplemented!()
+    }
+
+    fn take_segment_flush(&self) -> bool {
+        self.steps.set(self.steps.get() + 1);
+        self.flush_after.contains(&self.steps.get())
+    }
+}
+
+#[test]
+fn segment_flush() {
+    let program = testutil::long_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+
+    // The second flush comes too soon after the first, before the segment has run as many
+    // instructions as the smallest segment holds, so it is ignored.
+    let syscall = FlushSyscall {
+        flush_after: vec![10_000, 10_100],
+        steps: Cell::new(0),
+    };
+    let mut segments = Vec::new();
+    Executor::new(image, &syscall, None, vec![])
+        .run(20, DEFAULT_SESSION_LIMIT, |segment| {
+            segments.push(segment);
+            Ok(())
+        })
+        .unwrap();
+
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].exit_code, ExitCode::SystemSplit);
+    assert_eq!(segments[0].insn_cycles, 10_000);
+    assert_eq!(segments[0].po2, 15);
+    assert_eq!(segments[1].exit_code, ExitCode::Halted(0));
+    assert_eq!(segments[1].insn_cycles, 2 * 8192 + 4 - 10_000);
+}
+
+#[test]
+fn resume_from_checkpoint() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    let mut segments = Vec::new();
+    let mut checkpoints = Vec::new();
+    let result = Executor::new(image, &syscall, None, vec![])
+        .run_with_checkpoints(14, DEFAULT_SESSION_LIMIT, Some(0), |segment, checkpoint| {
+            segments.push(segment);
+            checkpoints.extend(checkpoint);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(checkpoints.len(), segments.len() - 1);
+
+    let checkpoint = checkpoints.remove(0);
+    assert_eq!(checkpoint.segment_index, 1);
+
+    let mut resumed_segments = Vec::new();
+    let resumed = Executor::resume(checkpoint, &syscall, None, vec![])
+        .run(14, DEFAULT_SESSION_LIMIT, |segment| {
+            resumed_segments.push(segment);
+            Ok(())
+        })
+        .unwrap();
+
+    assert_eq!(resumed.segments, result.segments);
+    assert_eq!(resumed.exit_code, result.exit_code);
+    assert_eq!(resumed.user_cycles, result.user_cycles);
+    assert_eq!(resumed.total_cycles, result.total_cycles);
+    assert_eq!(
+        resumed.pre_state.digest::<ShaImpl>(),
+        result.pre_state.digest::<ShaImpl>()
+    );
+    assert_eq!(
+        resumed.post_image.compute_id(),
+        result.post_image.compute_id()
+    );
+    assert_eq!(resumed_segments.len(), segments.len() - 1);
+    for (resumed, original) in resumed_segments.iter().zip(&segments[1..]) {
+        assert_eq!(resumed.index, original.index);
+        assert_eq!(
+            resumed.pre_state.digest::<ShaImpl>(),
+            original.pre_state.digest::<ShaImpl>()
+        );
+        assert_eq!(
+            resumed.post_state.digest::<ShaImpl>(),
+            original.post_state.digest::<ShaImpl>()
+        );
+    }
+}
@@ -261,15 +261,29 @@ impl PagedMemory {
         }
     }
 
-    fn page_changed(&mut self, page_idx: u32, state: PageState) {
-        let info = &self.image.info;
+    /// The cycles needed to page in every page in `faults.reads` and page out every page in
+    /// `faults.writes`.
+    pub fn paging_cycles(&self, faults: &PageFaults) -> usize {
+        faults
+            .reads
+            .iter()
+            .chain(&faults.writes)
+            .map(|page_idx| self.page_cycles(*page_idx))
+            .sum()
+    }
 
-        let page_cycles = if page_idx == info.root_idx {
+    fn page_cycles(&self, page_idx: u32) -> usize {
+        let info = &self.image.info;
+        if page_idx == info.root_idx {
             let num_root_entries = info.num_root_entries as usize;
             cycles_per_page(num_root_entries / 2)
         } else {
             cycles_per_page(BLOCKS_PER_PAGE)
-        };
+        }
+    }
+
+    fn page_changed(&mut self, page_idx: u32, state: PageState) {
+        let page_cycles = self.page_cycles(page_idx);
 
         tracing::trace!("page_changed(0x{page_idx:05x}, {state:?}) <= {page_cycles}");
         self.cycles += page_cycles;
@@ -93,6 +93,22 @@ pub fn simple_loop() -> Program {
     )
 }
 
+/// Like [simple_loop], but runs for 8192 iterations, which is long enough to fill more than one
+/// segment of the minimum size.
+pub fn long_loop() -> Program {
+    program_from_instructions(
+        0x4000,
+        [
+            0x00000713, // li      a4,0
+            0x000027b7, // lui     a5,0x2
+            0x00170713, // add     a4,a4,1
+            0xfef74ee3, // blt     a4,a5,8 <loop>
+            0x010005b7, // lui     a1,0x1000
+            0x00000073, // ecall
+        ],
+    )
+}
+
 pub fn large_text() -> Program {
     let iter = (0..2500).map(|_| {
         0x1234b137 // lui x2, 0x1234b000
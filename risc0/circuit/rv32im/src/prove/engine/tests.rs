@@ -30,7 +30,7 @@ use super::{loader::Loader, witgen::WitnessGenerator};
 use crate::{
     prove::{
         emu::{
-            exec::{execute, DEFAULT_SEGMENT_LIMIT_PO2},
+            exec::{execute, Executor, DEFAULT_SEGMENT_LIMIT_PO2},
             testutil::{self, NullSyscall, DEFAULT_SESSION_LIMIT},
         },
         hal::StepMode,
@@ -162,6 +162,32 @@ fn system_split() {
     }
 }
 
+#[test]
+fn coalesced_segment() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+
+    let mut segments = Vec::new();
+    Executor::new(image, &NullSyscall, None, vec![])
+        .with_split_policy(|point| point.po2 == 14)
+        .with_coalesce_po2(15)
+        .run(16, DEFAULT_SESSION_LIMIT, |segment| {
+            segments.push(segment);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(segments.len(), 1);
+
+    let prover = segment_prover("sha-256").unwrap();
+    let suite = Sha256HashSuite::new_suite();
+    let hal = CpuHal::new(suite.clone());
+
+    let segment = segments.first().unwrap();
+    let seal = prover.prove_segment(segment).unwrap();
+    let checker = ControlCheck::new(&hal, segment.po2);
+    risc0_zkp::verify::verify(&CIRCUIT, &suite, &seal, |x, y| checker.check_ctrl(x, y)).unwrap();
+}
+
 #[test]
 fn fwd_rev_ab_basic() {
     fwd_rev_ab_test(testutil::basic());
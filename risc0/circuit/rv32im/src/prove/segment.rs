@@ -40,11 +40,27 @@ pub struct Segment {
     #[debug(skip)]
     pub syscalls: Vec<SyscallRecord>,
     pub insn_cycles: usize,
+    /// The number of cycles spent paging memory in and out of this segment.
+    ///
+    /// Not serialized, so that the format of a segment is unchanged; this is zero for a
+    /// deserialized segment.
+    #[serde(skip)]
+    pub paging_cycles: usize,
     pub po2: usize,
     pub exit_code: ExitCode,
     pub index: usize,
     pub input_digest: Digest,
     pub output_digest: Option<Digest>,
+    /// The number of pages read into this segment, including page table pages.
+    ///
+    /// Not serialized, like `paging_cycles`.
+    #[serde(skip)]
+    pub page_in_count: usize,
+    /// The number of dirty pages written back at the end of this segment.
+    ///
+    /// Not serialized, like `paging_cycles`.
+    #[serde(skip)]
+    pub page_out_count: usize,
 }
 
 impl Segment {
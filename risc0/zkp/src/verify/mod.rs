@@ -71,6 +71,9 @@ pub enum VerificationError {
     UnresolvedAssumption {
         digest: Digest,
     },
+    ImageIdNotAllowed {
+        image_id: Digest,
+    },
 }
 
 impl fmt::Debug for VerificationError {
@@ -115,6 +118,12 @@ impl fmt::Display for VerificationError {
             VerificationError::UnresolvedAssumption { digest } => {
                 write!(f, "receipt contains an unresolved assumption: {digest}")
             }
+            VerificationError::ImageIdNotAllowed { image_id } => {
+                write!(
+                    f,
+                    "image_id {image_id} is not in the set of allowed image IDs"
+                )
+            }
         }
     }
 }
@@ -33,16 +33,19 @@ use risc0_zkvm::{
         env::{self, testing::sha_single_keccak, FdReader, FdWriter, Read as _, Write as _},
         memory_barrier, sha,
     },
+    serde::Postcard,
     sha::{Digest, Sha256, SHA256_INIT},
     Assumption, ReceiptClaim,
 };
-use risc0_zkvm_methods::multi_test::{MultiTestSpec, SYS_MULTI_TEST, SYS_MULTI_TEST_WORDS};
+use risc0_zkvm_methods::multi_test::{
+    CodecTestValue, MultiTestSpec, SYS_MULTI_TEST, SYS_MULTI_TEST_WORDS,
+};
 use risc0_zkvm_platform::{
     fileno,
     memory::{self, SYSTEM},
     syscall::{
-        bigint, sys_bigint, sys_exit, sys_fork, sys_keccak, sys_log, sys_pipe, sys_prove_zkr,
-        sys_read, sys_read_words, sys_write,
+        bigint, sys_bigint, sys_exit, sys_flush_segment, sys_fork, sys_keccak, sys_log, sys_pipe,
+        sys_prove_zkr, sys_read, sys_read_words, sys_write,
     },
     PAGE_SIZE,
 };
@@ -139,6 +142,17 @@ fn main() {
         MultiTestSpec::Fault => unsafe {
             asm!("sw x0, 1(x0)");
         },
+        MultiTestSpec::FlushSegments {
+            flushes,
+            iterations,
+        } => {
+            for _ in 0..flushes {
+                for i in 0..iterations {
+                    core::hint::black_box(i);
+                }
+                sys_flush_segment();
+            }
+        }
         MultiTestSpec::Halt(exit_code) => {
             env::exit(exit_code);
         }
@@ -147,6 +161,33 @@ fn main() {
             env::pause(exit_code);
             env::log("after");
         }
+        MultiTestSpec::ReadTypedThenSlice { nwords } => {
+            let value: (u32, u64) = env::read();
+            let mut words = vec![0u32; nwords as usize];
+            env::read_slice(&mut words);
+            env::commit(&(value, words));
+        }
+        MultiTestSpec::ReadChunkedDigest => {
+            let data = env::read_chunked();
+            env::commit(&(data.len() as u32, *sha::Impl::hash_bytes(&data)));
+        }
+        MultiTestSpec::ReadFrameDigest => {
+            let frame = env::read_frame();
+            let value: u32 = env::read();
+            env::commit(&(frame.len() as u32, *sha::Impl::hash_bytes(&frame), value));
+        }
+        MultiTestSpec::ReadPostcard => {
+            let value: CodecTestValue = env::read_with::<Postcard, _>().unwrap();
+            env::commit(&value);
+        }
+        MultiTestSpec::ReadSizedDigest => {
+            let mut words = vec![0u32; env::read_len()];
+            env::read_slice(&mut words);
+            env::commit(&(
+                words.len() as u32,
+                *sha::Impl::hash_bytes(bytemuck::cast_slice(&words)),
+            ));
+        }
         MultiTestSpec::ReadWriteMem { values } => {
             for (addr, value) in values.into_iter() {
                 if value != 0 {
@@ -238,6 +279,11 @@ fn main() {
         MultiTestSpec::Echo { bytes } => {
             env::commit_slice(&bytes);
         }
+        MultiTestSpec::EchoChunks { chunks } => {
+            for chunk in chunks {
+                env::commit_slice(&chunk);
+            }
+        }
         MultiTestSpec::EchoStdout { nbytes, fd } => {
             // Unaligned buffer size to exercise things a little bit.
             let mut buf = vec![0u8; nbytes as usize];
@@ -255,6 +301,11 @@ fn main() {
             unsafe { sys_read_words(fd, buf.as_mut_ptr(), nwords) };
             env::commit_slice(&buf);
         }
+        MultiTestSpec::CommitFrames => {
+            env::commit(&7u32);
+            env::commit(&"frames");
+            env::commit(&(1u8, 2u64));
+        }
         MultiTestSpec::BusyLoop { cycles } => {
             let mut last_cycles = env::cycle_count();
 
@@ -15,12 +15,20 @@
 // Definitions for test selection codes used by the "multi_test" test.
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 
 use risc0_zkvm::{declare_syscall, sha::Digest};
 use risc0_zkvm_platform::syscall::bigint;
 use serde::{Deserialize, Serialize};
 
+/// A value written to the guest with a codec other than the default one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CodecTestValue {
+    pub id: u64,
+    pub name: String,
+    pub tags: Vec<u8>,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum MultiTestSpec {
     AlignedAlloc,
@@ -34,11 +42,17 @@ pub enum MultiTestSpec {
         /// Busy loop until the guest has run for at least this number of cycles
         cycles: u64,
     },
+    /// Commits a `u32`, a `String` and a `(u8, u64)`, one at a time.
+    CommitFrames,
     DoNothing,
     DoRandom,
     Echo {
         bytes: Vec<u8>,
     },
+    /// Commits each chunk with a separate call to `env::commit_slice`.
+    EchoChunks {
+        chunks: Vec<Vec<u8>>,
+    },
     EchoStdout {
         nbytes: u32,
         fd: u32,
@@ -49,6 +63,11 @@ pub enum MultiTestSpec {
     },
     EventTrace,
     Fault,
+    /// Calls `sys_flush_segment` after each of `flushes` loops of `iterations` iterations.
+    FlushSegments {
+        flushes: u32,
+        iterations: u32,
+    },
     Halt(u8),
     KeccakUpdate,
     KeccakUpdate2,
@@ -59,6 +78,21 @@ pub enum MultiTestSpec {
     Panic,
     PauseResume(u8),
     Profiler,
+    /// Reads a typed `(u32, u64)` value followed by a raw slice of `nwords` words, then commits
+    /// both.
+    ReadTypedThenSlice {
+        nwords: u32,
+    },
+    /// Reads chunked data, then commits its length and SHA-256 digest.
+    ReadChunkedDigest,
+    /// Reads a frame followed by a typed `u32` value, then commits the length and SHA-256 digest
+    /// of the frame along with the value.
+    ReadFrameDigest,
+    /// Reads a [CodecTestValue] encoded with postcard, then commits it.
+    ReadPostcard,
+    /// Reads the length of a slice of words, allocates a buffer of that size and reads the words
+    /// into it, then commits the length and SHA-256 digest of the words.
+    ReadSizedDigest,
     ReadWriteMem {
         /// Tuples of (address, value). Zero means read the value and
         /// output it; nonzero means write that value.
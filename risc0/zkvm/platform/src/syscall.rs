@@ -138,6 +138,7 @@ pub mod nr {
     declare_syscall!(pub SYS_CYCLE_COUNT);
     declare_syscall!(pub SYS_EXECUTE_ZKR);
     declare_syscall!(pub SYS_EXIT);
+    declare_syscall!(pub SYS_FLUSH_SEGMENT);
     declare_syscall!(pub SYS_FORK);
     declare_syscall!(pub SYS_GETENV);
     declare_syscall!(pub SYS_KECCAK);
@@ -979,6 +980,15 @@ pub unsafe extern "C" fn sys_prove_keccak(
     }
 }
 
+/// Ask the host to end the current segment once this call returns.
+///
+/// The host may ignore the request, e.g. when the current segment has only just begun.
+#[cfg_attr(all(feature = "export-syscalls", feature = "unstable"), no_mangle)]
+#[stability::unstable]
+pub extern "C" fn sys_flush_segment() {
+    unsafe { syscall_0(nr::SYS_FLUSH_SEGMENT, null_mut(), 0) };
+}
+
 #[repr(C)]
 pub struct BigIntBlobHeader {
     pub nondet_program_size: u32,
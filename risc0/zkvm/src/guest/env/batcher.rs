@@ -17,15 +17,13 @@ use core::{ptr::addr_of, str::from_utf8};
 use alloc::vec;
 
 use risc0_circuit_keccak::{
-    KeccakState, KECCAK_CONTROL_ROOT, KECCAK_DEFAULT_PO2, KECCAK_PO2_RANGE,
+    KeccakState, KECCAK_CONTROL_ROOT, KECCAK_DEFAULT_PO2, KECCAK_PERMUTE_CYCLES, KECCAK_PO2_RANGE,
 };
 use risc0_zkp::core::{digest::Digest, hash::sha::SHA256_INIT};
 use risc0_zkvm_platform::syscall::{
     sys_getenv, sys_keccak, sys_prove_keccak, sys_sha_compress, DIGEST_WORDS,
 };
 
-const KECCAK_PERMUTE_CYCLES: usize = 200;
-
 /// This struct implements the batching of calls to the keccak accelerator.
 #[derive(Debug)]
 pub struct Keccak2Batcher {
@@ -94,6 +94,7 @@ use risc0_zkvm_platform::{
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
+    serde::Codec,
     sha::{
         rust_crypto::{Digest as _, Sha256},
         Digest, Digestible,
@@ -408,6 +409,18 @@ pub fn cycle_count() -> u64 {
     sys_cycle_count()
 }
 
+/// Request that the current segment end at this point, e.g. at a natural checkpoint such as the
+/// end of a transaction, so that the segments can be proven in parallel.
+///
+/// The executor splits the segment after this call unless the segment is still smaller than the
+/// minimum segment size. Segment boundaries do not affect the receipt, only how the proof is
+/// divided into segments.
+#[cfg(feature = "unstable")]
+#[stability::unstable]
+pub fn flush_segment() {
+    syscall::sys_flush_segment();
+}
+
 /// Print a message to the debug console.
 pub fn log(msg: &str) {
     let msg = msg.as_bytes();
@@ -482,12 +495,56 @@ pub fn read_frame() -> alloc::vec::Vec<u8> {
     bytes
 }
 
+/// Read the length header written by `ExecutorEnvBuilder::write_sized`.
+///
+/// This is the number of elements in the slice that follows, which can then be read with
+/// [read_slice] into a buffer of exactly that size.
+#[stability::unstable]
+pub fn read_len() -> usize {
+    let mut len: u32 = 0;
+    read_slice(core::slice::from_mut(&mut len));
+    len as usize
+}
+
+/// Bit set in the length header of the last chunk written by `ExecutorEnvBuilder::write_chunked`.
+pub(crate) const CHUNK_FINAL_FLAG: u32 = 1 << 31;
+
+/// Read a sequence of chunks from the host via `stdin` and reassemble them.
+///
+/// This reads the data written by `ExecutorEnvBuilder::write_chunked`. Each chunk is a frame
+/// whose length header has its top bit set on the last chunk, so the guest need not know the
+/// total length in advance.
+#[stability::unstable]
+pub fn read_chunked() -> alloc::vec::Vec<u8> {
+    let mut bytes = alloc::vec::Vec::new();
+    loop {
+        let mut header: u32 = 0;
+        read_slice(core::slice::from_mut(&mut header));
+        let len = (header & !CHUNK_FINAL_FLAG) as usize;
+        let start = bytes.len();
+        bytes.resize(start + len, 0);
+        read_slice(&mut bytes[start..]);
+        if header & CHUNK_FINAL_FLAG != 0 {
+            return bytes;
+        }
+    }
+}
+
 /// Read a frame from the host via `stdin` and deserialize it using the `risc0` codec.
 #[stability::unstable]
 pub fn read_framed<T: DeserializeOwned>() -> Result<T, crate::serde::Error> {
     crate::serde::from_slice(&read_frame())
 }
 
+/// Read a frame from the host via `stdin` and decode it with the [Codec] `C`.
+///
+/// This reads data written on the host with `ExecutorEnvBuilder::write_with::<C, _>`, which
+/// must use the same codec.
+#[stability::unstable]
+pub fn read_with<C: Codec, T: DeserializeOwned>() -> Result<T, C::Error> {
+    C::decode(&read_frame())
+}
+
 /// Internal API used for testing. Do not use.
 #[stability::unstable]
 #[cfg(feature = "std")]
@@ -0,0 +1,320 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use risc0_zkp::core::digest::Digest;
+
+use super::{
+    client::Client, pb, Asset, AssetRequest, Bytes, ProverError, ServerInfo, StoreKey, ZkrInfo,
+};
+use crate::{
+    host::client::{
+        env::{ProveKeccakRequest, ProveZkrRequest},
+        prove::get_r0vm_path,
+    },
+    ProverOpts, Receipt, ReceiptClaim, SegmentReceipt, SuccinctReceipt, Unknown,
+};
+
+type ClientFactory = dyn Fn() -> Result<Client> + Send + Sync;
+
+/// An async client implementation for interacting with a zkVM server.
+///
+/// Each request runs a blocking [Client] on the Tokio blocking thread pool, so
+/// independent requests (e.g. proving several segments) may be awaited
+/// concurrently. The methods must be called from within a Tokio runtime.
+///
+/// Errors are reported as a [ProverError], as they are by [Client]. A request
+/// whose blocking task fails to complete is reported as [ProverError::Other].
+///
+/// Execution is not offered here because an [ExecutorEnv](crate::ExecutorEnv)
+/// is not `Send`. Use [Client::execute_to_channel] on a dedicated thread to feed
+/// segments into async code instead.
+#[derive(Clone)]
+pub struct AsyncClient {
+    factory: Arc<ClientFactory>,
+}
+
+impl AsyncClient {
+    /// Construct an [AsyncClient] that connects to `r0vm` in a child process.
+    pub fn new() -> Self {
+        Self::new_sub_process("r0vm")
+    }
+
+    /// Construct an [AsyncClient] that connects to a sub-process which
+    /// implements the server by calling the specified `server_path`.
+    pub fn new_sub_process<P: AsRef<Path>>(server_path: P) -> Self {
+        let server_path: PathBuf = server_path.as_ref().into();
+        Self::with_factory(move || Ok(Client::new_sub_process(&server_path)?))
+    }
+
+    /// Construct an [AsyncClient] based on environment variables.
+    pub fn from_env() -> Result<Self> {
+        Ok(Self::new_sub_process(get_r0vm_path()?))
+    }
+
+    /// Construct an [AsyncClient] which uses `factory` to create a blocking
+    /// [Client] for each request.
+    pub fn with_factory<F>(factory: F) -> Self
+    where
+        F: Fn() -> Result<Client> + Send + Sync + 'static,
+    {
+        Self {
+            factory: Arc::new(factory),
+        }
+    }
+
+    /// Ask the server for its version and the options it supports.
+    ///
+    /// See [Client::server_info].
+    pub async fn server_info(&self) -> Result<ServerInfo, ProverError> {
+        self.spawn(|client| client.server_info()).await
+    }
+
+    /// Ask the server for the recursion programs (ZKRs) registered with it.
+    ///
+    /// See [Client::supported_zkrs].
+    pub async fn supported_zkrs(&self) -> Result<Vec<ZkrInfo>, ProverError> {
+        self.spawn(|client| client.supported_zkrs()).await
+    }
+
+    /// Ask the server to load the recursion programs used to prove with `opts`.
+    ///
+    /// See [Client::warm_up].
+    pub async fn warm_up(&self, opts: &ProverOpts) -> Result<(), ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| client.warm_up(&opts)).await
+    }
+
+    /// Prove the specified segment.
+    pub async fn prove_segment(
+        &self,
+        opts: &ProverOpts,
+        segment: Asset,
+        receipt_out: AssetRequest,
+    ) -> Result<SegmentReceipt, ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| client.prove_segment(&opts, segment, receipt_out))
+            .await
+    }
+
+    /// Prove the specified ZKR proof request.
+    #[stability::unstable]
+    pub async fn prove_zkr<Claim>(
+        &self,
+        proof_request: ProveZkrRequest,
+        receipt_out: AssetRequest,
+    ) -> Result<SuccinctReceipt<Claim>, ProverError>
+    where
+        Claim:
+            risc0_binfmt::Digestible + std::fmt::Debug + Clone + serde::Serialize + Send + 'static,
+        crate::MaybePruned<Claim>: TryFrom<pb::core::MaybePruned, Error = anyhow::Error>,
+    {
+        self.spawn(move |client| client.prove_zkr(proof_request, receipt_out))
+            .await
+    }
+
+    /// Prove a batch of ZKR proof requests using a single server process.
+    ///
+    /// See [Client::prove_zkr_batch].
+    #[stability::unstable]
+    pub async fn prove_zkr_batch<Claim>(
+        &self,
+        proof_requests: Vec<ProveZkrRequest>,
+        receipt_out: AssetRequest,
+    ) -> Result<Vec<SuccinctReceipt<Claim>>, ProverError>
+    where
+        Claim:
+            risc0_binfmt::Digestible + std::fmt::Debug + Clone + serde::Serialize + Send + 'static,
+        crate::MaybePruned<Claim>: TryFrom<pb::core::MaybePruned, Error = anyhow::Error>,
+    {
+        self.spawn(move |client| client.prove_zkr_batch(proof_requests, receipt_out))
+            .await
+    }
+
+    /// Prove the specified keccak proof request.
+    #[stability::unstable]
+    pub async fn prove_keccak<Claim>(
+        &self,
+        proof_request: ProveKeccakRequest,
+        receipt_out: AssetRequest,
+    ) -> Result<SuccinctReceipt<Claim>, ProverError>
+    where
+        Claim:
+            risc0_binfmt::Digestible + std::fmt::Debug + Clone + serde::Serialize + Send + 'static,
+        crate::MaybePruned<Claim>: TryFrom<pb::core::MaybePruned, Error = anyhow::Error>,
+    {
+        self.spawn(move |client| client.prove_keccak(proof_request, receipt_out))
+            .await
+    }
+
+    /// Run the lift program to transform a [SegmentReceipt] into a [SuccinctReceipt].
+    ///
+    /// See [Client::lift].
+    pub async fn lift(
+        &self,
+        opts: &ProverOpts,
+        receipt: Asset,
+        receipt_out: AssetRequest,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| client.lift(&opts, receipt, receipt_out))
+            .await
+    }
+
+    /// Run the join program to compress two [SuccinctReceipt]s in the same session into one.
+    ///
+    /// See [Client::join].
+    pub async fn join(
+        &self,
+        opts: &ProverOpts,
+        left_receipt: Asset,
+        right_receipt: Asset,
+        receipt_out: AssetRequest,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| client.join(&opts, left_receipt, right_receipt, receipt_out))
+            .await
+    }
+
+    /// Run the resolve program to remove an assumption from a conditional [SuccinctReceipt].
+    ///
+    /// See [Client::resolve].
+    pub async fn resolve(
+        &self,
+        opts: &ProverOpts,
+        conditional_receipt: Asset,
+        assumption_receipt: Asset,
+        receipt_out: AssetRequest,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| {
+            client.resolve(&opts, conditional_receipt, assumption_receipt, receipt_out)
+        })
+        .await
+    }
+
+    /// Resolve each of the given assumptions in turn, starting from a conditional
+    /// [SuccinctReceipt], in a single request to the server.
+    ///
+    /// See [Client::resolve_batch].
+    pub async fn resolve_batch(
+        &self,
+        opts: &ProverOpts,
+        conditional_receipt: SuccinctReceipt<ReceiptClaim>,
+        assumption_receipts: Vec<SuccinctReceipt<Unknown>>,
+        receipt_out: AssetRequest,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| {
+            client.resolve_batch(&opts, conditional_receipt, assumption_receipts, receipt_out)
+        })
+        .await
+    }
+
+    /// Prove the verification of a recursion receipt using the Poseidon254 hash function for FRI.
+    ///
+    /// See [Client::identity_p254].
+    pub async fn identity_p254(
+        &self,
+        opts: &ProverOpts,
+        receipt: Asset,
+        receipt_out: AssetRequest,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| client.identity_p254(&opts, receipt, receipt_out))
+            .await
+    }
+
+    /// Compress a [Receipt], proving the same computation using a smaller representation.
+    ///
+    /// See [Client::compress].
+    pub async fn compress(
+        &self,
+        opts: &ProverOpts,
+        receipt: Asset,
+        receipt_out: AssetRequest,
+    ) -> Result<Receipt, ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| client.compress(&opts, receipt, receipt_out))
+            .await
+    }
+
+    /// Compress a succinct [Receipt] into a [Groth16Receipt](crate::Groth16Receipt).
+    ///
+    /// See [Client::compress_to_groth16].
+    pub async fn compress_to_groth16(
+        &self,
+        opts: &ProverOpts,
+        receipt: Asset,
+        receipt_out: AssetRequest,
+    ) -> Result<Receipt, ProverError> {
+        let opts = opts.clone();
+        self.spawn(move |client| client.compress_to_groth16(&opts, receipt, receipt_out))
+            .await
+    }
+
+    /// Read the asset under `key` from the [AssetStore](super::AssetStore).
+    ///
+    /// See [Client::fetch_asset].
+    pub async fn fetch_asset(&self, key: &StoreKey) -> Result<Bytes, ProverError> {
+        let key = key.clone();
+        self.spawn(move |client| client.fetch_asset(&key)).await
+    }
+
+    /// Verify a [Receipt].
+    pub async fn verify(
+        &self,
+        receipt: Asset,
+        image_id: impl Into<Digest>,
+    ) -> Result<(), ProverError> {
+        let image_id = image_id.into();
+        self.spawn(move |client| client.verify(receipt, image_id))
+            .await
+    }
+
+    /// Verify a [Receipt], returning the claim it proves.
+    ///
+    /// See [Client::verify_and_claim].
+    pub async fn verify_and_claim(
+        &self,
+        receipt: Asset,
+        image_id: impl Into<Digest>,
+    ) -> Result<ReceiptClaim, ProverError> {
+        let image_id = image_id.into();
+        self.spawn(move |client| client.verify_and_claim(receipt, image_id))
+            .await
+    }
+
+    async fn spawn<T, F>(&self, f: F) -> Result<T, ProverError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Client) -> Result<T, ProverError> + Send + 'static,
+    {
+        let factory = self.factory.clone();
+        tokio::task::spawn_blocking(move || f(&factory()?))
+            .await
+            .map_err(|err| ProverError::Other(err.into()))?
+    }
+}
+
+impl Default for AsyncClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -12,7 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::Path;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use anyhow::{anyhow, bail, Context, Result};
 use bytes::Bytes;
@@ -20,23 +25,221 @@ use prost::Message;
 use risc0_zkp::core::digest::Digest;
 
 use super::{
-    malformed_err, pb, Asset, AssetRequest, ConnectionWrapper, Connector, ParentProcessConnector,
-    SessionInfo,
+    failover::FailoverConnector, keccak_po2_range, malformed_err, pb, store, Asset, AssetRequest,
+    AssetStore, CancellationToken, Cancelled, ConnectionWrapper, Connector, Endpoint,
+    EndpointSelection, ParentProcessConnector, ProverError, SegmentAction, ServerInfo, SessionInfo,
+    StoreKey, TimedOut, UnsupportedPo2, ZkrInfo,
 };
 use crate::{
     get_version,
     host::{
         api::SegmentInfo,
-        client::{env::ProveZkrRequest, prove::get_r0vm_path},
+        client::{
+            env::{ProveZkrRequest, SegmentPath},
+            prove::get_r0vm_path,
+        },
     },
     receipt::{AssumptionReceipt, SegmentReceipt, SuccinctReceipt},
-    ExecutorEnv, Journal, ProveInfo, ProverOpts, Receipt, ReceiptClaim,
+    sha::Digestible,
+    ExecutorEnv, ExitCode, InnerReceipt, Journal, PagingStats, ProveInfo, ProverOpts, Receipt,
+    ReceiptClaim, ReceiptKind, Unknown,
 };
 
 /// A client implementation for interacting with a zkVM server.
+///
+/// A client is `Send + Sync` and can be shared between threads. Each call is served over its own
+/// connection, so concurrent calls such as [Client::execute] run independent sessions.
 pub struct Client {
-    connector: Box<dyn Connector>,
+    connector: ClientConnector,
     compat: bool,
+    pool: Option<ConnectionPool>,
+    authorization: Option<String>,
+    store: Option<Arc<dyn AssetStore>>,
+}
+
+// Endpoints are kept apart from other connectors so that a failed handshake moves on to the next
+// endpoint too.
+enum ClientConnector {
+    Single(Box<dyn Connector + Send + Sync>),
+    Failover(FailoverConnector),
+}
+
+/// A builder for a [Client].
+///
+/// By default the client connects to `r0vm` found the same way as [Client::from_env], and every
+/// request starts a new server.
+#[derive(Default)]
+pub struct ClientBuilder {
+    server_path: Option<PathBuf>,
+    connector: Option<Box<dyn Connector + Send + Sync>>,
+    pool_size: usize,
+    stderr: Option<Arc<Mutex<dyn Write + Send>>>,
+    endpoints: Option<Vec<Endpoint>>,
+    selection: EndpointSelection,
+    store: Option<Arc<dyn AssetStore>>,
+}
+
+impl ClientBuilder {
+    /// Connect to a sub-process which implements the server by calling the specified
+    /// `server_path`.
+    pub fn server_path<P: AsRef<Path>>(mut self, server_path: P) -> Self {
+        self.server_path = Some(server_path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Use the specified [Connector] to establish connections with the server.
+    ///
+    /// This takes precedence over [ClientBuilder::server_path].
+    pub fn connector(mut self, connector: Box<dyn Connector + Send + Sync>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    /// Send requests to any of `endpoints`, moving on to the next endpoint when one cannot be
+    /// reached.
+    ///
+    /// This takes precedence over [ClientBuilder::server_path]. See [Client::from_endpoints].
+    pub fn endpoints(mut self, endpoints: &[Endpoint]) -> Self {
+        self.endpoints = Some(endpoints.to_vec());
+        self
+    }
+
+    /// Choose the endpoint tried first for each connection with `selection`, rather than in
+    /// [round-robin](EndpointSelection::RoundRobin) order.
+    pub fn endpoint_selection(mut self, selection: EndpointSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Use `store` to read assets that a server returns as an [Asset::Store], e.g. with
+    /// [Client::fetch_asset].
+    pub fn asset_store(mut self, store: Arc<dyn AssetStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Keep up to `pool_size` idle connections open, so that repeated [Client::prove_zkr] and
+    /// [Client::prove_keccak] calls reuse a warm server rather than starting a new one for each
+    /// request.
+    ///
+    /// Idle connections are checked before they are reused, and ones the server has closed are
+    /// replaced with new connections. A `pool_size` of zero, the default, disables pooling.
+    pub fn pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    /// Forward what the server writes to stderr to `writer` as it is written, rather than to the
+    /// stderr of this process.
+    ///
+    /// Either way, the end of the output is included in the [ProverError::ProcessFailed] returned
+    /// when the server exits with an error. This only applies to servers started with
+    /// [ClientBuilder::server_path] or found in the environment.
+    pub fn stderr<W: Write + Send + 'static>(mut self, writer: W) -> Self {
+        self.stderr = Some(Arc::new(Mutex::new(writer)));
+        self
+    }
+
+    /// Build the [Client].
+    pub fn build(self) -> Result<Client, ProverError> {
+        // Remote endpoints send the same token as a client from Client::connect.
+        #[cfg(feature = "tls")]
+        let authorization = self
+            .endpoints
+            .iter()
+            .flatten()
+            .any(|endpoint| matches!(endpoint, Endpoint::Remote { .. }))
+            .then(super::tls::AuthToken::from_env)
+            .flatten()
+            .map(|auth| auth.authorization());
+        #[cfg(not(feature = "tls"))]
+        let authorization = None;
+
+        let connector = match (self.connector, self.endpoints, self.server_path) {
+            (Some(connector), _, _) => ClientConnector::Single(connector),
+            (None, Some(endpoints), _) => ClientConnector::Failover(
+                FailoverConnector::new(&endpoints, self.selection, self.stderr)
+                    .map_err(ProverError::Connection)?,
+            ),
+            (None, None, server_path) => {
+                let server_path = match server_path {
+                    Some(server_path) => server_path,
+                    None => get_r0vm_path()?,
+                };
+                let mut connector =
+                    ParentProcessConnector::new(server_path).map_err(ProverError::Connection)?;
+                if let Some(stderr) = self.stderr {
+                    connector = connector.with_stderr(stderr);
+                }
+                ClientConnector::Single(Box::new(connector))
+            }
+        };
+        Ok(Client {
+            connector,
+            compat: false,
+            pool: (self.pool_size > 0).then(|| ConnectionPool::new(self.pool_size)),
+            authorization,
+            store: self.store,
+        })
+    }
+}
+
+/// Idle connections to servers that agreed to keep handling requests.
+struct ConnectionPool {
+    size: usize,
+    idle: Mutex<Vec<ConnectionWrapper>>,
+}
+
+impl ConnectionPool {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            idle: Mutex::new(Vec::with_capacity(size)),
+        }
+    }
+
+    /// Take an idle connection that is still usable, discarding any the server has closed.
+    fn take(&self) -> Option<ConnectionWrapper> {
+        let mut idle = self.idle.lock().unwrap_or_else(|err| err.into_inner());
+        while let Some(mut conn) = idle.pop() {
+            if conn.is_healthy() {
+                return Some(conn);
+            }
+            tracing::debug!("discarding closed pooled connection");
+            if let Err(err) = conn.terminate() {
+                tracing::warn!("failed to terminate pooled server: {err:#}");
+            }
+        }
+        None
+    }
+
+    /// Return a connection to the pool, or hang up if the pool is already full.
+    fn put(&self, mut conn: ConnectionWrapper) {
+        let mut idle = self.idle.lock().unwrap_or_else(|err| err.into_inner());
+        if idle.len() < self.size {
+            idle.push(conn);
+            return;
+        }
+        drop(idle);
+        hang_up(&mut conn);
+    }
+}
+
+impl Drop for ConnectionPool {
+    fn drop(&mut self) {
+        let idle = self.idle.get_mut().unwrap_or_else(|err| err.into_inner());
+        for mut conn in idle.drain(..) {
+            hang_up(&mut conn);
+        }
+    }
+}
+
+fn hang_up(conn: &mut ConnectionWrapper) {
+    match conn.hang_up() {
+        Ok(0) => {}
+        Ok(code) => tracing::warn!("pooled server exited with code {code}"),
+        Err(err) => tracing::warn!("failed to close pooled connection: {err:#}"),
+    }
 }
 
 impl Default for Client {
@@ -47,14 +250,15 @@ impl Default for Client {
 
 impl Client {
     /// Construct a [Client] that connects to `r0vm` in a child process.
-    pub fn new() -> Result<Self> {
+    pub fn new() -> Result<Self, ProverError> {
         Self::new_sub_process("r0vm")
     }
 
     /// Construct a [Client] that connects to a sub-process which implements
     /// the server by calling the specified `server_path`.
-    pub fn new_sub_process<P: AsRef<Path>>(server_path: P) -> Result<Self> {
-        let connector = ParentProcessConnector::new(server_path)?;
+    pub fn new_sub_process<P: AsRef<Path>>(server_path: P) -> Result<Self, ProverError> {
+        let connector =
+            ParentProcessConnector::new(server_path).map_err(ProverError::Connection)?;
         Ok(Self::with_connector(Box::new(connector)))
     }
 
@@ -62,41 +266,132 @@ impl Client {
     /// the server by calling the specified `server_path`.
     ///
     /// Additionally allows for wider version mismatches, only rejecting major differences
-    pub fn new_sub_process_compat<P: AsRef<Path>>(server_path: P) -> Result<Self> {
-        let connector = ParentProcessConnector::new_wide_version(server_path)?;
+    pub fn new_sub_process_compat<P: AsRef<Path>>(server_path: P) -> Result<Self, ProverError> {
+        let connector = ParentProcessConnector::new_wide_version(server_path)
+            .map_err(ProverError::Connection)?;
         Ok(Self {
-            connector: Box::new(connector),
+            connector: ClientConnector::Single(Box::new(connector)),
             compat: true,
+            pool: None,
+            authorization: None,
+            store: None,
         })
     }
 
     /// Construct a [Client] based on environment variables.
-    pub fn from_env() -> Result<Self> {
-        Client::new_sub_process(get_r0vm_path()?)
+    ///
+    /// If `RISC0_SERVER_CHECK` is set, the server is asked for its [ServerInfo] before returning,
+    /// so that a server that cannot be started or is incompatible is reported here rather than on
+    /// the first request.
+    pub fn from_env() -> Result<Self, ProverError> {
+        let client = Client::new_sub_process(get_r0vm_path()?)?;
+        if std::env::var_os("RISC0_SERVER_CHECK").is_some() {
+            client.server_info()?;
+        }
+        Ok(client)
+    }
+
+    /// Construct a [Client] based on environment variables, falling back to executing and proving
+    /// in-process when no server is available.
+    ///
+    /// This behaves like [Client::from_env] if `RISC0_SERVER_PATH` is set or an `r0vm` server can
+    /// be started. Otherwise, when the `prove` feature is enabled, each request is handled by a
+    /// server running on a thread of this process with the local prover. The requests and
+    /// resulting receipts are the same either way. Without the `prove` feature, the error from
+    /// [Client::from_env] is returned.
+    pub fn from_env_or_local() -> Result<Self, ProverError> {
+        let client = Self::from_env();
+        #[cfg(feature = "prove")]
+        if client.is_err() && std::env::var_os("RISC0_SERVER_PATH").is_none() {
+            tracing::info!("r0vm not found, executing and proving in-process");
+            return Ok(Self::with_connector(Box::new(super::LocalConnector::new())));
+        }
+        client
+    }
+
+    /// Construct a [Client] that sends requests to any of several servers.
+    ///
+    /// Each connection starts at the next endpoint in turn, and moves on to the following
+    /// endpoints when that one cannot be reached or fails the handshake, e.g. with an incompatible
+    /// version, so a call fails with [ProverError::Connection] only when no endpoint can be
+    /// reached. A call is not retried on another endpoint once it
+    /// has been sent to a server. Use [ClientBuilder::endpoint_selection] to prefer the endpoint
+    /// with the fewest open connections instead.
+    ///
+    /// An endpoint that cannot be set up, e.g. an `r0vm` path that does not exist, is skipped
+    /// like one that cannot be reached. Returns an error if `endpoints` is empty.
+    pub fn from_endpoints(endpoints: &[Endpoint]) -> Result<Self, ProverError> {
+        Self::builder().endpoints(endpoints).build()
     }
 
     /// Construct a [Client] using the specified [Connector] to establish a
     /// connection with the server.
-    pub fn with_connector(connector: Box<dyn Connector>) -> Self {
+    pub fn with_connector(connector: Box<dyn Connector + Send + Sync>) -> Self {
         Self {
-            connector,
+            connector: ClientConnector::Single(connector),
             compat: false,
+            pool: None,
+            authorization: None,
+            store: None,
         }
     }
 
+    /// Construct a [Client] that connects to a remote server at an `https://` URL.
+    ///
+    /// The server's certificate is checked against the roots in `tls`. When `auth` is given, or
+    /// otherwise when the `RISC0_SERVER_TOKEN` environment variable is set, the token is sent as a
+    /// bearer token each time a connection is opened, for a proxy in front of the server to
+    /// authenticate the client.
+    ///
+    /// An invalid URL, and any failure to reach the server on later calls, is reported as
+    /// [ProverError::Connection] naming the URL.
+    #[cfg(feature = "tls")]
+    pub fn connect(
+        url: &str,
+        tls: super::tls::TlsConfig,
+        auth: Option<super::tls::AuthToken>,
+    ) -> Result<Self, ProverError> {
+        let connector = super::tls::TlsConnector::new(url, tls).map_err(ProverError::Connection)?;
+        let auth = auth.or_else(super::tls::AuthToken::from_env);
+        Ok(Self {
+            connector: ClientConnector::Single(Box::new(connector)),
+            compat: false,
+            pool: None,
+            authorization: auth.map(|auth| auth.authorization()),
+            store: None,
+        })
+    }
+
+    /// Construct a [ClientBuilder] for configuring a [Client], for example to reuse connections
+    /// with [ClientBuilder::pool_size].
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
     /// Prove the specified ELF binary.
+    ///
+    /// This is the simplest way to prove a guest: the server executes it, proves every segment,
+    /// proves any keccak and ZKR requests made by the guest that are not handled by a
+    /// [CoprocessorCallback](crate::CoprocessorCallback) in `env`, and compresses the result into
+    /// the kind of receipt selected by `opts`. The server checks the integrity of the receipt, but
+    /// callers should still verify it against the expected image ID.
+    ///
+    /// Use [Client::execute] together with [Client::prove_segment], [Client::lift],
+    /// [Client::join] and [Client::resolve] instead to distribute proving across several workers.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn prove(
         &self,
         env: &ExecutorEnv<'_>,
         opts: &ProverOpts,
-        binary: Asset,
-    ) -> Result<ProveInfo> {
-        let mut conn = self.connect()?;
+        binary: impl Into<Asset>,
+    ) -> Result<ProveInfo, ProverError> {
+        opts.validate()?;
+        let mut conn = self.connect_with_timeout(opts.timeout)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::Prove(
                 pb::api::ProveRequest {
-                    env: Some(self.make_execute_env(env, binary.try_into()?)?),
+                    env: Some(self.make_execute_env(env, Some(binary.into().try_into()?))?),
                     opts: Some(opts.clone().into()),
                     receipt_out: Some(pb::api::AssetRequest {
                         kind: Some(pb::api::asset_request::Kind::Inline(())),
@@ -110,56 +405,230 @@ impl Client {
 
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        let prove_info_bytes = asset.as_bytes()?;
+        let prove_info_bytes = asset.as_bytes(self.asset_store())?;
         let prove_info_pb = pb::core::ProveInfo::decode(prove_info_bytes)?;
-        prove_info_pb.try_into()
+        Ok(prove_info_pb.try_into()?)
     }
 
     /// Execute the specified ELF binary.
-    pub fn execute<F>(
+    ///
+    /// Segments are encoded as requested by `segments_out`, except that inline segments are
+    /// written to the [segment path](crate::ExecutorEnvBuilder::segment_path) of `env` if one is
+    /// set.
+    ///
+    /// `segment_callback` is called with each segment as it is produced. It may return `()` to
+    /// continue, or a [SegmentAction]; returning [SegmentAction::Stop] halts execution after the
+    /// current segment and returns an [incomplete](SessionInfo::is_complete) session.
+    pub fn execute<F, R>(
         &self,
         env: &ExecutorEnv<'_>,
-        binary: Asset,
+        binary: impl Into<Asset>,
         segments_out: AssetRequest,
         segment_callback: F,
-    ) -> Result<SessionInfo>
+    ) -> Result<SessionInfo, ProverError>
     where
-        F: FnMut(SegmentInfo, Asset) -> Result<()>,
+        F: FnMut(SegmentInfo, Asset) -> Result<R>,
+        R: Into<SegmentAction>,
     {
-        let mut conn = self.connect()?;
+        self.execute_with_cancellation(
+            env,
+            binary,
+            segments_out,
+            &CancellationToken::new(),
+            segment_callback,
+        )
+    }
+
+    /// Execute the specified ELF binary, stopping early if `cancel` is cancelled.
+    ///
+    /// The token is checked whenever the server completes a segment or makes an I/O request. Once
+    /// it has been cancelled, the server is told to stop executing and this returns a
+    /// [ProverError::Cancelled] error after the server has exited. Segments already passed to
+    /// `segment_callback` are complete and remain valid.
+    pub fn execute_with_cancellation<F, R>(
+        &self,
+        env: &ExecutorEnv<'_>,
+        binary: impl Into<Asset>,
+        segments_out: AssetRequest,
+        cancel: &CancellationToken,
+        segment_callback: F,
+    ) -> Result<SessionInfo, ProverError>
+    where
+        F: FnMut(SegmentInfo, Asset) -> Result<R>,
+        R: Into<SegmentAction>,
+    {
+        let mut conn = self.connect_with_timeout(None)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::Execute(
                 pb::api::ExecuteRequest {
-                    env: Some(self.make_execute_env(env, binary.try_into()?)?),
-                    segments_out: Some(segments_out.try_into()?),
+                    env: Some(self.make_execute_env(env, Some(binary.into().try_into()?))?),
+                    segments_out: Some(segments_request(env, segments_out).try_into()?),
+                    snapshot: None,
+                    stats_only: false,
                 },
             )),
         };
         // tracing::trace!("tx: {request:?}");
         conn.send(request)?;
 
-        let result = self.execute_handler(segment_callback, &mut conn, env);
+        let result = self.execute_handler(
+            with_segment_asset(segment_callback),
+            &mut conn,
+            env,
+            Some(cancel),
+        );
+
+        let code = conn.close()?;
+        let cancelled = matches!(&result, Err(err) if err.is::<Cancelled>());
+        if code != 0 && !cancelled && !is_stopped(&result) {
+            return Err(conn.child_finished(code));
+        }
+
+        Ok(result?)
+    }
+
+    /// Execute the specified ELF binary without producing segments.
+    ///
+    /// The guest runs to completion as with [Client::execute], but segments are neither serialized
+    /// nor returned; only their cycle counts are reported. This is cheaper than a full execution
+    /// and is intended for estimating the cost of proving, e.g. with [SessionInfo::stats]. The
+    /// resulting session cannot be proven.
+    pub fn execute_stats_only(
+        &self,
+        env: &ExecutorEnv<'_>,
+        binary: impl Into<Asset>,
+    ) -> Result<SessionInfo, ProverError> {
+        let mut conn = self.connect_with_timeout(None)?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::Execute(
+                pb::api::ExecuteRequest {
+                    env: Some(self.make_execute_env(env, Some(binary.into().try_into()?))?),
+                    segments_out: Some(AssetRequest::Inline.try_into()?),
+                    snapshot: None,
+                    stats_only: true,
+                },
+            )),
+        };
+        conn.send(request)?;
+
+        let result = self.execute_handler(|_, _| Ok(SegmentAction::Continue), &mut conn, env, None);
 
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        result
+        Ok(result?)
+    }
+
+    /// Execute the specified ELF binary, stopping after the first `max_segments` segments.
+    ///
+    /// Returns the session together with its segments, which are held inline and can be proven
+    /// with [Client::prove_segment]. This is intended for debugging a guest whose proof fails at
+    /// an unknown segment: proving successively longer prefixes finds the first segment that
+    /// breaks.
+    ///
+    /// Unless the guest finishes within `max_segments` segments, the session is
+    /// [incomplete](SessionInfo::is_complete) and its segments cannot be joined into a receipt for
+    /// the whole program.
+    pub fn execute_prefix(
+        &self,
+        env: &ExecutorEnv<'_>,
+        binary: impl Into<Asset>,
+        max_segments: usize,
+    ) -> Result<(SessionInfo, Vec<Asset>), ProverError> {
+        if max_segments == 0 {
+            return Err(anyhow!("max_segments must be at least 1").into());
+        }
+        let mut segments = Vec::new();
+        let session = self.execute(env, binary, AssetRequest::Inline, |_info, segment| {
+            segments.push(segment);
+            Ok(match segments.len() >= max_segments {
+                true => SegmentAction::Stop,
+                false => SegmentAction::Continue,
+            })
+        })?;
+        Ok((session, segments))
+    }
+
+    /// Resume execution from an encoded [SessionSnapshot](crate::SessionSnapshot),
+    /// such as one returned by [SegmentInfo::snapshot].
+    ///
+    /// The snapshot already contains the guest's memory image, so no binary is
+    /// needed. `env` should provide the same input as the original execution;
+    /// input consumed before the snapshot was taken is skipped.
+    pub fn execute_from_snapshot<F, R>(
+        &self,
+        env: &ExecutorEnv<'_>,
+        snapshot: Asset,
+        segments_out: AssetRequest,
+        segment_callback: F,
+    ) -> Result<SessionInfo, ProverError>
+    where
+        F: FnMut(SegmentInfo, Asset) -> Result<R>,
+        R: Into<SegmentAction>,
+    {
+        let mut conn = self.connect_with_timeout(None)?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::Execute(
+                pb::api::ExecuteRequest {
+                    env: Some(self.make_execute_env(env, None)?),
+                    segments_out: Some(segments_request(env, segments_out).try_into()?),
+                    snapshot: Some(snapshot.try_into()?),
+                    stats_only: false,
+                },
+            )),
+        };
+        conn.send(request)?;
+
+        let result =
+            self.execute_handler(with_segment_asset(segment_callback), &mut conn, env, None);
+
+        let code = conn.close()?;
+        if code != 0 && !is_stopped(&result) {
+            return Err(conn.child_finished(code));
+        }
+
+        Ok(result?)
+    }
+
+    /// Execute the specified ELF binary, sending each segment to `segments_tx`
+    /// as soon as it is produced.
+    ///
+    /// This is a blocking call intended to run on a dedicated thread while the
+    /// receiving end of the channel is consumed from async code, e.g. by
+    /// proving each segment with an [AsyncApiClient](crate::AsyncApiClient).
+    #[cfg(feature = "async-client")]
+    pub fn execute_to_channel(
+        &self,
+        env: &ExecutorEnv<'_>,
+        binary: impl Into<Asset>,
+        segments_out: AssetRequest,
+        segments_tx: tokio::sync::mpsc::UnboundedSender<(SegmentInfo, Asset)>,
+    ) -> Result<SessionInfo, ProverError> {
+        self.execute(env, binary, segments_out, |info, segment| {
+            segments_tx
+                .send((info, segment))
+                .map_err(|_| anyhow!("segment receiver was dropped"))
+        })
     }
 
     /// Prove the specified segment.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn prove_segment(
         &self,
         opts: &ProverOpts,
         segment: Asset,
         receipt_out: AssetRequest,
-    ) -> Result<SegmentReceipt> {
-        let mut conn = self.connect()?;
+    ) -> Result<SegmentReceipt, ProverError> {
+        opts.validate()?;
+        let mut conn = self.connect_with_timeout(opts.timeout)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::ProveSegment(
@@ -177,7 +646,10 @@ impl Client {
 
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::prove_segment_reply::Kind::Ok(result) => {
-                let receipt_bytes = result.receipt.ok_or(malformed_err())?.as_bytes()?;
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
                 let receipt_pb = pb::core::SegmentReceipt::decode(receipt_bytes)?;
                 receipt_pb.try_into()
             }
@@ -186,10 +658,32 @@ impl Client {
 
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        result
+        Ok(result?)
+    }
+
+    /// Prove each of the specified segments, e.g. those persisted by an earlier call to
+    /// [Client::execute].
+    ///
+    /// This does not re-run the executor: each segment is proven on its own by a call to
+    /// [Client::prove_segment], so segments from a single session may equally be distributed
+    /// across several clients. The returned receipts are in the same order as `segments`.
+    pub fn prove_segments(
+        &self,
+        opts: &ProverOpts,
+        segments: impl IntoIterator<Item = Asset>,
+        receipt_out: AssetRequest,
+    ) -> Result<Vec<SegmentReceipt>, ProverError> {
+        segments
+            .into_iter()
+            .enumerate()
+            .map(|(idx, segment)| {
+                self.prove_segment(opts, segment, receipt_out.clone())
+                    .map_err(|err| err.context(format!("prove segment {idx}")))
+            })
+            .collect()
     }
 
     /// Prove the specified ZKR proof request.
@@ -198,12 +692,48 @@ impl Client {
         &self,
         proof_request: ProveZkrRequest,
         receipt_out: AssetRequest,
-    ) -> Result<SuccinctReceipt<Claim>>
+    ) -> Result<SuccinctReceipt<Claim>, ProverError>
+    where
+        Claim: risc0_binfmt::Digestible + std::fmt::Debug + Clone + serde::Serialize,
+        crate::MaybePruned<Claim>: TryFrom<pb::core::MaybePruned, Error = anyhow::Error>,
+    {
+        self.prove_zkr_inner(proof_request, receipt_out, None)
+    }
+
+    /// Prove the specified ZKR proof request, terminating the server if it takes longer than
+    /// `timeout`.
+    ///
+    /// When the timeout is exceeded, a [ProverError::Timeout] is returned.
+    #[stability::unstable]
+    pub fn prove_zkr_with_timeout<Claim>(
+        &self,
+        proof_request: ProveZkrRequest,
+        receipt_out: AssetRequest,
+        timeout: Duration,
+    ) -> Result<SuccinctReceipt<Claim>, ProverError>
+    where
+        Claim: risc0_binfmt::Digestible + std::fmt::Debug + Clone + serde::Serialize,
+        crate::MaybePruned<Claim>: TryFrom<pb::core::MaybePruned, Error = anyhow::Error>,
+    {
+        self.prove_zkr_inner(proof_request, receipt_out, Some(timeout))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "prove_zkr",
+        skip_all,
+        fields(claim_digest = %proof_request.claim_digest, control_id = %proof_request.control_id),
+    ))]
+    fn prove_zkr_inner<Claim>(
+        &self,
+        proof_request: ProveZkrRequest,
+        receipt_out: AssetRequest,
+        timeout: Option<Duration>,
+    ) -> Result<SuccinctReceipt<Claim>, ProverError>
     where
         Claim: risc0_binfmt::Digestible + std::fmt::Debug + Clone + serde::Serialize,
         crate::MaybePruned<Claim>: TryFrom<pb::core::MaybePruned, Error = anyhow::Error>,
     {
-        let mut conn = self.connect()?;
+        let mut conn = self.checkout(timeout)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::ProveZkr(
@@ -223,33 +753,113 @@ impl Client {
 
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::prove_zkr_reply::Kind::Ok(result) => {
-                let receipt_bytes = result.receipt.ok_or(malformed_err())?.as_bytes()?;
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
                 let receipt_pb = pb::core::SuccinctReceipt::decode(receipt_bytes)?;
                 receipt_pb.try_into()
             }
             pb::api::prove_zkr_reply::Kind::Error(err) => Err(err.into()),
         };
 
+        self.checkin(conn)?;
+
+        Ok(result?)
+    }
+
+    /// Prove a batch of ZKR proof requests using a single server process.
+    ///
+    /// This avoids paying the connection and process start-up cost of [Client::prove_zkr] for
+    /// each request. The returned receipts are in the same order as `proof_requests`.
+    #[stability::unstable]
+    pub fn prove_zkr_batch<Claim>(
+        &self,
+        proof_requests: Vec<ProveZkrRequest>,
+        receipt_out: AssetRequest,
+    ) -> Result<Vec<SuccinctReceipt<Claim>>, ProverError>
+    where
+        Claim: risc0_binfmt::Digestible + std::fmt::Debug + Clone + serde::Serialize,
+        crate::MaybePruned<Claim>: TryFrom<pb::core::MaybePruned, Error = anyhow::Error>,
+    {
+        if proof_requests.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.connect_with_timeout(None)?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::ProveZkrBatch(
+                pb::api::ProveZkrBatchRequest {
+                    requests: proof_requests
+                        .into_iter()
+                        .map(|proof_request| pb::api::ProveZkrRequest {
+                            claim_digest: Some(proof_request.claim_digest.into()),
+                            control_id: Some(proof_request.control_id.into()),
+                            input: proof_request.input,
+                            receipt_out: None,
+                        })
+                        .collect(),
+                    receipt_out: Some(receipt_out.try_into()?),
+                },
+            )),
+        };
+
+        tracing::trace!("tx: {request:?}");
+        conn.send(request)?;
+
+        let reply: pb::api::ProveZkrBatchReply = conn.recv()?;
+
+        let result = match reply.kind.ok_or(malformed_err())? {
+            pb::api::prove_zkr_batch_reply::Kind::Ok(result) => result
+                .receipts
+                .into_iter()
+                .map(|asset| {
+                    let receipt_bytes = asset.as_bytes(self.asset_store())?;
+                    let receipt_pb = pb::core::SuccinctReceipt::decode(receipt_bytes)?;
+                    receipt_pb.try_into()
+                })
+                .collect(),
+            pb::api::prove_zkr_batch_reply::Kind::Error(err) => Err(err.into()),
+        };
+
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        result
+        Ok(result?)
     }
 
     /// Prove the specified keccak proof request.
+    ///
+    /// Returns a [ProverError::UnsupportedPo2] error without contacting the server if the requested
+    /// po2 is outside of [keccak_po2_range](crate::keccak_po2_range).
     #[stability::unstable]
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(claim_digest = %proof_request.claim_digest, po2 = proof_request.po2),
+    ))]
     pub fn prove_keccak<Claim>(
         &self,
         proof_request: crate::host::client::env::ProveKeccakRequest,
         receipt_out: AssetRequest,
-    ) -> Result<SuccinctReceipt<Claim>>
+    ) -> Result<SuccinctReceipt<Claim>, ProverError>
     where
         Claim: risc0_binfmt::Digestible + std::fmt::Debug + Clone + serde::Serialize,
         crate::MaybePruned<Claim>: TryFrom<pb::core::MaybePruned, Error = anyhow::Error>,
     {
-        let mut conn = self.connect()?;
+        let po2_range = keccak_po2_range();
+        if !u32::try_from(proof_request.po2).is_ok_and(|po2| po2_range.contains(&po2)) {
+            return Err(UnsupportedPo2 {
+                requested: proof_request.po2,
+                min: *po2_range.start(),
+                max: *po2_range.end(),
+            }
+            .into());
+        }
+
+        let mut conn = self.checkout(None)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::ProveKeccak(
@@ -270,19 +880,19 @@ impl Client {
 
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::prove_keccak_reply::Kind::Ok(result) => {
-                let receipt_bytes = result.receipt.ok_or(malformed_err())?.as_bytes()?;
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
                 let receipt_pb = pb::core::SuccinctReceipt::decode(receipt_bytes)?;
                 receipt_pb.try_into()
             }
             pb::api::prove_keccak_reply::Kind::Error(err) => Err(err.into()),
         };
 
-        let code = conn.close()?;
-        if code != 0 {
-            bail!("Child finished with: {code}");
-        }
+        self.checkin(conn)?;
 
-        result
+        Ok(result?)
     }
 
     /// Run the lift program to transform a [SegmentReceipt] into a [SuccinctReceipt].
@@ -291,13 +901,15 @@ impl Client {
     /// resulting in a recursion circuit STARK proof. This recursion proof has a single
     /// constant-time verification procedure, with respect to the original segment length, and is then
     /// used as the input to all other recursion programs (e.g. join, resolve, and identity_p254).
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn lift(
         &self,
         opts: &ProverOpts,
         receipt: Asset,
         receipt_out: AssetRequest,
-    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
-        let mut conn = self.connect()?;
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        opts.validate()?;
+        let mut conn = self.connect_with_timeout(opts.timeout)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::Lift(pb::api::LiftRequest {
@@ -313,7 +925,10 @@ impl Client {
 
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::lift_reply::Kind::Ok(result) => {
-                let receipt_bytes = result.receipt.ok_or(malformed_err())?.as_bytes()?;
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
                 let receipt_pb = pb::core::SuccinctReceipt::decode(receipt_bytes)?;
                 receipt_pb.try_into()
             }
@@ -322,24 +937,26 @@ impl Client {
 
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        result
+        Ok(result?)
     }
 
     /// Run the join program to compress two [SuccinctReceipt]s in the same session into one.
     ///
     /// By repeated application of the join program, any number of receipts for execution spans within
     /// the same session can be compressed into a single receipt for the entire session.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn join(
         &self,
         opts: &ProverOpts,
         left_receipt: Asset,
         right_receipt: Asset,
         receipt_out: AssetRequest,
-    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
-        let mut conn = self.connect()?;
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        opts.validate()?;
+        let mut conn = self.connect_with_timeout(opts.timeout)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::Join(pb::api::JoinRequest {
@@ -356,7 +973,10 @@ impl Client {
 
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::join_reply::Kind::Ok(result) => {
-                let receipt_bytes = result.receipt.ok_or(malformed_err())?.as_bytes()?;
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
                 let receipt_pb = pb::core::SuccinctReceipt::decode(receipt_bytes)?;
                 receipt_pb.try_into()
             }
@@ -365,10 +985,10 @@ impl Client {
 
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        result
+        Ok(result?)
     }
 
     /// Run the resolve program to remove an assumption from a conditional [SuccinctReceipt] upon
@@ -377,14 +997,16 @@ impl Client {
     /// By applying the resolve program, a conditional receipt (i.e. a receipt for an execution
     /// using the `env::verify` API to logically verify a receipt) can be made into an
     /// unconditional receipt.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn resolve(
         &self,
         opts: &ProverOpts,
         conditional_receipt: Asset,
         assumption_receipt: Asset,
         receipt_out: AssetRequest,
-    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
-        let mut conn = self.connect()?;
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        opts.validate()?;
+        let mut conn = self.connect_with_timeout(opts.timeout)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::Resolve(
@@ -403,7 +1025,10 @@ impl Client {
 
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::resolve_reply::Kind::Ok(result) => {
-                let receipt_bytes = result.receipt.ok_or(malformed_err())?.as_bytes()?;
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
                 let receipt_pb = pb::core::SuccinctReceipt::decode(receipt_bytes)?;
                 receipt_pb.try_into()
             }
@@ -412,10 +1037,96 @@ impl Client {
 
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        result
+        Ok(result?)
+    }
+
+    /// Resolve each of the given assumptions in turn, starting from a conditional
+    /// [SuccinctReceipt], in a single request to the server.
+    ///
+    /// Assumptions are resolved in the order given, starting from the head assumption of the
+    /// conditional receipt, and the result is the same as calling [Client::resolve] once per
+    /// assumption. Unlike [Client::resolve_all], the receipts are sent to the server only once and
+    /// intermediate receipts never leave it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn resolve_batch(
+        &self,
+        opts: &ProverOpts,
+        conditional_receipt: SuccinctReceipt<ReceiptClaim>,
+        assumption_receipts: Vec<SuccinctReceipt<Unknown>>,
+        receipt_out: AssetRequest,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        opts.validate()?;
+        let mut conn = self.connect_with_timeout(opts.timeout)?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::ResolveBatch(
+                pb::api::ResolveBatchRequest {
+                    opts: Some(opts.clone().into()),
+                    conditional_receipt: Some(Asset::try_from(conditional_receipt)?.try_into()?),
+                    assumption_receipts: assumption_receipts
+                        .into_iter()
+                        .map(|receipt| Asset::try_from(receipt)?.try_into())
+                        .collect::<Result<_>>()?,
+                    receipt_out: Some(receipt_out.try_into()?),
+                },
+            )),
+        };
+        conn.send(request)?;
+
+        let reply: pb::api::ResolveReply = conn.recv()?;
+
+        let result = match reply.kind.ok_or(malformed_err())? {
+            pb::api::resolve_reply::Kind::Ok(result) => {
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
+                let receipt_pb = pb::core::SuccinctReceipt::decode(receipt_bytes)?;
+                receipt_pb.try_into()
+            }
+            pb::api::resolve_reply::Kind::Error(err) => Err(err.into()),
+        };
+
+        let code = conn.close()?;
+        if code != 0 {
+            return Err(conn.child_finished(code));
+        }
+
+        Ok(result?)
+    }
+
+    /// Resolve each of the given assumptions in turn, starting from a conditional
+    /// [SuccinctReceipt].
+    ///
+    /// This is equivalent to calling [Client::resolve] once per assumption receipt, in order. After
+    /// each assumption is resolved, `on_progress` is called with the number of assumptions resolved
+    /// so far, the total number of assumptions, and the claim digest of the assumption that was
+    /// just resolved.
+    pub fn resolve_all(
+        &self,
+        opts: &ProverOpts,
+        conditional_receipt: SuccinctReceipt<ReceiptClaim>,
+        assumption_receipts: Vec<SuccinctReceipt<Unknown>>,
+        mut on_progress: impl FnMut(usize, usize, Digest),
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        let total = assumption_receipts.len();
+        let mut receipt = conditional_receipt;
+        for (idx, assumption_receipt) in assumption_receipts.into_iter().enumerate() {
+            let claim_digest = assumption_receipt.claim.digest();
+            receipt = self
+                .resolve(
+                    opts,
+                    receipt.try_into()?,
+                    assumption_receipt.try_into()?,
+                    AssetRequest::Inline,
+                )
+                .map_err(|err| err.context(format!("resolve assumption {claim_digest}")))?;
+            on_progress(idx + 1, total, claim_digest);
+        }
+        Ok(receipt)
     }
 
     /// Prove the verification of a recursion receipt using the Poseidon254 hash function for FRI.
@@ -428,8 +1139,9 @@ impl Client {
         opts: &ProverOpts,
         receipt: Asset,
         receipt_out: AssetRequest,
-    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
-        let mut conn = self.connect()?;
+    ) -> Result<SuccinctReceipt<ReceiptClaim>, ProverError> {
+        opts.validate()?;
+        let mut conn = self.connect_with_timeout(opts.timeout)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::IdentityP254(
@@ -447,7 +1159,10 @@ impl Client {
 
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::identity_p254_reply::Kind::Ok(result) => {
-                let receipt_bytes = result.receipt.ok_or(malformed_err())?.as_bytes()?;
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
                 let receipt_pb = pb::core::SuccinctReceipt::decode(receipt_bytes)?;
                 receipt_pb.try_into()
             }
@@ -456,10 +1171,10 @@ impl Client {
 
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        result
+        Ok(result?)
     }
 
     /// Compress a [Receipt], proving the same computation using a smaller representation.
@@ -491,8 +1206,9 @@ impl Client {
         opts: &ProverOpts,
         receipt: Asset,
         receipt_out: AssetRequest,
-    ) -> Result<Receipt> {
-        let mut conn = self.connect()?;
+    ) -> Result<Receipt, ProverError> {
+        opts.validate()?;
+        let mut conn = self.connect_with_timeout(opts.timeout)?;
 
         let request = pb::api::ServerRequest {
             kind: Some(pb::api::server_request::Kind::Compress(
@@ -510,7 +1226,10 @@ impl Client {
 
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::compress_reply::Kind::Ok(result) => {
-                let receipt_bytes = result.receipt.ok_or(malformed_err())?.as_bytes()?;
+                let receipt_bytes = result
+                    .receipt
+                    .ok_or(malformed_err())?
+                    .as_bytes(self.asset_store())?;
                 let receipt_pb = pb::core::Receipt::decode(receipt_bytes)?;
                 receipt_pb.try_into()
             }
@@ -519,15 +1238,52 @@ impl Client {
 
         let code = conn.close()?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
-        result
+        Ok(result?)
+    }
+
+    /// Compress a succinct [Receipt] into a [Groth16Receipt](crate::Groth16Receipt), which can be
+    /// verified on-chain (e.g. on Ethereum).
+    ///
+    /// This runs [Client::identity_p254] followed by the STARK-to-SNARK operation. Unlike
+    /// [Client::compress], the input must already be a
+    /// [SuccinctReceipt](crate::SuccinctReceipt), such as one produced by joining and resolving
+    /// the lifted segment receipts of a session. The `receipt_kind` of `opts` is ignored.
+    ///
+    /// NOTE: Compression to [Groth16Receipt](crate::Groth16Receipt) is currently only supported on
+    /// x86 hosts, and requires Docker to be installed.
+    pub fn compress_to_groth16(
+        &self,
+        opts: &ProverOpts,
+        receipt: Asset,
+        receipt_out: AssetRequest,
+    ) -> Result<Receipt, ProverError> {
+        let decoded: Receipt = receipt.clone().try_into()?;
+        if !matches!(decoded.inner, InnerReceipt::Succinct(_)) {
+            return Err(ProverError::Other(anyhow!(
+                "compress_to_groth16 requires a succinct receipt, found a {} receipt",
+                decoded.inner.kind_name()
+            )));
+        }
+
+        let opts = opts.clone().with_receipt_kind(ReceiptKind::Groth16);
+        self.compress(&opts, receipt, receipt_out)
+    }
+
+    /// Read the asset under `key` from the [AssetStore](super::AssetStore), e.g. a receipt a server
+    /// returned as an [Asset::Store].
+    ///
+    /// The bytes can be converted with [Asset::Inline], in the same way as an asset the server
+    /// returned inline.
+    pub fn fetch_asset(&self, key: &StoreKey) -> Result<Bytes, ProverError> {
+        Ok(store::require(self.asset_store())?.get(key)?)
     }
 
     /// Verify a [Receipt].
-    pub fn verify(&self, receipt: Asset, image_id: impl Into<Digest>) -> Result<()> {
-        let mut conn = self.connect().context("connect")?;
+    pub fn verify(&self, receipt: Asset, image_id: impl Into<Digest>) -> Result<(), ProverError> {
+        let mut conn = self.connect_with_timeout(None)?;
         let image_id = image_id.into();
 
         let request = pb::api::ServerRequest {
@@ -544,23 +1300,226 @@ impl Client {
         let reply: pb::api::GenericReply = conn.recv().context("error from server")?;
         let result = match reply.kind.ok_or(malformed_err())? {
             pb::api::generic_reply::Kind::Ok(ok) => Ok(ok),
-            pb::api::generic_reply::Kind::Error(err) => Err(err.into()),
+            pb::api::generic_reply::Kind::Error(err) => {
+                Err(ProverError::VerificationFailed(err.into()))
+            }
         };
 
         let code = conn.close().context("close")?;
         if code != 0 {
-            bail!("Child finished with: {code}");
+            return Err(conn.child_finished(code));
         }
 
         result
     }
 
-    fn connect(&self) -> Result<ConnectionWrapper> {
-        let mut conn = self.connector.connect()?;
+    /// Verify a [Receipt], returning the claim it proves.
+    ///
+    /// This is equivalent to [Client::verify], except that on success the [ReceiptClaim] of the
+    /// receipt is returned, so that callers need not decode the receipt again. The receipt is read
+    /// once and sent to the server inline, so the claim is always that of the receipt which was
+    /// verified. Returns an error if the receipt only holds the digest of its claim.
+    pub fn verify_and_claim(
+        &self,
+        receipt: Asset,
+        image_id: impl Into<Digest>,
+    ) -> Result<ReceiptClaim, ProverError> {
+        let image_id = image_id.into();
+        let receipt_bytes = self.asset_bytes(&receipt).context("read receipt asset")?;
+        self.verify(Asset::Inline(receipt_bytes.clone()), image_id)?;
+
+        let receipt: Receipt =
+            bincode::deserialize(&receipt_bytes).context("deserialize receipt")?;
+        Ok(receipt
+            .claim()
+            .context("decode receipt claim")?
+            .value()
+            .context("receipt claim is pruned")?)
+    }
+
+    /// Ask the server for its version and the options it supports.
+    ///
+    /// This is a cheap request that can be used to check that a server is reachable before
+    /// submitting work to it.
+    pub fn server_info(&self) -> Result<ServerInfo, ProverError> {
+        let mut conn = self.connect_with_timeout(None)?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::ServerInfo(
+                pb::api::ServerInfoRequest {},
+            )),
+        };
+        conn.send(request)?;
+
+        let reply: pb::api::ServerInfoReply = conn.recv()?;
+        let result = match reply.kind.ok_or(malformed_err())? {
+            pb::api::server_info_reply::Kind::Ok(info) => info.try_into(),
+            pb::api::server_info_reply::Kind::Error(err) => Err(err.into()),
+        };
+
+        let code = conn.close()?;
+        if code != 0 {
+            return Err(conn.child_finished(code));
+        }
+
+        Ok(result?)
+    }
+
+    /// Ask the server for the recursion programs (ZKRs) registered with it.
+    ///
+    /// Only a ZKR listed here can be proven by the server with
+    /// [Client::prove_zkr](Self::prove_zkr) and related requests.
+    pub fn supported_zkrs(&self) -> Result<Vec<ZkrInfo>, ProverError> {
+        let mut conn = self.connect_with_timeout(None)?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::SupportedZkrs(
+                pb::api::SupportedZkrsRequest {},
+            )),
+        };
+        conn.send(request)?;
+
+        let reply: pb::api::SupportedZkrsReply = conn.recv()?;
+        let result = match reply.kind.ok_or(malformed_err())? {
+            pb::api::supported_zkrs_reply::Kind::Ok(result) => result
+                .zkrs
+                .into_iter()
+                .map(ZkrInfo::try_from)
+                .collect::<Result<_>>(),
+            pb::api::supported_zkrs_reply::Kind::Error(err) => Err(err.into()),
+        };
+
+        let code = conn.close()?;
+        if code != 0 {
+            return Err(conn.child_finished(code));
+        }
+
+        Ok(result?)
+    }
+
+    /// Ask the server to load the recursion programs used to prove with `opts`, so that later
+    /// requests do not pay the cost of loading them.
+    ///
+    /// The programs are cached by the server process for each hash function and maximum segment
+    /// po2. An in-process server shares them with every later request, while an `r0vm` server
+    /// only keeps them if its connection is pooled; see [ClientBuilder::pool_size].
+    pub fn warm_up(&self, opts: &ProverOpts) -> Result<(), ProverError> {
+        opts.validate()?;
+        let mut conn = self.checkout(None)?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::WarmUp(
+                pb::api::WarmUpRequest {
+                    opts: Some(opts.clone().into()),
+                },
+            )),
+        };
+        conn.send(request)?;
+
+        let reply: pb::api::GenericReply = conn.recv()?;
+        let result: Result<()> = match reply.kind.ok_or(malformed_err())? {
+            pb::api::generic_reply::Kind::Ok(_) => Ok(()),
+            pb::api::generic_reply::Kind::Error(err) => Err(err.into()),
+        };
+
+        self.checkin(conn)?;
+
+        Ok(result?)
+    }
+
+    fn asset_store(&self) -> Option<&dyn AssetStore> {
+        self.store.as_deref()
+    }
+
+    /// Read the bytes of `asset`, using the [AssetStore] of this client for an [Asset::Store].
+    fn asset_bytes(&self, asset: &Asset) -> Result<Bytes> {
+        match asset {
+            Asset::Store(key) => store::require(self.asset_store())?.get(key),
+            asset => asset.as_bytes(),
+        }
+    }
+
+    fn connect_with_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<ConnectionWrapper, ProverError> {
+        self.open(timeout, false)
+    }
+
+    /// Get a connection for a request that may reuse a pooled connection, connecting to a new
+    /// server if no idle connection is available.
+    fn checkout(&self, timeout: Option<Duration>) -> Result<ConnectionWrapper, ProverError> {
+        match self.pool.as_ref().and_then(ConnectionPool::take) {
+            Some(conn) => Ok(conn.with_timeout(timeout)),
+            None => self.open(timeout, self.pool.is_some()),
+        }
+    }
+
+    /// Finish with a connection from [Client::checkout], returning it to the pool if the server is
+    /// keeping it alive.
+    fn checkin(&self, mut conn: ConnectionWrapper) -> Result<(), ProverError> {
+        if let Some(pool) = self.pool.as_ref().filter(|_| conn.keep_alive) {
+            pool.put(conn.with_timeout(None));
+            return Ok(());
+        }
+        let code = conn.close()?;
+        if code != 0 {
+            return Err(conn.child_finished(code));
+        }
+        Ok(())
+    }
+
+    fn open(
+        &self,
+        timeout: Option<Duration>,
+        keep_alive: bool,
+    ) -> Result<ConnectionWrapper, ProverError> {
+        self.try_connect(timeout, keep_alive).map_err(|err| {
+            // A server that exited keeps the reason it exited.
+            let err = match err.downcast::<ProverError>() {
+                Ok(err @ ProverError::ProcessFailed { .. }) => return err,
+                Ok(err) => anyhow::Error::new(err),
+                Err(err) => err,
+            };
+            // A stalled handshake is still a timeout rather than a connection failure.
+            match err
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<TimedOut>())
+            {
+                Some(&TimedOut { timeout }) => ProverError::Timeout(TimedOut { timeout }),
+                None => ProverError::Connection(err),
+            }
+        })
+    }
+
+    fn try_connect(
+        &self,
+        timeout: Option<Duration>,
+        keep_alive: bool,
+    ) -> Result<ConnectionWrapper> {
+        match &self.connector {
+            ClientConnector::Single(connector) => {
+                self.handshake(connector.connect()?, timeout, keep_alive)
+            }
+            ClientConnector::Failover(connector) => {
+                connector.connect_with(|conn| self.handshake(conn, timeout, keep_alive))
+            }
+        }
+    }
+
+    fn handshake(
+        &self,
+        conn: ConnectionWrapper,
+        timeout: Option<Duration>,
+        keep_alive: bool,
+    ) -> Result<ConnectionWrapper> {
+        let mut conn = conn.with_timeout(timeout);
 
         let client_version = get_version().map_err(|err| anyhow!(err))?;
         let request = pb::api::HelloRequest {
             version: Some(client_version.clone().into()),
+            keep_alive,
+            authorization: self.authorization.clone().unwrap_or_default(),
         };
         // tracing::trace!("tx: {request:?}");
         conn.send(request)?;
@@ -585,6 +1544,8 @@ impl Client {
                     tracing::warn!("{msg}");
                     bail!(msg);
                 }
+                // Older servers ignore the request and close the connection after one request.
+                conn.keep_alive = reply.keep_alive;
             }
             pb::api::hello_reply::Kind::Error(err) => {
                 let code = conn.close()?;
@@ -599,17 +1560,20 @@ impl Client {
     fn make_execute_env(
         &self,
         env: &ExecutorEnv<'_>,
-        binary: pb::api::Asset,
+        binary: Option<pb::api::Asset>,
     ) -> Result<pb::api::ExecutorEnv> {
         Ok(pb::api::ExecutorEnv {
-            binary: Some(binary),
+            binary,
             env_vars: env.env_vars.clone(),
             args: env.args.clone(),
             slice_ios: env.slice_io.borrow().inner.keys().cloned().collect(),
             read_fds: env.posix_io.borrow().read_fds(),
             write_fds: env.posix_io.borrow().write_fds(),
             segment_limit_po2: env.segment_limit_po2,
+            segment_po2_hint: env.segment_po2_hint,
+            coalesce_po2: env.coalesce_po2,
             session_limit: env.session_limit,
+            checkpoint_interval: env.checkpoint_interval,
             trace_events: (!env.trace.is_empty()).then_some(()),
             coprocessor: env.coprocessor.is_some(),
             pprof_out: env
@@ -655,14 +1619,16 @@ impl Client {
         })
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "execute", skip_all))]
     fn execute_handler<F>(
         &self,
         segment_callback: F,
         conn: &mut ConnectionWrapper,
         env: &ExecutorEnv<'_>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<SessionInfo>
     where
-        F: FnMut(SegmentInfo, Asset) -> Result<()>,
+        F: FnMut(SegmentInfo, Option<Asset>) -> Result<SegmentAction>,
     {
         let mut segment_callback = segment_callback;
         let mut segments = Vec::new();
@@ -672,29 +1638,82 @@ impl Client {
 
             match reply.kind.ok_or(malformed_err())? {
                 pb::api::server_reply::Kind::Ok(request) => {
-                    match request.kind.ok_or(malformed_err())? {
+                    let kind = request.kind.ok_or(malformed_err())?;
+                    if cancel.is_some_and(CancellationToken::is_cancelled) {
+                        // Answer the pending callback with an error so that the server stops
+                        // executing and exits on its own.
+                        match kind {
+                            pb::api::client_callback::Kind::Io(_) => {
+                                let msg: pb::api::OnIoReply =
+                                    Result::<Bytes>::Err(Cancelled.into()).into();
+                                conn.send(msg)?;
+                            }
+                            pb::api::client_callback::Kind::SegmentDone(_) => {
+                                let msg: pb::api::GenericReply =
+                                    Result::<()>::Err(Cancelled.into()).into();
+                                conn.send(msg)?;
+                            }
+                            _ => {}
+                        }
+                        return Err(Cancelled.into());
+                    }
+                    match kind {
                         pb::api::client_callback::Kind::Io(io) => {
                             let msg: pb::api::OnIoReply = self.on_io(env, io).into();
                             // tracing::trace!("tx: {msg:?}");
                             conn.send(msg)?;
                         }
                         pb::api::client_callback::Kind::SegmentDone(segment) => {
-                            let reply: pb::api::GenericReply = segment
-                                .segment
-                                .map_or_else(
-                                    || Err(malformed_err()),
-                                    |segment| {
-                                        let asset =
-                                            segment.segment.ok_or(malformed_err())?.try_into()?;
-                                        let info = SegmentInfo {
-                                            po2: segment.po2,
-                                            cycles: segment.cycles,
-                                        };
-                                        segments.push(info.clone());
-                                        segment_callback(info, asset)
-                                    },
-                                )
+                            let result = segment.segment.map_or_else(
+                                || Err(malformed_err()),
+                                |segment| {
+                                    let asset = segment.segment.map(Asset::try_from).transpose()?;
+                                    let snapshot = segment
+                                        .snapshot
+                                        .map(|snapshot| Asset::try_from(snapshot)?.as_bytes())
+                                        .transpose()?;
+                                    let info = SegmentInfo::new(
+                                        segment.po2,
+                                        segment.cycles,
+                                        segment.paging_cycles,
+                                        PagingStats {
+                                            page_in_count: segment.page_in_count,
+                                            page_out_count: segment.page_out_count,
+                                            unique_pages_touched: segment.unique_pages_touched,
+                                        },
+                                    );
+                                    #[cfg(feature = "tracing")]
+                                    let _span = tracing::info_span!(
+                                        "segment",
+                                        segment_idx = segments.len(),
+                                        po2 = info.po2,
+                                    )
+                                    .entered();
+                                    // Snapshots hold the whole memory image, so only the callback
+                                    // receives them.
+                                    segments.push(info.clone());
+                                    segment_callback(info.with_snapshot(snapshot), asset)
+                                },
+                            );
+                            if let Ok(SegmentAction::Stop) = result {
+                                // Answer with an error so that the server stops executing and
+                                // exits on its own, as with cancellation.
+                                let msg: pb::api::GenericReply = Result::<()>::Err(anyhow!(
+                                    "execution stopped by segment callback"
+                                ))
                                 .into();
+                                conn.send(msg)?;
+                                return Ok(SessionInfo {
+                                    segments,
+                                    journal: Journal::new(Vec::new()),
+                                    exit_code: ExitCode::SystemSplit,
+                                    receipt_claim: None,
+                                    unique_pages_touched: 0,
+                                    partial_journal: Vec::new(),
+                                    complete: false,
+                                });
+                            }
+                            let reply: pb::api::GenericReply = result.map(|_| ()).into();
                             // tracing::trace!("tx: {reply:?}");
                             conn.send(reply)?;
                         }
@@ -703,8 +1722,10 @@ impl Client {
                                 Some(session) => {
                                     let receipt_claim = match session.receipt_claim {
                                         Some(claim) => Some(
-                                            pb::core::ReceiptClaim::decode(claim.as_bytes()?)?
-                                                .try_into()?,
+                                            pb::core::ReceiptClaim::decode(
+                                                claim.as_bytes(self.asset_store())?,
+                                            )?
+                                            .try_into()?,
                                         ),
                                         None => None,
                                     };
@@ -717,6 +1738,9 @@ impl Client {
                                             .ok_or(malformed_err())?
                                             .try_into()?,
                                         receipt_claim,
+                                        unique_pages_touched: session.unique_pages_touched,
+                                        partial_journal: session.partial_journal,
+                                        complete: true,
                                     })
                                 }
                                 None => Err(malformed_err()),
@@ -728,6 +1752,7 @@ impl Client {
                     }
                 }
                 pb::api::server_reply::Kind::Error(err) => return Err(err.into()),
+                pb::api::server_reply::Kind::GuestFault(fault) => return Err(fault.into()),
             }
         }
     }
@@ -761,6 +1786,7 @@ impl Client {
                     }
                 }
                 pb::api::server_reply::Kind::Error(err) => return Err(err.into()),
+                pb::api::server_reply::Kind::GuestFault(fault) => return Err(fault.into()),
             }
         }
     }
@@ -863,6 +1889,33 @@ impl From<Result<Bytes, anyhow::Error>> for pb::api::OnIoReply {
     }
 }
 
+/// Segments requested inline are written to the [segment path](crate::ExecutorEnvBuilder::segment_path)
+/// of `env` instead, if one was set.
+fn segments_request(env: &ExecutorEnv<'_>, segments_out: AssetRequest) -> AssetRequest {
+    match (segments_out, &env.segment_path) {
+        (AssetRequest::Inline, Some(SegmentPath::Path(path))) => AssetRequest::Path(path.clone()),
+        (segments_out, _) => segments_out,
+    }
+}
+
+/// Adapt a segment callback to the one used by [Client::execute_handler], requiring that the
+/// server sent a segment asset.
+fn with_segment_asset<F, R>(
+    mut segment_callback: F,
+) -> impl FnMut(SegmentInfo, Option<Asset>) -> Result<SegmentAction>
+where
+    F: FnMut(SegmentInfo, Asset) -> Result<R>,
+    R: Into<SegmentAction>,
+{
+    move |info, asset| Ok(segment_callback(info, asset.ok_or(malformed_err())?)?.into())
+}
+
+/// Returns true if execution was stopped early by a segment callback, in which case the server
+/// is expected to exit with a nonzero code.
+fn is_stopped(result: &Result<SessionInfo>) -> bool {
+    result.as_ref().is_ok_and(|session| !session.is_complete())
+}
+
 pub(crate) fn check_server_version(requested: &semver::Version, server: &semver::Version) -> bool {
     if requested.pre.is_empty() {
         requested.major == server.major && requested.minor == server.minor
@@ -20,7 +20,10 @@ use risc0_binfmt::SystemState;
 use risc0_zkp::core::digest::Digest;
 use serde::Serialize;
 
-use super::{malformed_err, path_to_string, pb, Asset, AssetRequest, RedisParams};
+use super::{
+    malformed_err, path_to_string, pb, Asset, AssetRequest, ProverError, RedisParams, ServerInfo,
+    ZkrInfo,
+};
 use crate::{
     host::client::env::ProveKeccakRequest,
     host::client::env::ProveZkrRequest,
@@ -31,7 +34,8 @@ use crate::{
     },
     receipt_claim::Unknown,
     Assumption, Assumptions, ExitCode, Groth16Receipt, Input, Journal, MaybePruned, Output,
-    ProveInfo, ProverOpts, Receipt, ReceiptClaim, ReceiptKind, SessionStats, TraceEvent,
+    PagingStats, ProveInfo, ProverOpts, Receipt, ReceiptClaim, ReceiptKind, SessionStats,
+    TraceEvent,
 };
 
 mod ver {
@@ -60,6 +64,7 @@ impl TryFrom<AssetRequest> for pb::api::AssetRequest {
                         ttl: params.ttl,
                     })
                 }
+                AssetRequest::Store(key) => pb::api::asset_request::Kind::Store(key.to_string()),
             }),
         })
     }
@@ -74,6 +79,7 @@ impl TryFrom<Asset> for pb::api::Asset {
                 Asset::Inline(bytes) => Some(pb::api::asset::Kind::Inline(bytes.into())),
                 Asset::Path(path) => Some(pb::api::asset::Kind::Path(path_to_string(path)?)),
                 Asset::Redis(key) => Some(pb::api::asset::Kind::Redis(key)),
+                Asset::Store(key) => Some(pb::api::asset::Kind::Store(key.to_string())),
             },
         })
     }
@@ -106,6 +112,34 @@ impl TryFrom<Receipt> for Asset {
     }
 }
 
+impl<Claim> TryFrom<Asset> for SuccinctReceipt<Claim>
+where
+    Claim: risc0_binfmt::Digestible + Debug + Clone + Serialize,
+    SuccinctReceipt<Claim>: serde::de::DeserializeOwned,
+{
+    type Error = anyhow::Error;
+
+    fn try_from(asset: Asset) -> Result<Self> {
+        Ok(bincode::deserialize(&asset.as_bytes()?)?)
+    }
+}
+
+impl TryFrom<Asset> for SegmentReceipt {
+    type Error = anyhow::Error;
+
+    fn try_from(asset: Asset) -> Result<Self> {
+        Ok(bincode::deserialize(&asset.as_bytes()?)?)
+    }
+}
+
+impl TryFrom<Asset> for Receipt {
+    type Error = anyhow::Error;
+
+    fn try_from(asset: Asset) -> Result<Self> {
+        Ok(bincode::deserialize(&asset.as_bytes()?)?)
+    }
+}
+
 impl TryFrom<pb::api::Asset> for Asset {
     type Error = anyhow::Error;
 
@@ -114,6 +148,7 @@ impl TryFrom<pb::api::Asset> for Asset {
             pb::api::asset::Kind::Inline(bytes) => Asset::Inline(bytes.into()),
             pb::api::asset::Kind::Path(path) => Asset::Path(PathBuf::from(path)),
             pb::api::asset::Kind::Redis(key) => Asset::Redis(key),
+            pb::api::asset::Kind::Store(key) => Asset::Store(key.into()),
         })
     }
 }
@@ -132,6 +167,7 @@ impl TryFrom<pb::api::AssetRequest> for AssetRequest {
                 key: params.key,
                 ttl: params.ttl,
             }),
+            pb::api::asset_request::Kind::Store(key) => AssetRequest::Store(key.into()),
         })
     }
 }
@@ -238,6 +274,16 @@ impl From<pb::api::GenericError> for anyhow::Error {
     }
 }
 
+impl From<pb::api::GuestFault> for anyhow::Error {
+    fn from(fault: pb::api::GuestFault) -> Self {
+        ProverError::GuestFault {
+            reason: fault.reason,
+            partial_journal: fault.partial_journal,
+        }
+        .into()
+    }
+}
+
 impl TryFrom<pb::api::ProverOpts> for ProverOpts {
     type Error = anyhow::Error;
 
@@ -256,10 +302,23 @@ impl TryFrom<pb::api::ProverOpts> for ProverOpts {
                 .into_iter()
                 .map(TryInto::try_into)
                 .collect::<Result<_>>()?,
+            // Clients that predate this field leave it unset.
+            min_segment_po2: match opts.min_segment_po2 {
+                0 => risc0_zkp::MIN_CYCLES_PO2,
+                po2 => po2.try_into().map_err(|_| malformed_err())?,
+            },
             max_segment_po2: opts
                 .max_segment_po2
                 .try_into()
                 .map_err(|_| malformed_err())?,
+            segment_limit_po2: opts.segment_limit_po2,
+            // The timeout is enforced by the client, not the server.
+            timeout: None,
+            deterministic: opts.deterministic,
+            keep_composite: opts.keep_composite,
+            // A segment cache is local to the process that set it.
+            #[cfg(feature = "prove")]
+            segment_cache: None,
         })
     }
 }
@@ -272,6 +331,10 @@ impl From<ProverOpts> for pb::api::ProverOpts {
             receipt_kind: opts.receipt_kind as i32,
             control_ids: opts.control_ids.into_iter().map(Into::into).collect(),
             max_segment_po2: opts.max_segment_po2 as u64,
+            min_segment_po2: opts.min_segment_po2 as u64,
+            segment_limit_po2: opts.segment_limit_po2,
+            deterministic: opts.deterministic,
+            keep_composite: opts.keep_composite,
         }
     }
 }
@@ -302,6 +365,58 @@ impl TryFrom<pb::base::SemanticVersion> for semver::Version {
     }
 }
 
+impl From<ServerInfo> for pb::api::ServerInfoResult {
+    fn from(value: ServerInfo) -> Self {
+        Self {
+            version: Some(value.version.into()),
+            hashfns: value.supported_hashfns,
+            max_po2: value.max_po2,
+            features: value.features,
+            control_root: value.control_root.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<pb::api::ServerInfoResult> for ServerInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(value: pb::api::ServerInfoResult) -> Result<Self> {
+        Ok(Self {
+            version: value
+                .version
+                .ok_or(malformed_err())?
+                .try_into()
+                .map_err(|err: semver::Error| anyhow!(err))?,
+            supported_hashfns: value.hashfns,
+            max_po2: value.max_po2,
+            features: value.features,
+            control_root: value.control_root.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<ZkrInfo> for pb::api::ZkrInfo {
+    fn from(value: ZkrInfo) -> Self {
+        Self {
+            control_id: Some(value.control_id.into()),
+            name: value.name,
+            input_words: value.input_words,
+        }
+    }
+}
+
+impl TryFrom<pb::api::ZkrInfo> for ZkrInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(value: pb::api::ZkrInfo) -> Result<Self> {
+        Ok(Self {
+            control_id: value.control_id.ok_or(malformed_err())?.try_into()?,
+            name: value.name,
+            input_words: value.input_words,
+        })
+    }
+}
+
 impl From<SessionStats> for pb::core::SessionStats {
     fn from(value: SessionStats) -> Self {
         Self {
@@ -311,6 +426,7 @@ impl From<SessionStats> for pb::core::SessionStats {
             user_cycles: value.user_cycles,
             paging_cycles: value.paging_cycles,
             reserved_cycles: value.reserved_cycles,
+            paging: Some(value.paging.into()),
         }
     }
 }
@@ -325,15 +441,37 @@ impl TryFrom<pb::core::SessionStats> for SessionStats {
             user_cycles: value.user_cycles,
             paging_cycles: value.paging_cycles,
             reserved_cycles: value.reserved_cycles,
+            paging: value.paging.map(Into::into).unwrap_or_default(),
         })
     }
 }
 
+impl From<PagingStats> for pb::core::PagingStats {
+    fn from(value: PagingStats) -> Self {
+        Self {
+            page_in_count: value.page_in_count,
+            page_out_count: value.page_out_count,
+            unique_pages_touched: value.unique_pages_touched,
+        }
+    }
+}
+
+impl From<pb::core::PagingStats> for PagingStats {
+    fn from(value: pb::core::PagingStats) -> Self {
+        Self {
+            page_in_count: value.page_in_count,
+            page_out_count: value.page_out_count,
+            unique_pages_touched: value.unique_pages_touched,
+        }
+    }
+}
+
 impl From<ProveInfo> for pb::core::ProveInfo {
     fn from(value: ProveInfo) -> Self {
         Self {
             receipt: Some(value.receipt.into()),
             stats: Some(value.stats.into()),
+            composite: value.composite.map(Into::into),
         }
     }
 }
@@ -345,6 +483,7 @@ impl TryFrom<pb::core::ProveInfo> for ProveInfo {
         Ok(Self {
             receipt: value.receipt.ok_or(malformed_err())?.try_into()?,
             stats: value.stats.ok_or(malformed_err())?.try_into()?,
+            composite: value.composite.map(TryInto::try_into).transpose()?,
         })
     }
 }
@@ -0,0 +1,193 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Spreading requests over several servers, moving on to the next server when one cannot be
+//! reached or fails the handshake.
+
+use std::{
+    io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use super::{ConnectionWrapper, Connector, ParentProcessConnector};
+
+/// A server that a [Client](super::client::Client) can send requests to, for use with
+/// [Client::from_endpoints](super::client::Client::from_endpoints).
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum Endpoint {
+    /// An `r0vm` server started as a child process from the given path.
+    SubProcess(PathBuf),
+
+    /// A remote server at an `https://` URL.
+    #[cfg(feature = "tls")]
+    Remote {
+        /// The URL of the server.
+        url: String,
+
+        /// The certificates trusted to identify the server.
+        tls: super::tls::TlsConfig,
+    },
+
+    /// A server reached with a custom [Connector].
+    Connector(Arc<dyn Connector + Send + Sync>),
+}
+
+/// How a [Client](super::client::Client) with several [Endpoint]s chooses the endpoint to try
+/// first for each connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EndpointSelection {
+    /// Start at the endpoint after the one the previous connection started at.
+    #[default]
+    RoundRobin,
+
+    /// Start at the endpoint with the fewest open connections.
+    LeastLoaded,
+}
+
+struct Target {
+    name: String,
+    // An endpoint that could not be set up fails each connection with the reason.
+    connector: Result<Arc<dyn Connector + Send + Sync>, String>,
+    load: Arc<AtomicUsize>,
+}
+
+/// Connects to the first of several endpoints that can be reached.
+pub(crate) struct FailoverConnector {
+    targets: Vec<Target>,
+    selection: EndpointSelection,
+    next: AtomicUsize,
+}
+
+impl FailoverConnector {
+    pub(crate) fn new(
+        endpoints: &[Endpoint],
+        selection: EndpointSelection,
+        stderr: Option<Arc<Mutex<dyn Write + Send>>>,
+    ) -> Result<Self> {
+        if endpoints.is_empty() {
+            bail!("at least one endpoint is required");
+        }
+        let targets = endpoints
+            .iter()
+            .enumerate()
+            .map(|(idx, endpoint)| {
+                let (name, connector): (_, Result<Arc<dyn Connector + Send + Sync>>) =
+                    match endpoint {
+                        Endpoint::SubProcess(path) => (
+                            path.display().to_string(),
+                            ParentProcessConnector::new(path).map(|connector| {
+                                let connector = match stderr.clone() {
+                                    Some(stderr) => connector.with_stderr(stderr),
+                                    None => connector,
+                                };
+                                Arc::new(connector) as Arc<dyn Connector + Send + Sync>
+                            }),
+                        ),
+                        #[cfg(feature = "tls")]
+                        Endpoint::Remote { url, tls } => (
+                            url.clone(),
+                            super::tls::TlsConnector::new(url, tls.clone()).map(|connector| {
+                                Arc::new(connector) as Arc<dyn Connector + Send + Sync>
+                            }),
+                        ),
+                        Endpoint::Connector(connector) => {
+                            (format!("endpoint {idx}"), Ok(connector.clone()))
+                        }
+                    };
+                let connector = connector.map_err(|err| {
+                    tracing::warn!("endpoint {name} is unavailable: {err:#}");
+                    format!("{err:#}")
+                });
+                Target {
+                    name,
+                    connector,
+                    load: Arc::new(AtomicUsize::new(0)),
+                }
+            })
+            .collect();
+        Ok(Self {
+            targets,
+            selection,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    fn first(&self) -> usize {
+        match self.selection {
+            EndpointSelection::RoundRobin => {
+                self.next.fetch_add(1, Ordering::Relaxed) % self.targets.len()
+            }
+            EndpointSelection::LeastLoaded => (0..self.targets.len())
+                .min_by_key(|idx| self.targets[*idx].load.load(Ordering::Relaxed))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl FailoverConnector {
+    /// Connect to the first endpoint that can be reached and for which `handshake` succeeds on
+    /// the new connection.
+    pub(crate) fn connect_with(
+        &self,
+        handshake: impl Fn(ConnectionWrapper) -> Result<ConnectionWrapper>,
+    ) -> Result<ConnectionWrapper> {
+        let first = self.first();
+        let mut errors = Vec::new();
+        for offset in 0..self.targets.len() {
+            let target = &self.targets[(first + offset) % self.targets.len()];
+            let result = match &target.connector {
+                Ok(connector) => connector
+                    .connect()
+                    .and_then(|conn| handshake(conn.with_lease(Lease::new(target.load.clone())))),
+                Err(err) => Err(anyhow!("{err}")),
+            };
+            match result {
+                Ok(conn) => return Ok(conn),
+                Err(err) => {
+                    tracing::warn!("failed to connect to {}: {err:#}", target.name);
+                    errors.push(format!("{}: {err:#}", target.name));
+                }
+            }
+        }
+        bail!(
+            "failed to connect to any of {} endpoints:\n{}",
+            self.targets.len(),
+            errors.join("\n")
+        )
+    }
+}
+
+/// Counts a connection to an endpoint for as long as the connection is open.
+pub(crate) struct Lease(Arc<AtomicUsize>);
+
+impl Lease {
+    fn new(load: Arc<AtomicUsize>) -> Self {
+        load.fetch_add(1, Ordering::Relaxed);
+        Self(load)
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -12,36 +12,49 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "async-client")]
+pub(crate) mod async_client;
 pub(crate) mod client;
 pub(crate) mod convert;
+pub(crate) mod failover;
 #[cfg(feature = "prove")]
 pub(crate) mod server;
+pub(crate) mod store;
 #[cfg(test)]
 #[cfg(feature = "prove")]
 mod tests;
+#[cfg(feature = "tls")]
+pub(crate) mod tls;
 
 use std::{
     cell::RefCell,
+    fmt,
     io::{Error as IoError, ErrorKind as IoErrorKind, Read, Write},
-    net::{TcpListener, TcpStream},
+    net::{Shutdown, TcpListener, TcpStream},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
-    process::{Child, Command},
+    process::{Child, ChildStderr, Command, Stdio},
     sync::{
         atomic::{AtomicBool, Ordering},
-        mpsc::channel,
+        mpsc::{channel, RecvTimeoutError},
         Arc, Mutex,
     },
-    thread,
-    time::Duration,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use bytes::{Buf, BufMut, Bytes};
 use lazy_regex::regex_captures;
 use prost::Message;
+use risc0_zkp::core::digest::Digest;
 use semver::Version;
 
-use crate::{get_version, ExitCode, Journal, ReceiptClaim};
+pub use self::{
+    failover::{Endpoint, EndpointSelection},
+    store::{AssetStore, InMemoryAssetStore, StoreKey},
+};
+use crate::{get_version, ExitCode, Journal, PagingStats, ReceiptClaim, SessionStats};
 
 mod pb {
     pub(crate) mod api {
@@ -57,16 +70,340 @@ mod pb {
 
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
+/// How long a server process is given to exit after SIGTERM before it is killed.
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How long to wait for a server process that dropped its connection to exit, so that its exit
+/// code and stderr can be reported.
+const EXIT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// The most output kept from the stderr of a server process. Only the end is kept, as that is
+/// where the cause of a failure is usually reported.
+const MAX_STDERR_LEN: usize = 64 * 1024;
+
+/// The error returned when an [ApiClient](crate::ApiClient) call does not complete within the
+/// timeout set with [ProverOpts::with_timeout](crate::ProverOpts::with_timeout).
+///
+/// The server handling the call has been terminated by the time this error is returned, so
+/// the call can be retried.
+#[derive(Debug)]
+pub struct TimedOut {
+    /// The timeout that was exceeded.
+    pub timeout: Duration,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "server call timed out after {:?}", self.timeout)
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// A handle used to cancel a long-running operation, such as
+/// [ApiClient::execute_with_cancellation](crate::ApiClient::execute_with_cancellation).
+///
+/// Clones share the same state, so one clone can be handed to the operation while another is
+/// kept to cancel it, possibly from a different thread. Cancellation takes effect at the next
+/// boundary at which the operation checks the token, e.g. the end of the current segment.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Construct a new [CancellationToken] which has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of every operation using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if [CancellationToken::cancel] has been called on this token or a clone of it.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The error returned when an operation is stopped by its [CancellationToken].
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "operation was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Returns the range of po2 values supported by the keccak circuit.
+///
+/// Callers can use this to clamp [ProveKeccakRequest::po2](crate::ProveKeccakRequest::po2)
+/// before submitting a request with [ApiClient::prove_keccak](crate::ApiClient::prove_keccak).
+pub fn keccak_po2_range() -> RangeInclusive<u32> {
+    let range = risc0_circuit_keccak::KECCAK_PO2_RANGE;
+    *range.start() as u32..=*range.end() as u32
+}
+
+/// The error returned when a keccak proof is requested with a po2 outside of
+/// [keccak_po2_range].
+#[derive(Debug)]
+pub struct UnsupportedPo2 {
+    /// The po2 given in the request.
+    pub requested: usize,
+
+    /// The smallest supported po2.
+    pub min: u32,
+
+    /// The largest supported po2.
+    pub max: u32,
+}
+
+impl fmt::Display for UnsupportedPo2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unsupported keccak po2 {}: must be between {} and {}",
+            self.requested, self.min, self.max
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedPo2 {}
+
+/// The error returned by [ApiClient](crate::ApiClient) methods.
+///
+/// Each variant identifies a class of failure so that callers can decide how to react, e.g. by
+/// retrying [ProverError::Connection] and [ProverError::Timeout] errors but alerting on
+/// [ProverError::VerificationFailed]. A [ProverError] converts into an [anyhow::Error] with `?`, and
+/// an [anyhow::Error] converts into the [ProverError] variant matching the errors in its chain.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProverError {
+    /// An I/O error unrelated to the connection with the server, e.g. when reading an asset.
+    Io(anyhow::Error),
+
+    /// The server could not be started or reached, or the connection to it was lost.
+    Connection(anyhow::Error),
+
+    /// A receipt failed verification.
+    VerificationFailed(anyhow::Error),
+
+    /// A keccak proof was requested with a po2 outside of [keccak_po2_range].
+    UnsupportedPo2(UnsupportedPo2),
+
+    /// A message or receipt could not be encoded or decoded.
+    Serialize(anyhow::Error),
+
+    /// The call was stopped by its [CancellationToken].
+    Cancelled,
+
+    /// The call did not complete within its timeout.
+    Timeout(TimedOut),
+
+    /// The server process exited with a non-zero exit code.
+    ProcessFailed {
+        /// The exit code of the server process.
+        code: i32,
+
+        /// The end of what the server process wrote to stderr.
+        stderr: String,
+    },
+
+    /// The guest faulted during execution, e.g. because it panicked or accessed invalid memory.
+    GuestFault {
+        /// The error which stopped execution.
+        reason: String,
+
+        /// The bytes committed to the journal before the fault.
+        partial_journal: Vec<u8>,
+    },
+
+    /// Any other error, such as an invalid request reported by the server.
+    Other(anyhow::Error),
+}
+
+impl ProverError {
+    /// Wrap the underlying error with additional context, keeping the variant.
+    pub(crate) fn context(self, context: String) -> Self {
+        match self {
+            Self::Io(err) => Self::Io(err.context(context)),
+            Self::Connection(err) => Self::Connection(err.context(context)),
+            Self::VerificationFailed(err) => Self::VerificationFailed(err.context(context)),
+            Self::Serialize(err) => Self::Serialize(err.context(context)),
+            Self::Other(err) => Self::Other(err.context(context)),
+            Self::UnsupportedPo2(_)
+            | Self::Cancelled
+            | Self::Timeout(_)
+            | Self::ProcessFailed { .. }
+            | Self::GuestFault { .. } => self,
+        }
+    }
+}
+
+impl fmt::Display for ProverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => write!(f, "I/O error"),
+            Self::Connection(_) => write!(f, "server connection failed"),
+            Self::VerificationFailed(_) => write!(f, "verification failed"),
+            Self::UnsupportedPo2(err) => write!(f, "{err}"),
+            Self::Serialize(_) => write!(f, "serialization failed"),
+            Self::Cancelled => write!(f, "{Cancelled}"),
+            Self::Timeout(err) => write!(f, "{err}"),
+            Self::ProcessFailed { code, stderr } => {
+                write!(f, "server exited with code {code}")?;
+                match stderr.trim_end() {
+                    "" => Ok(()),
+                    stderr => write!(f, ":\n{stderr}"),
+                }
+            }
+            Self::GuestFault { reason, .. } => write!(f, "{reason}"),
+            Self::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ProverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err)
+            | Self::Connection(err)
+            | Self::VerificationFailed(err)
+            | Self::Serialize(err) => Some(err.as_ref()),
+            // The wrapped error is the message of this one, so its causes come next.
+            Self::Other(err) => err.source(),
+            Self::UnsupportedPo2(_)
+            | Self::Cancelled
+            | Self::Timeout(_)
+            | Self::ProcessFailed { .. }
+            | Self::GuestFault { .. } => None,
+        }
+    }
+}
+
+impl From<UnsupportedPo2> for ProverError {
+    fn from(err: UnsupportedPo2) -> Self {
+        Self::UnsupportedPo2(err)
+    }
+}
+
+impl From<prost::DecodeError> for ProverError {
+    fn from(err: prost::DecodeError) -> Self {
+        Self::Serialize(err.into())
+    }
+}
+
+impl From<anyhow::Error> for ProverError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<ProverError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+
+        if let Some(timed_out) = err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<TimedOut>())
+        {
+            return Self::Timeout(TimedOut {
+                timeout: timed_out.timeout,
+            });
+        }
+        if err.chain().any(|cause| cause.is::<Cancelled>()) {
+            return Self::Cancelled;
+        }
+        if let Some(po2) = err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<UnsupportedPo2>())
+        {
+            return Self::UnsupportedPo2(UnsupportedPo2 {
+                requested: po2.requested,
+                min: po2.min,
+                max: po2.max,
+            });
+        }
+        #[cfg(feature = "prove")]
+        if let Some(fault) = err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<crate::GuestFault>())
+        {
+            return Self::GuestFault {
+                reason: err.to_string(),
+                partial_journal: fault.partial_journal.clone(),
+            };
+        }
+        if err
+            .chain()
+            .any(|cause| cause.is::<risc0_zkp::verify::VerificationError>())
+        {
+            return Self::VerificationFailed(err);
+        }
+        if err.chain().any(|cause| {
+            cause.is::<prost::DecodeError>()
+                || cause.is::<prost::EncodeError>()
+                || cause.is::<bincode::Error>()
+                || cause.is::<crate::serde::Error>()
+        }) {
+            return Self::Serialize(err);
+        }
+        if let Some(io_err) = err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<IoError>())
+        {
+            return match io_err.kind() {
+                IoErrorKind::ConnectionRefused
+                | IoErrorKind::ConnectionReset
+                | IoErrorKind::ConnectionAborted
+                | IoErrorKind::NotConnected
+                | IoErrorKind::BrokenPipe
+                | IoErrorKind::UnexpectedEof => Self::Connection(err),
+                _ => Self::Io(err),
+            };
+        }
+        Self::Other(err)
+    }
+}
+
 trait RootMessage: Message {}
 
+/// A stream that messages can be sent and received on.
+pub trait Channel: Read + Write {}
+
+impl<T: Read + Write> Channel for T {}
+
 pub trait Connection {
     fn stream(&mut self) -> &mut TcpStream;
+
+    /// The channel messages are sent and received on, which is [Connection::stream] unless the
+    /// connection is encrypted.
+    fn channel(&mut self) -> &mut dyn Channel {
+        self.stream()
+    }
+
     fn close(&mut self) -> Result<i32>;
+    fn terminate(&mut self) -> Result<()>;
+
+    /// What the server wrote to stderr, if it runs as a child process whose stderr is captured.
+    fn stderr(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Wait briefly for a server that dropped the connection to exit, returning its exit code.
+    ///
+    /// Returns `None` if the server is still running or does not run as a child process.
+    fn exited(&mut self) -> Option<i32> {
+        None
+    }
 }
 
 #[derive(Clone)]
 pub struct ConnectionWrapper {
     inner: Arc<Mutex<dyn Connection + Send>>,
+    deadline: Option<(Instant, Duration)>,
+    keep_alive: bool,
+    _lease: Option<Arc<failover::Lease>>,
 }
 
 thread_local! {
@@ -82,6 +419,9 @@ impl RootMessage for pb::api::OnIoReply {}
 impl RootMessage for pb::api::ProveKeccakReply {}
 impl RootMessage for pb::api::ProveSegmentReply {}
 impl RootMessage for pb::api::ProveZkrReply {}
+impl RootMessage for pb::api::ProveZkrBatchReply {}
+impl RootMessage for pb::api::ServerInfoReply {}
+impl RootMessage for pb::api::SupportedZkrsReply {}
 impl RootMessage for pb::api::LiftRequest {}
 impl RootMessage for pb::api::LiftReply {}
 impl RootMessage for pb::api::JoinRequest {}
@@ -99,42 +439,140 @@ fn lock_err() -> IoError {
 
 impl ConnectionWrapper {
     fn new(inner: Arc<Mutex<dyn Connection + Send>>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            deadline: None,
+            keep_alive: false,
+            _lease: None,
+        }
+    }
+
+    /// Keep `lease` for as long as this connection, or any clone of it, is open.
+    fn with_lease(mut self, lease: failover::Lease) -> Self {
+        self._lease = Some(Arc::new(lease));
+        self
+    }
+
+    /// Fail any send or receive that happens after `timeout` has elapsed, terminating the
+    /// server.
+    fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.deadline = timeout.map(|timeout| (Instant::now() + timeout, timeout));
+        self
     }
 
     fn send<T: RootMessage>(&mut self, msg: T) -> Result<()> {
         let mut guard = self.inner.lock().map_err(|_| lock_err())?;
-        self.inner_send(guard.stream(), msg)
+        let result = self
+            .set_stream_timeout(guard.stream())
+            .and_then(|_| self.inner_send(guard.channel(), msg));
+        let result = self.check_deadline(&mut *guard, result);
+        check_exited(&mut *guard, result)
     }
 
     fn recv<T: Default + RootMessage>(&mut self) -> Result<T> {
         let mut guard = self.inner.lock().map_err(|_| lock_err())?;
-        self.inner_recv(guard.stream())
+        let result = self
+            .set_stream_timeout(guard.stream())
+            .and_then(|_| self.inner_recv(guard.channel()));
+        let result = self.check_deadline(&mut *guard, result);
+        check_exited(&mut *guard, result)
     }
 
     #[cfg(feature = "prove")]
     fn send_recv<S: RootMessage, R: Default + RootMessage>(&mut self, msg: S) -> Result<R> {
         let mut guard = self.inner.lock().map_err(|_| lock_err())?;
-        let stream = guard.stream();
-        self.inner_send(stream, msg)?;
-        self.inner_recv(stream)
+        let result = self.set_stream_timeout(guard.stream()).and_then(|_| {
+            let channel = guard.channel();
+            self.inner_send(channel, msg)
+                .and_then(|_| self.inner_recv(channel))
+        });
+        let result = self.check_deadline(&mut *guard, result);
+        check_exited(&mut *guard, result)
+    }
+
+    fn set_stream_timeout(&self, stream: &mut TcpStream) -> Result<()> {
+        let Some((deadline, timeout)) = self.deadline else {
+            return Ok(());
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            bail!(TimedOut { timeout });
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        stream.set_write_timeout(Some(remaining))?;
+        Ok(())
+    }
+
+    fn check_deadline<T>(&self, conn: &mut dyn Connection, result: Result<T>) -> Result<T> {
+        match (result, self.deadline) {
+            (Err(_), Some((deadline, timeout))) if Instant::now() >= deadline => {
+                tracing::warn!("server call timed out after {timeout:?}, terminating server");
+                if let Err(err) = conn.terminate() {
+                    tracing::warn!("failed to terminate server: {err:#}");
+                }
+                Err(TimedOut { timeout }.into())
+            }
+            (result, _) => result,
+        }
     }
 
     fn close(&mut self) -> Result<i32> {
         self.inner.lock().map_err(|_| lock_err())?.close()
     }
 
-    fn inner_send<T: RootMessage>(&self, stream: &mut TcpStream, msg: T) -> Result<()> {
+    /// The error for a server that exited with a non-zero `code`, including what it wrote to
+    /// stderr when that was captured.
+    fn child_finished(&mut self, code: i32) -> ProverError {
+        let stderr = self.inner.lock().ok().and_then(|mut conn| conn.stderr());
+        ProverError::ProcessFailed {
+            code,
+            stderr: stderr.unwrap_or_default(),
+        }
+    }
+
+    /// Tell a server that is keeping this connection alive that no more requests will follow, and
+    /// wait for it to finish.
+    fn hang_up(&mut self) -> Result<i32> {
+        let mut guard = self.inner.lock().map_err(|_| lock_err())?;
+        guard.stream().shutdown(Shutdown::Write)?;
+        guard.close()
+    }
+
+    fn terminate(&mut self) -> Result<()> {
+        self.inner.lock().map_err(|_| lock_err())?.terminate()
+    }
+
+    /// Check, without blocking, that an idle connection can still be used.
+    ///
+    /// A server never sends anything on an idle connection, so either data or EOF means that the
+    /// server has gone away or is in an unexpected state.
+    fn is_healthy(&self) -> bool {
+        let Ok(mut guard) = self.inner.lock() else {
+            return false;
+        };
+        let stream = guard.stream();
+        if stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let mut buf = [0u8; 1];
+        let idle =
+            matches!(stream.peek(&mut buf), Err(err) if err.kind() == IoErrorKind::WouldBlock);
+        stream.set_nonblocking(false).is_ok() && idle
+    }
+
+    fn inner_send<T: RootMessage>(&self, stream: &mut dyn Channel, msg: T) -> Result<()> {
         let len = msg.encoded_len();
         LOCAL_BUF.with_borrow_mut(|buf| {
             buf.clear();
             buf.put_u32_le(len as u32);
             msg.encode(buf)?;
-            Ok(stream.write_all(buf)?)
+            stream.write_all(buf)?;
+            // An encrypted channel may buffer the message until it is flushed.
+            Ok(stream.flush()?)
         })
     }
 
-    fn inner_recv<T: Default + RootMessage>(&self, stream: &mut TcpStream) -> Result<T> {
+    fn inner_recv<T: Default + RootMessage>(&self, stream: &mut dyn Channel) -> Result<T> {
         LOCAL_BUF.with_borrow_mut(|buf| {
             buf.resize(4, 0);
             stream.read_exact(buf).context("rx len failed")?;
@@ -146,7 +584,42 @@ impl ConnectionWrapper {
     }
 }
 
+/// Replace a failure caused by the server dropping the connection with the reason it exited.
+fn check_exited<T>(conn: &mut dyn Connection, result: Result<T>) -> Result<T> {
+    let err = match result {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+    let dropped = err
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<IoError>())
+        .any(|err| {
+            matches!(
+                err.kind(),
+                IoErrorKind::ConnectionReset
+                    | IoErrorKind::ConnectionAborted
+                    | IoErrorKind::BrokenPipe
+                    | IoErrorKind::UnexpectedEof
+            )
+        });
+    if !dropped {
+        return Err(err);
+    }
+    match conn.exited() {
+        Some(code) if code != 0 => Err(ProverError::ProcessFailed {
+            code,
+            stderr: conn.stderr().unwrap_or_default(),
+        }
+        .into()),
+        _ => Err(err),
+    }
+}
+
 /// Connects a zkVM client and server
+///
+/// A connector given to an [ApiClient](crate::ApiClient) must be `Send + Sync`, as it is shared by
+/// every thread using the client. `connect` may then be called concurrently and each call must
+/// return a connection to its own server.
 pub trait Connector {
     /// Create a client-server connection
     fn connect(&self) -> Result<ConnectionWrapper>;
@@ -154,7 +627,7 @@ pub trait Connector {
 
 struct ParentProcessConnector {
     server_path: PathBuf,
-    listener: TcpListener,
+    stderr: Option<Arc<Mutex<dyn Write + Send>>>,
 }
 
 impl ParentProcessConnector {
@@ -190,7 +663,7 @@ impl ParentProcessConnector {
 
         Ok(Self {
             server_path: server_path.as_ref().to_path_buf(),
-            listener: TcpListener::bind("127.0.0.1:0")?,
+            stderr: None,
         })
     }
 
@@ -208,10 +681,17 @@ impl ParentProcessConnector {
         }
         Ok(Self {
             server_path: server_path.as_ref().to_path_buf(),
-            listener: TcpListener::bind("127.0.0.1:0")?,
+            stderr: None,
         })
     }
 
+    /// Forward what each server writes to stderr to `writer` rather than to the stderr of this
+    /// process.
+    pub fn with_stderr(mut self, writer: Arc<Mutex<dyn Write + Send>>) -> Self {
+        self.stderr = Some(writer);
+        self
+    }
+
     fn spawn_fail(&self) -> String {
         format!(
             "Could not launch zkvm: \"{}\". \n
@@ -233,17 +713,21 @@ fn get_server_version<P: AsRef<Path>>(server_path: P) -> Result<Version> {
 
 impl Connector for ParentProcessConnector {
     fn connect(&self) -> Result<ConnectionWrapper> {
-        let addr = self.listener.local_addr()?;
-        let child = Command::new(&self.server_path)
+        // Each connection gets its own listener so that concurrent connects cannot accept each
+        // other's server.
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let mut child = Command::new(&self.server_path)
             .arg("--port")
             .arg(addr.port().to_string())
+            .stderr(Stdio::piped())
             .spawn()
             .with_context(|| self.spawn_fail())?;
+        let mut stderr = StderrCapture::new(child.stderr.take(), self.stderr.clone());
 
         let shutdown = Arc::new(AtomicBool::new(false));
         let server_shutdown = shutdown.clone();
         let (tx, rx) = channel();
-        let listener = self.listener.try_clone()?;
         let handle = thread::spawn(move || {
             let stream = listener.accept();
             if server_shutdown.load(Ordering::Relaxed) {
@@ -254,15 +738,38 @@ impl Connector for ParentProcessConnector {
             }
         });
 
-        let stream = rx.recv_timeout(CONNECT_TIMEOUT);
-        let stream = stream.inspect_err(|_| {
-            shutdown.store(true, Ordering::Relaxed);
-            let _ = TcpStream::connect(addr);
-            handle.join().unwrap();
-        })?;
+        // Stop waiting early if the server exits without connecting, e.g. because it failed to
+        // start.
+        let start = Instant::now();
+        let stream = loop {
+            let result = rx.recv_timeout(Duration::from_millis(50));
+            let exited = match &result {
+                Err(RecvTimeoutError::Timeout) => child.try_wait()?,
+                _ => None,
+            };
+            if result.is_ok() || exited.is_some() || start.elapsed() >= CONNECT_TIMEOUT {
+                break result.map_err(|err| (err, exited));
+            }
+        };
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err((err, exited)) => {
+                shutdown.store(true, Ordering::Relaxed);
+                let _ = TcpStream::connect(addr);
+                handle.join().unwrap();
+                return Err(match exited {
+                    Some(status) => ProverError::ProcessFailed {
+                        code: status.code().unwrap_or_default(),
+                        stderr: stderr.finish(),
+                    }
+                    .into(),
+                    None => err.into(),
+                });
+            }
+        };
 
         Ok(ConnectionWrapper::new(Arc::new(Mutex::new(
-            ParentProcessConnection::new(child, stream),
+            ParentProcessConnection::new(child, stream, stderr),
         ))))
     }
 }
@@ -292,9 +799,105 @@ impl Connector for TcpConnector {
     }
 }
 
+/// Runs a [Server](server::Server) on a thread of this process for each connection, for use when
+/// no `r0vm` is available.
+#[cfg(feature = "prove")]
+#[derive(Default)]
+pub(crate) struct LocalConnector;
+
+#[cfg(feature = "prove")]
+impl LocalConnector {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(feature = "prove")]
+impl Connector for LocalConnector {
+    fn connect(&self) -> Result<ConnectionWrapper> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?.to_string();
+        let handle = thread::Builder::new()
+            .name("r0vm-local".into())
+            .spawn(move || server::Server::new_tcp(addr).run())?;
+        let (stream, _) = listener.accept()?;
+        Ok(ConnectionWrapper::new(Arc::new(Mutex::new(
+            LocalConnection {
+                stream,
+                handle: Some(handle),
+            },
+        ))))
+    }
+}
+
 struct ParentProcessConnection {
     child: Child,
     stream: TcpStream,
+    stderr: StderrCapture,
+}
+
+/// Collects what a server process writes to stderr on a background thread, forwarding it as it
+/// arrives.
+struct StderrCapture {
+    output: Arc<Mutex<Vec<u8>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl StderrCapture {
+    /// Start reading `stderr`, forwarding it to `writer` or, by default, to the stderr of this
+    /// process.
+    fn new(stderr: Option<ChildStderr>, writer: Option<Arc<Mutex<dyn Write + Send>>>) -> Self {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let handle = stderr.map(|mut stderr| {
+            let output = output.clone();
+            thread::spawn(move || {
+                let mut buf = [0u8; 4096];
+                loop {
+                    let len = match stderr.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(len) => len,
+                        Err(err) if err.kind() == IoErrorKind::Interrupted => continue,
+                        Err(_) => break,
+                    };
+                    let chunk = &buf[..len];
+                    match &writer {
+                        Some(writer) => {
+                            let mut writer = writer.lock().unwrap_or_else(|err| err.into_inner());
+                            let _ = writer.write_all(chunk).and_then(|_| writer.flush());
+                        }
+                        None => {
+                            let _ = std::io::stderr().write_all(chunk);
+                        }
+                    }
+                    let mut output = output.lock().unwrap_or_else(|err| err.into_inner());
+                    output.extend_from_slice(chunk);
+                    let excess = output.len().saturating_sub(MAX_STDERR_LEN);
+                    output.drain(..excess);
+                }
+            })
+        });
+        Self { output, handle }
+    }
+
+    /// What has been captured, after giving the reader a moment to see the end of the stream.
+    ///
+    /// The reader is not waited on indefinitely, as a process started by the server could keep
+    /// its stderr open.
+    fn finish(&mut self) -> String {
+        if let Some(handle) = self.handle.take() {
+            let start = Instant::now();
+            while !handle.is_finished() && start.elapsed() < EXIT_GRACE_PERIOD {
+                thread::sleep(Duration::from_millis(10));
+            }
+            if handle.is_finished() {
+                let _ = handle.join();
+            } else {
+                self.handle = Some(handle);
+            }
+        }
+        let output = self.output.lock().unwrap_or_else(|err| err.into_inner());
+        String::from_utf8_lossy(&output).into_owned()
+    }
 }
 
 #[cfg(feature = "prove")]
@@ -303,8 +906,12 @@ struct TcpConnection {
 }
 
 impl ParentProcessConnection {
-    pub fn new(child: Child, stream: TcpStream) -> Self {
-        Self { child, stream }
+    pub fn new(child: Child, stream: TcpStream, stderr: StderrCapture) -> Self {
+        Self {
+            child,
+            stream,
+            stderr,
+        }
     }
 }
 
@@ -317,6 +924,77 @@ impl Connection for ParentProcessConnection {
         let status = self.child.wait()?;
         Ok(status.code().unwrap_or_default())
     }
+
+    fn stderr(&mut self) -> Option<String> {
+        Some(self.stderr.finish())
+    }
+
+    fn exited(&mut self) -> Option<i32> {
+        let start = Instant::now();
+        loop {
+            match self.child.try_wait() {
+                Ok(Some(status)) => return Some(status.code().unwrap_or_default()),
+                Ok(None) if start.elapsed() < EXIT_GRACE_PERIOD => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn terminate(&mut self) -> Result<()> {
+        let _ = self.stream.shutdown(Shutdown::Both);
+
+        // Give the server a chance to exit cleanly before killing it.
+        #[cfg(unix)]
+        {
+            // SAFETY: sending a signal to our own child process, which has not been reaped yet.
+            unsafe { libc::kill(self.child.id() as libc::pid_t, libc::SIGTERM) };
+            let start = Instant::now();
+            while start.elapsed() < TERMINATE_GRACE_PERIOD {
+                if self.child.try_wait()?.is_some() {
+                    return Ok(());
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+
+        self.child.kill()?;
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "prove")]
+struct LocalConnection {
+    stream: TcpStream,
+    handle: Option<thread::JoinHandle<Result<()>>>,
+}
+
+#[cfg(feature = "prove")]
+impl Connection for LocalConnection {
+    fn stream(&mut self) -> &mut TcpStream {
+        &mut self.stream
+    }
+
+    // Report the outcome of the server thread the way a child process would report its exit code.
+    fn close(&mut self) -> Result<i32> {
+        let Some(handle) = self.handle.take() else {
+            return Ok(0);
+        };
+        match handle.join() {
+            Ok(Ok(())) => Ok(0),
+            Ok(Err(err)) => {
+                tracing::error!("local server failed: {err:#}");
+                Ok(1)
+            }
+            Err(_) => Ok(1),
+        }
+    }
+
+    fn terminate(&mut self) -> Result<()> {
+        Ok(self.stream.shutdown(Shutdown::Both)?)
+    }
 }
 
 #[cfg(feature = "prove")]
@@ -335,6 +1013,10 @@ impl Connection for TcpConnection {
     fn close(&mut self) -> Result<i32> {
         Ok(0)
     }
+
+    fn terminate(&mut self) -> Result<()> {
+        Ok(self.stream.shutdown(Shutdown::Both)?)
+    }
 }
 
 fn malformed_err() -> anyhow::Error {
@@ -342,11 +1024,14 @@ fn malformed_err() -> anyhow::Error {
 }
 
 impl pb::api::Asset {
-    fn as_bytes(&self) -> Result<Bytes> {
+    fn as_bytes(&self, store: Option<&dyn AssetStore>) -> Result<Bytes> {
         let bytes = match self.kind.as_ref().ok_or(malformed_err())? {
             pb::api::asset::Kind::Inline(bytes) => bytes.clone(),
             pb::api::asset::Kind::Path(path) => std::fs::read(path)?,
             pb::api::asset::Kind::Redis(_) => bail!("as_bytes not supported for redis"),
+            pb::api::asset::Kind::Store(key) => {
+                return store::require(store)?.get(&StoreKey::new(key.as_str()))
+            }
         };
         Ok(bytes.into())
     }
@@ -363,6 +1048,11 @@ pub enum Asset {
 
     /// The asset is written to redis.
     Redis(String),
+
+    /// The asset is written to an [AssetStore] under a key.
+    ///
+    /// Its bytes are read with [Client::fetch_asset](client::Client::fetch_asset).
+    Store(StoreKey),
 }
 
 /// Determines the parameters for AssetRequest::Redis
@@ -389,10 +1079,53 @@ pub enum AssetRequest {
 
     /// The asset is written to redis.
     Redis(RedisParams),
+
+    /// The asset is written to the [AssetStore] of the server, under the given key.
+    Store(StoreKey),
+}
+
+/// Describes a server and what it supports, as reported by
+/// [ApiClient::server_info](crate::ApiClient::server_info).
+#[derive(Clone, Debug)]
+pub struct ServerInfo {
+    /// The version of the server.
+    pub version: semver::Version,
+
+    /// The names of the hash functions the server can prove with, as accepted by
+    /// [ProverOpts::with_hashfn](crate::ProverOpts::with_hashfn).
+    pub supported_hashfns: Vec<String>,
+
+    /// The largest segment size, as a power of two (po2), the server can prove.
+    pub max_po2: u32,
+
+    /// Optional features the server was built with, such as `cuda`.
+    pub features: Vec<String>,
+
+    /// The control root of the recursion programs the server proves succinct receipts with, or
+    /// `None` if the server does not report it.
+    ///
+    /// Receipts from the server report the same root from
+    /// [SuccinctReceipt::control_root](crate::SuccinctReceipt::control_root).
+    pub control_root: Option<Digest>,
+}
+
+/// Describes a recursion program (ZKR) registered with a server, as reported by
+/// [ApiClient::supported_zkrs](crate::ApiClient::supported_zkrs).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZkrInfo {
+    /// The control ID that identifies the program.
+    pub control_id: Digest,
+
+    /// A human readable name for the program, if one was given when it was registered.
+    pub name: Option<String>,
+
+    /// The number of words of input the program takes, if known.
+    pub input_words: Option<u32>,
 }
 
 /// Provides information about the result of execution.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct SessionInfo {
     /// The number of user cycles for each segment.
     pub segments: Vec<SegmentInfo>,
@@ -406,18 +1139,94 @@ pub struct SessionInfo {
     /// The [ReceiptClaim] associated with the executed session. This receipt claim is what will be
     /// proven if this session is passed to the Prover.
     pub receipt_claim: Option<ReceiptClaim>,
+
+    /// The number of distinct pages touched across all segments.
+    ///
+    /// This is zero for an [incomplete](SessionInfo::is_complete) session.
+    pub unique_pages_touched: u64,
+
+    pub(crate) partial_journal: Vec<u8>,
+
+    pub(crate) complete: bool,
 }
 
 impl SessionInfo {
+    /// Construct the [SessionInfo] of a complete session, e.g. from an [Executor](crate::Executor)
+    /// implemented outside of this crate.
+    ///
+    /// The [partial journal](SessionInfo::partial_journal) is the given `journal`, and
+    /// [SessionInfo::unique_pages_touched] starts at zero.
+    pub fn new(
+        segments: Vec<SegmentInfo>,
+        journal: Journal,
+        exit_code: ExitCode,
+        receipt_claim: Option<ReceiptClaim>,
+    ) -> Self {
+        Self {
+            segments,
+            partial_journal: journal.bytes.clone(),
+            journal,
+            exit_code,
+            receipt_claim,
+            unique_pages_touched: 0,
+            complete: true,
+        }
+    }
+
+    /// Returns false if execution was stopped early by a segment callback returning
+    /// [SegmentAction::Stop].
+    ///
+    /// An incomplete session has the [ExitCode::SystemSplit] exit code of its last segment, an
+    /// empty journal and no [ReceiptClaim]; its segments cannot be proven into a receipt for the
+    /// whole program.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The bytes committed to the journal by the guest.
+    ///
+    /// Unlike [SessionInfo::journal], these are kept even when the guest exits without setting an
+    /// output. Execution which stops with a fault returns the committed bytes in a
+    /// [ProverError::GuestFault] instead.
+    pub fn partial_journal(&self) -> &[u8] {
+        &self.partial_journal
+    }
+
     /// The total number of user cycles across all segments, without any
     /// overhead for continuations or po2 padding.
     pub fn cycles(&self) -> u64 {
         self.segments.iter().map(|s| s.cycles as u64).sum()
     }
+
+    /// Aggregate cycle counts across all segments.
+    ///
+    /// This can be used to estimate the cost of proving the session before doing so. Reserved
+    /// cycles are the per-segment overhead and po2 padding, i.e. everything that is neither user
+    /// nor paging cycles.
+    pub fn stats(&self) -> SessionStats {
+        let total_cycles: u64 = self.segments.iter().map(SegmentInfo::total_cycles).sum();
+        let user_cycles = self.cycles();
+        let paging_cycles: u64 = self.segments.iter().map(SegmentInfo::paging_cycles).sum();
+        SessionStats {
+            segments: self.segments.len(),
+            total_cycles,
+            user_cycles,
+            paging_cycles,
+            reserved_cycles: total_cycles
+                .saturating_sub(user_cycles)
+                .saturating_sub(paging_cycles),
+            paging: PagingStats {
+                page_in_count: self.segments.iter().map(|s| s.paging.page_in_count).sum(),
+                page_out_count: self.segments.iter().map(|s| s.paging.page_out_count).sum(),
+                unique_pages_touched: self.unique_pages_touched,
+            },
+        }
+    }
 }
 
 /// Provides information about a segment of execution.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
+#[non_exhaustive]
 pub struct SegmentInfo {
     /// The number of cycles used for proving in powers of 2.
     pub po2: u32,
@@ -425,6 +1234,81 @@ pub struct SegmentInfo {
     /// The number of user cycles without any overhead for continuations or po2
     /// padding.
     pub cycles: u32,
+
+    /// The pages moved in and out of this segment.
+    pub paging: PagingStats,
+
+    paging_cycles: u64,
+    snapshot: Option<Bytes>,
+}
+
+impl SegmentInfo {
+    /// Construct a [SegmentInfo] for a segment of `2^po2` cycles, of which `user_cycles` ran the
+    /// guest and `paging_cycles` moved the pages counted in `paging`.
+    pub fn new(po2: u32, user_cycles: u32, paging_cycles: u64, paging: PagingStats) -> Self {
+        Self {
+            po2,
+            cycles: user_cycles,
+            paging,
+            paging_cycles,
+            snapshot: None,
+        }
+    }
+
+    /// The total number of cycles in this segment, including padding: `2^po2`.
+    pub fn total_cycles(&self) -> u64 {
+        1 << self.po2
+    }
+
+    /// The number of cycles spent paging memory in and out of this segment.
+    pub fn paging_cycles(&self) -> u64 {
+        self.paging_cycles
+    }
+
+    pub(crate) fn with_snapshot(self, snapshot: Option<Bytes>) -> Self {
+        Self { snapshot, ..self }
+    }
+
+    /// The encoded session snapshot taken at the end of this segment, if any.
+    ///
+    /// Snapshots are only taken when checkpoints are enabled with
+    /// [ExecutorEnvBuilder::enable_checkpoints](crate::ExecutorEnvBuilder::enable_checkpoints).
+    /// Pass the snapshot to [Client::execute_from_snapshot] to resume execution after this
+    /// segment.
+    pub fn snapshot(&self) -> Option<Asset> {
+        self.snapshot.clone().map(Asset::Inline)
+    }
+}
+
+impl fmt::Debug for SegmentInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SegmentInfo")
+            .field("po2", &self.po2)
+            .field("cycles", &self.cycles)
+            .field("paging", &self.paging)
+            .field("paging_cycles", &self.paging_cycles)
+            .field("snapshot", &self.snapshot.as_ref().map(Bytes::len))
+            .finish()
+    }
+}
+
+/// Returned by a segment callback to control whether execution continues.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SegmentAction {
+    /// Continue executing the guest.
+    #[default]
+    Continue,
+
+    /// Stop executing after the current segment.
+    ///
+    /// The resulting [SessionInfo] is [incomplete](SessionInfo::is_complete).
+    Stop,
+}
+
+impl From<()> for SegmentAction {
+    fn from(_: ()) -> Self {
+        Self::Continue
+    }
 }
 
 impl Asset {
@@ -434,10 +1318,62 @@ impl Asset {
             Asset::Inline(bytes) => bytes.clone(),
             Asset::Path(path) => std::fs::read(path)?.into(),
             Asset::Redis(_) => bail!("as_bytes not supported for Asset::Redis"),
+            Asset::Store(key) => {
+                bail!("as_bytes not supported for Asset::Store; use Client::fetch_asset for {key}")
+            }
         })
     }
 }
 
+/// A guest ELF binary bundled with its image ID.
+///
+/// The image ID is always computed from the ELF, so the two cannot drift apart. A
+/// [GuestArtifact] can be passed wherever an ELF [Asset] or an image ID is expected, e.g. to
+/// [Client::execute](client::Client::execute) and [Client::verify](client::Client::verify).
+#[derive(Clone, Debug)]
+pub struct GuestArtifact {
+    elf: Bytes,
+    image_id: Digest,
+}
+
+impl GuestArtifact {
+    /// Create a [GuestArtifact] from ELF bytes, computing its image ID.
+    pub fn from_elf(elf: impl Into<Bytes>) -> Result<Self> {
+        let elf = elf.into();
+        let image_id = risc0_binfmt::compute_image_id(&elf).context("compute image ID")?;
+        Ok(Self { elf, image_id })
+    }
+
+    /// Load a [GuestArtifact] from the ELF file at `path`, computing its image ID.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let elf = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        Self::from_elf(elf)
+    }
+
+    /// The ELF binary of the guest.
+    pub fn elf(&self) -> &Bytes {
+        &self.elf
+    }
+
+    /// The image ID of the guest.
+    pub fn image_id(&self) -> Digest {
+        self.image_id
+    }
+}
+
+impl From<&GuestArtifact> for Asset {
+    fn from(artifact: &GuestArtifact) -> Self {
+        Asset::Inline(artifact.elf.clone())
+    }
+}
+
+impl From<&GuestArtifact> for Digest {
+    fn from(artifact: &GuestArtifact) -> Self {
+        artifact.image_id
+    }
+}
+
 fn invalid_path() -> anyhow::Error {
     anyhow::Error::msg("Path must be UTF-8")
 }
@@ -16,14 +16,18 @@ use std::{
     error::Error as StdError,
     io::{BufReader, Error as IoError, ErrorKind as IoErrorKind, Read, Write},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use bytes::Bytes;
 use prost::Message;
-use risc0_zkp::core::digest::Digest;
+use risc0_zkp::{core::digest::Digest, MAX_CYCLES_PO2};
 
-use super::{malformed_err, path_to_string, pb, ConnectionWrapper, Connector, TcpConnector};
+use super::{
+    malformed_err, path_to_string, pb, store, AssetStore, ConnectionWrapper, Connector, ServerInfo,
+    StoreKey, TcpConnector,
+};
 use crate::{
     get_prover_server, get_version,
     host::{
@@ -31,18 +35,21 @@ use crate::{
             env::{CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest},
             slice_io::SliceIo,
         },
+        recursion::prove::{registered_zkrs, warm_up},
         server::{prove::keccak::prove_keccak, session::NullSegmentRef},
     },
     prove_registered_zkr,
     recursion::identity_p254,
-    AssetRequest, Assumption, ExecutorEnv, ExecutorImpl, InnerAssumptionReceipt, ProverOpts,
-    Receipt, ReceiptClaim, Segment, SegmentReceipt, Session, SuccinctReceipt, TraceCallback,
-    TraceEvent, VerifierContext,
+    AssetRequest, Assumption, ExecutorEnv, ExecutorImpl, GuestFault, HashFn,
+    InnerAssumptionReceipt, ProverOpts, Receipt, ReceiptClaim, Segment, SegmentReceipt, Session,
+    SessionSnapshot, SuccinctReceipt, SuccinctReceiptVerifierParameters, TraceCallback, TraceEvent,
+    VerifierContext,
 };
 
 /// A server implementation for handling requests by clients of the zkVM.
 pub struct Server {
     connector: Box<dyn Connector>,
+    store: Option<Arc<dyn AssetStore>>,
 }
 struct PosixIoProxy {
     fd: u32,
@@ -262,7 +269,10 @@ impl CoprocessorCallback for CoprocessorProxy {
 impl Server {
     /// Construct a new [Server] with the specified [Connector].
     pub fn new(connector: Box<dyn Connector>) -> Self {
-        Self { connector }
+        Self {
+            connector,
+            store: None,
+        }
     }
 
     /// Construct a new [Server] which will connect to the specified TCP/IP
@@ -272,6 +282,17 @@ impl Server {
         Self::new(Box::new(connector))
     }
 
+    /// Use `store` for assets requested with [AssetRequest::Store] and read from
+    /// [Asset::Store](super::Asset::Store).
+    pub fn with_asset_store(mut self, store: Arc<dyn AssetStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    fn asset_store(&self) -> Option<&dyn AssetStore> {
+        self.store.as_deref()
+    }
+
     /// Start the [Server] and run until all requests are complete.
     pub fn run(&self) -> Result<()> {
         tracing::debug!("connect");
@@ -301,15 +322,35 @@ impl Server {
             bail!(msg);
         }
 
+        // A client that asks to keep the connection alive sends requests one after another until it
+        // hangs up, rather than a single request.
+        let keep_alive = request.keep_alive;
         let reply = pb::api::HelloReply {
             kind: Some(pb::api::hello_reply::Kind::Ok(pb::api::HelloResult {
                 version: Some(server_version.into()),
+                keep_alive,
             })),
         };
         tracing::trace!("tx: {reply:?}");
-        let request: pb::api::ServerRequest = conn.send_recv(reply)?;
-        tracing::trace!("rx: {request:?}");
+        let mut request: pb::api::ServerRequest = conn.send_recv(reply)?;
+        loop {
+            tracing::trace!("rx: {request:?}");
+            self.handle(conn.clone(), request)?;
+            if !keep_alive {
+                return Ok(());
+            }
+            request = match conn.recv() {
+                Ok(request) => request,
+                Err(err) if is_hang_up(&err) => {
+                    tracing::debug!("client closed the connection");
+                    return Ok(());
+                }
+                Err(err) => return Err(err),
+            };
+        }
+    }
 
+    fn handle(&self, conn: ConnectionWrapper, request: pb::api::ServerRequest) -> Result<()> {
         match request.kind.ok_or(malformed_err())? {
             pb::api::server_request::Kind::Prove(request) => self.on_prove(conn, request),
             pb::api::server_request::Kind::Execute(request) => self.on_execute(conn, request),
@@ -328,6 +369,15 @@ impl Server {
             pb::api::server_request::Kind::ProveKeccak(request) => {
                 self.on_prove_keccak(conn, request)
             }
+            pb::api::server_request::Kind::ProveZkrBatch(request) => {
+                self.on_prove_zkr_batch(conn, request)
+            }
+            pb::api::server_request::Kind::ServerInfo(_) => self.on_server_info(conn),
+            pb::api::server_request::Kind::ResolveBatch(request) => {
+                self.on_resolve_batch(conn, request)
+            }
+            pb::api::server_request::Kind::SupportedZkrs(_) => self.on_supported_zkrs(conn),
+            pb::api::server_request::Kind::WarmUp(request) => self.on_warm_up(conn, request),
         }
     }
 
@@ -337,22 +387,30 @@ impl Server {
         request: pb::api::ExecuteRequest,
     ) -> Result<()> {
         fn inner(
+            store: Option<&dyn AssetStore>,
             conn: &mut ConnectionWrapper,
             request: pb::api::ExecuteRequest,
         ) -> Result<pb::api::ServerReply> {
             let env_request = request.env.ok_or(malformed_err())?;
-            let env = build_env(conn, &env_request)?;
-
-            let binary = env_request.binary.ok_or(malformed_err())?;
+            let env = build_env(conn, &env_request, store)?;
 
             let segments_out = request.segments_out.ok_or(malformed_err())?;
-            let bytes = binary.as_bytes()?;
-            let mut exec = ExecutorImpl::from_elf(env, &bytes)?;
+            let mut exec = match request.snapshot {
+                Some(snapshot) => {
+                    let snapshot = SessionSnapshot::decode(&snapshot.as_bytes(store)?)?;
+                    ExecutorImpl::from_snapshot(env, snapshot)?
+                }
+                None => {
+                    let binary = env_request.binary.ok_or(malformed_err())?;
+                    ExecutorImpl::from_elf(env, &binary.as_bytes(store)?)?
+                }
+            };
 
             let session = match AssetRequest::try_from(segments_out.clone())? {
+                _ if request.stats_only => execute_stats_only(conn, &mut exec)?,
                 #[cfg(feature = "redis")]
                 AssetRequest::Redis(params) => execute_redis(conn, &mut exec, params)?,
-                _ => execute_default(conn, &mut exec, &segments_out)?,
+                _ => execute_default(conn, &mut exec, &segments_out, store)?,
             };
 
             let receipt_claim = session.claim()?;
@@ -372,7 +430,10 @@ impl Server {
                                         .encode_to_vec()
                                         .into(),
                                     "session_info.claim",
+                                    None,
                                 )?),
+                                unique_pages_touched: session.paging.unique_pages_touched,
+                                partial_journal: session.partial_journal,
                             }),
                         },
                     )),
@@ -380,11 +441,7 @@ impl Server {
             })
         }
 
-        let msg = inner(&mut conn, request).unwrap_or_else(|err| pb::api::ServerReply {
-            kind: Some(pb::api::server_reply::Kind::Error(pb::api::GenericError {
-                reason: err.to_string(),
-            })),
-        });
+        let msg = inner(self.asset_store(), &mut conn, request).unwrap_or_else(server_error_reply);
 
         tracing::trace!("tx: {msg:?}");
         conn.send(msg)
@@ -392,14 +449,15 @@ impl Server {
 
     fn on_prove(&self, mut conn: ConnectionWrapper, request: pb::api::ProveRequest) -> Result<()> {
         fn inner(
+            store: Option<&dyn AssetStore>,
             conn: &mut ConnectionWrapper,
             request: pb::api::ProveRequest,
         ) -> Result<pb::api::ServerReply> {
             let env_request = request.env.ok_or(malformed_err())?;
-            let env = build_env(conn, &env_request)?;
+            let env = build_env(conn, &env_request, store)?;
 
             let binary = env_request.binary.ok_or(malformed_err())?;
-            let bytes = binary.as_bytes()?;
+            let bytes = binary.as_bytes(store)?;
 
             let opts: ProverOpts = request.opts.ok_or(malformed_err())?.try_into()?;
             let prover = get_prover_server(&opts)?;
@@ -412,6 +470,7 @@ impl Server {
                 &request.receipt_out.ok_or(malformed_err())?,
                 prove_info_bytes.into(),
                 "prove_info.zkp",
+                store,
             )?;
 
             Ok(pb::api::ServerReply {
@@ -425,11 +484,7 @@ impl Server {
             })
         }
 
-        let msg = inner(&mut conn, request).unwrap_or_else(|err| pb::api::ServerReply {
-            kind: Some(pb::api::server_reply::Kind::Error(pb::api::GenericError {
-                reason: err.to_string(),
-            })),
-        });
+        let msg = inner(self.asset_store(), &mut conn, request).unwrap_or_else(server_error_reply);
 
         tracing::trace!("tx: {msg:?}");
         conn.send(msg)
@@ -440,9 +495,12 @@ impl Server {
         mut conn: ConnectionWrapper,
         request: pb::api::ProveSegmentRequest,
     ) -> Result<()> {
-        fn inner(request: pb::api::ProveSegmentRequest) -> Result<pb::api::ProveSegmentReply> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::ProveSegmentRequest,
+        ) -> Result<pb::api::ProveSegmentReply> {
             let opts: ProverOpts = request.opts.ok_or(malformed_err())?.try_into()?;
-            let segment_bytes = request.segment.ok_or(malformed_err())?.as_bytes()?;
+            let segment_bytes = request.segment.ok_or(malformed_err())?.as_bytes(store)?;
             let segment: Segment = bincode::deserialize(&segment_bytes)?;
 
             let prover = get_prover_server(&opts)?;
@@ -455,6 +513,7 @@ impl Server {
                 &request.receipt_out.ok_or(malformed_err())?,
                 receipt_bytes.into(),
                 "receipt.zkp",
+                store,
             )?;
 
             Ok(pb::api::ProveSegmentReply {
@@ -466,13 +525,14 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::ProveSegmentReply {
-            kind: Some(pb::api::prove_segment_reply::Kind::Error(
-                pb::api::GenericError {
-                    reason: err.to_string(),
-                },
-            )),
-        });
+        let msg =
+            inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::ProveSegmentReply {
+                kind: Some(pb::api::prove_segment_reply::Kind::Error(
+                    pb::api::GenericError {
+                        reason: err.to_string(),
+                    },
+                )),
+            });
 
         tracing::trace!("tx: {msg:?}");
         conn.send(msg)
@@ -483,7 +543,10 @@ impl Server {
         mut conn: ConnectionWrapper,
         request: pb::api::ProveZkrRequest,
     ) -> Result<()> {
-        fn inner(request: pb::api::ProveZkrRequest) -> Result<pb::api::ProveZkrReply> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::ProveZkrRequest,
+        ) -> Result<pb::api::ProveZkrReply> {
             let control_id = request.control_id.ok_or(malformed_err())?.try_into()?;
             let receipt = prove_registered_zkr(&control_id, vec![control_id], &request.input)?;
 
@@ -493,6 +556,7 @@ impl Server {
                 &request.receipt_out.ok_or(malformed_err())?,
                 receipt_bytes.into(),
                 "receipt.zkp",
+                store,
             )?;
 
             Ok(pb::api::ProveZkrReply {
@@ -504,7 +568,7 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::ProveZkrReply {
+        let msg = inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::ProveZkrReply {
             kind: Some(pb::api::prove_zkr_reply::Kind::Error(
                 pb::api::GenericError {
                     reason: err.to_string(),
@@ -516,12 +580,65 @@ impl Server {
         conn.send(msg)
     }
 
+    fn on_prove_zkr_batch(
+        &self,
+        mut conn: ConnectionWrapper,
+        request: pb::api::ProveZkrBatchRequest,
+    ) -> Result<()> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::ProveZkrBatchRequest,
+        ) -> Result<pb::api::ProveZkrBatchReply> {
+            let receipt_out = request.receipt_out.ok_or(malformed_err())?;
+            let receipts = request
+                .requests
+                .into_iter()
+                .enumerate()
+                .map(|(idx, request)| {
+                    let control_id = request.control_id.ok_or(malformed_err())?.try_into()?;
+                    let receipt =
+                        prove_registered_zkr(&control_id, vec![control_id], &request.input)?;
+
+                    let receipt_pb: pb::core::SuccinctReceipt = receipt.into();
+                    let receipt_bytes = receipt_pb.encode_to_vec();
+                    pb::api::Asset::from_bytes(
+                        &receipt_out,
+                        receipt_bytes.into(),
+                        format!("receipt-{idx}.zkp"),
+                        store,
+                    )
+                })
+                .collect::<Result<_>>()?;
+
+            Ok(pb::api::ProveZkrBatchReply {
+                kind: Some(pb::api::prove_zkr_batch_reply::Kind::Ok(
+                    pb::api::ProveZkrBatchResult { receipts },
+                )),
+            })
+        }
+
+        let msg =
+            inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::ProveZkrBatchReply {
+                kind: Some(pb::api::prove_zkr_batch_reply::Kind::Error(
+                    pb::api::GenericError {
+                        reason: err.to_string(),
+                    },
+                )),
+            });
+
+        tracing::trace!("tx: {msg:?}");
+        conn.send(msg)
+    }
+
     fn on_prove_keccak(
         &self,
         mut conn: ConnectionWrapper,
         request: pb::api::ProveKeccakRequest,
     ) -> Result<()> {
-        fn inner(request_pb: pb::api::ProveKeccakRequest) -> Result<pb::api::ProveKeccakReply> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request_pb: pb::api::ProveKeccakRequest,
+        ) -> Result<pb::api::ProveKeccakReply> {
             let request: ProveKeccakRequest = request_pb.clone().try_into()?;
             let receipt = prove_keccak(&request)?;
 
@@ -531,6 +648,7 @@ impl Server {
                 &request_pb.receipt_out.ok_or(malformed_err())?,
                 receipt_bytes.into(),
                 "receipt.zkp",
+                store,
             )?;
 
             Ok(pb::api::ProveKeccakReply {
@@ -542,22 +660,26 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::ProveKeccakReply {
-            kind: Some(pb::api::prove_keccak_reply::Kind::Error(
-                pb::api::GenericError {
-                    reason: err.to_string(),
-                },
-            )),
-        });
+        let msg =
+            inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::ProveKeccakReply {
+                kind: Some(pb::api::prove_keccak_reply::Kind::Error(
+                    pb::api::GenericError {
+                        reason: err.to_string(),
+                    },
+                )),
+            });
 
         tracing::trace!("tx: {msg:?}");
         conn.send(msg)
     }
 
     fn on_lift(&self, mut conn: ConnectionWrapper, request: pb::api::LiftRequest) -> Result<()> {
-        fn inner(request: pb::api::LiftRequest) -> Result<pb::api::LiftReply> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::LiftRequest,
+        ) -> Result<pb::api::LiftReply> {
             let opts: ProverOpts = request.opts.ok_or(malformed_err())?.try_into()?;
-            let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes()?;
+            let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes(store)?;
             let segment_receipt: SegmentReceipt = bincode::deserialize(&receipt_bytes)?;
 
             let prover = get_prover_server(&opts)?;
@@ -569,6 +691,7 @@ impl Server {
                 &request.receipt_out.ok_or(malformed_err())?,
                 succinct_receipt_bytes.into(),
                 "receipt.zkp",
+                store,
             )?;
 
             Ok(pb::api::LiftReply {
@@ -578,7 +701,7 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::LiftReply {
+        let msg = inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::LiftReply {
             kind: Some(pb::api::lift_reply::Kind::Error(pb::api::GenericError {
                 reason: err.to_string(),
             })),
@@ -589,12 +712,21 @@ impl Server {
     }
 
     fn on_join(&self, mut conn: ConnectionWrapper, request: pb::api::JoinRequest) -> Result<()> {
-        fn inner(request: pb::api::JoinRequest) -> Result<pb::api::JoinReply> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::JoinRequest,
+        ) -> Result<pb::api::JoinReply> {
             let opts: ProverOpts = request.opts.ok_or(malformed_err())?.try_into()?;
-            let left_receipt_bytes = request.left_receipt.ok_or(malformed_err())?.as_bytes()?;
+            let left_receipt_bytes = request
+                .left_receipt
+                .ok_or(malformed_err())?
+                .as_bytes(store)?;
             let left_succinct_receipt: SuccinctReceipt<ReceiptClaim> =
                 bincode::deserialize(&left_receipt_bytes)?;
-            let right_receipt_bytes = request.right_receipt.ok_or(malformed_err())?.as_bytes()?;
+            let right_receipt_bytes = request
+                .right_receipt
+                .ok_or(malformed_err())?
+                .as_bytes(store)?;
             let right_succinct_receipt: SuccinctReceipt<ReceiptClaim> =
                 bincode::deserialize(&right_receipt_bytes)?;
 
@@ -607,6 +739,7 @@ impl Server {
                 &request.receipt_out.ok_or(malformed_err())?,
                 succinct_receipt_bytes.into(),
                 "receipt.zkp",
+                store,
             )?;
 
             Ok(pb::api::JoinReply {
@@ -616,7 +749,7 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::JoinReply {
+        let msg = inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::JoinReply {
             kind: Some(pb::api::join_reply::Kind::Error(pb::api::GenericError {
                 reason: err.to_string(),
             })),
@@ -631,18 +764,21 @@ impl Server {
         mut conn: ConnectionWrapper,
         request: pb::api::ResolveRequest,
     ) -> Result<()> {
-        fn inner(request: pb::api::ResolveRequest) -> Result<pb::api::ResolveReply> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::ResolveRequest,
+        ) -> Result<pb::api::ResolveReply> {
             let opts: ProverOpts = request.opts.ok_or(malformed_err())?.try_into()?;
             let conditional_receipt_bytes = request
                 .conditional_receipt
                 .ok_or(malformed_err())?
-                .as_bytes()?;
+                .as_bytes(store)?;
             let conditional_succinct_receipt: SuccinctReceipt<ReceiptClaim> =
                 bincode::deserialize(&conditional_receipt_bytes)?;
             let assumption_receipt_bytes = request
                 .assumption_receipt
                 .ok_or(malformed_err())?
-                .as_bytes()?;
+                .as_bytes(store)?;
             let assumption_succinct_receipt: SuccinctReceipt<ReceiptClaim> =
                 bincode::deserialize(&assumption_receipt_bytes)?;
 
@@ -658,6 +794,7 @@ impl Server {
                 &request.receipt_out.ok_or(malformed_err())?,
                 succinct_receipt_bytes.into(),
                 "receipt.zkp",
+                store,
             )?;
 
             Ok(pb::api::ResolveReply {
@@ -667,7 +804,7 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::ResolveReply {
+        let msg = inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::ResolveReply {
             kind: Some(pb::api::resolve_reply::Kind::Error(pb::api::GenericError {
                 reason: err.to_string(),
             })),
@@ -677,13 +814,68 @@ impl Server {
         conn.send(msg)
     }
 
+    fn on_resolve_batch(
+        &self,
+        mut conn: ConnectionWrapper,
+        request: pb::api::ResolveBatchRequest,
+    ) -> Result<()> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::ResolveBatchRequest,
+        ) -> Result<pb::api::ResolveReply> {
+            let opts: ProverOpts = request.opts.ok_or(malformed_err())?.try_into()?;
+            let conditional_receipt_bytes = request
+                .conditional_receipt
+                .ok_or(malformed_err())?
+                .as_bytes(store)?;
+            let mut receipt: SuccinctReceipt<ReceiptClaim> =
+                bincode::deserialize(&conditional_receipt_bytes)?;
+
+            let prover = get_prover_server(&opts)?;
+            for (idx, assumption_receipt) in request.assumption_receipts.into_iter().enumerate() {
+                let assumption_receipt_bytes = assumption_receipt.as_bytes(store)?;
+                let assumption_succinct_receipt: SuccinctReceipt<ReceiptClaim> =
+                    bincode::deserialize(&assumption_receipt_bytes)?;
+                receipt = prover
+                    .resolve(&receipt, &assumption_succinct_receipt.into_unknown())
+                    .with_context(|| format!("resolve assumption {idx}"))?;
+            }
+
+            let succinct_receipt_pb: pb::core::SuccinctReceipt = receipt.into();
+            let succinct_receipt_bytes = succinct_receipt_pb.encode_to_vec();
+            let asset = pb::api::Asset::from_bytes(
+                &request.receipt_out.ok_or(malformed_err())?,
+                succinct_receipt_bytes.into(),
+                "receipt.zkp",
+                store,
+            )?;
+
+            Ok(pb::api::ResolveReply {
+                kind: Some(pb::api::resolve_reply::Kind::Ok(pb::api::ResolveResult {
+                    receipt: Some(asset),
+                })),
+            })
+        }
+
+        let msg = inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::ResolveReply {
+            kind: Some(pb::api::resolve_reply::Kind::Error(pb::api::GenericError {
+                reason: err.to_string(),
+            })),
+        });
+
+        conn.send(msg)
+    }
+
     fn on_identity_p254(
         &self,
         mut conn: ConnectionWrapper,
         request: pb::api::IdentityP254Request,
     ) -> Result<()> {
-        fn inner(request: pb::api::IdentityP254Request) -> Result<pb::api::IdentityP254Reply> {
-            let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes()?;
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::IdentityP254Request,
+        ) -> Result<pb::api::IdentityP254Reply> {
+            let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes(store)?;
             let succinct_receipt: SuccinctReceipt<ReceiptClaim> =
                 bincode::deserialize(&receipt_bytes)?;
 
@@ -694,6 +886,7 @@ impl Server {
                 &request.receipt_out.ok_or(malformed_err())?,
                 succinct_receipt_bytes.into(),
                 "receipt.zkp",
+                store,
             )?;
 
             Ok(pb::api::IdentityP254Reply {
@@ -705,13 +898,14 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::IdentityP254Reply {
-            kind: Some(pb::api::identity_p254_reply::Kind::Error(
-                pb::api::GenericError {
-                    reason: err.to_string(),
-                },
-            )),
-        });
+        let msg =
+            inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::IdentityP254Reply {
+                kind: Some(pb::api::identity_p254_reply::Kind::Error(
+                    pb::api::GenericError {
+                        reason: err.to_string(),
+                    },
+                )),
+            });
 
         // tracing::trace!("tx: {msg:?}");
         conn.send(msg)
@@ -722,9 +916,12 @@ impl Server {
         mut conn: ConnectionWrapper,
         request: pb::api::CompressRequest,
     ) -> Result<()> {
-        fn inner(request: pb::api::CompressRequest) -> Result<pb::api::CompressReply> {
+        fn inner(
+            store: Option<&dyn AssetStore>,
+            request: pb::api::CompressRequest,
+        ) -> Result<pb::api::CompressReply> {
             let opts: ProverOpts = request.opts.ok_or(malformed_err())?.try_into()?;
-            let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes()?;
+            let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes(store)?;
             let receipt: Receipt = bincode::deserialize(&receipt_bytes)?;
 
             let prover = get_prover_server(&opts)?;
@@ -736,6 +933,7 @@ impl Server {
                 &request.receipt_out.ok_or(malformed_err())?,
                 receipt_bytes.into(),
                 "receipt.zkp",
+                store,
             )?;
 
             Ok(pb::api::CompressReply {
@@ -745,7 +943,7 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::CompressReply {
+        let msg = inner(self.asset_store(), request).unwrap_or_else(|err| pb::api::CompressReply {
             kind: Some(pb::api::compress_reply::Kind::Error(
                 pb::api::GenericError {
                     reason: err.to_string(),
@@ -762,8 +960,8 @@ impl Server {
         mut conn: ConnectionWrapper,
         request: pb::api::VerifyRequest,
     ) -> Result<()> {
-        fn inner(request: pb::api::VerifyRequest) -> Result<()> {
-            let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes()?;
+        fn inner(store: Option<&dyn AssetStore>, request: pb::api::VerifyRequest) -> Result<()> {
+            let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes(store)?;
             let receipt: Receipt =
                 bincode::deserialize(&receipt_bytes).context("deserialize receipt")?;
             let image_id: Digest = request.image_id.ok_or(malformed_err())?.try_into()?;
@@ -772,15 +970,88 @@ impl Server {
                 .map_err(|err| anyhow!("verify failed: {err}"))
         }
 
-        let msg: pb::api::GenericReply = inner(request).into();
+        let msg: pb::api::GenericReply = inner(self.asset_store(), request).into();
         // tracing::trace!("tx: {msg:?}");
         conn.send(msg)
     }
+
+    fn on_server_info(&self, mut conn: ConnectionWrapper) -> Result<()> {
+        fn inner() -> Result<ServerInfo> {
+            let features = [
+                ("cuda", cfg!(feature = "cuda")),
+                ("redis", cfg!(feature = "redis")),
+            ];
+            Ok(ServerInfo {
+                version: get_version().map_err(|err| anyhow!(err))?,
+                supported_hashfns: [HashFn::Poseidon2, HashFn::Sha256]
+                    .iter()
+                    .map(|hashfn| hashfn.as_str().to_string())
+                    .collect(),
+                max_po2: MAX_CYCLES_PO2 as u32,
+                features: features
+                    .into_iter()
+                    .filter(|(_, enabled)| *enabled)
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+                control_root: Some(SuccinctReceiptVerifierParameters::default().control_root),
+            })
+        }
+
+        let msg = pb::api::ServerInfoReply {
+            kind: Some(match inner() {
+                Ok(info) => pb::api::server_info_reply::Kind::Ok(info.into()),
+                Err(err) => pb::api::server_info_reply::Kind::Error(err.into()),
+            }),
+        };
+        conn.send(msg)
+    }
+
+    fn on_supported_zkrs(&self, mut conn: ConnectionWrapper) -> Result<()> {
+        let zkrs = registered_zkrs().into_iter().map(Into::into).collect();
+        let msg = pb::api::SupportedZkrsReply {
+            kind: Some(pb::api::supported_zkrs_reply::Kind::Ok(
+                pb::api::SupportedZkrsResult { zkrs },
+            )),
+        };
+        conn.send(msg)
+    }
+
+    fn on_warm_up(
+        &self,
+        mut conn: ConnectionWrapper,
+        request: pb::api::WarmUpRequest,
+    ) -> Result<()> {
+        fn inner(request: pb::api::WarmUpRequest) -> Result<()> {
+            let opts: ProverOpts = request.opts.ok_or(malformed_err())?.try_into()?;
+            warm_up(&opts)
+        }
+
+        let msg: pb::api::GenericReply = inner(request).into();
+        conn.send(msg)
+    }
+}
+
+/// The reply to an execute or prove request that failed.
+///
+/// A fault of the guest is reported along with the journal committed before it, which the client
+/// returns as a [ProverError::GuestFault](crate::ProverError::GuestFault).
+pub(crate) fn server_error_reply(err: anyhow::Error) -> pb::api::ServerReply {
+    let kind = match err.downcast_ref::<GuestFault>() {
+        Some(fault) => pb::api::server_reply::Kind::GuestFault(pb::api::GuestFault {
+            reason: err.to_string(),
+            partial_journal: fault.partial_journal.clone(),
+        }),
+        None => pb::api::server_reply::Kind::Error(pb::api::GenericError {
+            reason: err.to_string(),
+        }),
+    };
+    pb::api::ServerReply { kind: Some(kind) }
 }
 
 fn build_env<'a>(
     conn: &ConnectionWrapper,
     request: &pb::api::ExecutorEnv,
+    store: Option<&dyn AssetStore>,
 ) -> Result<ExecutorEnv<'a>> {
     let mut env_builder = ExecutorEnv::builder();
     env_builder.env_vars(request.env_vars.clone());
@@ -801,6 +1072,15 @@ fn build_env<'a>(
     if let Some(segment_limit_po2) = request.segment_limit_po2 {
         env_builder.segment_limit_po2(segment_limit_po2);
     }
+    if let Some(po2) = request.segment_po2_hint {
+        env_builder.segment_po2_hint(po2);
+    }
+    if let Some(po2) = request.coalesce_po2 {
+        env_builder.coalesce_small_segments(po2);
+    }
+    if let Some(interval_cycles) = request.checkpoint_interval {
+        env_builder.enable_checkpoints(interval_cycles);
+    }
     env_builder.session_limit(request.session_limit);
     if request.trace_events.is_some() {
         let proxy = TraceProxy::new(conn.clone());
@@ -821,12 +1101,12 @@ fn build_env<'a>(
         match assumption.kind.as_ref().ok_or(malformed_err())? {
             pb::api::assumption_receipt::Kind::Proven(asset) => {
                 let receipt: InnerAssumptionReceipt =
-                    pb::core::InnerReceipt::decode(asset.as_bytes()?)?.try_into()?;
+                    pb::core::InnerReceipt::decode(asset.as_bytes(store)?)?.try_into()?;
                 env_builder.add_assumption(receipt)
             }
             pb::api::assumption_receipt::Kind::Unresolved(asset) => {
                 let assumption: Assumption =
-                    pb::core::Assumption::decode(asset.as_bytes()?)?.try_into()?;
+                    pb::core::Assumption::decode(asset.as_bytes(store)?)?.try_into()?;
                 env_builder.add_assumption(assumption)
             }
         };
@@ -855,6 +1135,7 @@ impl pb::api::Asset {
         request: &pb::api::AssetRequest,
         bytes: Bytes,
         path: P,
+        store: Option<&dyn AssetStore>,
     ) -> Result<Self> {
         match request.kind.as_ref().ok_or(malformed_err())? {
             pb::api::asset_request::Kind::Inline(()) => Ok(Self {
@@ -872,10 +1153,29 @@ impl pb::api::Asset {
                 tracing::error!("It's likely that r0vm is not installed with the redis feature");
                 bail!("from_bytes not supported for redis")
             }
+            pb::api::asset_request::Kind::Store(key) => {
+                let key = StoreKey::new(key.as_str()).join(path.as_ref().to_string_lossy());
+                store::require(store)?.put(&key, bytes)?;
+                Ok(Self {
+                    kind: Some(pb::api::asset::Kind::Store(key.to_string())),
+                })
+            }
         }
     }
 }
 
+/// Whether a failed receive means that the client closed a kept-alive connection.
+fn is_hang_up(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<IoError>())
+        .any(|err| {
+            matches!(
+                err.kind(),
+                IoErrorKind::UnexpectedEof | IoErrorKind::ConnectionReset
+            )
+        })
+}
+
 #[allow(dead_code)]
 fn check_client_version(client: &semver::Version, server: &semver::Version) -> bool {
     if server.pre.is_empty() {
@@ -1002,6 +1302,7 @@ fn execute_default(
     conn: &mut ConnectionWrapper,
     exec: &mut ExecutorImpl,
     segments_out: &pb::api::AssetRequest,
+    store: Option<&dyn AssetStore>,
 ) -> Result<Session> {
     exec.run_with_callback(|segment| {
         let segment_bytes = bincode::serialize(&segment)?;
@@ -1009,22 +1310,49 @@ fn execute_default(
             segments_out,
             segment_bytes.into(),
             format!("segment-{}", segment.index),
+            store,
         )?;
         send_segment_done_msg(conn, segment, Some(asset))?;
         Ok(Box::new(NullSegmentRef))
     })
 }
 
+fn execute_stats_only(conn: &mut ConnectionWrapper, exec: &mut ExecutorImpl) -> Result<Session> {
+    exec.run_with_callback(|segment| {
+        send_segment_done_msg(conn, segment, None)?;
+        Ok(Box::new(NullSegmentRef))
+    })
+}
+
 fn send_segment_done_msg(
     conn: &mut ConnectionWrapper,
     segment: Segment,
     some_asset: Option<pb::api::Asset>,
 ) -> Result<()> {
+    let snapshot = segment
+        .snapshot()
+        .map(|snapshot| {
+            pb::api::Asset::from_bytes(
+                &pb::api::AssetRequest {
+                    kind: Some(pb::api::asset_request::Kind::Inline(())),
+                },
+                snapshot.encode()?.into(),
+                format!("snapshot-{}", segment.index),
+                None,
+            )
+        })
+        .transpose()?;
+    let paging = segment.paging();
     let segment = Some(pb::api::SegmentInfo {
         index: segment.index,
         po2: segment.inner.po2 as u32,
         cycles: segment.inner.insn_cycles as u32,
         segment: some_asset,
+        paging_cycles: segment.inner.paging_cycles as u64,
+        page_in_count: paging.page_in_count,
+        page_out_count: paging.page_out_count,
+        snapshot,
+        unique_pages_touched: paging.unique_pages_touched,
     });
 
     let msg = pb::api::ServerReply {
@@ -0,0 +1,114 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pluggable storage for the assets produced by a server.
+
+use std::{collections::HashMap, fmt, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+
+/// The key of an asset held by an [AssetStore].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StoreKey(String);
+
+impl StoreKey {
+    /// Construct a [StoreKey] from a string.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// The key as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The key of the asset called `name` under this key.
+    ///
+    /// A server writes each result requested with [AssetRequest::Store](super::AssetRequest::Store)
+    /// under the requested key, in the same way [AssetRequest::Path](super::AssetRequest::Path)
+    /// names a directory.
+    pub fn join(&self, name: impl AsRef<str>) -> Self {
+        Self(format!("{}/{}", self.0, name.as_ref()))
+    }
+}
+
+impl fmt::Display for StoreKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for StoreKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+impl From<&str> for StoreKey {
+    fn from(key: &str) -> Self {
+        Self(key.to_string())
+    }
+}
+
+/// A place for a server to write the assets it produces, so that a coordinator can pass around
+/// their keys rather than the assets themselves.
+///
+/// Results requested with [AssetRequest::Store](super::AssetRequest::Store) are written to the
+/// store set with [Server::with_asset_store](super::server::Server::with_asset_store), and read
+/// back with [Client::fetch_asset](super::client::Client::fetch_asset) from the store set with
+/// [ClientBuilder::asset_store](super::client::ClientBuilder::asset_store). The server and the
+/// client must both see the same assets, e.g. by sharing a store with a server running in this
+/// process or by using a store backed by shared storage.
+pub trait AssetStore: Send + Sync {
+    /// Write `bytes` under `key`, replacing any asset already there.
+    fn put(&self, key: &StoreKey, bytes: Bytes) -> Result<()>;
+
+    /// Returns the asset under `key`, or an error if there is none.
+    fn get(&self, key: &StoreKey) -> Result<Bytes>;
+}
+
+/// An [AssetStore] which holds assets in the memory of this process, e.g. for tests.
+#[derive(Default)]
+pub struct InMemoryAssetStore {
+    assets: Mutex<HashMap<StoreKey, Bytes>>,
+}
+
+impl InMemoryAssetStore {
+    /// Construct an empty [InMemoryAssetStore].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AssetStore for InMemoryAssetStore {
+    fn put(&self, key: &StoreKey, bytes: Bytes) -> Result<()> {
+        self.assets.lock().unwrap().insert(key.clone(), bytes);
+        Ok(())
+    }
+
+    fn get(&self, key: &StoreKey) -> Result<Bytes> {
+        self.assets
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("asset {key} not found in store"))
+    }
+}
+
+/// Returns `store`, or an error if no [AssetStore] has been configured.
+pub(crate) fn require(store: Option<&dyn AssetStore>) -> Result<&dyn AssetStore> {
+    store.ok_or_else(|| anyhow!("no asset store has been configured"))
+}
@@ -18,14 +18,23 @@ use std::{
     net::{SocketAddr, TcpListener},
     path::PathBuf,
     rc::Rc,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     thread,
 };
 
 use anyhow::Result;
-use risc0_circuit_recursion::control_id::{ALLOWED_CONTROL_ROOT, BN254_IDENTITY_CONTROL_ID};
+use risc0_circuit_recursion::{
+    control_id::{ALLOWED_CONTROL_ROOT, BN254_IDENTITY_CONTROL_ID},
+    prove::Program,
+};
 use risc0_zkp::{
-    core::hash::{poseidon2::Poseidon2HashSuite, poseidon_254::Poseidon254HashSuite},
+    core::{
+        digest::{Digest, DIGEST_WORDS},
+        hash::{poseidon2::Poseidon2HashSuite, poseidon_254::Poseidon254HashSuite},
+    },
     digest,
 };
 use risc0_zkvm_methods::{
@@ -35,13 +44,23 @@ use risc0_zkvm_methods::{
 use tempfile::{tempdir, TempDir};
 use test_log::test;
 
-use super::{Asset, AssetRequest, ConnectionWrapper, Connector, TcpConnection};
+use super::{
+    pb, Asset, AssetRequest, AssetStore, ConnectionWrapper, Connector, Endpoint, EndpointSelection,
+    InMemoryAssetStore, LocalConnector, SegmentAction, SegmentInfo, StoreKey, TcpConnection,
+    TcpConnector,
+};
 use crate::{
+    host::recursion::prove::{get_registered_zkr, SETUP_CACHE_HITS},
+    keccak_po2_range,
     receipt::SuccinctReceipt,
     recursion::{prove::zkr::test_recursion_circuit, MerkleGroup},
-    register_zkr, ApiClient, ApiServer, CoprocessorCallback, ExecutorEnv, InnerReceipt,
-    ProveKeccakRequest, ProveZkrRequest, ProverOpts, Receipt, ReceiptClaim, SegmentReceipt,
-    SessionInfo, SuccinctReceiptVerifierParameters, Unknown, VerifierContext,
+    register_zkr, register_zkr_with_info,
+    sha::Digestible,
+    ApiClient, ApiServer, Bytes, CancellationToken, Cancelled, CoprocessorCallback, ExecutorEnv,
+    ExecutorImpl, ExitCode, FakeReceipt, GuestArtifact, InnerReceipt, Journal, PagingStats,
+    ProveKeccakRequest, ProveZkrRequest, ProverError, ProverOpts, Receipt, ReceiptClaim, Segment,
+    SegmentReceipt, SessionInfo, SuccinctReceiptVerifierParameters, TimedOut, Unknown,
+    UnsupportedPo2, VerifierContext, ZkrInfo, RECURSION_PO2,
 };
 
 struct TestClientConnector {
@@ -130,6 +149,13 @@ impl TestClient {
         })
     }
 
+    fn prove_segments(&self, opts: &ProverOpts, segments: Vec<Asset>) -> Vec<SegmentReceipt> {
+        with_servers(self.addr, segments.len(), || {
+            let receipt_out = AssetRequest::Path(self.get_work_path());
+            self.client.prove_segments(opts, segments, receipt_out)
+        })
+    }
+
     fn prove_zkr(&self, request: ProveZkrRequest) -> SuccinctReceipt<Unknown> {
         with_server(self.addr, || {
             let receipt_out = AssetRequest::Path(self.get_work_path());
@@ -137,6 +163,13 @@ impl TestClient {
         })
     }
 
+    fn prove_zkr_batch(&self, requests: Vec<ProveZkrRequest>) -> Vec<SuccinctReceipt<Unknown>> {
+        with_server(self.addr, || {
+            let receipt_out = AssetRequest::Path(self.get_work_path());
+            self.client.prove_zkr_batch(requests, receipt_out)
+        })
+    }
+
     fn prove_keccak(&self, request: ProveKeccakRequest) -> SuccinctReceipt<Unknown> {
         with_server(self.addr, || {
             let receipt_out = AssetRequest::Path(self.get_work_path());
@@ -177,6 +210,32 @@ impl TestClient {
         })
     }
 
+    fn resolve_all(
+        &self,
+        opts: &ProverOpts,
+        conditional_receipt: SuccinctReceipt<ReceiptClaim>,
+        assumption_receipts: Vec<SuccinctReceipt<Unknown>>,
+        on_progress: impl FnMut(usize, usize, Digest),
+    ) -> SuccinctReceipt<ReceiptClaim> {
+        with_server(self.addr, || {
+            self.client
+                .resolve_all(opts, conditional_receipt, assumption_receipts, on_progress)
+        })
+    }
+
+    fn resolve_batch(
+        &self,
+        opts: &ProverOpts,
+        conditional_receipt: SuccinctReceipt<ReceiptClaim>,
+        assumption_receipts: Vec<SuccinctReceipt<Unknown>>,
+    ) -> SuccinctReceipt<ReceiptClaim> {
+        with_server(self.addr, || {
+            let receipt_out = AssetRequest::Path(self.get_work_path());
+            self.client
+                .resolve_batch(opts, conditional_receipt, assumption_receipts, receipt_out)
+        })
+    }
+
     fn identity_p254(&self, opts: &ProverOpts, receipt: Asset) -> SuccinctReceipt<ReceiptClaim> {
         with_server(self.addr, || {
             let receipt_out = AssetRequest::Path(self.get_work_path());
@@ -185,43 +244,960 @@ impl TestClient {
     }
 }
 
-fn with_server<T, F: FnOnce() -> Result<T>>(addr: SocketAddr, f: F) -> T {
-    let addr = addr.to_string();
-    let handle = thread::Builder::new()
-        .name("server".into())
-        .spawn(move || {
-            let server = ApiServer::new_tcp(addr);
-            server.run().unwrap();
-        })
-        .unwrap();
+fn with_server<T, E, F>(addr: SocketAddr, f: F) -> T
+where
+    E: std::fmt::Debug,
+    F: FnOnce() -> Result<T, E>,
+{
+    with_servers(addr, 1, f)
+}
+
+/// Like [with_server], but serves `count` requests one after the other.
+fn with_servers<T, E, F>(addr: SocketAddr, count: usize, f: F) -> T
+where
+    E: std::fmt::Debug,
+    F: FnOnce() -> Result<T, E>,
+{
+    let handle = thread::Builder::new()
+        .name("server".into())
+        .spawn(move || {
+            for _ in 0..count {
+                let server = ApiServer::new_tcp(addr.to_string());
+                server.run().unwrap();
+            }
+        })
+        .unwrap();
+
+    let result = f().unwrap();
+    handle.join().unwrap();
+    result
+}
+
+#[test]
+fn execute() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Inline(MULTI_TEST_ELF.into());
+    let session = TestClient::new().execute(env, binary);
+    assert!(session.is_complete());
+}
+
+#[test]
+fn execute_journal_callback() {
+    let chunks = vec![b"abcd".to_vec(), b"efghijkl".to_vec()];
+    let mut delivered = Vec::new();
+    let session = {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::EchoChunks {
+                chunks: chunks.clone(),
+            })
+            .unwrap()
+            .journal_callback(|bytes: &[u8]| {
+                delivered.push(bytes.to_vec());
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        let binary = Asset::Inline(MULTI_TEST_ELF.into());
+        TestClient::new().execute(env, binary)
+    };
+    assert_eq!(delivered, chunks);
+    assert_eq!(session.journal.bytes, chunks.concat());
+}
+
+#[test]
+fn execute_segment_cycles() {
+    let spec = MultiTestSpec::BusyLoop { cycles: 1 << 16 };
+    let segment_limit_po2 = 14; // 16k cycles
+
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(segment_limit_po2)
+        .build()
+        .unwrap();
+    let session_info = TestClient::new().execute(env, Asset::Inline(MULTI_TEST_ELF.into()));
+
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(segment_limit_po2)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    assert_eq!(session_info.segments.len(), session.segments.len());
+    for (info, segment) in session_info.segments.iter().zip(session.segments.iter()) {
+        let segment = segment.resolve().unwrap();
+        assert_eq!(info.po2, segment.po2() as u32);
+        assert_eq!(info.total_cycles(), 1 << segment.po2());
+        assert_eq!(info.cycles, segment.inner.insn_cycles as u32);
+        assert_eq!(info.paging_cycles(), segment.inner.paging_cycles as u64);
+        assert!(info.cycles as u64 + info.paging_cycles() < info.total_cycles());
+        assert_eq!(info.paging, segment.paging());
+    }
+    assert_eq!(session_info.cycles(), session.user_cycles);
+
+    let info_stats = session_info.stats();
+    let session_stats = session.stats();
+    assert_eq!(info_stats.segments, session_stats.segments);
+    assert_eq!(info_stats.total_cycles, session_stats.total_cycles);
+    assert_eq!(info_stats.user_cycles, session_stats.user_cycles);
+    assert_eq!(info_stats.paging_cycles, session_stats.paging_cycles);
+    assert_eq!(info_stats.reserved_cycles, session_stats.reserved_cycles);
+}
+
+#[test]
+fn execute_stats_only() {
+    let spec = MultiTestSpec::BusyLoop { cycles: 1 << 16 };
+    let env = || {
+        ExecutorEnv::builder()
+            .write(&spec)
+            .unwrap()
+            .segment_limit_po2(14)
+            .build()
+            .unwrap()
+    };
+    let binary = Asset::Inline(MULTI_TEST_ELF.into());
+
+    let mut client = TestClient::new();
+    let full = client.execute(env(), binary.clone());
+    let stats_only = with_server(client.addr, || {
+        client.client.execute_stats_only(&env(), binary)
+    });
+
+    assert_eq!(stats_only.journal.bytes, full.journal.bytes);
+    assert_eq!(stats_only.cycles(), full.cycles());
+    let (stats, full_stats) = (stats_only.stats(), full.stats());
+    assert_eq!(stats.segments, full_stats.segments);
+    assert_eq!(stats.total_cycles, full_stats.total_cycles);
+    assert_eq!(stats.paging_cycles, full_stats.paging_cycles);
+}
+
+#[test]
+fn session_info_stats() {
+    let paging = |page_in_count, page_out_count| PagingStats {
+        page_in_count,
+        page_out_count,
+        unique_pages_touched: page_in_count,
+    };
+    let session_info = SessionInfo {
+        segments: vec![
+            SegmentInfo::new(16, 50_000, 8_000, paging(40, 10)),
+            SegmentInfo::new(16, 40_000, 6_000, paging(30, 8)),
+            SegmentInfo::new(15, 10_000, 4_000, paging(20, 5)),
+        ],
+        journal: Journal::new(vec![]),
+        exit_code: ExitCode::Halted(0),
+        receipt_claim: None,
+        unique_pages_touched: 50,
+        partial_journal: vec![],
+        complete: true,
+    };
+
+    let stats = session_info.stats();
+    assert_eq!(stats.segments, 3);
+    assert_eq!(
+        stats.total_cycles,
+        session_info
+            .segments
+            .iter()
+            .map(SegmentInfo::total_cycles)
+            .sum::<u64>()
+    );
+    assert_eq!(stats.total_cycles, (1 << 16) + (1 << 16) + (1 << 15));
+    assert_eq!(stats.user_cycles, 100_000);
+    assert_eq!(stats.paging_cycles, 18_000);
+    assert_eq!(
+        stats.reserved_cycles,
+        stats.total_cycles - stats.user_cycles - stats.paging_cycles
+    );
+    assert_eq!(stats.paging.page_in_count, 90);
+    assert_eq!(stats.paging.page_out_count, 23);
+    assert_eq!(stats.paging.unique_pages_touched, 50);
+}
+
+#[test]
+fn session_info_stats_reserved_cycles_saturate() {
+    // Cycle counts reported by a server are not trusted to add up.
+    let session_info = SessionInfo::new(
+        vec![SegmentInfo::new(10, 2_000, 500, PagingStats::default())],
+        Journal::new(vec![]),
+        ExitCode::Halted(0),
+        None,
+    );
+    assert_eq!(session_info.stats().reserved_cycles, 0);
+}
+
+#[test]
+fn guest_fault_reply() {
+    let fault = crate::GuestFault {
+        error: anyhow::anyhow!("StoreAccessFault"),
+        partial_journal: b"committed".to_vec(),
+    };
+    let reply = super::server::server_error_reply(fault.into());
+    let Some(pb::api::server_reply::Kind::GuestFault(fault)) = reply.kind else {
+        panic!("expected a guest fault reply");
+    };
+    let err = ProverError::from(anyhow::Error::from(fault));
+    let ProverError::GuestFault {
+        reason,
+        partial_journal,
+    } = err
+    else {
+        panic!("expected a guest fault: {err}");
+    };
+    assert_eq!(reason, "StoreAccessFault");
+    assert_eq!(partial_journal, b"committed");
+}
+
+#[test]
+fn execute_cancel() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 24 })
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let client = TestClient::new();
+    let cancel = CancellationToken::new();
+    let mut segments = Vec::new();
+    let addr = client.addr.to_string();
+    let server = thread::spawn(move || ApiServer::new_tcp(addr).run().unwrap());
+    let err = client
+        .client
+        .execute_with_cancellation(
+            &env,
+            Asset::Inline(MULTI_TEST_ELF.into()),
+            AssetRequest::Path(client.get_work_path()),
+            &cancel,
+            |_info, segment| {
+                segments.push(segment);
+                cancel.cancel();
+                Ok(())
+            },
+        )
+        .unwrap_err();
+    server.join().unwrap();
+    assert!(
+        matches!(err, ProverError::Cancelled),
+        "unexpected error: {err:#}"
+    );
+
+    // Execution stopped at the next segment boundary, and the segment that was delivered is intact.
+    assert_eq!(segments.len(), 1);
+    let segment: Segment = bincode::deserialize(&segments[0].as_bytes().unwrap()).unwrap();
+    assert_eq!(segment.index, 0);
+}
+
+#[test]
+fn execute_stop() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 24 })
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let client = TestClient::new();
+    let mut segments = Vec::new();
+    let addr = client.addr.to_string();
+    let server = thread::spawn(move || ApiServer::new_tcp(addr).run().unwrap());
+    let session = client
+        .client
+        .execute(
+            &env,
+            Asset::Inline(MULTI_TEST_ELF.into()),
+            AssetRequest::Path(client.get_work_path()),
+            |_info, segment| {
+                segments.push(segment);
+                Ok(if segments.len() == 2 {
+                    SegmentAction::Stop
+                } else {
+                    SegmentAction::Continue
+                })
+            },
+        )
+        .unwrap();
+    server.join().unwrap();
+
+    assert_eq!(segments.len(), 2);
+    assert_eq!(session.segments.len(), 2);
+    assert!(!session.is_complete());
+    assert_eq!(session.exit_code, ExitCode::SystemSplit);
+    assert!(session.receipt_claim.is_none());
+}
+
+#[test]
+fn execute_prefix() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 17 })
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let client = TestClient::new();
+    let (session, segments) = with_server(client.addr, || {
+        client
+            .client
+            .execute_prefix(&env, Asset::Inline(MULTI_TEST_ELF.into()), 2)
+    });
+    assert_eq!(segments.len(), 2);
+    assert_eq!(session.segments.len(), 2);
+    assert!(!session.is_complete());
+    assert!(session.receipt_claim.is_none());
+
+    // The segments of the prefix are proven as usual, to find the first one which fails.
+    let receipts = client.prove_segments(&ProverOpts::fast(), segments);
+    let ctx = VerifierContext::default();
+    for (idx, receipt) in receipts.iter().enumerate() {
+        receipt.verify_integrity_with_context(&ctx).unwrap();
+        assert_eq!(receipt.index, idx as u32);
+    }
+}
+
+#[test]
+fn execute_from_snapshot() {
+    let spec = MultiTestSpec::BusyLoop { cycles: 1 << 17 };
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .enable_checkpoints(0)
+        .build()
+        .unwrap();
+    let client = TestClient::new();
+    let mut snapshots = Vec::new();
+    let session = with_server(client.addr, || {
+        client.client.execute(
+            &env,
+            Asset::Inline(MULTI_TEST_ELF.into()),
+            AssetRequest::Inline,
+            |info, _segment| {
+                snapshots.extend(info.snapshot());
+                Ok(())
+            },
+        )
+    });
+    assert!(session.segments.len() >= 3);
+    assert_eq!(snapshots.len(), session.segments.len() - 1);
+
+    // Resume halfway through, with the same input as the original execution.
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let snapshot = snapshots.swap_remove(snapshots.len() / 2);
+    let mut resumed_segments = 0;
+    let resumed = with_server(client.addr, || {
+        client.client.execute_from_snapshot(
+            &env,
+            snapshot,
+            AssetRequest::Inline,
+            |_info, _segment| {
+                resumed_segments += 1;
+                Ok(())
+            },
+        )
+    });
+    assert!(resumed_segments < session.segments.len());
+    assert_eq!(resumed.exit_code, session.exit_code);
+    assert_eq!(resumed.journal, session.journal);
+    assert_eq!(
+        resumed.receipt_claim.unwrap().digest(),
+        session.receipt_claim.unwrap().digest()
+    );
+}
+
+#[test]
+fn prove() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Path(MULTI_TEST_PATH.into());
+    let opts = ProverOpts::default();
+    let receipt = TestClient::new().prove(&env, &opts, binary);
+    receipt.verify(MULTI_TEST_ID).unwrap();
+}
+
+#[test]
+fn control_root() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let client = TestClient::new();
+    let info = with_server(client.addr, || client.client.server_info());
+    let receipt = client.prove(
+        &env,
+        &ProverOpts::succinct(),
+        Asset::Path(MULTI_TEST_PATH.into()),
+    );
+
+    let control_root = receipt.inner.succinct().unwrap().control_root().unwrap();
+    assert_eq!(info.control_root, Some(control_root));
+    assert_eq!(receipt.summary().unwrap().control_root, Some(control_root));
+}
+
+#[test]
+fn verify_and_claim() {
+    let env = ExecutorEnv::builder().build().unwrap();
+    let binary = Asset::Inline(HELLO_COMMIT_ELF.into());
+    let client = TestClient::new();
+    let receipt = client.prove(&env, &ProverOpts::default(), binary);
+
+    let asset: Asset = receipt.clone().try_into().unwrap();
+    let claim = with_server(client.addr, || {
+        client.client.verify_and_claim(asset, HELLO_COMMIT_ID)
+    });
+    assert_eq!(claim.pre.digest(), HELLO_COMMIT_ID.into());
+    assert_eq!(claim.exit_code, ExitCode::Halted(0));
+    assert_eq!(claim, receipt.claim().unwrap().value().unwrap());
+    let output = claim.output.as_value().unwrap().as_ref().unwrap();
+    assert_eq!(output.journal.digest(), receipt.journal.digest());
+}
+
+#[test]
+fn prove_keccak_elf() {
+    use risc0_circuit_keccak_methods::{KECCAK_ELF, KECCAK_ID};
+
+    let claim_digest = digest!("b83c10da0c23587bf318cbcec2c2ac0260dbd6c0fa6905df639f8f6056f0d56c");
+    let env = ExecutorEnv::builder()
+        .write(&(claim_digest, 16u32))
+        .unwrap()
+        .build()
+        .unwrap();
+
+    // Executing, proving the keccak assumption, joining and resolving all happen in one call.
+    let binary = Asset::Inline(KECCAK_ELF.into());
+    let receipt = TestClient::new().prove(&env, &ProverOpts::succinct(), binary);
+    receipt.inner.succinct().unwrap();
+    receipt.verify(KECCAK_ID).unwrap();
+}
+
+#[test]
+fn concurrent_execute() {
+    use risc0_circuit_keccak_methods::{KECCAK_ELF, KECCAK_ID};
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let client = ApiClient::with_connector(Box::new(LocalConnector::new()));
+    assert_send_sync(&client);
+
+    let claim_digest = digest!("b83c10da0c23587bf318cbcec2c2ac0260dbd6c0fa6905df639f8f6056f0d56c");
+    let receipts: Vec<Receipt> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    let env = ExecutorEnv::builder()
+                        .write(&(claim_digest, 16u32))
+                        .unwrap()
+                        .build()
+                        .unwrap();
+                    let binary = Asset::Inline(KECCAK_ELF.into());
+                    client
+                        .prove(&env, &ProverOpts::succinct(), binary)
+                        .unwrap()
+                        .receipt
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    assert_eq!(receipts.len(), 4);
+    for receipt in receipts {
+        receipt.verify(KECCAK_ID).unwrap();
+    }
+}
+
+#[test]
+fn server_connector_need_not_be_send() {
+    // Only a connector given to a client, which may be shared between threads, must be
+    // `Send + Sync`.
+    struct RcConnector {
+        attempts: Rc<std::cell::Cell<usize>>,
+    }
+
+    impl Connector for RcConnector {
+        fn connect(&self) -> Result<ConnectionWrapper> {
+            self.attempts.set(self.attempts.get() + 1);
+            anyhow::bail!("not connected")
+        }
+    }
+
+    let attempts = Rc::new(std::cell::Cell::new(0));
+    let server = ApiServer::new(Box::new(RcConnector {
+        attempts: attempts.clone(),
+    }));
+    assert!(server.run().is_err());
+    assert_eq!(attempts.get(), 1);
+}
+
+#[test]
+fn receipt_path_round_trip() {
+    let mut client = TestClient::new();
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Path(MULTI_TEST_PATH.into());
+    client.execute(env, binary.clone());
+    assert!(client
+        .segments
+        .iter()
+        .all(|segment| matches!(segment, Asset::Path(_))));
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let opts = ProverOpts::default();
+    let receipt = client.prove(&env, &opts, binary);
+
+    let receipt_path = client.get_work_path().join("receipt.bin");
+    let Asset::Inline(receipt_bytes) = receipt.clone().try_into().unwrap() else {
+        panic!("expected an inline asset");
+    };
+    std::fs::write(&receipt_path, receipt_bytes).unwrap();
+
+    let loaded: Receipt = Asset::Path(receipt_path.clone()).try_into().unwrap();
+    assert_eq!(loaded.journal, receipt.journal);
+    with_server(client.addr, || {
+        client
+            .client
+            .verify(Asset::Path(receipt_path), MULTI_TEST_ID)
+    });
+}
+
+#[test]
+fn receipt_store_round_trip() {
+    let store = Arc::new(InMemoryAssetStore::new());
+
+    let journal = b"stored".to_vec();
+    let claim = ReceiptClaim::ok(MULTI_TEST_ID, journal.clone());
+    let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+    let receipt_bytes: Bytes = bincode::serialize(&receipt).unwrap().into();
+
+    // The server writes the receipt to the store and replies with its key.
+    let receipt_out = AssetRequest::Store(StoreKey::new("receipt_store_round_trip"));
+    let asset = pb::api::Asset::from_bytes(
+        &receipt_out.try_into().unwrap(),
+        receipt_bytes.clone(),
+        "receipt.zkp",
+        Some(store.as_ref()),
+    )
+    .unwrap();
+    let Asset::Store(key) = asset.try_into().unwrap() else {
+        panic!("expected a store asset");
+    };
+    assert_eq!(key.as_str(), "receipt_store_round_trip/receipt.zkp");
+    assert_eq!(store.get(&key).unwrap(), receipt_bytes);
+
+    // The coordinator resolves the key later.
+    let client = ApiClient::builder()
+        .connector(Box::new(TestClientConnector::new().unwrap()))
+        .asset_store(store.clone())
+        .build()
+        .unwrap();
+    let bytes = client.fetch_asset(&key).unwrap();
+    assert_eq!(bytes, receipt_bytes);
+    let loaded: Receipt = Asset::Inline(bytes).try_into().unwrap();
+    assert_eq!(loaded.journal, receipt.journal);
+    assert_eq!(
+        loaded.claim().unwrap().digest(),
+        receipt.claim().unwrap().digest()
+    );
+
+    let missing = StoreKey::new("receipt_store_round_trip/missing.zkp");
+    assert!(client.fetch_asset(&missing).is_err());
+}
+
+#[test]
+fn execute_segments_to_path() {
+    let segment_limit_po2 = 14; // 16k cycles
+    let segment_dir = tempdir().unwrap();
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop {
+            cycles: 4 << segment_limit_po2,
+        })
+        .unwrap()
+        .segment_limit_po2(segment_limit_po2)
+        .segment_path(segment_dir.path())
+        .build()
+        .unwrap();
+
+    let client = TestClient::new();
+    let mut segments = Vec::new();
+    let session = with_server(client.addr, || {
+        client.client.execute(
+            &env,
+            Asset::Path(MULTI_TEST_PATH.into()),
+            AssetRequest::Inline,
+            |_info, asset| {
+                segments.push(asset);
+                Ok(())
+            },
+        )
+    });
+    assert!(session.segments.len() > 1);
+    assert_eq!(session.segments.len(), segments.len());
+
+    // Every segment is written to the segment path as it is produced, so the client only holds
+    // paths in memory regardless of the number of segments.
+    for segment in segments.iter() {
+        let Asset::Path(path) = segment else {
+            panic!("segment not written to the segment path");
+        };
+        assert_eq!(path.parent(), Some(segment_dir.path()));
+        assert!(std::fs::metadata(path).unwrap().len() > 0);
+        let segment: Segment = bincode::deserialize(&segment.as_bytes().unwrap()).unwrap();
+        assert!(segment.po2() <= segment_limit_po2 as usize);
+    }
+}
+
+#[test]
+fn compress_to_groth16_rejects_composite() {
+    let client = TestClient::new();
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Path(MULTI_TEST_PATH.into());
+    let opts = ProverOpts::composite();
+    let receipt = client.prove(&env, &opts, binary);
+
+    // The receipt kind is checked before connecting to the server.
+    let err = client
+        .client
+        .compress_to_groth16(&opts, receipt.try_into().unwrap(), AssetRequest::Inline)
+        .unwrap_err();
+    assert!(err.to_string().contains("requires a succinct receipt"));
+}
+
+struct UnreachableConnector;
+
+impl Connector for UnreachableConnector {
+    fn connect(&self) -> Result<ConnectionWrapper> {
+        panic!("unexpected connection to the server")
+    }
+}
+
+#[test]
+fn guest_artifact() {
+    use risc0_circuit_keccak_methods::{KECCAK_ELF, KECCAK_ID, KECCAK_PATH};
+
+    let artifact = GuestArtifact::from_elf(KECCAK_ELF).unwrap();
+    assert_eq!(artifact.image_id(), Digest::from(KECCAK_ID));
+    assert_eq!(Digest::from(&artifact), Digest::from(KECCAK_ID));
+    assert_eq!(
+        Asset::from(&artifact).as_bytes().unwrap(),
+        Bytes::from_static(KECCAK_ELF)
+    );
+
+    let loaded = GuestArtifact::load(KECCAK_PATH).unwrap();
+    assert_eq!(loaded.image_id(), artifact.image_id());
+    assert_eq!(loaded.elf(), artifact.elf());
+
+    assert!(GuestArtifact::from_elf(&b"not an elf"[..]).is_err());
+}
+
+#[test]
+fn prove_keccak_unsupported_po2() {
+    let client = ApiClient::with_connector(Box::new(UnreachableConnector));
+    let range = keccak_po2_range();
+    for po2 in [*range.start() - 1, *range.end() + 1] {
+        let request = ProveKeccakRequest {
+            claim_digest: Digest::ZERO,
+            po2: po2 as usize,
+            control_root: Digest::ZERO,
+            input: vec![],
+        };
+
+        // The po2 is checked before connecting to the server.
+        let err = client
+            .prove_keccak::<Unknown>(request, AssetRequest::Inline)
+            .unwrap_err();
+        let ProverError::UnsupportedPo2(err) = err else {
+            panic!("unexpected error: {err:#}");
+        };
+        assert_eq!(err.requested, po2 as usize);
+        assert_eq!(err.min..=err.max, range);
+    }
+}
+
+// The transcript proven by the keccak test guest.
+fn keccak_test_input(po2: u32) -> Vec<u8> {
+    let mut state = [0u64; 25];
+    let mut pows = 987654321_u64;
+    for part in state.iter_mut() {
+        *part = pows;
+        pows = pows.wrapping_mul(123456789);
+    }
+    bytemuck::cast_slice(&vec![state; (1 << po2) / 200]).to_vec()
+}
+
+#[test]
+fn keccak_request_claim_digest() {
+    let request = ProveKeccakRequest::new(&keccak_test_input(16), 16).unwrap();
+    assert_eq!(
+        request.claim_digest,
+        digest!("b83c10da0c23587bf318cbcec2c2ac0260dbd6c0fa6905df639f8f6056f0d56c")
+    );
+    assert_eq!(request.po2, 16);
+
+    let err = ProveKeccakRequest::new(&[], *keccak_po2_range().end() + 1)
+        .err()
+        .unwrap();
+    assert!(err.downcast_ref::<UnsupportedPo2>().is_some());
+    assert!(ProveKeccakRequest::new(&[0; 100], 16).is_err());
+    assert!(ProveKeccakRequest::new(&keccak_test_input(17), 16).is_err());
+}
+
+#[test]
+fn keccak_compute_claim_digest() {
+    let po2 = *keccak_po2_range().start();
+    let request = ProveKeccakRequest::new(&keccak_test_input(po2), po2).unwrap();
+    let claim_digest = request.compute_claim_digest();
+    assert_eq!(claim_digest, request.claim_digest);
+
+    // The digest is derived from the input, not from the claim the request carries.
+    let mut unclaimed = request.clone();
+    unclaimed.claim_digest = Digest::ZERO;
+    assert_eq!(unclaimed.compute_claim_digest(), claim_digest);
+
+    let receipt = TestClient::new().prove_keccak(request);
+    assert_eq!(receipt.claim.digest(), claim_digest);
+}
+
+#[test]
+fn keccak_request_from_path() {
+    use std::io::Read as _;
+
+    let input = keccak_test_input(16);
+    let inline = ProveKeccakRequest::new(&input, 16).unwrap();
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("keccak.bin");
+    std::fs::write(&path, &input).unwrap();
+    let from_path = ProveKeccakRequest::from_path(&path, 16).unwrap();
+    assert_eq!(from_path.claim_digest, inline.claim_digest);
+    assert_eq!(from_path.input, inline.input);
+
+    // States split across reads are reassembled.
+    let reader = input[..100].chain(&input[100..]);
+    let streamed = ProveKeccakRequest::from_reader(reader, 16).unwrap();
+    assert_eq!(streamed.claim_digest, inline.claim_digest);
+
+    std::fs::write(&path, keccak_test_input(17)).unwrap();
+    assert!(ProveKeccakRequest::from_path(&path, 16).is_err());
+    assert!(ProveKeccakRequest::from_path(dir.path().join("missing.bin"), 16).is_err());
+}
+
+#[test]
+fn zkr_request_builder() {
+    let control_id = BN254_IDENTITY_CONTROL_ID;
+    let inner_claim = digest!("00000000000000de00000000000000ad00000000000000be00000000000000ef");
+    let mut input = Vec::new();
+    input.extend(ALLOWED_CONTROL_ROOT.as_words());
+    input.extend(inner_claim.as_words());
+
+    // A stand-in for the program's claim, taken from the second digest of its input.
+    let claim = |input: &[u32]| Digest::try_from(&input[DIGEST_WORDS..]).unwrap();
+    let zkr = ZkrInfo {
+        control_id,
+        name: None,
+        input_words: Some(2 * DIGEST_WORDS as u32),
+    };
+    let builder = || {
+        ProveZkrRequest::builder()
+            .control_id(control_id)
+            .zkrs([zkr.clone()])
+    };
+
+    let request = builder().input(&input).claim(claim).build().unwrap();
+    assert_eq!(request.control_id, control_id);
+    assert_eq!(request.claim_digest, inner_claim);
+    assert_eq!(request.input, bytemuck::cast_slice::<_, u8>(&input));
+
+    let request = builder()
+        .input(&input)
+        .claim_digest(inner_claim)
+        .claim(claim)
+        .build()
+        .unwrap();
+    assert_eq!(request.claim_digest, inner_claim);
+
+    let err = builder()
+        .input(&input[..DIGEST_WORDS])
+        .claim_digest(inner_claim)
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("expects 16"), "{err}");
+
+    assert!(builder()
+        .input(&input)
+        .claim_digest(Digest::ZERO)
+        .claim(claim)
+        .build()
+        .is_err());
+    assert!(builder().input(&input).build().is_err());
+
+    // The expected length is taken from the registered program, so one that is not registered,
+    // or that does not report its length, is rejected.
+    let err = ProveZkrRequest::builder()
+        .control_id(Digest::ZERO)
+        .zkrs([zkr.clone()])
+        .input(&input)
+        .claim_digest(inner_claim)
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("not registered"), "{err}");
+    let err = ProveZkrRequest::builder()
+        .control_id(control_id)
+        .zkrs([ZkrInfo {
+            input_words: None,
+            ..zkr
+        }])
+        .input(&input)
+        .claim_digest(inner_claim)
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("input length"), "{err}");
+
+    // Without a list of programs, those registered in this process are used.
+    let control_id = digest!("5a4b3c2d00000000000000000000000000000000000000000000000000000002");
+    register_zkr_with_info(&control_id, "stub", DIGEST_WORDS as u32, || {
+        Err(anyhow::anyhow!("program not loaded"))
+    });
+    let request = ProveZkrRequest::builder()
+        .control_id(control_id)
+        .input(&input[..DIGEST_WORDS])
+        .claim_digest(inner_claim)
+        .build()
+        .unwrap();
+    assert_eq!(request.control_id, control_id);
+    assert!(ProveZkrRequest::builder()
+        .control_id(control_id)
+        .input(&input)
+        .claim_digest(inner_claim)
+        .build()
+        .is_err());
+}
+
+#[test]
+fn prove_keccak_standalone() {
+    let po2 = *keccak_po2_range().start();
+    let request = ProveKeccakRequest::new(&keccak_test_input(po2), po2).unwrap();
+    let claim_digest = request.claim_digest;
+
+    let start = std::time::Instant::now();
+    let receipt = TestClient::new().prove_keccak(request);
+    tracing::info!("proved keccak po2 {po2} in {:?}", start.elapsed());
+
+    assert_eq!(receipt.claim.digest(), claim_digest);
+    receipt
+        .verify_integrity_with_context(&VerifierContext::default())
+        .unwrap();
+}
+
+#[test]
+fn prover_error_variants() {
+    use std::io::{Error as IoError, ErrorKind};
 
-    let result = f().unwrap();
-    handle.join().unwrap();
-    result
+    use prost::Message as _;
+    use risc0_zkp::verify::VerificationError;
+
+    use super::pb;
+
+    let classify = |err: anyhow::Error| ProverError::from(err);
+
+    let err = classify(IoError::from(ErrorKind::NotFound).into());
+    assert!(matches!(err, ProverError::Io(_)), "{err}");
+
+    let err = anyhow::Error::from(IoError::from(ErrorKind::ConnectionReset)).context("rx reply");
+    let err = classify(err);
+    assert!(matches!(err, ProverError::Connection(_)));
+    // The wrapped error is the source, so its context and causes are part of the chain.
+    let source = std::error::Error::source(&err).unwrap();
+    assert_eq!(source.to_string(), "rx reply");
+    assert_eq!(
+        format!("{:#}", anyhow::Error::from(err)),
+        "server connection failed: rx reply: connection reset"
+    );
+
+    let err = classify(VerificationError::InvalidProof.into());
+    assert!(matches!(err, ProverError::VerificationFailed(_)), "{err}");
+
+    let err = pb::api::HelloReply::decode([0xff].as_slice()).unwrap_err();
+    assert!(matches!(classify(err.into()), ProverError::Serialize(_)));
+
+    let err = anyhow::Error::from(Cancelled).context("execute");
+    assert!(matches!(classify(err), ProverError::Cancelled));
+
+    let timeout = std::time::Duration::from_secs(1);
+    let err = classify(TimedOut { timeout }.into());
+    assert!(matches!(err, ProverError::Timeout(TimedOut { timeout: t }) if t == timeout));
+
+    let po2 = UnsupportedPo2 {
+        requested: 30,
+        min: 14,
+        max: 18,
+    };
+    let err = classify(po2.into());
+    assert!(matches!(
+        err,
+        ProverError::UnsupportedPo2(UnsupportedPo2 { requested: 30, .. })
+    ));
+
+    let err = classify(anyhow::anyhow!("invalid request"));
+    assert!(matches!(err, ProverError::Other(_)));
+    assert_eq!(err.to_string(), "invalid request");
+
+    // Converting to anyhow and back preserves the variant.
+    let err: anyhow::Error = ProverError::Cancelled.into();
+    assert!(matches!(classify(err), ProverError::Cancelled));
 }
 
 #[test]
-fn execute() {
-    let env = ExecutorEnv::builder()
-        .write(&MultiTestSpec::DoNothing)
-        .unwrap()
-        .build()
-        .unwrap();
-    let binary = Asset::Inline(MULTI_TEST_ELF.into());
-    TestClient::new().execute(env, binary);
+fn invalid_prover_opts() {
+    let client = TestClient::new();
+    let env = ExecutorEnv::builder().build().unwrap();
+    let opts = ProverOpts::fast().with_min_po2(16).with_segment_po2_max(15);
+
+    // The options are checked before connecting to the server.
+    let Err(err) = client
+        .client
+        .prove(&env, &opts, Asset::Path(MULTI_TEST_PATH.into()))
+    else {
+        panic!("expected the options to be rejected");
+    };
+    assert!(err.to_string().contains("min_po2"), "{err}");
 }
 
 #[test]
-fn prove() {
-    let env = ExecutorEnv::builder()
-        .write(&MultiTestSpec::DoNothing)
-        .unwrap()
-        .build()
+fn prover_error_connection() {
+    let err = ApiClient::new_sub_process("/nonexistent/r0vm")
+        .err()
         .unwrap();
-    let binary = Asset::Path(MULTI_TEST_PATH.into());
-    let opts = ProverOpts::default();
-    let receipt = TestClient::new().prove(&env, &opts, binary);
-    receipt.verify(MULTI_TEST_ID).unwrap();
+    assert!(matches!(err, ProverError::Connection(_)), "{err}");
 }
 
 #[test]
@@ -246,6 +1222,34 @@ fn prove_segment_elf() {
     }
 }
 
+#[test]
+fn prove_segments_from_path() {
+    let segment_limit_po2 = 14; // 16k cycles
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop {
+            cycles: 2 << segment_limit_po2,
+        })
+        .unwrap()
+        .segment_limit_po2(segment_limit_po2)
+        .build()
+        .unwrap();
+
+    let mut executor = TestClient::new();
+    let session = executor.execute(env, Asset::Path(MULTI_TEST_PATH.into()));
+    assert!(session.segments.len() > 1);
+
+    // A fresh client proves the persisted segments without re-running the executor.
+    let prover = TestClient::new();
+    let receipts = prover.prove_segments(&ProverOpts::fast(), executor.segments.clone());
+    assert_eq!(receipts.len(), session.segments.len());
+
+    let ctx = VerifierContext::default();
+    for (idx, receipt) in receipts.iter().enumerate() {
+        receipt.verify_integrity_with_context(&ctx).unwrap();
+        assert_eq!(receipt.index, idx as u32);
+    }
+}
+
 #[test]
 fn lift_join_identity() {
     let segment_limit_po2 = 16; // 64k cycles
@@ -362,9 +1366,21 @@ fn lift_resolve() {
     // Use resolve to create an unconditional succinct receipt
     let succinct_receipt = client.resolve(
         opts,
-        composition_succinct_receipt.try_into().unwrap(),
-        assumption_succinct_receipt.try_into().unwrap(),
+        composition_succinct_receipt.clone().try_into().unwrap(),
+        assumption_succinct_receipt.clone().try_into().unwrap(),
+    );
+
+    // resolve_all must produce the same claim, reporting progress for every assumption.
+    let assumption_claim = assumption_succinct_receipt.claim.digest();
+    let mut progress = vec![];
+    let resolved_all = client.resolve_all(
+        opts,
+        composition_succinct_receipt,
+        vec![assumption_succinct_receipt.into_unknown()],
+        |resolved, total, claim| progress.push((resolved, total, claim)),
     );
+    assert_eq!(progress, vec![(1, 1, assumption_claim)]);
+    assert_eq!(resolved_all.claim.digest(), succinct_receipt.claim.digest());
 
     // Wrap into a Receipt and verify
     let receipt = Receipt::new(
@@ -374,6 +1390,172 @@ fn lift_resolve() {
     receipt.verify(MULTI_TEST_ID).unwrap();
 }
 
+#[test]
+fn resolve_batch() {
+    let opts = &ProverOpts::default();
+
+    // Execute, prove and lift a single segment guest.
+    let prove_lifted = |env: ExecutorEnv<'_>, elf: &[u8]| {
+        let mut client = TestClient::new();
+        client.execute(env, Asset::Inline(Bytes::copy_from_slice(elf)));
+        assert_eq!(client.segments.len(), 1);
+        let segment_receipt = client.prove_segment(opts, client.segments[0].clone());
+        client.lift(opts, segment_receipt.try_into().unwrap())
+    };
+
+    let hello_commit = prove_lifted(ExecutorEnv::default(), HELLO_COMMIT_ELF);
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let do_nothing = prove_lifted(env, MULTI_TEST_ELF);
+
+    // A conditional receipt with both as assumptions, in the order they are verified.
+    let env = ExecutorEnv::builder()
+        .add_assumption(hello_commit.claim.clone())
+        .add_assumption(do_nothing.claim.clone())
+        .write(&MultiTestSpec::SysVerify(vec![
+            (HELLO_COMMIT_ID.into(), b"hello world".to_vec()),
+            (MULTI_TEST_ID.into(), vec![]),
+        ]))
+        .unwrap()
+        .build()
+        .unwrap();
+    let conditional = prove_lifted(env, MULTI_TEST_ELF);
+
+    let client = TestClient::new();
+    let mut sequential = conditional.clone();
+    for assumption in [&hello_commit, &do_nothing] {
+        sequential = client.resolve(
+            opts,
+            sequential.try_into().unwrap(),
+            assumption.clone().try_into().unwrap(),
+        );
+    }
+
+    let batch = client.resolve_batch(
+        opts,
+        conditional,
+        vec![hello_commit.into_unknown(), do_nothing.into_unknown()],
+    );
+    assert_eq!(batch.claim.digest(), sequential.claim.digest());
+    batch
+        .verify_integrity_with_context(&VerifierContext::default())
+        .unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn prove_zkr_timeout() {
+    use std::{
+        fs::Permissions,
+        os::unix::fs::PermissionsExt,
+        time::{Duration, Instant},
+    };
+
+    // A server that connects but never replies, and ignores SIGTERM so that it has to be killed.
+    let work_dir = tempdir().unwrap();
+    let pid_path = work_dir.path().join("server.pid");
+    let server_path = work_dir.path().join("r0vm");
+    let script = format!(
+        r#"#!/bin/bash
+if [ "$1" = "--version" ]; then
+  echo "r0vm {version}"
+  exit 0
+fi
+echo $$ > {pid_path}
+trap '' TERM
+exec 3<>/dev/tcp/127.0.0.1/$2
+exec sleep 600
+"#,
+        version = crate::get_version().unwrap(),
+        pid_path = pid_path.display(),
+    );
+    std::fs::write(&server_path, script).unwrap();
+    std::fs::set_permissions(&server_path, Permissions::from_mode(0o755)).unwrap();
+
+    let client = ApiClient::new_sub_process(&server_path).unwrap();
+    let request = ProveZkrRequest {
+        claim_digest: Digest::ZERO,
+        control_id: Digest::ZERO,
+        input: vec![],
+    };
+    let timeout = Duration::from_millis(500);
+    let start = Instant::now();
+    let err = client
+        .prove_zkr_with_timeout::<Unknown>(request, AssetRequest::Inline, timeout)
+        .unwrap_err();
+    assert!(start.elapsed() >= timeout);
+    let ProverError::Timeout(err) = err else {
+        panic!("unexpected error: {err:#}");
+    };
+    assert_eq!(err.timeout, timeout);
+
+    // The server must have been killed and reaped rather than left behind as a zombie.
+    let pid: libc::pid_t = std::fs::read_to_string(&pid_path)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert_eq!(unsafe { libc::kill(pid, 0) }, -1);
+}
+
+#[cfg(unix)]
+#[test]
+fn process_failed_stderr() {
+    use std::{fs::Permissions, io::Write, os::unix::fs::PermissionsExt};
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // A server that connects, reports a failure on stderr and exits without replying.
+    let work_dir = tempdir().unwrap();
+    let server_path = work_dir.path().join("r0vm");
+    let script = format!(
+        r#"#!/bin/bash
+if [ "$1" = "--version" ]; then
+  echo "r0vm {version}"
+  exit 0
+fi
+exec 3<>/dev/tcp/127.0.0.1/$2
+echo "out of GPU memory" >&2
+exit 3
+"#,
+        version = crate::get_version().unwrap(),
+    );
+    std::fs::write(&server_path, script).unwrap();
+    std::fs::set_permissions(&server_path, Permissions::from_mode(0o755)).unwrap();
+
+    let streamed = SharedBuf::default();
+    let client = ApiClient::builder()
+        .server_path(&server_path)
+        .stderr(streamed.clone())
+        .build()
+        .unwrap();
+    let err = client.server_info().unwrap_err();
+    let ProverError::ProcessFailed { code, stderr } = &err else {
+        panic!("unexpected error: {err:#}");
+    };
+    assert_eq!(*code, 3);
+    assert_eq!(stderr, "out of GPU memory\n");
+    assert!(err.to_string().ends_with("out of GPU memory"), "{err}");
+    assert_eq!(
+        streamed.0.lock().unwrap().as_slice(),
+        b"out of GPU memory\n"
+    );
+}
+
 #[test]
 #[should_panic(expected = "MultiTestSpec::Panic invoked")]
 fn guest_error_forwarding() {
@@ -535,6 +1717,589 @@ fn coprocessor_handler() {
     receipt.verify(MULTI_TEST_ID).unwrap();
 }
 
+#[test]
+fn prove_zkr_batch() {
+    let client = TestClient::new();
+
+    let suite = Poseidon2HashSuite::new_suite();
+    let (program, control_id) = test_recursion_circuit("poseidon2").unwrap();
+    register_zkr(&control_id, move || Ok(program.clone()));
+    let control_tree = MerkleGroup::new(vec![control_id]).unwrap();
+    let control_root = control_tree.calc_root(suite.hashfn.as_ref());
+
+    let inner_claim_digests = [
+        digest!("00000000000000de00000000000000ad00000000000000be00000000000000ef"),
+        digest!("00000000000000ca00000000000000fe00000000000000ba00000000000000be"),
+    ];
+    let requests = inner_claim_digests
+        .iter()
+        .map(|inner_claim_digest| {
+            let mut input: Vec<u32> = Vec::new();
+            input.extend(control_root.as_words());
+            input.extend(inner_claim_digest.as_words());
+            ProveZkrRequest {
+                claim_digest: Digest::ZERO,
+                control_id,
+                input: bytemuck::cast_slice(&input).to_vec(),
+            }
+        })
+        .collect();
+
+    let receipts = client.prove_zkr_batch(requests);
+    assert_eq!(receipts.len(), 2);
+
+    // The first request matches the claim used by the coprocessor_handler test.
+    let claim_digest = digest!("a558268a11892374b41d03857a40cdc5e87e351a3bfc17aa2054f47712a17bc3");
+    assert_eq!(receipts[0].claim.digest(), claim_digest);
+    assert_ne!(receipts[1].claim.digest(), claim_digest);
+
+    let ctx = VerifierContext::default().with_succinct_verifier_parameters(
+        SuccinctReceiptVerifierParameters {
+            control_root,
+            ..Default::default()
+        },
+    );
+    for receipt in receipts {
+        receipt.verify_integrity_with_context(&ctx).unwrap();
+    }
+}
+
+/// Counts the connections made by a client to in-process servers.
+struct CountingConnector {
+    inner: LocalConnector,
+    connects: Arc<AtomicUsize>,
+}
+
+impl Connector for CountingConnector {
+    fn connect(&self) -> Result<ConnectionWrapper> {
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        self.inner.connect()
+    }
+}
+
+#[test]
+fn connection_pool() {
+    const POOL_SIZE: usize = 2;
+    const NUM_CALLS: usize = 4;
+
+    let (program, control_id) = test_recursion_circuit("poseidon2").unwrap();
+    register_zkr(&control_id, move || Ok(program.clone()));
+    let suite = Poseidon2HashSuite::new_suite();
+    let control_root = MerkleGroup::new(vec![control_id])
+        .unwrap()
+        .calc_root(suite.hashfn.as_ref());
+
+    let connects = Arc::new(AtomicUsize::new(0));
+    let client = ApiClient::builder()
+        .connector(Box::new(CountingConnector {
+            inner: LocalConnector::new(),
+            connects: connects.clone(),
+        }))
+        .pool_size(POOL_SIZE)
+        .build()
+        .unwrap();
+
+    let work_dir = tempdir().unwrap();
+    for idx in 0..NUM_CALLS {
+        let mut input: Vec<u32> = Vec::new();
+        input.extend(control_root.as_words());
+        input.extend([idx as u32; 8]);
+        let request = ProveZkrRequest {
+            claim_digest: Digest::ZERO,
+            control_id,
+            input: bytemuck::cast_slice(&input).to_vec(),
+        };
+        let receipt_out = AssetRequest::Path(work_dir.path().to_path_buf());
+        let _: SuccinctReceipt<Unknown> = client.prove_zkr(request, receipt_out).unwrap();
+    }
+
+    // Sequential calls are all served by warm connections from the pool.
+    assert!(connects.load(Ordering::SeqCst) <= POOL_SIZE);
+
+    // Dropping the client hangs up on the pooled servers, which exit cleanly.
+    drop(client);
+}
+
+#[test]
+fn endpoint_failover() {
+    // Nothing listens on the port of a listener that has been dropped.
+    let dead_addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let dead = Endpoint::Connector(Arc::new(TcpConnector::new(&dead_addr)));
+    let live = Endpoint::Connector(Arc::new(LocalConnector::new()));
+    let endpoints = [dead.clone(), live];
+
+    // Round-robin selection starts alternate calls at the dead endpoint.
+    let client = ApiClient::from_endpoints(&endpoints).unwrap();
+    for _ in 0..2 {
+        client.server_info().unwrap();
+    }
+
+    let client = ApiClient::builder()
+        .endpoints(&endpoints)
+        .endpoint_selection(EndpointSelection::LeastLoaded)
+        .build()
+        .unwrap();
+    client.server_info().unwrap();
+
+    // An endpoint that can be reached but rejects the handshake is skipped too.
+    let rejecting = TestClientConnector::new().unwrap();
+    let addr = rejecting.listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut conn = ConnectionWrapper::new(Arc::new(Mutex::new(TcpConnection::new(stream))));
+        let _: pb::api::HelloRequest = conn.recv().unwrap();
+        conn.send(pb::api::HelloReply {
+            kind: Some(pb::api::hello_reply::Kind::Error(pb::api::GenericError {
+                reason: "server is shutting down".into(),
+            })),
+        })
+        .unwrap();
+    });
+    let client = ApiClient::from_endpoints(&[
+        Endpoint::Connector(Arc::new(rejecting)),
+        Endpoint::Connector(Arc::new(LocalConnector::new())),
+    ])
+    .unwrap();
+    client.server_info().unwrap();
+    server.join().unwrap();
+
+    let err = ApiClient::from_endpoints(&[dead]).unwrap().server_info();
+    assert!(matches!(err, Err(ProverError::Connection(_))));
+    assert!(ApiClient::from_endpoints(&[]).is_err());
+}
+
+#[test]
+fn server_info() {
+    let connector = TestClientConnector::new().unwrap();
+    let addr = connector.listener.local_addr().unwrap();
+    let client = ApiClient::with_connector(Box::new(connector));
+
+    // A mock server that completes the handshake and replies with a known ServerInfo.
+    let server = thread::spawn(move || {
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut conn = ConnectionWrapper::new(Arc::new(Mutex::new(TcpConnection::new(stream))));
+        let hello: pb::api::HelloRequest = conn.recv().unwrap();
+        let reply = pb::api::HelloReply {
+            kind: Some(pb::api::hello_reply::Kind::Ok(pb::api::HelloResult {
+                version: hello.version,
+                keep_alive: false,
+            })),
+        };
+        let request: pb::api::ServerRequest = conn.send_recv(reply).unwrap();
+        assert!(matches!(
+            request.kind,
+            Some(pb::api::server_request::Kind::ServerInfo(_))
+        ));
+        conn.send(pb::api::ServerInfoReply {
+            kind: Some(pb::api::server_info_reply::Kind::Ok(
+                pb::api::ServerInfoResult {
+                    version: Some(semver::Version::new(1, 2, 3).into()),
+                    hashfns: vec!["poseidon2".into(), "sha-256".into()],
+                    max_po2: 24,
+                    features: vec!["cuda".into()],
+                    control_root: Some(Digest::ZERO.into()),
+                },
+            )),
+        })
+        .unwrap();
+    });
+
+    let info = client.server_info().unwrap();
+    server.join().unwrap();
+    assert_eq!(info.version, semver::Version::new(1, 2, 3));
+    assert_eq!(info.supported_hashfns, ["poseidon2", "sha-256"]);
+    assert_eq!(info.max_po2, 24);
+    assert_eq!(info.features, ["cuda"]);
+    assert_eq!(info.control_root, Some(Digest::ZERO));
+}
+
+#[test]
+fn supported_zkrs() {
+    let connector = TestClientConnector::new().unwrap();
+    let addr = connector.listener.local_addr().unwrap();
+    let client = ApiClient::with_connector(Box::new(connector));
+
+    let named_id = digest!("00000000000000de00000000000000ad00000000000000be00000000000000ef");
+    let unnamed_id = Digest::ZERO;
+
+    // A mock server that completes the handshake and replies with a known ZKR list.
+    let server = thread::spawn(move || {
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut conn = ConnectionWrapper::new(Arc::new(Mutex::new(TcpConnection::new(stream))));
+        let hello: pb::api::HelloRequest = conn.recv().unwrap();
+        let reply = pb::api::HelloReply {
+            kind: Some(pb::api::hello_reply::Kind::Ok(pb::api::HelloResult {
+                version: hello.version,
+                keep_alive: false,
+            })),
+        };
+        let request: pb::api::ServerRequest = conn.send_recv(reply).unwrap();
+        assert!(matches!(
+            request.kind,
+            Some(pb::api::server_request::Kind::SupportedZkrs(_))
+        ));
+        conn.send(pb::api::SupportedZkrsReply {
+            kind: Some(pb::api::supported_zkrs_reply::Kind::Ok(
+                pb::api::SupportedZkrsResult {
+                    zkrs: vec![
+                        pb::api::ZkrInfo {
+                            control_id: Some(named_id.into()),
+                            name: Some("sha256".into()),
+                            input_words: Some(16),
+                        },
+                        pb::api::ZkrInfo {
+                            control_id: Some(unnamed_id.into()),
+                            name: None,
+                            input_words: None,
+                        },
+                    ],
+                },
+            )),
+        })
+        .unwrap();
+    });
+
+    let zkrs = client.supported_zkrs().unwrap();
+    server.join().unwrap();
+    assert_eq!(
+        zkrs,
+        [
+            ZkrInfo {
+                control_id: named_id,
+                name: Some("sha256".into()),
+                input_words: Some(16),
+            },
+            ZkrInfo {
+                control_id: unnamed_id,
+                name: None,
+                input_words: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn server_info_local() {
+    let client = TestClient::new();
+    let info = with_server(client.addr, || client.client.server_info());
+    assert_eq!(info.version, crate::get_version().unwrap());
+    assert!(info
+        .supported_hashfns
+        .iter()
+        .any(|name| name == "poseidon2"));
+    assert_eq!(info.max_po2, risc0_zkp::MAX_CYCLES_PO2 as u32);
+    assert_eq!(
+        info.control_root,
+        Some(crate::SuccinctReceiptVerifierParameters::default().control_root)
+    );
+}
+
+#[test]
+fn supported_zkrs_local() {
+    // Registered under its own control ID so that other tests registering ZKRs do not overwrite
+    // its info. Listing ZKRs never loads the program, so none is provided.
+    let control_id = digest!("5a4b3c2d00000000000000000000000000000000000000000000000000000001");
+    register_zkr_with_info(&control_id, "stub", 16, || {
+        Err(anyhow::anyhow!("program not loaded"))
+    });
+
+    let client = TestClient::new();
+    let zkrs = with_server(client.addr, || client.client.supported_zkrs());
+    assert!(zkrs.contains(&ZkrInfo {
+        control_id,
+        name: Some("stub".into()),
+        input_words: Some(16),
+    }));
+}
+
+#[test]
+fn warm_up() {
+    let connector = TestClientConnector::new().unwrap();
+    let addr = connector.listener.local_addr().unwrap();
+    let client = ApiClient::with_connector(Box::new(connector));
+
+    // A mock server that completes the handshake and returns the options it was asked to warm up.
+    let server = thread::spawn(move || {
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut conn = ConnectionWrapper::new(Arc::new(Mutex::new(TcpConnection::new(stream))));
+        let hello: pb::api::HelloRequest = conn.recv().unwrap();
+        let reply = pb::api::HelloReply {
+            kind: Some(pb::api::hello_reply::Kind::Ok(pb::api::HelloResult {
+                version: hello.version,
+                keep_alive: false,
+            })),
+        };
+        let request: pb::api::ServerRequest = conn.send_recv(reply).unwrap();
+        let Some(pb::api::server_request::Kind::WarmUp(request)) = request.kind else {
+            panic!("expected a warm up request");
+        };
+        conn.send(pb::api::GenericReply::from(Ok(()))).unwrap();
+        ProverOpts::try_from(request.opts.unwrap()).unwrap()
+    });
+
+    let opts = ProverOpts {
+        max_segment_po2: 16,
+        ..ProverOpts::default()
+    };
+    client.warm_up(&opts).unwrap();
+    let received = server.join().unwrap();
+    assert_eq!(received.hashfn, opts.hashfn);
+    assert_eq!(received.max_segment_po2, 16);
+}
+
+#[test]
+fn warm_up_local() {
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+    // Registered under its own control ID so that other tests do not replace it.
+    let control_id = digest!("5a4b3c2d00000000000000000000000000000000000000000000000000000002");
+    register_zkr(&control_id, || {
+        LOADS.fetch_add(1, Ordering::SeqCst);
+        Ok(Program::from_encoded(&[], RECURSION_PO2))
+    });
+
+    let client = ApiClient::with_connector(Box::new(LocalConnector::new()));
+    client.warm_up(&ProverOpts::default()).unwrap();
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+
+    // Later requests take the program from the cache instead of loading it again.
+    let hits = SETUP_CACHE_HITS.load(Ordering::SeqCst);
+    client.warm_up(&ProverOpts::default()).unwrap();
+    get_registered_zkr(&control_id).unwrap();
+    get_registered_zkr(&control_id).unwrap();
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+    assert!(SETUP_CACHE_HITS.load(Ordering::SeqCst) >= hits + 2);
+}
+
+#[test]
+fn local_connector() {
+    let client = ApiClient::with_connector(Box::new(super::LocalConnector::new()));
+
+    // Each request is served by a new in-process server.
+    for _ in 0..2 {
+        let info = client.server_info().unwrap();
+        assert_eq!(info.version, crate::get_version().unwrap());
+    }
+}
+
+#[test]
+fn prove_local() {
+    let env = || {
+        ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap()
+    };
+    let binary = Asset::Inline(MULTI_TEST_ELF.into());
+    let opts = ProverOpts::default();
+
+    let client = ApiClient::with_connector(Box::new(super::LocalConnector::new()));
+    let local = client.prove(&env(), &opts, binary.clone()).unwrap().receipt;
+    local.verify(MULTI_TEST_ID).unwrap();
+
+    // Proving in-process produces the same claim as proving with a server.
+    let remote = TestClient::new().prove(&env(), &opts, binary);
+    assert_eq!(
+        local.claim().unwrap().digest(),
+        remote.claim().unwrap().digest()
+    );
+}
+
+#[cfg(feature = "tracing")]
+mod tracing_spans {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::{bail, Result};
+    use risc0_zkp::{core::digest::Digest, digest};
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id},
+        Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+    use super::{Asset, ConnectionWrapper, Connector};
+    use crate::{
+        keccak_po2_range, ApiClient, AssetRequest, Bytes, ProveKeccakRequest, ProveZkrRequest,
+        ProverOpts, Unknown,
+    };
+
+    type Spans = Arc<Mutex<Vec<(String, Vec<(String, String)>)>>>;
+
+    /// Records the name and fields of every span that is created.
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Spans);
+
+    impl<S: Subscriber> Layer<S> for SpanRecorder {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            let mut fields = FieldRecorder(vec![]);
+            attrs.record(&mut fields);
+            self.0
+                .lock()
+                .unwrap()
+                .push((attrs.metadata().name().to_string(), fields.0));
+        }
+    }
+
+    struct FieldRecorder(Vec<(String, String)>);
+
+    impl Visit for FieldRecorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{value:?}")));
+        }
+    }
+
+    struct RefusingConnector;
+
+    impl Connector for RefusingConnector {
+        fn connect(&self) -> Result<ConnectionWrapper> {
+            bail!("connection refused")
+        }
+    }
+
+    fn field<'a>(fields: &'a [(String, String)], name: &str) -> &'a str {
+        &fields.iter().find(|(key, _)| key == name).unwrap().1
+    }
+
+    #[test]
+    fn client_spans() {
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        let client = ApiClient::with_connector(Box::new(RefusingConnector));
+        let claim_digest =
+            digest!("0102030405060708091011121314151617181920212223242526272829303132");
+        let po2 = *keccak_po2_range().start();
+
+        tracing::subscriber::with_default(subscriber, || {
+            // The spans are entered before connecting, so they are emitted even though every
+            // request fails.
+            let zkr = ProveZkrRequest {
+                claim_digest,
+                control_id: Digest::ZERO,
+                input: vec![],
+            };
+            client
+                .prove_zkr::<Unknown>(zkr, AssetRequest::Inline)
+                .unwrap_err();
+
+            let keccak = ProveKeccakRequest {
+                claim_digest,
+                po2: po2 as usize,
+                control_root: Digest::ZERO,
+                input: vec![],
+            };
+            client
+                .prove_keccak::<Unknown>(keccak, AssetRequest::Inline)
+                .unwrap_err();
+
+            let receipt = || Asset::Inline(Bytes::new());
+            client
+                .resolve(
+                    &ProverOpts::default(),
+                    receipt(),
+                    receipt(),
+                    AssetRequest::Inline,
+                )
+                .unwrap_err();
+        });
+
+        let spans = recorder.0.lock().unwrap();
+        let names: Vec<_> = spans.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["prove_zkr", "prove_keccak", "resolve"]);
+
+        let (_, zkr_fields) = &spans[0];
+        assert_eq!(field(zkr_fields, "claim_digest"), claim_digest.to_string());
+        assert_eq!(field(zkr_fields, "control_id"), Digest::ZERO.to_string());
+
+        let (_, keccak_fields) = &spans[1];
+        assert_eq!(
+            field(keccak_fields, "claim_digest"),
+            claim_digest.to_string()
+        );
+        assert_eq!(field(keccak_fields, "po2"), po2.to_string());
+    }
+}
+
+#[cfg(feature = "async-client")]
+fn async_test_client() -> crate::AsyncApiClient {
+    crate::AsyncApiClient::with_factory(|| {
+        let connector = TestClientConnector::new()?;
+        let addr = connector.listener.local_addr()?.to_string();
+        thread::spawn(move || ApiServer::new_tcp(addr).run().unwrap());
+        Ok(ApiClient::with_connector(Box::new(connector)))
+    })
+}
+
+#[test(tokio::test)]
+#[cfg(feature = "async-client")]
+async fn async_prove_segments() {
+    let (segments_tx, mut segments_rx) = tokio::sync::mpsc::unbounded_channel();
+    let executor = thread::spawn(move || {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap();
+        let binary = Asset::Inline(MULTI_TEST_ELF.into());
+        let client = TestClient::new();
+        with_server(client.addr, || {
+            client
+                .client
+                .execute_to_channel(&env, binary, AssetRequest::Inline, segments_tx)
+        })
+    });
+
+    let client = async_test_client();
+    let opts = ProverOpts::default();
+    let mut tasks = Vec::new();
+    while let Some((_info, segment)) = segments_rx.recv().await {
+        let client = client.clone();
+        let opts = opts.clone();
+        tasks.push(tokio::spawn(async move {
+            client
+                .prove_segment(&opts, segment, AssetRequest::Inline)
+                .await
+        }));
+    }
+    let session = executor.join().unwrap();
+    assert_eq!(session.segments.len(), tasks.len());
+
+    let ctx = VerifierContext::default();
+    for task in tasks {
+        let receipt = task.await.unwrap().unwrap();
+        receipt.verify_integrity_with_context(&ctx).unwrap();
+    }
+}
+
+#[test(tokio::test)]
+#[cfg(feature = "async-client")]
+async fn async_prover_error() {
+    let client = crate::AsyncApiClient::with_factory(|| {
+        Ok(ApiClient::with_connector(Box::new(UnreachableConnector)))
+    });
+    let request = ProveKeccakRequest {
+        claim_digest: Digest::ZERO,
+        po2: *keccak_po2_range().end() as usize + 1,
+        control_root: Digest::ZERO,
+        input: vec![],
+    };
+    let err = client
+        .prove_keccak::<Unknown>(request, AssetRequest::Inline)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ProverError::UnsupportedPo2(_)), "{err}");
+
+    // A request which panics is reported as any other error.
+    let err = client.server_info().await.unwrap_err();
+    assert!(matches!(err, ProverError::Other(_)), "{err}");
+}
+
 #[test(tokio::test)]
 #[cfg(feature = "redis")]
 async fn redis_asset() {
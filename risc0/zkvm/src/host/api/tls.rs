@@ -0,0 +1,345 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Connections to a remote server over TLS, for use with
+//! [ApiClient::connect](crate::ApiClient::connect).
+
+use std::{
+    fmt,
+    io::Write,
+    net::{Shutdown, TcpStream},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use rustls::{
+    crypto::ring::default_provider,
+    pki_types::{CertificateDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, StreamOwned,
+};
+
+use super::{Channel, Connection, ConnectionWrapper, Connector};
+
+/// The environment variable [AuthToken::from_env] reads the token from.
+const TOKEN_ENV: &str = "RISC0_SERVER_TOKEN";
+
+/// The certificates a [TlsConnector] trusts to identify the server.
+#[derive(Clone)]
+pub struct TlsConfig {
+    roots: RootCertStore,
+}
+
+impl Default for TlsConfig {
+    /// Trust the Mozilla root certificates, as bundled by the `webpki-roots` crate.
+    fn default() -> Self {
+        Self {
+            roots: RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            },
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Construct a [TlsConfig] which trusts the Mozilla root certificates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Additionally trust each certificate in `pem`, e.g. the CA of a private prover pool.
+    pub fn add_root_certificates_pem(mut self, pem: &[u8]) -> Result<Self> {
+        let certs = rustls_pemfile::certs(&mut &pem[..])
+            .collect::<Result<Vec<CertificateDer>, _>>()
+            .context("failed to parse PEM certificates")?;
+        ensure!(!certs.is_empty(), "no certificates found in PEM");
+        for cert in certs {
+            self.roots.add(cert)?;
+        }
+        Ok(self)
+    }
+}
+
+/// A bearer token sent to the server when each connection is opened.
+///
+/// The token is meant for a proxy in front of the server that authenticates clients; the server
+/// itself does not check it.
+#[derive(Clone)]
+pub struct AuthToken(String);
+
+impl AuthToken {
+    /// Construct an [AuthToken] from the token issued by the server operator.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    /// Read the token from the `RISC0_SERVER_TOKEN` environment variable, if it is set and
+    /// non-empty.
+    pub fn from_env() -> Option<Self> {
+        std::env::var(TOKEN_ENV)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .map(Self)
+    }
+
+    /// The value of the authorization sent in the handshake.
+    pub(crate) fn authorization(&self) -> String {
+        format!("Bearer {}", self.0)
+    }
+}
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuthToken(..)")
+    }
+}
+
+/// Connects to a server at an `https://` URL.
+pub(crate) struct TlsConnector {
+    url: String,
+    addr: (String, u16),
+    server_name: ServerName<'static>,
+    config: Arc<ClientConfig>,
+}
+
+impl TlsConnector {
+    pub(crate) fn new(url: &str, tls: TlsConfig) -> Result<Self> {
+        let (host, port) = parse_url(url).with_context(|| format!("invalid server URL {url}"))?;
+        let server_name = ServerName::try_from(host.clone())
+            .with_context(|| format!("invalid server name in {url}"))?;
+        let config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(tls.roots)
+            .with_no_client_auth();
+        Ok(Self {
+            url: url.to_string(),
+            addr: (host, port),
+            server_name,
+            config: Arc::new(config),
+        })
+    }
+
+    fn try_connect(&self) -> Result<TlsConnection> {
+        let sock = TcpStream::connect((self.addr.0.as_str(), self.addr.1))?;
+        let conn = ClientConnection::new(self.config.clone(), self.server_name.clone())?;
+        let mut stream = StreamOwned::new(conn, sock);
+        // Complete the handshake now, so that certificate errors are reported as connection
+        // errors rather than on the first message.
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock)?;
+        }
+        Ok(TlsConnection { stream })
+    }
+}
+
+impl Connector for TlsConnector {
+    fn connect(&self) -> Result<ConnectionWrapper> {
+        tracing::debug!("connect: {}", self.url);
+        let conn = self
+            .try_connect()
+            .with_context(|| format!("failed to connect to {}", self.url))?;
+        Ok(ConnectionWrapper::new(Arc::new(Mutex::new(conn))))
+    }
+}
+
+struct TlsConnection {
+    stream: StreamOwned<ClientConnection, TcpStream>,
+}
+
+impl Connection for TlsConnection {
+    fn stream(&mut self) -> &mut TcpStream {
+        &mut self.stream.sock
+    }
+
+    fn channel(&mut self) -> &mut dyn Channel {
+        &mut self.stream
+    }
+
+    fn close(&mut self) -> Result<i32> {
+        self.stream.conn.send_close_notify();
+        // The server may already have closed its end.
+        let _ = self.stream.flush();
+        Ok(0)
+    }
+
+    fn terminate(&mut self) -> Result<()> {
+        Ok(self.stream.sock.shutdown(Shutdown::Both)?)
+    }
+}
+
+/// Split an `https://host[:port][/path]` URL into its host and port.
+fn parse_url(url: &str) -> Result<(String, u16)> {
+    let rest = url
+        .strip_prefix("https://")
+        .ok_or_else(|| anyhow!("expected an https:// URL"))?;
+    let authority = rest.split('/').next().unwrap_or_default();
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| anyhow!("unterminated IPv6 address"))?;
+            (host, rest.strip_prefix(':'))
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().context("invalid port")?,
+        None => 443,
+    };
+    ensure!(!host.is_empty(), "missing host");
+    Ok((host.to_string(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::Arc,
+        thread,
+    };
+
+    use prost::Message;
+    use rustls::{crypto::ring::default_provider, ServerConfig, ServerConnection, StreamOwned};
+
+    use super::{parse_url, AuthToken, TlsConfig};
+    use crate::{host::api::pb, ApiClient, ProverError};
+
+    const CA_CERT: &[u8] = include_bytes!("testdata/ca.pem");
+    const SERVER_CERT: &[u8] = include_bytes!("testdata/server.pem");
+    const SERVER_KEY: &[u8] = include_bytes!("testdata/server.key");
+
+    fn send(stream: &mut impl Write, msg: impl Message) {
+        stream
+            .write_all(&(msg.encoded_len() as u32).to_le_bytes())
+            .unwrap();
+        stream.write_all(&msg.encode_to_vec()).unwrap();
+        stream.flush().unwrap();
+    }
+
+    fn recv<T: Message + Default>(stream: &mut impl Read) -> T {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).unwrap();
+        let mut buf = vec![0u8; u32::from_le_bytes(len) as usize];
+        stream.read_exact(&mut buf).unwrap();
+        T::decode(buf.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn parse_urls() {
+        let parse = |url| parse_url(url).unwrap();
+        assert_eq!(
+            parse("https://prover.example"),
+            ("prover.example".into(), 443)
+        );
+        assert_eq!(
+            parse("https://prover.example:8443/"),
+            ("prover.example".into(), 8443)
+        );
+        assert_eq!(parse("https://[::1]:8443"), ("::1".into(), 8443));
+        assert_eq!(parse("https://[::1]"), ("::1".into(), 443));
+        assert!(parse_url("http://prover.example").is_err());
+        assert!(parse_url("https://:443").is_err());
+        assert!(parse_url("https://prover.example:port").is_err());
+    }
+
+    #[test]
+    fn auth_token_sent_over_tls() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // A mock TLS endpoint that completes the handshake, replies to a server info request, and
+        // returns the authorization it was sent.
+        let server = thread::spawn(move || {
+            let certs = rustls_pemfile::certs(&mut &SERVER_CERT[..])
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let key = rustls_pemfile::private_key(&mut &SERVER_KEY[..])
+                .unwrap()
+                .unwrap();
+            let config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(certs, key)
+                .unwrap();
+            let (sock, _) = listener.accept().unwrap();
+            let conn = ServerConnection::new(Arc::new(config)).unwrap();
+            let mut stream = StreamOwned::new(conn, sock);
+
+            let hello: pb::api::HelloRequest = recv(&mut stream);
+            send(
+                &mut stream,
+                pb::api::HelloReply {
+                    kind: Some(pb::api::hello_reply::Kind::Ok(pb::api::HelloResult {
+                        version: hello.version,
+                        keep_alive: false,
+                    })),
+                },
+            );
+            let request: pb::api::ServerRequest = recv(&mut stream);
+            assert!(matches!(
+                request.kind,
+                Some(pb::api::server_request::Kind::ServerInfo(_))
+            ));
+            send(
+                &mut stream,
+                pb::api::ServerInfoReply {
+                    kind: Some(pb::api::server_info_reply::Kind::Ok(
+                        pb::api::ServerInfoResult {
+                            version: Some(semver::Version::new(1, 2, 3).into()),
+                            hashfns: vec!["poseidon2".into()],
+                            max_po2: 24,
+                            features: vec![],
+                            control_root: None,
+                        },
+                    )),
+                },
+            );
+            hello.authorization
+        });
+
+        let tls = TlsConfig::new().add_root_certificates_pem(CA_CERT).unwrap();
+        let url = format!("https://localhost:{port}");
+        let client = ApiClient::connect(&url, tls, Some(AuthToken::new("secret"))).unwrap();
+        let info = client.server_info().unwrap();
+        assert_eq!(info.version, semver::Version::new(1, 2, 3));
+        assert_eq!(server.join().unwrap(), "Bearer secret");
+    }
+
+    #[test]
+    fn connection_error_names_url() {
+        // Nothing is listening on the port once the listener is dropped.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = format!("https://localhost:{port}");
+        let client = ApiClient::connect(&url, TlsConfig::new(), None).unwrap();
+        let Err(ProverError::Connection(err)) = client.server_info() else {
+            panic!("expected a connection error");
+        };
+        assert!(err.to_string().contains(&url));
+
+        let Err(ProverError::Connection(err)) =
+            ApiClient::connect("http://localhost", TlsConfig::new(), None)
+        else {
+            panic!("expected a connection error");
+        };
+        assert!(err.to_string().contains("http://localhost"));
+    }
+}
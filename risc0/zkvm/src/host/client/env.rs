@@ -17,6 +17,7 @@
 use std::{
     cell::RefCell,
     collections::HashMap,
+    fs::File,
     io::{BufRead, BufReader, Cursor, Read, Write},
     mem,
     path::{Path, PathBuf},
@@ -24,22 +25,27 @@ use std::{
     sync::Arc,
 };
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use bytemuck::Pod;
 use bytes::Bytes;
-use risc0_circuit_keccak::KECCAK_PO2_RANGE;
-use risc0_zkp::core::digest::Digest;
+use risc0_circuit_keccak::{KeccakState, KECCAK_CONTROL_ROOT, KECCAK_PO2_RANGE};
+use risc0_zkp::core::{
+    digest::{Digest, DIGEST_WORDS},
+    hash::sha::SHA256_INIT,
+};
 use risc0_zkvm_platform::{self, fileno};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tempfile::TempDir;
 
 use crate::{
+    guest::env::CHUNK_FINAL_FLAG,
     host::client::{
         posix_io::PosixIo,
         slice_io::{slice_io_from_fn, SliceIo, SliceIoTable},
     },
-    serde::to_vec,
-    AssumptionReceipt, TraceCallback,
+    serde::{to_vec, Codec},
+    sha::{self, Sha256},
+    AssumptionReceipt, TraceCallback, TraceEvent, ZkrInfo,
 };
 
 /// A builder pattern used to construct an [ExecutorEnv].
@@ -66,6 +72,7 @@ impl SegmentPath {
 
 /// A ZKR proof request.
 #[stability::unstable]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ProveZkrRequest {
     /// The digest of the claim that this ZKR program is expected to produce.
     pub claim_digest: Digest,
@@ -77,8 +84,133 @@ pub struct ProveZkrRequest {
     pub input: Vec<u8>,
 }
 
+impl ProveZkrRequest {
+    /// Returns a [ProveZkrRequestBuilder] for constructing a request for a custom ZKR program.
+    #[stability::unstable]
+    pub fn builder() -> ProveZkrRequestBuilder {
+        Default::default()
+    }
+}
+
+/// A builder for [ProveZkrRequest], validating the input against the ZKR program.
+///
+/// The claim digest of a ZKR program is derived from its input by the program itself, so it is
+/// either given directly with [claim_digest](Self::claim_digest) or computed with
+/// [claim](Self::claim).
+///
+/// The expected input length is that of the program registered under the control ID, as reported
+/// by [ApiClient::supported_zkrs](crate::ApiClient::supported_zkrs) and given with
+/// [zkrs](Self::zkrs). With the `prove` feature, the programs registered in this process with
+/// [register_zkr_with_info](crate::register_zkr_with_info) are used if none are given.
+#[stability::unstable]
+#[derive(Default)]
+pub struct ProveZkrRequestBuilder {
+    control_id: Option<Digest>,
+    input: Vec<u32>,
+    zkrs: Vec<ZkrInfo>,
+    claim_digest: Option<Digest>,
+    claim_fn: Option<ZkrClaimFn>,
+}
+
+type ZkrClaimFn = Box<dyn FnOnce(&[u32]) -> Digest>;
+
+impl ProveZkrRequestBuilder {
+    /// Set the control ID of the ZKR program to be proven.
+    #[stability::unstable]
+    pub fn control_id(mut self, control_id: Digest) -> Self {
+        self.control_id = Some(control_id);
+        self
+    }
+
+    /// Append words to the input of the ZKR program.
+    #[stability::unstable]
+    pub fn input(mut self, words: &[u32]) -> Self {
+        self.input.extend_from_slice(words);
+        self
+    }
+
+    /// Set the ZKR programs registered with the prover, as returned by
+    /// [ApiClient::supported_zkrs](crate::ApiClient::supported_zkrs).
+    ///
+    /// [build](Self::build) fails unless the program with the control ID is among them and
+    /// reports the number of input words it takes.
+    #[stability::unstable]
+    pub fn zkrs(mut self, zkrs: impl IntoIterator<Item = ZkrInfo>) -> Self {
+        self.zkrs.extend(zkrs);
+        self
+    }
+
+    /// Set the digest of the claim that the ZKR program is expected to produce.
+    ///
+    /// If [claim](Self::claim) is also used, the two digests must match.
+    #[stability::unstable]
+    pub fn claim_digest(mut self, claim_digest: Digest) -> Self {
+        self.claim_digest = Some(claim_digest);
+        self
+    }
+
+    /// Compute the claim digest from the input words when the request is built.
+    ///
+    /// `claim_fn` should mirror how the ZKR program derives its claim from its input.
+    #[stability::unstable]
+    pub fn claim(mut self, claim_fn: impl FnOnce(&[u32]) -> Digest + 'static) -> Self {
+        self.claim_fn = Some(Box::new(claim_fn));
+        self
+    }
+
+    /// Build the [ProveZkrRequest].
+    ///
+    /// Returns an error if the control ID or claim is missing, if the program is not registered
+    /// or does not report its input length, if the input length does not match the program's
+    /// expectation, or if the computed claim digest differs from the one given.
+    #[stability::unstable]
+    pub fn build(self) -> Result<ProveZkrRequest> {
+        let Some(control_id) = self.control_id else {
+            bail!("ZKR request is missing a control ID");
+        };
+        #[cfg(feature = "prove")]
+        let zkrs = if self.zkrs.is_empty() {
+            crate::host::recursion::prove::registered_zkrs()
+        } else {
+            self.zkrs
+        };
+        #[cfg(not(feature = "prove"))]
+        let zkrs = self.zkrs;
+        let Some(zkr) = zkrs.iter().find(|zkr| zkr.control_id == control_id) else {
+            bail!("ZKR program with control ID {control_id} is not registered");
+        };
+        let Some(input_words) = zkr.input_words else {
+            bail!("ZKR program with control ID {control_id} does not report its input length");
+        };
+        if self.input.len() != input_words as usize {
+            bail!(
+                "ZKR input has {} words, but the program with control ID {control_id} expects {input_words}",
+                self.input.len()
+            );
+        }
+        let claim_digest = match (self.claim_fn, self.claim_digest) {
+            (Some(claim_fn), expected) => {
+                let claim_digest = claim_fn(&self.input);
+                if let Some(expected) = expected.filter(|expected| *expected != claim_digest) {
+                    bail!("ZKR claim digest {claim_digest} does not match expected {expected}");
+                }
+                claim_digest
+            }
+            (None, Some(claim_digest)) => claim_digest,
+            (None, None) => bail!("ZKR request is missing a claim digest"),
+        };
+
+        Ok(ProveZkrRequest {
+            claim_digest,
+            control_id,
+            input: bytemuck::cast_slice(&self.input).to_vec(),
+        })
+    }
+}
+
 /// A Keccak proof request.
 #[stability::unstable]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ProveKeccakRequest {
     /// The digest of the claim that this keccak input is expected to produce.
     pub claim_digest: Digest,
@@ -93,6 +225,135 @@ pub struct ProveKeccakRequest {
     pub input: Vec<u8>,
 }
 
+impl ProveKeccakRequest {
+    /// Construct a request to prove the keccak permutation of each state in `input`, using a
+    /// keccak circuit of size `2^po2`.
+    ///
+    /// `input` is the transcript of keccak states, each given as 25 little-endian `u64`s. The
+    /// claim digest is computed from the transcript in the same way as it is by a guest using the
+    /// keccak accelerator, so the resulting request can be proven with
+    /// [ApiClient::prove_keccak](crate::ApiClient::prove_keccak) without executing a guest.
+    ///
+    /// Returns an error if `po2` is outside of [keccak_po2_range](crate::keccak_po2_range), or if
+    /// `input` is not a whole number of states or holds more states than fit in the circuit.
+    #[stability::unstable]
+    pub fn new(input: &[u8], po2: u32) -> Result<Self> {
+        Self::from_reader(input, po2)
+    }
+
+    /// Construct a request to prove the keccak transcript read from `reader`, using a keccak
+    /// circuit of size `2^po2`.
+    ///
+    /// The transcript is read one state at a time, and reading stops with an error as soon as it
+    /// holds more states than fit in the circuit, so an oversized input is never buffered in
+    /// full. The resulting request is the same as one constructed by [ProveKeccakRequest::new]
+    /// from the same bytes.
+    #[stability::unstable]
+    pub fn from_reader(mut reader: impl Read, po2: u32) -> Result<Self> {
+        let po2_range = crate::keccak_po2_range();
+        if !po2_range.contains(&po2) {
+            return Err(crate::UnsupportedPo2 {
+                requested: po2 as usize,
+                min: *po2_range.start(),
+                max: *po2_range.end(),
+            }
+            .into());
+        }
+        let max_states = (1 << po2) / risc0_circuit_keccak::KECCAK_PERMUTE_CYCLES;
+
+        let mut input = Vec::new();
+        let mut buf = [0u8; STATE_BYTES];
+        loop {
+            let len = read_full(&mut reader, &mut buf)?;
+            if len == 0 {
+                break;
+            }
+            if len < STATE_BYTES {
+                bail!("keccak input must be a multiple of {STATE_BYTES} bytes");
+            }
+            if input.len() / STATE_BYTES == max_states {
+                bail!("keccak input has more than the {max_states} states that fit in po2 {po2}");
+            }
+            input.extend_from_slice(&buf);
+        }
+
+        Ok(Self {
+            claim_digest: keccak_claim_digest(&input),
+            po2: po2 as usize,
+            control_root: KECCAK_CONTROL_ROOT,
+            input,
+        })
+    }
+
+    /// Construct a request to prove the keccak transcript in the file at `path`, using a keccak
+    /// circuit of size `2^po2`.
+    ///
+    /// See [ProveKeccakRequest::from_reader].
+    #[stability::unstable]
+    pub fn from_path(path: impl AsRef<Path>, po2: u32) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("failed to open keccak input {}", path.display()))?;
+        Self::from_reader(BufReader::new(file), po2)
+    }
+
+    /// Compute the digest of the claim that proving this request produces, from its input alone.
+    ///
+    /// This matches the claim of the receipt returned by
+    /// [ApiClient::prove_keccak](crate::ApiClient::prove_keccak), so requests can be deduplicated,
+    /// cached, or checked against [ProveKeccakRequest::claim_digest] before they are proven.
+    #[stability::unstable]
+    pub fn compute_claim_digest(&self) -> Digest {
+        keccak_claim_digest(&self.input)
+    }
+}
+
+const STATE_BYTES: usize = mem::size_of::<KeccakState>();
+
+/// The claim of a keccak proof, which is the SHA-256 state after absorbing each input state
+/// followed by its permutation, with each state zero-padded to a whole number of blocks.
+fn keccak_claim_digest(input: &[u8]) -> Digest {
+    let mut claim_digest = SHA256_INIT;
+    let mut absorb = |state: &KeccakState| {
+        let words: &[u32] = bytemuck::cast_slice(state);
+        for block in words.chunks(2 * DIGEST_WORDS) {
+            let mut padded = [0u32; 2 * DIGEST_WORDS];
+            padded[..block.len()].copy_from_slice(block);
+            let (half1, half2) = padded.split_at(DIGEST_WORDS);
+            claim_digest = *sha::Impl::compress(
+                &claim_digest,
+                &Digest::try_from(half1).unwrap(),
+                &Digest::try_from(half2).unwrap(),
+            );
+        }
+    };
+
+    for chunk in input.chunks_exact(STATE_BYTES) {
+        let mut state: KeccakState = bytemuck::pod_read_unaligned(chunk);
+        absorb(&state);
+        keccak::f1600(&mut state);
+        absorb(&state);
+    }
+    for word in claim_digest.as_mut_words() {
+        *word = word.to_be();
+    }
+    claim_digest
+}
+
+/// Fill `buf` from `reader`, returning fewer bytes than requested only at the end of the input.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(len)
+}
+
 /// A trait that supports the ability to be notified of proof requests
 /// on-demand.
 #[stability::unstable]
@@ -106,6 +367,48 @@ pub trait CoprocessorCallback {
 
 pub type CoprocessorCallbackRef<'a> = Rc<RefCell<dyn CoprocessorCallback + 'a>>;
 
+/// A trait for being notified of data committed to the journal while the guest is running.
+pub trait JournalCallback {
+    /// Called with the bytes of each commit, in the order the guest made them.
+    fn on_journal_append(&mut self, bytes: &[u8]) -> Result<()>;
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> JournalCallback for F {
+    fn on_journal_append(&mut self, bytes: &[u8]) -> Result<()> {
+        self(bytes)
+    }
+}
+
+/// A segment that is being built by the executor, passed to a policy set with
+/// [ExecutorEnvBuilder::segment_policy].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct PartialSegment {
+    /// The index of the segment within the session.
+    pub index: u32,
+
+    /// The po2 the segment would have if it were split now.
+    pub po2: u32,
+
+    /// The instruction and paging cycles used by the segment so far.
+    pub cycles: u64,
+
+    /// The user cycles executed by the session so far.
+    pub session_user_cycles: u64,
+}
+
+/// Whether a policy set with [ExecutorEnvBuilder::segment_policy] splits the current segment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentDecision {
+    /// Keep adding cycles to the current segment.
+    Continue,
+
+    /// End the current segment at the offered po2.
+    Split,
+}
+
+pub(crate) type SegmentPolicyRef<'a> = Rc<dyn Fn(&PartialSegment) -> SegmentDecision + 'a>;
+
 /// Container for assumptions in the executor environment.
 #[derive(Default)]
 pub(crate) struct AssumptionReceipts(pub(crate) Vec<AssumptionReceipt>);
@@ -119,10 +422,16 @@ pub struct ExecutorEnv<'a> {
     pub(crate) env_vars: HashMap<String, String>,
     pub(crate) args: Vec<String>,
     pub(crate) segment_limit_po2: Option<u32>,
+    pub(crate) segment_po2_hint: Option<u32>,
+    pub(crate) coalesce_po2: Option<u32>,
+    pub(crate) segment_policy: Option<SegmentPolicyRef<'a>>,
     pub(crate) session_limit: Option<u64>,
+    pub(crate) checkpoint_interval: Option<u64>,
     pub(crate) posix_io: Rc<RefCell<PosixIo<'a>>>,
     pub(crate) slice_io: Rc<RefCell<SliceIoTable<'a>>>,
     pub(crate) input: Vec<u8>,
+    // Streamed input, delivered on stdin ahead of `input`.
+    pub(crate) input_readers: Vec<Box<dyn Read + 'a>>,
     pub(crate) trace: Vec<Rc<RefCell<dyn TraceCallback + 'a>>>,
     pub(crate) assumptions: Rc<RefCell<AssumptionReceipts>>,
     pub(crate) segment_path: Option<SegmentPath>,
@@ -162,7 +471,18 @@ impl<'a> ExecutorEnvBuilder<'a> {
     pub fn build(&mut self) -> Result<ExecutorEnv<'a>> {
         let mut inner = mem::take(&mut self.inner);
 
-        if !inner.input.is_empty() {
+        if !inner.input_readers.is_empty() {
+            let mut readers = mem::take(&mut inner.input_readers);
+            readers.push(Box::new(Cursor::new(mem::take(&mut inner.input))));
+            let reader = readers
+                .into_iter()
+                .reduce(|head, tail| Box::new(head.chain(tail)))
+                .unwrap();
+            inner
+                .posix_io
+                .borrow_mut()
+                .with_read_fd(fileno::STDIN, reader);
+        } else if !inner.input.is_empty() {
             let reader = Cursor::new(inner.input.clone());
             inner
                 .posix_io
@@ -206,8 +526,82 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Make every segment, including the last one, use `po2`.
+    ///
+    /// Segments are split at `po2` cycles in place of the
+    /// [segment limit](ExecutorEnvBuilder::segment_limit_po2), and the final segment is padded up
+    /// to `po2`. This is useful for backends that prove one segment size faster than others.
+    ///
+    /// When a lower segment limit is also set, segments use the limit instead.
+    ///
+    /// Given value must be between [risc0_zkp::MIN_CYCLES_PO2] and
+    /// [risc0_zkp::MAX_CYCLES_PO2] (inclusive).
+    pub fn segment_po2_hint(&mut self, po2: u32) -> &mut Self {
+        self.inner.segment_po2_hint = Some(po2);
+        self
+    }
+
+    /// Merge a final segment smaller than `min_po2` into the segment before it.
+    ///
+    /// A guest that finishes just past a segment boundary would otherwise end with a tiny segment
+    /// whose proving cost is mostly fixed overhead. Only a segment that the
+    /// [segment policy](ExecutorEnvBuilder::segment_policy) ended below the
+    /// [segment limit](ExecutorEnvBuilder::segment_limit_po2) is merged into, and only when the
+    /// merged segment fits within the segment limit; otherwise the segments are left as they are.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .coalesce_small_segments(16)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn coalesce_small_segments(&mut self, min_po2: u32) -> &mut Self {
+        self.inner.coalesce_po2 = Some(min_po2);
+        self
+    }
+
+    /// Decide where segments are split with a callback.
+    ///
+    /// Each time the current segment outgrows a po2 that is smaller than the segment limit, the
+    /// executor passes it to `policy` as a [PartialSegment]. Returning [SegmentDecision::Split]
+    /// ends the segment at that po2, and segments always end at the segment limit. The policy is
+    /// only consulted when executing in this process; it is not sent to an `r0vm` server.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, SegmentDecision};
+    ///
+    /// // Keep segments at po2 18 after the first million user cycles.
+    /// let env = ExecutorEnv::builder()
+    ///     .segment_policy(|segment| {
+    ///         if segment.session_user_cycles >= 1 << 20 && segment.po2 == 18 {
+    ///             SegmentDecision::Split
+    ///         } else {
+    ///             SegmentDecision::Continue
+    ///         }
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn segment_policy(
+        &mut self,
+        policy: impl Fn(&PartialSegment) -> SegmentDecision + 'a,
+    ) -> &mut Self {
+        self.inner.segment_policy = Some(Rc::new(policy));
+        self
+    }
+
     /// Set a session limit, specified in number of cycles.
     ///
+    /// Execution of a guest that runs for more user cycles than the limit is stopped with a
+    /// [CycleLimitExceeded](crate::CycleLimitExceeded) error, e.g. to protect a service from a
+    /// guest that never halts.
+    ///
     /// # Example
     ///
     /// ```
@@ -223,6 +617,31 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Take a [SessionSnapshot](crate::SessionSnapshot) at the first segment
+    /// boundary after every `interval_cycles` user cycles.
+    ///
+    /// The snapshot is attached to the [Segment](crate::Segment) that ends at the
+    /// boundary, where the segment callback can persist it; execution can later
+    /// be resumed from it with [ExecutorImpl::from_snapshot](crate::ExecutorImpl::from_snapshot).
+    /// When executing with an [ApiClient](crate::ApiClient), the encoded snapshot is
+    /// passed to the segment callback as [SegmentInfo::snapshot](crate::SegmentInfo::snapshot)
+    /// instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .enable_checkpoints(1 << 24)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn enable_checkpoints(&mut self, interval_cycles: u64) -> &mut Self {
+        self.inner.checkpoint_interval = Some(interval_cycles);
+        self
+    }
+
     /// Add environment variables to the guest environment.
     ///
     /// # Example
@@ -280,12 +699,41 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Add all environment variables of the host process to the guest environment.
+    ///
+    /// Variables already set on this builder keep their values, and variables set afterwards
+    /// override inherited ones. Variables that are not valid unicode are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .inherit_env()
+    ///     .env_var("VAR1", "SOME_VALUE")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn inherit_env(&mut self) -> &mut Self {
+        for (name, val) in std::env::vars_os() {
+            if let (Ok(name), Ok(val)) = (name.into_string(), val.into_string()) {
+                self.inner.env_vars.entry(name).or_insert(val);
+            }
+        }
+        self
+    }
+
     /// Write input data to the zkVM guest stdin.
     ///
     /// This function will serialize `data` using a zkVM-optimized codec that
     /// can be deserialized in the guest with a corresponding `env::read` with
     /// the same data type.
     ///
+    /// Calls to this function and [ExecutorEnvBuilder::write_slice] append to
+    /// the same stdin buffer, so the guest reads values back in the order they
+    /// were written.
+    ///
     /// # Example
     ///
     /// ```
@@ -310,11 +758,39 @@ impl<'a> ExecutorEnvBuilder<'a> {
         Ok(self.write_slice(&to_vec(data)?))
     }
 
+    /// Write input data to the zkVM guest stdin, encoded with the [Codec] `C`.
+    ///
+    /// The encoded value is written as a frame, i.e. preceded by its length in bytes, and can be
+    /// read within the guest with a corresponding `env::read_with::<C, _>`. This allows the guest
+    /// to read data in a format shared with other systems, such as
+    /// [Postcard](crate::serde::Postcard), rather than the format used by
+    /// [ExecutorEnvBuilder::write].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{serde::DefaultCodec, ExecutorEnv};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .write_with::<DefaultCodec, _>(&(1u32, 2u64))
+    ///     .unwrap()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn write_with<C: Codec, T: Serialize + ?Sized>(&mut self, data: &T) -> Result<&mut Self> {
+        let payload = C::encode(data)?;
+        let len = payload.len() as u32;
+        self.inner.input.extend_from_slice(&len.to_le_bytes());
+        self.inner.input.extend_from_slice(&payload);
+        Ok(self)
+    }
+
     /// Write input data to the zkVM guest stdin.
     ///
-    /// This function writes a slice directly to the underlying buffer. A
-    /// corresponding `env::read_slice` can be used within the guest to read the
-    /// data.
+    /// This function writes a slice directly to the underlying buffer without
+    /// any serialization or length header, so the guest sees the exact word
+    /// layout of `slice`. A corresponding `env::read_slice` can be used within
+    /// the guest to read the data.
     ///
     /// # Example
     ///
@@ -350,12 +826,89 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Write a slice to the zkVM guest via stdin, preceded by its length in elements.
+    ///
+    /// The guest can read the length with `env::read_len` to allocate a buffer of exactly the
+    /// right size, then fill it with `env::read_slice`.
+    #[stability::unstable]
+    pub fn write_sized<T: Pod>(&mut self, slice: &[T]) -> &mut Self {
+        let len = slice.len() as u32;
+        self.inner.input.extend_from_slice(&len.to_le_bytes());
+        self.write_slice(slice)
+    }
+
+    /// Write `data` to the zkVM guest via stdin as a sequence of frames of at most `chunk_len`
+    /// bytes each.
+    ///
+    /// The last frame is flagged so that the guest can reassemble the data with
+    /// `env::read_chunked` without knowing its total length in advance. Empty `data` is written
+    /// as a single empty frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_len` is zero or does not fit in 31 bits.
+    #[stability::unstable]
+    pub fn write_chunked(&mut self, data: &[u8], chunk_len: usize) -> &mut Self {
+        assert!(
+            chunk_len > 0 && chunk_len < CHUNK_FINAL_FLAG as usize,
+            "chunk_len must be between 1 and {}",
+            CHUNK_FINAL_FLAG - 1
+        );
+        let mut chunks = data.chunks(chunk_len).peekable();
+        if chunks.peek().is_none() {
+            self.inner
+                .input
+                .extend_from_slice(&CHUNK_FINAL_FLAG.to_le_bytes());
+        }
+        while let Some(chunk) = chunks.next() {
+            let mut header = chunk.len() as u32;
+            if chunks.peek().is_none() {
+                header |= CHUNK_FINAL_FLAG;
+            }
+            self.inner.input.extend_from_slice(&header.to_le_bytes());
+            self.inner.input.extend_from_slice(chunk);
+        }
+        self
+    }
+
+    /// Write a frame to the zkVM guest via stdin, streaming its payload of `len` bytes from
+    /// `reader`.
+    ///
+    /// This is equivalent to [ExecutorEnvBuilder::write_frame], except that the payload is read
+    /// from `reader` as the guest consumes it instead of being copied into the input buffer, so
+    /// large inputs need not be held in memory. The guest reads the frame as usual, e.g. with
+    /// `env::read_frame` or `env::read_framed`.
+    ///
+    /// Exactly `len` bytes are read from `reader`; if it ends early, execution fails with an
+    /// [UnexpectedEof](std::io::ErrorKind::UnexpectedEof) error.
+    #[stability::unstable]
+    pub fn write_frame_from(&mut self, reader: impl Read + 'a, len: u32) -> &mut Self {
+        self.inner.input.extend_from_slice(&len.to_le_bytes());
+        let buffered = mem::take(&mut self.inner.input);
+        self.inner
+            .input_readers
+            .push(Box::new(Cursor::new(buffered)));
+        self.inner.input_readers.push(Box::new(FrameReader {
+            inner: reader,
+            remaining: len as u64,
+        }));
+        self
+    }
+
     /// Add a posix-style standard input.
+    ///
+    /// The guest reads this as a raw byte stream, e.g. with `std::io::stdin()`. Input added with
+    /// [ExecutorEnvBuilder::write] is also delivered on stdin and replaces this reader, so the two
+    /// should not be combined.
     pub fn stdin(&mut self, reader: impl Read + 'a) -> &mut Self {
         self.read_fd(fileno::STDIN, BufReader::new(reader))
     }
 
     /// Add a posix-style standard output.
+    ///
+    /// Everything the guest writes to stdout is passed to `writer`. When writing to a borrowed
+    /// buffer such as `&mut Vec<u8>`, the contents are available once the [ExecutorEnv] has been
+    /// dropped after execution.
     pub fn stdout(&mut self, writer: impl Write + 'a) -> &mut Self {
         self.write_fd(fileno::STDOUT, writer)
     }
@@ -387,10 +940,15 @@ impl<'a> ExecutorEnvBuilder<'a> {
     }
 
     /// Add a handler for simple I/O handling.
+    ///
+    /// The guest invokes the handler with `env::send_recv_slice`, using a syscall name declared
+    /// with [declare_syscall][crate::declare_syscall] that matches `channel`. The handler receives
+    /// the bytes sent by the guest and returns the bytes to send back. Calling a syscall name that
+    /// has no registered handler fails execution with an "Unknown syscall" error.
     pub fn io_callback<C: AsRef<str>>(
         &mut self,
         channel: C,
-        callback: impl Fn(Bytes) -> Result<Bytes> + 'a,
+        callback: impl FnMut(Bytes) -> Result<Bytes> + 'a,
     ) -> &mut Self {
         self.inner
             .slice_io
@@ -419,13 +977,71 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Add a callback that is passed the bytes of each commit to the journal as the guest makes
+    /// it.
+    ///
+    /// The concatenation of everything passed to the callback is equal to the journal of the
+    /// resulting session, unless execution was resumed from a snapshot, in which case bytes
+    /// committed before the snapshot are not delivered again. An error returned by the callback
+    /// aborts execution.
+    pub fn journal_callback(&mut self, callback: impl JournalCallback + 'a) -> &mut Self {
+        self.write_fd(fileno::JOURNAL, JournalCallbackWriter(callback))
+    }
+
     /// Add a callback handler for raw trace messages.
+    ///
+    /// The callback is passed a [TraceEvent::InstructionStart](crate::TraceEvent::InstructionStart)
+    /// for every instruction executed, followed by the registers and memory it writes. Events are
+    /// only generated when at least one callback is set.
     pub fn trace_callback(&mut self, callback: impl TraceCallback + 'a) -> &mut Self {
         self.inner.trace.push(Rc::new(RefCell::new(callback)));
         self
     }
 
+    /// Add a callback handler for trace messages of instructions sampled every `interval` user
+    /// cycles.
+    ///
+    /// The callback is passed the events of the first instruction to start at or after each
+    /// multiple of `interval`, as with [ExecutorEnvBuilder::trace_callback]; the events of other
+    /// instructions are dropped. An `interval` of 1 delivers every event. Sampling keeps the cost
+    /// of a profiler low enough for long executions, while still attributing cycles to the guest's
+    /// functions in proportion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, TraceEvent};
+    ///
+    /// let mut pcs = Vec::new();
+    /// let env = ExecutorEnv::builder()
+    ///     .trace_callback_sampled(1000, |event| {
+    ///         if let TraceEvent::InstructionStart { pc, .. } = event {
+    ///             pcs.push(pc);
+    ///         }
+    ///         Ok(())
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn trace_callback_sampled(
+        &mut self,
+        interval: u64,
+        callback: impl TraceCallback + 'a,
+    ) -> &mut Self {
+        self.trace_callback(SampledTrace {
+            interval: interval.max(1),
+            next_sample: 0,
+            sampling: false,
+            inner: callback,
+        })
+    }
+
     /// Set the path where segments will be stored.
+    ///
+    /// When executing with [Client::execute](crate::ApiClient::execute), segments requested with
+    /// [AssetRequest::Inline](crate::AssetRequest::Inline) are written to this directory and passed
+    /// to the segment callback as [Asset::Path](crate::Asset::Path), so that they need not be held
+    /// in memory.
     pub fn segment_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
         self.inner.segment_path = Some(SegmentPath::Path(path.as_ref().to_path_buf()));
         self
@@ -457,3 +1073,66 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 }
+
+struct JournalCallbackWriter<C>(C);
+
+impl<C: JournalCallback> Write for JournalCallbackWriter<C> {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .on_journal_append(bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Forwards the trace events of the first instruction to start at or after each multiple of
+/// `interval` user cycles, dropping the rest.
+struct SampledTrace<C> {
+    interval: u64,
+    next_sample: u64,
+    sampling: bool,
+    inner: C,
+}
+
+impl<C: TraceCallback> TraceCallback for SampledTrace<C> {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        if let TraceEvent::InstructionStart { cycle, .. } = event {
+            self.sampling = cycle >= self.next_sample;
+            if self.sampling {
+                self.next_sample = (cycle / self.interval + 1) * self.interval;
+            }
+        }
+        match self.sampling {
+            true => self.inner.trace_callback(event),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Reads exactly `remaining` bytes of a streamed frame payload.
+struct FrameReader<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for FrameReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+        let max = buf.len().min(self.remaining as usize);
+        let nread = self.inner.read(&mut buf[..max])?;
+        if nread == 0 && max > 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("input frame ended {} bytes early", self.remaining),
+            ));
+        }
+        self.remaining -= nread as u64;
+        Ok(nread)
+    }
+}
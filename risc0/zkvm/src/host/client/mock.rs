@@ -0,0 +1,133 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A [CoprocessorCallback] for unit testing guests which request coprocessor proofs.
+
+use anyhow::Result;
+
+use crate::{
+    host::client::env::{CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest},
+    FakeReceipt, InnerAssumptionReceipt, MaybePruned,
+};
+
+/// A [CoprocessorCallback] that records each request instead of proving it.
+///
+/// This allows a test to check which ZKR and keccak proofs a guest asked for without a running
+/// prover. Share it with the executor via
+/// [ExecutorEnvBuilder::coprocessor_callback_ref][crate::ExecutorEnvBuilder::coprocessor_callback_ref]
+/// to inspect it once execution is done.
+///
+/// The receipts returned by [MockCoprocessor::receipts] are [FakeReceipt]s, which fail
+/// verification unless dev mode is enabled. This type is not available when the
+/// `disable-dev-mode` feature is set.
+#[stability::unstable]
+#[derive(Clone, Default)]
+pub struct MockCoprocessor {
+    zkr_requests: Vec<ProveZkrRequest>,
+    keccak_requests: Vec<ProveKeccakRequest>,
+}
+
+impl MockCoprocessor {
+    /// Construct a [MockCoprocessor] with no recorded requests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The ZKR proof requests received so far, in the order they were made.
+    pub fn zkr_requests(&self) -> &[ProveZkrRequest] {
+        &self.zkr_requests
+    }
+
+    /// The keccak proof requests received so far, in the order they were made.
+    pub fn keccak_requests(&self) -> &[ProveKeccakRequest] {
+        &self.keccak_requests
+    }
+
+    /// Fake receipts for every request received so far: ZKR requests first, then keccak requests.
+    pub fn receipts(&self) -> Vec<InnerAssumptionReceipt> {
+        let zkr_claims = self.zkr_requests.iter().map(|req| req.claim_digest);
+        let keccak_claims = self.keccak_requests.iter().map(|req| req.claim_digest);
+        zkr_claims
+            .chain(keccak_claims)
+            .map(|claim_digest| {
+                InnerAssumptionReceipt::Fake(FakeReceipt::new(MaybePruned::Pruned(claim_digest)))
+            })
+            .collect()
+    }
+}
+
+impl CoprocessorCallback for MockCoprocessor {
+    fn prove_zkr(&mut self, request: ProveZkrRequest) -> Result<()> {
+        self.zkr_requests.push(request);
+        Ok(())
+    }
+
+    fn prove_keccak(&mut self, request: ProveKeccakRequest) -> Result<()> {
+        self.keccak_requests.push(request);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkp::digest;
+
+    use super::MockCoprocessor;
+    use crate::{
+        sha::Digest, CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest, VerifierContext,
+    };
+
+    #[test]
+    fn records_requests() {
+        let zkr_claim = digest!("00000000000000de00000000000000ad00000000000000be00000000000000ef");
+        let keccak_claim =
+            digest!("a558268a11892374b41d03857a40cdc5e87e351a3bfc17aa2054f47712a17bc3");
+
+        let mut coprocessor = MockCoprocessor::new();
+        coprocessor
+            .prove_keccak(ProveKeccakRequest {
+                claim_digest: keccak_claim,
+                po2: 15,
+                control_root: Digest::ZERO,
+                input: vec![1, 2, 3],
+            })
+            .unwrap();
+        coprocessor
+            .prove_zkr(ProveZkrRequest {
+                claim_digest: zkr_claim,
+                control_id: Digest::ZERO,
+                input: vec![],
+            })
+            .unwrap();
+
+        assert_eq!(coprocessor.zkr_requests().len(), 1);
+        assert_eq!(coprocessor.zkr_requests()[0].claim_digest, zkr_claim);
+        assert_eq!(coprocessor.keccak_requests().len(), 1);
+        assert_eq!(coprocessor.keccak_requests()[0].input, vec![1, 2, 3]);
+
+        let receipts = coprocessor.receipts();
+        let claims: Vec<Digest> = receipts
+            .iter()
+            .map(|receipt| receipt.claim_digest().unwrap())
+            .collect();
+        assert_eq!(claims, vec![zkr_claim, keccak_claim]);
+
+        if !crate::is_dev_mode() {
+            let ctx = VerifierContext::default();
+            for receipt in receipts {
+                assert!(receipt.verify_integrity_with_context(&ctx).is_err());
+            }
+        }
+    }
+}
@@ -13,6 +13,13 @@
 // limitations under the License.
 
 pub(crate) mod env;
+#[cfg(feature = "unstable")]
+#[cfg(not(feature = "disable-dev-mode"))]
+pub(crate) mod mock;
 pub(crate) mod posix_io;
 pub(crate) mod prove;
+#[cfg(feature = "unstable")]
+pub(crate) mod record;
 pub(crate) mod slice_io;
+#[cfg(feature = "unstable")]
+pub(crate) mod trace;
@@ -16,6 +16,7 @@ use std::time::Duration;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use bonsai_sdk::blocking::Client;
+use risc0_zkvm_platform::fileno;
 
 use super::Prover;
 use crate::{
@@ -78,8 +79,14 @@ impl Prover for BonsaiProver {
         let image_id_hex = hex::encode(image_id);
         client.upload_img(&image_id_hex, elf.to_vec())?;
 
-        // upload input data
-        let input_id = client.upload_input(env.input)?;
+        // upload input data, reading it back from stdin so that streamed frames are included
+        let mut input = Vec::new();
+        env.posix_io
+            .borrow()
+            .get_reader(fileno::STDIN)?
+            .borrow_mut()
+            .read_to_end(&mut input)?;
+        let input_id = client.upload_input(input)?;
 
         // upload receipts
         let mut receipts_ids = vec![];
@@ -139,17 +146,18 @@ impl Prover for BonsaiProver {
                 } else {
                     receipt.verify_with_context(ctx, image_id)?;
                 }
-                break ProveInfo {
+                break ProveInfo::new(
                     receipt,
-                    stats: SessionStats {
+                    SessionStats {
                         segments: stats.segments,
                         total_cycles: stats.total_cycles,
                         user_cycles: stats.cycles,
                         // These are currently unavailable from Bonsai
                         paging_cycles: 0,
                         reserved_cycles: 0,
+                        paging: Default::default(),
                     },
-                };
+                );
             } else {
                 bail!(
                     "Bonsai prover workflow [{}] exited: {} err: {}",
@@ -212,10 +220,7 @@ impl Prover for BonsaiProver {
             .context("failed to verify Groth16Receipt returned by Bonsai")?;
 
         // Return the groth16 receipt, with the stats collected earlier.
-        Ok(ProveInfo {
-            receipt: groth16_receipt,
-            stats: succinct_prove_info.stats,
-        })
+        Ok(ProveInfo::new(groth16_receipt, succinct_prove_info.stats))
     }
 
     fn compress(&self, opts: &ProverOpts, receipt: &Receipt) -> Result<Receipt> {
@@ -85,7 +85,7 @@ impl Prover for ExternalProver {
             }
             (_, _) => {
                 let client = ApiClient::new_sub_process(&self.r0vm_path)?;
-                client.compress(opts, receipt.clone().try_into()?, AssetRequest::Inline)
+                Ok(client.compress(opts, receipt.clone().try_into()?, AssetRequest::Inline)?)
             }
         }
     }
@@ -96,6 +96,6 @@ impl Executor for ExternalProver {
         let binary = Asset::Inline(elf.to_vec().into());
         let client = ApiClient::new_sub_process(&self.r0vm_path)?;
         let segments_out = AssetRequest::Inline;
-        client.execute(&env, binary, segments_out, |_, _| Ok(()))
+        Ok(client.execute(&env, binary, segments_out, |_, _| Ok(()))?)
     }
 }
@@ -60,10 +60,12 @@ impl Executor for LocalProver {
         let mut exec = ExecutorImpl::from_elf(env, elf)?;
         let mut segments = Vec::new();
         let session = exec.run_with_callback(|segment| {
-            segments.push(SegmentInfo {
-                po2: segment.inner.po2 as u32,
-                cycles: segment.inner.insn_cycles as u32,
-            });
+            segments.push(SegmentInfo::new(
+                segment.inner.po2 as u32,
+                segment.inner.insn_cycles as u32,
+                segment.inner.paging_cycles as u64,
+                segment.paging(),
+            ));
             Ok(Box::new(NullSegmentRef))
         })?;
         let receipt_claim = session.claim()?;
@@ -72,6 +74,9 @@ impl Executor for LocalProver {
             journal: session.journal.unwrap_or_default(),
             exit_code: session.exit_code,
             receipt_claim: Some(receipt_claim),
+            unique_pages_touched: session.paging.unique_pages_touched,
+            partial_journal: session.partial_journal,
+            complete: true,
         })
     }
 }
@@ -18,14 +18,16 @@ pub(crate) mod external;
 #[cfg(feature = "prove")]
 pub(crate) mod local;
 
-use std::{path::PathBuf, rc::Rc};
+#[cfg(feature = "prove")]
+use std::sync::Arc;
+use std::{path::PathBuf, rc::Rc, time::Duration};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use risc0_build::risc0_data;
 use serde::{Deserialize, Serialize};
 
 use risc0_circuit_recursion::control_id::ALLOWED_CONTROL_IDS;
-use risc0_zkp::core::digest::Digest;
+use risc0_zkp::{core::digest::Digest, MAX_CYCLES_PO2, MIN_CYCLES_PO2};
 
 #[cfg(feature = "bonsai")]
 use {self::bonsai::BonsaiProver, crate::is_dev_mode};
@@ -176,8 +178,66 @@ pub struct ProverOpts {
     /// [SuccinctReceiptVerifierParameters][crate::SuccinctReceiptVerifierParameters].
     pub control_ids: Vec<Digest>,
 
+    /// Minimum segment size limit, as a power of two (po2), used when executing for proving.
+    #[serde(default = "default_min_segment_po2")]
+    pub(crate) min_segment_po2: usize,
+
     /// Maximum cycle count, as a power of two (po2) that these prover options support.
     pub(crate) max_segment_po2: usize,
+
+    /// Segment size limit, as a power of two (po2), that overrides larger limits used when
+    /// executing for proving.
+    #[serde(default)]
+    pub(crate) segment_limit_po2: Option<u32>,
+
+    /// How long an [ApiClient](crate::ApiClient) call using these options may take before the
+    /// server process is terminated.
+    #[serde(default)]
+    pub(crate) timeout: Option<Duration>,
+
+    /// Whether execution for proving is split into segments independently of the [ExecutorEnv].
+    #[serde(default)]
+    pub(crate) deterministic: bool,
+
+    /// Whether a compressed receipt keeps the composite receipt it was compressed from.
+    #[serde(default)]
+    pub(crate) keep_composite: bool,
+
+    /// Cache of segment receipts used by the local prover.
+    #[cfg(feature = "prove")]
+    #[serde(skip)]
+    pub(crate) segment_cache: Option<Arc<dyn crate::SegmentCache>>,
+}
+
+fn default_min_segment_po2() -> usize {
+    MIN_CYCLES_PO2
+}
+
+/// Hash functions that can be used for the STARK proving protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HashFn {
+    /// SHA-256, the fastest option, but it does not support recursion.
+    Sha256,
+
+    /// Poseidon2, which is required for recursion.
+    Poseidon2,
+}
+
+impl HashFn {
+    /// Return the name used to identify this hash function.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashFn::Sha256 => "sha-256",
+            HashFn::Poseidon2 => "poseidon2",
+        }
+    }
+}
+
+impl From<HashFn> for String {
+    fn from(hashfn: HashFn) -> Self {
+        hashfn.as_str().to_string()
+    }
 }
 
 /// An enumeration of receipt kinds that can be requested to be generated.
@@ -214,7 +274,14 @@ impl Default for ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Composite,
             control_ids: ALLOWED_CONTROL_IDS.to_vec(),
+            min_segment_po2: MIN_CYCLES_PO2,
             max_segment_po2: DEFAULT_MAX_PO2,
+            segment_limit_po2: None,
+            timeout: None,
+            deterministic: false,
+            keep_composite: false,
+            #[cfg(feature = "prove")]
+            segment_cache: None,
         }
     }
 }
@@ -235,7 +302,14 @@ impl ProverOpts {
             control_ids: crate::receipt::succinct::allowed_control_ids("poseidon2", po2_max)
                 .unwrap()
                 .collect(),
+            min_segment_po2: MIN_CYCLES_PO2,
             max_segment_po2: po2_max,
+            segment_limit_po2: None,
+            timeout: None,
+            deterministic: false,
+            keep_composite: false,
+            #[cfg(feature = "prove")]
+            segment_cache: None,
         }
     }
 
@@ -243,7 +317,7 @@ impl ProverOpts {
     /// control ID associated with cycle counts of all supported powers of two (po2).
     #[stability::unstable]
     pub fn all_po2s() -> Self {
-        Self::from_max_po2(risc0_zkp::MAX_CYCLES_PO2)
+        Self::from_max_po2(MAX_CYCLES_PO2)
     }
 
     /// Choose the fastest prover options. Receipt will be linear in length of the execution,
@@ -254,7 +328,14 @@ impl ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Composite,
             control_ids: risc0_circuit_rv32im::control_ids("sha-256", DEFAULT_MAX_PO2).collect(),
+            min_segment_po2: MIN_CYCLES_PO2,
             max_segment_po2: DEFAULT_MAX_PO2,
+            segment_limit_po2: None,
+            timeout: None,
+            deterministic: false,
+            keep_composite: false,
+            #[cfg(feature = "prove")]
+            segment_cache: None,
         }
     }
 
@@ -266,7 +347,14 @@ impl ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Composite,
             control_ids: ALLOWED_CONTROL_IDS.to_vec(),
+            min_segment_po2: MIN_CYCLES_PO2,
             max_segment_po2: DEFAULT_MAX_PO2,
+            segment_limit_po2: None,
+            timeout: None,
+            deterministic: false,
+            keep_composite: false,
+            #[cfg(feature = "prove")]
+            segment_cache: None,
         }
     }
 
@@ -278,7 +366,14 @@ impl ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Succinct,
             control_ids: ALLOWED_CONTROL_IDS.to_vec(),
+            min_segment_po2: MIN_CYCLES_PO2,
             max_segment_po2: DEFAULT_MAX_PO2,
+            segment_limit_po2: None,
+            timeout: None,
+            deterministic: false,
+            keep_composite: false,
+            #[cfg(feature = "prove")]
+            segment_cache: None,
         }
     }
 
@@ -292,14 +387,23 @@ impl ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Groth16,
             control_ids: ALLOWED_CONTROL_IDS.to_vec(),
+            min_segment_po2: MIN_CYCLES_PO2,
             max_segment_po2: DEFAULT_MAX_PO2,
+            segment_limit_po2: None,
+            timeout: None,
+            deterministic: false,
+            keep_composite: false,
+            #[cfg(feature = "prove")]
+            segment_cache: None,
         }
     }
 
     /// Return [ProverOpts] with the hashfn set to the given value.
-    pub fn with_hashfn(self, hashfn: String) -> Self {
+    ///
+    /// Accepts either a [HashFn] or the name of a hash function.
+    pub fn with_hashfn(self, hashfn: impl Into<String>) -> Self {
         Self {
-            hashfn: hashfn.to_owned(),
+            hashfn: hashfn.into(),
             ..self
         }
     }
@@ -329,6 +433,12 @@ impl ProverOpts {
     }
 
     /// Return [ProverOpts] with the max_segment_po2 set to the given value.
+    ///
+    /// When proving, execution will be split into segments of at most 2^max_po2 cycles, which
+    /// bounds the memory required to prove each segment.
+    ///
+    /// NOTE: Raising this above [DEFAULT_MAX_PO2] also requires setting control IDs that support
+    /// the larger po2. See [ProverOpts::from_max_po2].
     #[stability::unstable]
     pub fn with_segment_po2_max(self, max_segment_po2: usize) -> Self {
         Self {
@@ -337,6 +447,141 @@ impl ProverOpts {
         }
     }
 
+    /// Return [ProverOpts] with the minimum segment po2 set to the given value.
+    ///
+    /// When proving, executions requesting a smaller segment limit will be split into segments of
+    /// at most 2^min_po2 cycles instead.
+    pub fn with_min_po2(self, min_segment_po2: usize) -> Self {
+        Self {
+            min_segment_po2,
+            ..self
+        }
+    }
+
+    /// Return [ProverOpts] which split execution into segments of at most 2^po2 cycles when
+    /// proving.
+    ///
+    /// This trades more, smaller segments for a lower peak memory use while proving each one.
+    /// Unlike [ProverOpts::with_segment_po2_max], it only lowers the segment limit used for execution: the
+    /// po2 bounds of the prover and verifier, and hence the receipts produced, are unchanged. A
+    /// limit below the [minimum po2](ProverOpts::with_min_po2) takes precedence over it.
+    pub fn with_segment_limit_po2(self, po2: u32) -> Self {
+        Self {
+            segment_limit_po2: Some(po2),
+            ..self
+        }
+    }
+
+    /// Return [ProverOpts] with a timeout for [ApiClient](crate::ApiClient) calls.
+    ///
+    /// If a call does not complete in time, the server process is terminated and the call fails
+    /// with a [TimedOut](crate::TimedOut) error.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Return [ProverOpts] with deterministic proving set to the given value.
+    ///
+    /// With deterministic proving, proving the same guest binary with the same input yields
+    /// receipts with byte-identical claims, for the receipt as a whole as well as for each
+    /// segment. This controls the following sources of nondeterminism:
+    ///
+    /// * Segment boundaries: execution is always split into segments of up to 2^max_po2 cycles
+    ///   (see [ProverOpts::with_segment_po2_max]), ignoring any segment limit requested by the
+    ///   [ExecutorEnv] or the default used by the prover in use.
+    ///
+    /// The following are not controlled, and still vary between runs:
+    ///
+    /// * Seals: the prover mixes random noise into every proof so that it reveals nothing about
+    ///   the execution beyond its claim. Seals, and therefore serialized receipts, differ between
+    ///   runs; compare claim digests instead.
+    /// * Guest randomness: guests that call `sys_random` (e.g. through `getrandom`) receive fresh
+    ///   entropy from the host, and so may execute differently.
+    pub fn with_deterministic(self, deterministic: bool) -> Self {
+        Self {
+            deterministic,
+            ..self
+        }
+    }
+
+    /// Return [ProverOpts] that keep the composite receipt alongside a compressed receipt.
+    ///
+    /// When set, proving a succinct or Groth16 receipt also returns the per-segment composite
+    /// receipt it was compressed from, as [ProveInfo::composite](crate::ProveInfo::composite). This
+    /// is intended for debugging: if a compressed receipt fails to verify, verifying the composite
+    /// receipt's segments individually localizes the bad segment. The composite receipt is linear
+    /// in the length of the execution. The receipt itself is unchanged.
+    pub fn with_keep_composite(self, keep_composite: bool) -> Self {
+        Self {
+            keep_composite,
+            ..self
+        }
+    }
+
+    /// Return [ProverOpts] with a [SegmentCache](crate::SegmentCache) for the local prover.
+    ///
+    /// The local prover looks each segment up in the cache before proving it, and stores the
+    /// receipt there once proven. A cached receipt is only used once it has been verified against
+    /// the segment. The cache is not sent to a prover in another process, such as an
+    /// [ApiClient](crate::ApiClient).
+    #[cfg(feature = "prove")]
+    pub fn with_segment_cache(self, cache: Arc<dyn crate::SegmentCache>) -> Self {
+        Self {
+            segment_cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Check that the segment po2 bounds are consistent and supported.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.min_segment_po2 <= self.max_segment_po2,
+            "min_po2 ({}) must not be greater than max_po2 ({})",
+            self.min_segment_po2,
+            self.max_segment_po2
+        );
+        ensure!(
+            self.min_segment_po2 >= MIN_CYCLES_PO2,
+            "min_po2 ({}) must be at least {MIN_CYCLES_PO2}",
+            self.min_segment_po2
+        );
+        ensure!(
+            self.max_segment_po2 <= MAX_CYCLES_PO2,
+            "max_po2 ({}) must be at most {MAX_CYCLES_PO2}",
+            self.max_segment_po2
+        );
+        if let Some(po2) = self.segment_limit_po2 {
+            ensure!(
+                (MIN_CYCLES_PO2..=self.max_segment_po2).contains(&(po2 as usize)),
+                "segment_limit_po2 ({po2}) must be between {MIN_CYCLES_PO2} and max_po2 ({})",
+                self.max_segment_po2
+            );
+        }
+        Ok(())
+    }
+
+    /// Return the segment limit to execute with, given the limit requested by the
+    /// [ExecutorEnv], clamped to the po2 bounds of these options and capped at the
+    /// [segment limit](ProverOpts::with_segment_limit_po2), if any.
+    ///
+    /// With [deterministic proving](ProverOpts::with_deterministic), the requested limit is
+    /// ignored and the maximum po2 is always used, subject to the same cap.
+    #[cfg(feature = "prove")]
+    pub(crate) fn segment_limit_po2(&self, requested: Option<u32>) -> u32 {
+        use risc0_circuit_rv32im::prove::emu::exec::DEFAULT_SEGMENT_LIMIT_PO2;
+
+        let po2 = if self.deterministic {
+            self.max_segment_po2 as u32
+        } else {
+            let requested = requested.map_or(DEFAULT_SEGMENT_LIMIT_PO2, |po2| po2 as usize);
+            requested.clamp(self.min_segment_po2, self.max_segment_po2) as u32
+        };
+        self.segment_limit_po2.map_or(po2, |limit| po2.min(limit))
+    }
+
     #[cfg(feature = "prove")]
     pub(crate) fn hash_suite(
         &self,
@@ -0,0 +1,248 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! [CoprocessorCallback]s for recording the requests made by a guest and replaying them without
+//! a prover.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Cursor, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    host::client::env::{CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest},
+    sha::Digest,
+};
+
+/// A request made to a [CoprocessorCallback].
+#[derive(Clone, Serialize, Deserialize)]
+enum CoprocessorRequest {
+    Zkr(ProveZkrRequest),
+    Keccak(ProveKeccakRequest),
+}
+
+impl CoprocessorRequest {
+    fn claim_digest(&self) -> Digest {
+        match self {
+            Self::Zkr(request) => request.claim_digest,
+            Self::Keccak(request) => request.claim_digest,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Zkr(_) => "ZKR",
+            Self::Keccak(_) => "keccak",
+        }
+    }
+}
+
+/// A request together with the response of the [CoprocessorCallback] which handled it.
+#[derive(Serialize, Deserialize)]
+struct CoprocessorRecord {
+    request: CoprocessorRequest,
+
+    /// The error returned by the callback, if any.
+    error: Option<String>,
+}
+
+/// A [CoprocessorCallback] that forwards each request to another callback, recording the request
+/// and its response to a file.
+///
+/// The file can be loaded with [ReplayingCoprocessor::from_file] to run the same guest again
+/// without the wrapped callback, e.g. to make a test hermetic.
+#[stability::unstable]
+pub struct RecordingCoprocessor<C: CoprocessorCallback> {
+    inner: C,
+    writer: BufWriter<File>,
+}
+
+impl<C: CoprocessorCallback> RecordingCoprocessor<C> {
+    /// Construct a [RecordingCoprocessor] which forwards requests to `inner`, creating or
+    /// truncating the file at `path` to hold the recording.
+    pub fn new(inner: C, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("failed to create recording {}", path.display()))?;
+        Ok(Self {
+            inner,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// The wrapped callback.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Consume the recorder, returning the wrapped callback.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn record(&mut self, request: CoprocessorRequest, result: Result<()>) -> Result<()> {
+        let record = CoprocessorRecord {
+            request,
+            error: result.as_ref().err().map(|err| format!("{err:#}")),
+        };
+        // Each record is flushed so that the recording is complete even if execution fails.
+        bincode::serialize_into(&mut self.writer, &record)?;
+        self.writer.flush()?;
+        result
+    }
+}
+
+impl<C: CoprocessorCallback> CoprocessorCallback for RecordingCoprocessor<C> {
+    fn prove_zkr(&mut self, request: ProveZkrRequest) -> Result<()> {
+        let result = self.inner.prove_zkr(request.clone());
+        self.record(CoprocessorRequest::Zkr(request), result)
+    }
+
+    fn prove_keccak(&mut self, request: ProveKeccakRequest) -> Result<()> {
+        let result = self.inner.prove_keccak(request.clone());
+        self.record(CoprocessorRequest::Keccak(request), result)
+    }
+}
+
+/// A [CoprocessorCallback] that responds to each request as recorded by a
+/// [RecordingCoprocessor].
+///
+/// Requests are matched to the recording by claim digest. A request for a claim that was not
+/// recorded returns an error.
+#[stability::unstable]
+pub struct ReplayingCoprocessor {
+    records: HashMap<Digest, CoprocessorRecord>,
+}
+
+impl ReplayingCoprocessor {
+    /// Load a recording written by a [RecordingCoprocessor].
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("failed to read recording {}", path.display()))?;
+        let mut reader = Cursor::new(bytes.as_slice());
+        let mut records = HashMap::new();
+        while (reader.position() as usize) < bytes.len() {
+            let record: CoprocessorRecord = bincode::deserialize_from(&mut reader)
+                .with_context(|| format!("malformed recording {}", path.display()))?;
+            records.insert(record.request.claim_digest(), record);
+        }
+        Ok(Self { records })
+    }
+
+    fn replay(&self, request: CoprocessorRequest) -> Result<()> {
+        let claim_digest = request.claim_digest();
+        let Some(record) = self.records.get(&claim_digest) else {
+            bail!(
+                "no recorded response for {} request with claim {claim_digest}",
+                request.kind()
+            );
+        };
+        if record.request.kind() != request.kind() {
+            bail!(
+                "{} request with claim {claim_digest} was recorded as a {} request",
+                request.kind(),
+                record.request.kind()
+            );
+        }
+        match &record.error {
+            Some(error) => bail!("{error}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl CoprocessorCallback for ReplayingCoprocessor {
+    fn prove_zkr(&mut self, request: ProveZkrRequest) -> Result<()> {
+        self.replay(CoprocessorRequest::Zkr(request))
+    }
+
+    fn prove_keccak(&mut self, request: ProveKeccakRequest) -> Result<()> {
+        self.replay(CoprocessorRequest::Keccak(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{bail, Result};
+    use risc0_zkp::digest;
+
+    use super::{RecordingCoprocessor, ReplayingCoprocessor};
+    use crate::{sha::Digest, CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest};
+
+    /// Accepts keccak requests and rejects ZKR requests.
+    struct KeccakOnly;
+
+    impl CoprocessorCallback for KeccakOnly {
+        fn prove_zkr(&mut self, _request: ProveZkrRequest) -> Result<()> {
+            bail!("ZKR proving is not supported");
+        }
+
+        fn prove_keccak(&mut self, _request: ProveKeccakRequest) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn keccak_request(claim_digest: Digest) -> ProveKeccakRequest {
+        ProveKeccakRequest {
+            claim_digest,
+            po2: 15,
+            control_root: Digest::ZERO,
+            input: vec![1, 2, 3],
+        }
+    }
+
+    fn zkr_request(claim_digest: Digest) -> ProveZkrRequest {
+        ProveZkrRequest {
+            claim_digest,
+            control_id: Digest::ZERO,
+            input: vec![],
+        }
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let keccak_claim =
+            digest!("a558268a11892374b41d03857a40cdc5e87e351a3bfc17aa2054f47712a17bc3");
+        let zkr_claim = digest!("00000000000000de00000000000000ad00000000000000be00000000000000ef");
+        let unknown_claim =
+            digest!("b83c10da0c23587bf318cbcec2c2ac0260dbd6c0fa6905df639f8f6056f0d56c");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coprocessor.bin");
+        let mut recorder = RecordingCoprocessor::new(KeccakOnly, &path).unwrap();
+        recorder.prove_keccak(keccak_request(keccak_claim)).unwrap();
+        let err = recorder.prove_zkr(zkr_request(zkr_claim)).unwrap_err();
+        assert_eq!(err.to_string(), "ZKR proving is not supported");
+        drop(recorder);
+
+        // Responses are replayed by claim digest, including errors.
+        let mut replayer = ReplayingCoprocessor::from_file(&path).unwrap();
+        replayer.prove_keccak(keccak_request(keccak_claim)).unwrap();
+        let err = replayer.prove_zkr(zkr_request(zkr_claim)).unwrap_err();
+        assert_eq!(err.to_string(), "ZKR proving is not supported");
+
+        let err = replayer
+            .prove_keccak(keccak_request(unknown_claim))
+            .unwrap_err();
+        assert!(err.to_string().contains("no recorded response"));
+        let err = replayer.prove_zkr(zkr_request(keccak_claim)).unwrap_err();
+        assert!(err.to_string().contains("recorded as a keccak request"));
+    }
+}
@@ -39,14 +39,14 @@ pub struct SliceIoTable<'a> {
     pub(crate) inner: BTreeMap<String, Rc<RefCell<dyn SliceIo + 'a>>>,
 }
 
-type Callback<'a> = Rc<RefCell<dyn Fn(Bytes) -> Result<Bytes> + 'a>>;
+type Callback<'a> = Rc<RefCell<dyn FnMut(Bytes) -> Result<Bytes> + 'a>>;
 
 struct FnWrapper<'a> {
     callback: Callback<'a>,
 }
 
 pub fn slice_io_from_fn<'a>(
-    callback: impl Fn(Bytes) -> Result<Bytes> + 'a,
+    callback: impl FnMut(Bytes) -> Result<Bytes> + 'a,
 ) -> Rc<RefCell<dyn SliceIo + 'a>> {
     let callback = Rc::new(RefCell::new(callback));
     Rc::new(RefCell::new(FnWrapper { callback }))
@@ -54,7 +54,7 @@ pub fn slice_io_from_fn<'a>(
 
 impl<'a> SliceIo for FnWrapper<'a> {
     fn handle_io(&mut self, _syscall: &str, from_guest: Bytes) -> Result<Bytes> {
-        let callback = self.callback.borrow_mut();
+        let mut callback = self.callback.borrow_mut();
         callback(from_guest)
     }
 }
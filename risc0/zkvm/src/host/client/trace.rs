@@ -0,0 +1,189 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Coarse traces of the program counter during execution, for finding where two executions of a
+//! guest diverge.
+
+use anyhow::Result;
+
+use crate::{TraceCallback, TraceEvent};
+
+/// The program counter of the instruction starting at a user cycle of an execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TracePoint {
+    /// The number of user cycles executed before the instruction.
+    pub cycle: u64,
+
+    /// The program counter of the instruction.
+    pub pc: u32,
+}
+
+/// The first point at which two [SessionTrace]s differ, as found by [SessionTrace::diff].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DivergencePoint {
+    /// The cycle of the first sample at which the traces differ.
+    ///
+    /// The executions diverged after the previous sample, at or before this cycle.
+    pub cycle: u64,
+
+    /// The sample of the trace [SessionTrace::diff] was called on, or `None` if its execution had
+    /// already ended.
+    pub left: Option<TracePoint>,
+
+    /// The sample of the other trace, or `None` if its execution had already ended.
+    pub right: Option<TracePoint>,
+}
+
+/// A [TraceCallback] which samples the program counter every `interval` user cycles.
+///
+/// Add it to an execution with
+/// [ExecutorEnvBuilder::trace_callback](crate::ExecutorEnvBuilder::trace_callback), passing
+/// `&mut trace` so that it can be inspected once the [ExecutorEnv](crate::ExecutorEnv) is dropped.
+/// Comparing the traces of two executions with [SessionTrace::diff] then locates where they
+/// diverge, e.g. to debug a guest which is not deterministic across inputs or versions.
+#[stability::unstable]
+#[derive(Clone, Debug)]
+pub struct SessionTrace {
+    interval: u64,
+    next_sample: u64,
+    points: Vec<TracePoint>,
+}
+
+impl SessionTrace {
+    /// Construct an empty [SessionTrace] which samples every `interval` user cycles.
+    ///
+    /// A smaller interval locates a divergence more precisely, at the cost of memory.
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+            next_sample: 0,
+            points: Vec::new(),
+        }
+    }
+
+    /// The samples taken so far, in order of cycle.
+    ///
+    /// Each is the first instruction to start at or after a multiple of the interval.
+    pub fn points(&self) -> &[TracePoint] {
+        &self.points
+    }
+
+    /// Returns the first sample at which this trace and `other` differ, or `None` if they are the
+    /// same.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the traces were sampled at different intervals.
+    pub fn diff(&self, other: &SessionTrace) -> Option<DivergencePoint> {
+        assert_eq!(
+            self.interval, other.interval,
+            "traces must be sampled at the same interval"
+        );
+        let len = self.points.len().max(other.points.len());
+        (0..len).find_map(|idx| {
+            let left = self.points.get(idx).copied();
+            let right = other.points.get(idx).copied();
+            (left != right).then(|| DivergencePoint {
+                cycle: left
+                    .into_iter()
+                    .chain(right)
+                    .map(|point| point.cycle)
+                    .min()
+                    .unwrap(),
+                left,
+                right,
+            })
+        })
+    }
+}
+
+impl TraceCallback for SessionTrace {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        if let TraceEvent::InstructionStart { cycle, pc, .. } = event {
+            if cycle >= self.next_sample {
+                self.points.push(TracePoint { cycle, pc });
+                self.next_sample = (cycle / self.interval + 1) * self.interval;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TraceCallback for &mut SessionTrace {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        (*self).trace_callback(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DivergencePoint, SessionTrace, TracePoint};
+    use crate::{TraceCallback, TraceEvent};
+
+    /// Trace an execution which runs the instruction at `pcs[cycle]` on each cycle.
+    fn trace(interval: u64, pcs: impl IntoIterator<Item = u32>) -> SessionTrace {
+        let mut trace = SessionTrace::new(interval);
+        for (cycle, pc) in pcs.into_iter().enumerate() {
+            let event = TraceEvent::InstructionStart {
+                cycle: cycle as u64,
+                pc,
+                insn: 0,
+            };
+            trace.trace_callback(event).unwrap();
+        }
+        trace
+    }
+
+    #[test]
+    fn samples_at_interval() {
+        let trace = trace(4, (0..10).map(|cycle| 0x1000 + 4 * cycle));
+        let cycles: Vec<_> = trace.points().iter().map(|point| point.cycle).collect();
+        assert_eq!(cycles, [0, 4, 8]);
+    }
+
+    #[test]
+    fn diff() {
+        // Both executions loop over the same four instructions, until the second one branches
+        // away at cycle 21.
+        let pcs = |diverge_at: u64| {
+            (0..100).map(move |cycle| match cycle < diverge_at {
+                true => 0x1000 + 4 * (cycle as u32 % 4),
+                false => 0x2000,
+            })
+        };
+        let left = trace(5, pcs(u64::MAX));
+        let right = trace(5, pcs(21));
+        assert_eq!(left.diff(&left), None);
+        assert_eq!(
+            left.diff(&right),
+            Some(DivergencePoint {
+                cycle: 25,
+                left: Some(TracePoint {
+                    cycle: 25,
+                    pc: 0x1004
+                }),
+                right: Some(TracePoint {
+                    cycle: 25,
+                    pc: 0x2000
+                }),
+            })
+        );
+
+        // An execution which ends early diverges at the first sample past its end.
+        let short = trace(5, pcs(u64::MAX).take(50));
+        let divergence = left.diff(&short).unwrap();
+        assert_eq!(divergence.cycle, 50);
+        assert_eq!(divergence.right, None);
+    }
+}
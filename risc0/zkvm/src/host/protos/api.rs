@@ -4,7 +4,7 @@
 pub struct ServerRequest {
     #[prost(
         oneof = "server_request::Kind",
-        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11"
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16"
     )]
     pub kind: ::core::option::Option<server_request::Kind>,
 }
@@ -35,6 +35,16 @@ pub mod server_request {
         ProveZkr(super::ProveZkrRequest),
         #[prost(message, tag = "11")]
         ProveKeccak(super::ProveKeccakRequest),
+        #[prost(message, tag = "12")]
+        ProveZkrBatch(super::ProveZkrBatchRequest),
+        #[prost(message, tag = "13")]
+        ServerInfo(super::ServerInfoRequest),
+        #[prost(message, tag = "14")]
+        ResolveBatch(super::ResolveBatchRequest),
+        #[prost(message, tag = "15")]
+        SupportedZkrs(super::SupportedZkrsRequest),
+        #[prost(message, tag = "16")]
+        WarmUp(super::WarmUpRequest),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -42,6 +52,13 @@ pub mod server_request {
 pub struct HelloRequest {
     #[prost(message, optional, tag = "1")]
     pub version: ::core::option::Option<super::base::SemanticVersion>,
+    /// Ask the server to keep handling requests on this connection until the client hangs up.
+    #[prost(bool, tag = "2")]
+    pub keep_alive: bool,
+    /// Credentials for servers behind an authenticating proxy, e.g. `Bearer <token>`. Empty when the
+    /// client has none.
+    #[prost(string, tag = "3")]
+    pub authorization: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -65,6 +82,85 @@ pub mod hello_reply {
 pub struct HelloResult {
     #[prost(message, optional, tag = "1")]
     pub version: ::core::option::Option<super::base::SemanticVersion>,
+    /// Set when the server agreed to keep the connection alive.
+    #[prost(bool, tag = "2")]
+    pub keep_alive: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ServerInfoRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ServerInfoReply {
+    #[prost(oneof = "server_info_reply::Kind", tags = "1, 2")]
+    pub kind: ::core::option::Option<server_info_reply::Kind>,
+}
+/// Nested message and enum types in `ServerInfoReply`.
+pub mod server_info_reply {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Ok(super::ServerInfoResult),
+        #[prost(message, tag = "2")]
+        Error(super::GenericError),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ServerInfoResult {
+    #[prost(message, optional, tag = "1")]
+    pub version: ::core::option::Option<super::base::SemanticVersion>,
+    #[prost(string, repeated, tag = "2")]
+    pub hashfns: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint32, tag = "3")]
+    pub max_po2: u32,
+    #[prost(string, repeated, tag = "4")]
+    pub features: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "5")]
+    pub control_root: ::core::option::Option<super::base::Digest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SupportedZkrsRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SupportedZkrsReply {
+    #[prost(oneof = "supported_zkrs_reply::Kind", tags = "1, 2")]
+    pub kind: ::core::option::Option<supported_zkrs_reply::Kind>,
+}
+/// Nested message and enum types in `SupportedZkrsReply`.
+pub mod supported_zkrs_reply {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Ok(super::SupportedZkrsResult),
+        #[prost(message, tag = "2")]
+        Error(super::GenericError),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SupportedZkrsResult {
+    #[prost(message, repeated, tag = "1")]
+    pub zkrs: ::prost::alloc::vec::Vec<ZkrInfo>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WarmUpRequest {
+    #[prost(message, optional, tag = "1")]
+    pub opts: ::core::option::Option<ProverOpts>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ZkrInfo {
+    #[prost(message, optional, tag = "1")]
+    pub control_id: ::core::option::Option<super::base::Digest>,
+    #[prost(string, optional, tag = "2")]
+    pub name: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint32, optional, tag = "3")]
+    pub input_words: ::core::option::Option<u32>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -73,6 +169,14 @@ pub struct ExecuteRequest {
     pub env: ::core::option::Option<ExecutorEnv>,
     #[prost(message, optional, tag = "2")]
     pub segments_out: ::core::option::Option<AssetRequest>,
+    /// When present, execution resumes from this encoded SessionSnapshot
+    /// instead of starting from the binary in `env`.
+    #[prost(message, optional, tag = "3")]
+    pub snapshot: ::core::option::Option<Asset>,
+    /// When set, segments are not serialized or written to `segments_out`; only
+    /// their cycle counts are reported.
+    #[prost(bool, tag = "4")]
+    pub stats_only: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -149,6 +253,38 @@ pub struct ProveZkrResult {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProveZkrBatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub requests: ::prost::alloc::vec::Vec<ProveZkrRequest>,
+    #[prost(message, optional, tag = "2")]
+    pub receipt_out: ::core::option::Option<AssetRequest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProveZkrBatchReply {
+    #[prost(oneof = "prove_zkr_batch_reply::Kind", tags = "1, 2")]
+    pub kind: ::core::option::Option<prove_zkr_batch_reply::Kind>,
+}
+/// Nested message and enum types in `ProveZkrBatchReply`.
+pub mod prove_zkr_batch_reply {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Ok(super::ProveZkrBatchResult),
+        #[prost(message, tag = "2")]
+        Error(super::GenericError),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProveZkrBatchResult {
+    /// Receipts are in the same order as the requests.
+    #[prost(message, repeated, tag = "1")]
+    pub receipts: ::prost::alloc::vec::Vec<Asset>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ProveKeccakRequest {
     #[prost(message, optional, tag = "1")]
     pub claim_digest: ::core::option::Option<super::base::Digest>,
@@ -288,6 +424,20 @@ pub struct ResolveResult {
     #[prost(message, optional, tag = "1")]
     pub receipt: ::core::option::Option<Asset>,
 }
+/// Replied to with a ResolveReply.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResolveBatchRequest {
+    #[prost(message, optional, tag = "1")]
+    pub opts: ::core::option::Option<ProverOpts>,
+    #[prost(message, optional, tag = "2")]
+    pub conditional_receipt: ::core::option::Option<Asset>,
+    /// Resolved in order, starting from the head assumption of the conditional receipt.
+    #[prost(message, repeated, tag = "3")]
+    pub assumption_receipts: ::prost::alloc::vec::Vec<Asset>,
+    #[prost(message, optional, tag = "4")]
+    pub receipt_out: ::core::option::Option<AssetRequest>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct IdentityP254Request {
@@ -393,6 +543,12 @@ pub struct ExecutorEnv {
     pub segment_path: ::prost::alloc::string::String,
     #[prost(bool, tag = "13")]
     pub coprocessor: bool,
+    #[prost(uint64, optional, tag = "14")]
+    pub checkpoint_interval: ::core::option::Option<u64>,
+    #[prost(uint32, optional, tag = "15")]
+    pub segment_po2_hint: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "16")]
+    pub coalesce_po2: ::core::option::Option<u32>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -426,6 +582,14 @@ pub struct ProverOpts {
     pub control_ids: ::prost::alloc::vec::Vec<super::base::Digest>,
     #[prost(uint64, tag = "5")]
     pub max_segment_po2: u64,
+    #[prost(uint64, tag = "6")]
+    pub min_segment_po2: u64,
+    #[prost(bool, tag = "7")]
+    pub deterministic: bool,
+    #[prost(bool, tag = "8")]
+    pub keep_composite: bool,
+    #[prost(uint32, optional, tag = "9")]
+    pub segment_limit_po2: ::core::option::Option<u32>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -438,6 +602,10 @@ pub struct SessionInfo {
     pub exit_code: ::core::option::Option<super::base::ExitCode>,
     #[prost(message, optional, tag = "4")]
     pub receipt_claim: ::core::option::Option<Asset>,
+    #[prost(uint64, tag = "5")]
+    pub unique_pages_touched: u64,
+    #[prost(bytes = "vec", tag = "6")]
+    pub partial_journal: ::prost::alloc::vec::Vec<u8>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -450,6 +618,16 @@ pub struct SegmentInfo {
     pub cycles: u32,
     #[prost(message, optional, tag = "4")]
     pub segment: ::core::option::Option<Asset>,
+    #[prost(uint64, tag = "5")]
+    pub paging_cycles: u64,
+    #[prost(uint64, tag = "6")]
+    pub page_in_count: u64,
+    #[prost(uint64, tag = "7")]
+    pub page_out_count: u64,
+    #[prost(message, optional, tag = "8")]
+    pub snapshot: ::core::option::Option<Asset>,
+    #[prost(uint64, tag = "9")]
+    pub unique_pages_touched: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -471,7 +649,7 @@ pub struct RedisParams {
 #[derive(Clone, PartialEq, ::prost::Message)]
 #[prost(skip_debug)]
 pub struct Asset {
-    #[prost(oneof = "asset::Kind", tags = "1, 2, 3")]
+    #[prost(oneof = "asset::Kind", tags = "1, 2, 3, 4")]
     pub kind: ::core::option::Option<asset::Kind>,
 }
 /// Nested message and enum types in `Asset`.
@@ -486,12 +664,14 @@ pub mod asset {
         Path(::prost::alloc::string::String),
         #[prost(string, tag = "3")]
         Redis(::prost::alloc::string::String),
+        #[prost(string, tag = "4")]
+        Store(::prost::alloc::string::String),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AssetRequest {
-    #[prost(oneof = "asset_request::Kind", tags = "1, 2, 3")]
+    #[prost(oneof = "asset_request::Kind", tags = "1, 2, 3, 4")]
     pub kind: ::core::option::Option<asset_request::Kind>,
 }
 /// Nested message and enum types in `AssetRequest`.
@@ -505,12 +685,14 @@ pub mod asset_request {
         Path(::prost::alloc::string::String),
         #[prost(message, tag = "3")]
         Redis(super::RedisParams),
+        #[prost(string, tag = "4")]
+        Store(::prost::alloc::string::String),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ServerReply {
-    #[prost(oneof = "server_reply::Kind", tags = "1, 2")]
+    #[prost(oneof = "server_reply::Kind", tags = "1, 2, 3")]
     pub kind: ::core::option::Option<server_reply::Kind>,
 }
 /// Nested message and enum types in `ServerReply`.
@@ -522,6 +704,8 @@ pub mod server_reply {
         Ok(super::ClientCallback),
         #[prost(message, tag = "2")]
         Error(super::GenericError),
+        #[prost(message, tag = "3")]
+        GuestFault(super::GuestFault),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -726,6 +910,14 @@ pub struct GenericError {
     #[prost(string, tag = "1")]
     pub reason: ::prost::alloc::string::String,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GuestFault {
+    #[prost(string, tag = "1")]
+    pub reason: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub partial_journal: ::prost::alloc::vec::Vec<u8>,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum ReceiptKind {
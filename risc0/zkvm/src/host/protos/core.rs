@@ -6,6 +6,8 @@ pub struct ProveInfo {
     pub receipt: ::core::option::Option<Receipt>,
     #[prost(message, optional, tag = "2")]
     pub stats: ::core::option::Option<SessionStats>,
+    #[prost(message, optional, tag = "3")]
+    pub composite: ::core::option::Option<CompositeReceipt>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -20,6 +22,18 @@ pub struct SessionStats {
     pub paging_cycles: u64,
     #[prost(uint64, tag = "5")]
     pub reserved_cycles: u64,
+    #[prost(message, optional, tag = "6")]
+    pub paging: ::core::option::Option<PagingStats>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PagingStats {
+    #[prost(uint64, tag = "1")]
+    pub page_in_count: u64,
+    #[prost(uint64, tag = "2")]
+    pub page_out_count: u64,
+    #[prost(uint64, tag = "3")]
+    pub unique_pages_touched: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -24,6 +24,7 @@ impl fmt::Debug for api::asset::Kind {
             Self::Inline(_) => f.debug_tuple("Inline").field(&"..").finish(),
             Self::Path(arg0) => f.debug_tuple("Path").field(arg0).finish(),
             Self::Redis(arg0) => f.debug_tuple("Redis").field(arg0).finish(),
+            Self::Store(arg0) => f.debug_tuple("Store").field(arg0).finish(),
         }
     }
 }
@@ -14,19 +14,40 @@
 
 //! Struct containing information about a prover's execution including the receipt.
 
-use crate::Receipt;
+use crate::{CompositeReceipt, Receipt};
 
 /// Information returned by the prover including receipt as well as other information useful for debugging
+#[non_exhaustive]
 pub struct ProveInfo {
     /// receipt from the computation
     pub receipt: Receipt,
 
     /// stats about cycle counts of the execution
     pub stats: SessionStats,
+
+    /// The composite receipt that a succinct or Groth16 [receipt](ProveInfo::receipt) was
+    /// compressed from.
+    ///
+    /// This is only kept when proving with
+    /// [ProverOpts::with_keep_composite](crate::ProverOpts::with_keep_composite), and is `None`
+    /// otherwise.
+    pub composite: Option<CompositeReceipt>,
+}
+
+impl ProveInfo {
+    /// Construct a [ProveInfo] from a receipt and the stats of the session it proves.
+    pub fn new(receipt: Receipt, stats: SessionStats) -> Self {
+        Self {
+            receipt,
+            stats,
+            composite: None,
+        }
+    }
 }
 
 /// Struct containing information about a prover's cycle count after running the guest program
-#[derive(Debug)]
+#[derive(Debug, Default)]
+#[non_exhaustive]
 pub struct SessionStats {
     /// Count of segments in this proof request
     pub segments: usize,
@@ -42,4 +63,99 @@ pub struct SessionStats {
 
     /// Reserved cycles run within guest
     pub reserved_cycles: u64,
+
+    /// Breakdown of the paging work done across all segments
+    pub paging: PagingStats,
+}
+
+/// Counts of the memory pages moved in and out of segments during execution.
+///
+/// Each segment pages in every page it touches (including the page table pages needed to verify
+/// them) and pages out every page it modified, so workloads that touch many pages, or touch the
+/// same pages across many segments, pay for it in paging cycles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PagingStats {
+    /// The number of page-in operations, summed over segments.
+    pub page_in_count: u64,
+
+    /// The number of page-out operations, summed over segments.
+    pub page_out_count: u64,
+
+    /// The number of distinct pages touched by the execution.
+    ///
+    /// For a single segment this equals `page_in_count`.
+    pub unique_pages_touched: u64,
+}
+
+impl SessionStats {
+    /// Estimate the cost of proving this session with the given [PricingModel].
+    ///
+    /// Combined with the stats returned by `ApiClient::execute_stats_only`, this allows a price to
+    /// be quoted before any proving work is done.
+    pub fn estimate_cost(&self, model: &impl PricingModel) -> u128 {
+        model.price(self)
+    }
+}
+
+/// A function from the cycle counts of a session to the cost of proving it.
+pub trait PricingModel {
+    /// The price of proving a session with the given stats, in the smallest unit of the currency
+    /// used by the model.
+    fn price(&self, stats: &SessionStats) -> u128;
+}
+
+/// A [PricingModel] charging a fixed price per user and paging cycle, plus a base price per
+/// session.
+#[derive(Clone, Debug, Default)]
+pub struct LinearPricing {
+    /// Price of each user cycle.
+    pub per_user_cycle: u128,
+
+    /// Price of each paging cycle.
+    pub per_paging_cycle: u128,
+
+    /// Price charged for every session, regardless of its cycle counts.
+    pub base: u128,
+}
+
+impl PricingModel for LinearPricing {
+    fn price(&self, stats: &SessionStats) -> u128 {
+        let user = self.per_user_cycle.saturating_mul(stats.user_cycles.into());
+        let paging = self
+            .per_paging_cycle
+            .saturating_mul(stats.paging_cycles.into());
+        self.base.saturating_add(user).saturating_add(paging)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LinearPricing, PricingModel, SessionStats};
+
+    #[test]
+    fn linear_pricing() {
+        let stats = SessionStats {
+            segments: 3,
+            total_cycles: 3 << 20,
+            user_cycles: 2_500_000,
+            paging_cycles: 400_000,
+            reserved_cycles: 245_728,
+            paging: Default::default(),
+        };
+        let model = LinearPricing {
+            per_user_cycle: 3,
+            per_paging_cycle: 5,
+            base: 1_000,
+        };
+        assert_eq!(model.price(&stats), 1_000 + 3 * 2_500_000 + 5 * 400_000);
+        assert_eq!(stats.estimate_cost(&model), 9_501_000);
+
+        // Prices saturate rather than overflow.
+        let model = LinearPricing {
+            per_user_cycle: u128::MAX,
+            ..Default::default()
+        };
+        assert_eq!(stats.estimate_cost(&model), u128::MAX);
+    }
 }
@@ -23,7 +23,7 @@ use std::{
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use risc0_binfmt::read_sha_halfs;
 use risc0_circuit_recursion::{
-    control_id::BN254_IDENTITY_CONTROL_ID,
+    control_id::{BN254_IDENTITY_CONTROL_ID, MIN_LIFT_PO2},
     prove::{DigestKind, RecursionReceipt},
     CircuitImpl,
 };
@@ -48,7 +48,7 @@ use crate::{
     },
     receipt_claim::{Assumption, MaybePruned, Merge},
     sha::Digestible,
-    ProverOpts, ReceiptClaim, Unknown,
+    ProverOpts, ReceiptClaim, Unknown, ZkrInfo,
 };
 
 use risc0_circuit_recursion::prove::Program;
@@ -58,11 +58,76 @@ pub const RECURSION_PO2: usize = 18;
 
 pub(crate) type ZkrRegistryEntry = Box<dyn Fn() -> Result<Program> + Send + 'static>;
 
-pub(crate) type ZkrRegistry = BTreeMap<Digest, ZkrRegistryEntry>;
+/// A registered ZKR program, along with the details reported by
+/// [ApiClient::supported_zkrs](crate::ApiClient::supported_zkrs).
+pub(crate) struct RegisteredZkr {
+    name: Option<String>,
+    input_words: Option<u32>,
+    get_program: ZkrRegistryEntry,
+}
+
+pub(crate) type ZkrRegistry = BTreeMap<Digest, RegisteredZkr>;
 
 /// A registry to look up programs by control ID.
 pub(crate) static ZKR_REGISTRY: Mutex<ZkrRegistry> = Mutex::new(BTreeMap::new());
 
+/// Programs loaded ahead of time by [warm_up], by control ID, for each hash function and maximum
+/// segment po2 that has been warmed up.
+static SETUP_CACHE: Mutex<BTreeMap<(String, usize), BTreeMap<Digest, Program>>> =
+    Mutex::new(BTreeMap::new());
+
+/// The number of programs taken from [SETUP_CACHE] rather than loaded.
+#[cfg(test)]
+pub(crate) static SETUP_CACHE_HITS: std::sync::atomic::AtomicUsize =
+    std::sync::atomic::AtomicUsize::new(0);
+
+/// Load the recursion programs used to prove with `opts`, so that later proofs take them from a
+/// cache rather than loading them again.
+///
+/// This covers the lift programs for each segment po2 up to `opts.max_segment_po2`, the join,
+/// resolve and identity programs, and every registered ZKR. Warming up the same hash function and
+/// maximum po2 again does nothing.
+pub(crate) fn warm_up(opts: &ProverOpts) -> Result<()> {
+    let key = (opts.hashfn.clone(), opts.max_segment_po2);
+    if SETUP_CACHE.lock().unwrap().contains_key(&key) {
+        return Ok(());
+    }
+
+    let mut programs = BTreeMap::new();
+    let lifts = (MIN_LIFT_PO2..=opts.max_segment_po2).map(|po2| zkr::lift(po2, &opts.hashfn));
+    let others = [
+        zkr::join(&opts.hashfn),
+        zkr::resolve(&opts.hashfn),
+        zkr::identity(&opts.hashfn),
+    ];
+    for zkr in lifts.chain(others) {
+        let (program, control_id) = zkr?;
+        programs.insert(control_id, program);
+    }
+    for (control_id, zkr) in ZKR_REGISTRY.lock().unwrap().iter() {
+        let program = (zkr.get_program)()
+            .with_context(|| format!("failed to load registered ZKR {control_id}"))?;
+        programs.insert(*control_id, program);
+    }
+    SETUP_CACHE.lock().unwrap().insert(key, programs);
+    Ok(())
+}
+
+/// Returns the program for `control_id` if it was loaded by [warm_up].
+pub(crate) fn cached_program(control_id: &Digest) -> Option<Program> {
+    let program = SETUP_CACHE
+        .lock()
+        .unwrap()
+        .values()
+        .find_map(|programs| programs.get(control_id))
+        .cloned();
+    #[cfg(test)]
+    if program.is_some() {
+        SETUP_CACHE_HITS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+    program
+}
+
 /// Run the lift program to transform an rv32im segment receipt into a recursion receipt.
 ///
 /// The lift program verifies the rv32im circuit STARK proof inside the recursion circuit,
@@ -298,16 +363,71 @@ pub fn register_zkr(
     control_id: &Digest,
     get_program_fn: impl Fn() -> Result<Program> + Send + 'static,
 ) -> Option<ZkrRegistryEntry> {
+    insert_zkr(control_id, None, None, Box::new(get_program_fn))
+}
+
+/// Registers a function to retrieve a recursion program (zkr) based on a control id, along with a
+/// human readable name and the number of words of input the program takes.
+///
+/// The name and input size are reported to clients by
+/// [ApiClient::supported_zkrs](crate::ApiClient::supported_zkrs).
+pub fn register_zkr_with_info(
+    control_id: &Digest,
+    name: &str,
+    input_words: u32,
+    get_program_fn: impl Fn() -> Result<Program> + Send + 'static,
+) -> Option<ZkrRegistryEntry> {
+    insert_zkr(
+        control_id,
+        Some(name.to_string()),
+        Some(input_words),
+        Box::new(get_program_fn),
+    )
+}
+
+fn insert_zkr(
+    control_id: &Digest,
+    name: Option<String>,
+    input_words: Option<u32>,
+    get_program: ZkrRegistryEntry,
+) -> Option<ZkrRegistryEntry> {
+    // A program cached for the previous registration is no longer valid.
+    for programs in SETUP_CACHE.lock().unwrap().values_mut() {
+        programs.remove(control_id);
+    }
     let mut registry = ZKR_REGISTRY.lock().unwrap();
-    registry.insert(*control_id, Box::new(get_program_fn))
+    let zkr = RegisteredZkr {
+        name,
+        input_words,
+        get_program,
+    };
+    registry
+        .insert(*control_id, zkr)
+        .map(|prev| prev.get_program)
+}
+
+/// Returns the details of each registered ZKR program, ordered by control id.
+pub(crate) fn registered_zkrs() -> Vec<ZkrInfo> {
+    let registry = ZKR_REGISTRY.lock().unwrap();
+    registry
+        .iter()
+        .map(|(control_id, zkr)| ZkrInfo {
+            control_id: *control_id,
+            name: zkr.name.clone(),
+            input_words: zkr.input_words,
+        })
+        .collect()
 }
 
 /// Returns a registered ZKR program, or an error if not found.
 pub fn get_registered_zkr(control_id: &Digest) -> Result<Program> {
+    if let Some(program) = cached_program(control_id) {
+        return Ok(program);
+    }
     let registry = ZKR_REGISTRY.lock().unwrap();
     registry
         .get(control_id)
-        .map(|f| f())
+        .map(|zkr| (zkr.get_program)())
         .unwrap_or_else(|| bail!("Control id {control_id} unregistered"))
 }
 
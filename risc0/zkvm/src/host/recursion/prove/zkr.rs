@@ -28,14 +28,16 @@ fn get_zkr(name: &str, hashfn: &str) -> Result<(Program, Digest)> {
         _ => bail!("no control id found for {name} with {hashfn}"),
     };
 
-    Ok((
-        risc0_circuit_recursion::prove::zkr::get_zkr(name, RECURSION_PO2)?,
-        control_ids
-            .iter()
-            .copied()
-            .find_map(|(n, id)| (n == name).then_some(id))
-            .ok_or(anyhow!("failed to find {name} in the list of control IDs"))?,
-    ))
+    let control_id = control_ids
+        .iter()
+        .copied()
+        .find_map(|(n, id)| (n == name).then_some(id))
+        .ok_or(anyhow!("failed to find {name} in the list of control IDs"))?;
+    let program = match super::cached_program(&control_id) {
+        Some(program) => program,
+        None => risc0_circuit_recursion::prove::zkr::get_zkr(name, RECURSION_PO2)?,
+    };
+    Ok((program, control_id))
 }
 
 pub fn test_recursion_circuit(hashfn: &str) -> Result<(Program, Digest)> {
@@ -382,6 +382,37 @@ fn test_po2_16() {
     receipt.verify_integrity_with_context(&ctx).unwrap();
 }
 
+#[test]
+fn verify_rejects_unexpected_control_root() {
+    use risc0_zkp::{core::hash::poseidon2::Poseidon2HashSuite, verify::VerificationError};
+
+    let po2 = 16;
+    let suite = Poseidon2HashSuite::new_suite();
+    let program =
+        risc0_circuit_recursion::prove::zkr::get_zkr("test_recursion_circuit.zkr", po2).unwrap();
+    let control_id = program.compute_control_id(suite.clone());
+    let control_tree = MerkleGroup::new(vec![control_id]).unwrap();
+    let control_root = control_tree.calc_root(suite.hashfn.as_ref());
+    let digest = digest!("00000000000000de00000000000000ad00000000000000be00000000000000ef");
+    let receipt = super::test_zkr(&control_root, &digest, po2).unwrap();
+
+    // A verifier pinned to the standard control root must reject a receipt whose control ID is
+    // outside of it, even though the receipt is otherwise valid.
+    let ctx = VerifierContext::empty()
+        .with_suites(VerifierContext::default_hash_suites())
+        .with_succinct_verifier_parameters(SuccinctReceiptVerifierParameters {
+            control_root: ALLOWED_CONTROL_ROOT,
+            inner_control_root: Some(control_root),
+            proof_system_info: PROOF_SYSTEM_INFO,
+            circuit_info: CircuitImpl::CIRCUIT_INFO,
+        });
+    let err = receipt.verify_integrity_with_context(&ctx).unwrap_err();
+    assert!(
+        matches!(err, VerificationError::ControlVerificationError { .. }),
+        "unexpected error: {err}"
+    );
+}
+
 #[test]
 fn stable_root() {
     // This tests that none of the control IDs have changed unexpectedly.
@@ -12,25 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{cell::RefCell, io::Write, rc::Rc, sync::Arc, time::Instant};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{Read, Write},
+    rc::Rc,
+    sync::Arc,
+    time::Instant,
+};
 
-use anyhow::{Context as _, Result};
+use anyhow::{ensure, Context as _, Result};
 use risc0_binfmt::{MemoryImage, Program};
 use risc0_circuit_rv32im::prove::emu::{
     addr::ByteAddr,
     exec::{
-        Executor, Syscall as NewSyscall, SyscallContext as NewSyscallContext,
-        DEFAULT_SEGMENT_LIMIT_PO2,
+        CycleLimitExceeded, Executor, ExecutorCheckpoint, Syscall as NewSyscall,
+        SyscallContext as NewSyscallContext, DEFAULT_SEGMENT_LIMIT_PO2,
     },
 };
 use risc0_core::scope;
-use risc0_zkp::core::digest::Digest;
+use risc0_zkp::{core::digest::Digest, MAX_CYCLES_PO2, MIN_CYCLES_PO2};
 use risc0_zkvm_platform::{fileno, memory::GUEST_MAX_MEM, PAGE_SIZE};
 use tempfile::tempdir;
 
 use crate::{
-    host::client::env::SegmentPath, Assumptions, ExecutorEnv, FileSegmentRef, Output, Segment,
-    SegmentRef, Session,
+    host::client::env::{PartialSegment, SegmentDecision, SegmentPath},
+    Assumptions, ExecutorEnv, FileSegmentRef, Output, PagingStats, Segment, SegmentRef, Session,
+    SessionSnapshot,
 };
 
 use super::{
@@ -38,6 +46,36 @@ use super::{
     syscall::{SyscallContext, SyscallTable},
 };
 
+/// The error returned when execution stops before the guest exits because of the guest, e.g.
+/// because it panicked or accessed invalid memory.
+///
+/// Failures of the host, such as I/O errors, errors from syscall handlers or segment callbacks, and
+/// running out of cycles, are returned as they are.
+///
+/// No [Session] is produced for such an execution, since it cannot be proven. The journal bytes
+/// committed by the guest before the fault are kept here to help debug the failure.
+#[derive(Debug)]
+pub struct GuestFault {
+    /// The error which stopped execution.
+    pub error: anyhow::Error,
+
+    /// The bytes committed to the journal before the fault.
+    pub partial_journal: Vec<u8>,
+}
+
+impl fmt::Display for GuestFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Report the fault as the underlying error would be, so that its message is unchanged.
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for GuestFault {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.error)
+    }
+}
+
 // The Executor provides an implementation for the execution phase.
 ///
 /// The proving phase uses an execution trace generated by the Executor.
@@ -46,6 +84,9 @@ pub struct ExecutorImpl<'a> {
     image: MemoryImage,
     pub(crate) syscall_table: SyscallTable<'a>,
     profiler: Option<Rc<RefCell<Profiler>>>,
+    snapshot: Option<SessionSnapshot>,
+    // Writer registered for the journal by the env, which is passed each commit.
+    journal_callback: Option<Rc<RefCell<dyn Write + 'a>>>,
 }
 
 impl<'a> ExecutorImpl<'a> {
@@ -91,17 +132,33 @@ impl<'a> ExecutorImpl<'a> {
         Self::with_details(env, image, profiler)
     }
 
+    /// Construct a new [ExecutorImpl] that resumes execution from a
+    /// [SessionSnapshot].
+    ///
+    /// The [ExecutorEnv] should provide the same input as the execution the
+    /// snapshot was taken from; input that was already consumed before the
+    /// snapshot is skipped.
+    pub fn from_snapshot(env: ExecutorEnv<'a>, snapshot: SessionSnapshot) -> Result<Self> {
+        let image = snapshot.checkpoint.image.clone();
+        let mut exec = Self::with_details(env, image, None)?;
+        exec.snapshot = Some(snapshot);
+        Ok(exec)
+    }
+
     fn with_details(
         env: ExecutorEnv<'a>,
         image: MemoryImage,
         profiler: Option<Rc<RefCell<Profiler>>>,
     ) -> Result<Self> {
         let syscall_table = SyscallTable::from_env(&env);
+        let journal_callback = env.posix_io.borrow().get_writer(fileno::JOURNAL).ok();
         Ok(Self {
             env,
             image,
             syscall_table,
             profiler,
+            snapshot: None,
+            journal_callback,
         })
     }
 
@@ -118,80 +175,168 @@ impl<'a> ExecutorImpl<'a> {
 
     /// Run the executor until [crate::ExitCode::Halted] or
     /// [crate::ExitCode::Paused] is reached, producing a [Session] as a result.
+    ///
+    /// If the guest faults, a [GuestFault] holding the journal committed so far is returned.
+    /// Errors returned by `callback`, and [CycleLimitExceeded] when the guest runs past the
+    /// [session limit](crate::ExecutorEnvBuilder::session_limit), are passed through as they are.
     pub fn run_with_callback<F>(&mut self, mut callback: F) -> Result<Session>
     where
         F: FnMut(Segment) -> Result<Box<dyn SegmentRef>>,
     {
         scope!("execute");
 
-        let journal = Journal::default();
+        let journal = Journal {
+            buf: Default::default(),
+            callback: self.journal_callback.clone(),
+        };
         self.env
             .posix_io
             .borrow_mut()
             .with_write_fd(fileno::JOURNAL, journal.clone());
 
-        let segment_limit_po2 = self
-            .env
-            .segment_limit_po2
-            .unwrap_or(DEFAULT_SEGMENT_LIMIT_PO2 as u32) as usize;
+        let segment_limit_po2 = match self.env.segment_po2_hint {
+            Some(po2) => {
+                ensure!(
+                    (MIN_CYCLES_PO2..=MAX_CYCLES_PO2).contains(&(po2 as usize)),
+                    "segment po2 hint {po2} is outside of [{MIN_CYCLES_PO2}, {MAX_CYCLES_PO2}]"
+                );
+                match self.env.segment_limit_po2 {
+                    Some(limit) => po2.min(limit),
+                    None => po2,
+                }
+            }
+            None => self
+                .env
+                .segment_limit_po2
+                .unwrap_or(DEFAULT_SEGMENT_LIMIT_PO2 as u32),
+        } as usize;
 
         let mut refs = Vec::new();
-        let mut exec = Executor::new(
-            self.image.clone(),
-            self,
-            self.env.input_digest,
-            self.env.trace.clone(),
-        );
+        let mut exec = match self.snapshot.take() {
+            Some(snapshot) => {
+                let checkpoint = self.restore(snapshot, &journal)?;
+                Executor::resume(
+                    checkpoint,
+                    self,
+                    self.env.input_digest,
+                    self.env.trace.clone(),
+                )
+            }
+            None => Executor::new(
+                self.image.clone(),
+                self,
+                self.env.input_digest,
+                self.env.trace.clone(),
+            ),
+        };
+
+        if self.env.segment_po2_hint.is_some() {
+            exec = exec.with_min_segment_po2(segment_limit_po2);
+        }
+        if let Some(po2) = self.env.coalesce_po2 {
+            exec = exec.with_coalesce_po2(po2 as usize);
+        }
+        if let Some(policy) = self.env.segment_policy.clone() {
+            exec = exec.with_split_policy(move |point| {
+                let segment = PartialSegment {
+                    index: point.index as u32,
+                    po2: point.po2 as u32,
+                    cycles: point.cycles as u64,
+                    session_user_cycles: point.user_cycles,
+                };
+                policy(&segment) == SegmentDecision::Split
+            });
+        }
 
         let start_time = Instant::now();
-        let result = exec.run(segment_limit_po2, self.env.session_limit, |inner| {
-            let output = inner
-                .exit_code
-                .expects_output()
-                .then(|| -> Option<Result<_>> {
-                    inner
-                        .output_digest
-                        .and_then(|digest| {
-                            (digest != Digest::ZERO).then(|| journal.buf.borrow().clone())
-                        })
-                        .map(|journal| {
-                            Ok(Output {
-                                journal: journal.into(),
-                                assumptions: Assumptions(
-                                    self.syscall_table
-                                        .assumptions_used
-                                        .borrow()
-                                        .iter()
-                                        .map(|(a, _)| a.clone().into())
-                                        .collect::<Vec<_>>(),
-                                )
-                                .into(),
+        let mut callback_failed = false;
+        let checkpoint_interval = self.env.checkpoint_interval;
+        let session_limit = self.env.session_limit;
+        let result = exec.run_with_checkpoints(
+            segment_limit_po2,
+            session_limit,
+            checkpoint_interval,
+            |inner, checkpoint| {
+                let output = inner
+                    .exit_code
+                    .expects_output()
+                    .then(|| -> Option<Result<_>> {
+                        inner
+                            .output_digest
+                            .and_then(|digest| {
+                                (digest != Digest::ZERO).then(|| journal.buf.borrow().clone())
                             })
-                        })
-                })
-                .flatten()
-                .transpose()?;
-
-            let segment = Segment {
-                index: inner.index as u32,
-                inner,
-                output,
-            };
-            let segment_ref = callback(segment)?;
-            refs.push(segment_ref);
-            Ok(())
-        })?;
+                            .map(|journal| {
+                                Ok(Output {
+                                    journal: journal.into(),
+                                    assumptions: Assumptions(
+                                        self.syscall_table
+                                            .assumptions_used
+                                            .borrow()
+                                            .iter()
+                                            .map(|(a, _)| a.clone().into())
+                                            .collect::<Vec<_>>(),
+                                    )
+                                    .into(),
+                                })
+                            })
+                    })
+                    .flatten()
+                    .transpose()?;
+
+                let snapshot = checkpoint.map(|checkpoint| {
+                    Box::new(SessionSnapshot {
+                        checkpoint,
+                        journal: journal.buf.borrow().clone(),
+                        read_offsets: self.syscall_table.read_offsets.borrow().clone(),
+                        assumptions_used: self.syscall_table.assumptions_used.borrow().clone(),
+                        pending_zkrs: self.syscall_table.pending_zkrs.borrow().clone(),
+                        pending_keccaks: self.syscall_table.pending_keccaks.borrow().clone(),
+                    })
+                });
+
+                let segment = Segment {
+                    index: inner.index as u32,
+                    inner,
+                    output,
+                    snapshot,
+                };
+                let segment_ref = callback(segment).inspect_err(|_| callback_failed = true)?;
+                refs.push(segment_ref);
+                Ok(())
+            },
+        );
+        let result = match result {
+            Ok(result) => result,
+            // Failures of the host and running out of cycles are not faults of the guest, so they
+            // are reported as they are.
+            Err(err)
+                if callback_failed
+                    || self.syscall_table.host_failed.get()
+                    || err.is::<CycleLimitExceeded>() =>
+            {
+                return Err(err)
+            }
+            Err(error) => {
+                return Err(GuestFault {
+                    error,
+                    partial_journal: journal.buf.take(),
+                }
+                .into())
+            }
+        };
         let elapsed = start_time.elapsed();
 
         // Set the session_journal to the committed data iff the guest set a non-zero output.
+        let partial_journal = journal.buf.take();
         let session_journal = result
             .output_digest
-            .and_then(|digest| (digest != Digest::ZERO).then(|| journal.buf.take()));
+            .and_then(|digest| (digest != Digest::ZERO).then(|| partial_journal.clone()));
         if !result.exit_code.expects_output() && session_journal.is_some() {
             tracing::debug!(
                 "dropping non-empty journal due to exit code {:?}: 0x{}",
                 result.exit_code,
-                hex::encode(journal.buf.borrow().as_slice())
+                hex::encode(partial_journal.as_slice())
             );
         };
 
@@ -213,6 +358,7 @@ impl<'a> ExecutorImpl<'a> {
             refs,
             self.env.input_digest.unwrap_or_default(),
             session_journal,
+            partial_journal,
             result.exit_code,
             result.post_image,
             assumptions,
@@ -220,6 +366,11 @@ impl<'a> ExecutorImpl<'a> {
             result.paging_cycles,
             result.reserved_cycles,
             result.total_cycles,
+            PagingStats {
+                page_in_count: result.page_in_count,
+                page_out_count: result.page_out_count,
+                unique_pages_touched: result.unique_pages_touched,
+            },
             result.pre_state,
             result.post_state,
             pending_zkrs,
@@ -233,6 +384,26 @@ impl<'a> ExecutorImpl<'a> {
 
         Ok(session)
     }
+
+    /// Restore the host-side state captured in a [SessionSnapshot], returning
+    /// the checkpoint to resume the guest from.
+    fn restore(&self, snapshot: SessionSnapshot, journal: &Journal) -> Result<ExecutorCheckpoint> {
+        for (fd, offset) in snapshot.read_offsets.iter() {
+            let reader = self.syscall_table.posix_io.borrow().get_reader(*fd)?;
+            let mut reader = reader.borrow_mut();
+            let skipped = std::io::copy(&mut (&mut *reader).take(*offset), &mut std::io::sink())?;
+            ensure!(
+                skipped == *offset,
+                "input on fd {fd} ended before the snapshot offset ({skipped} < {offset})"
+            );
+        }
+        *self.syscall_table.read_offsets.borrow_mut() = snapshot.read_offsets;
+        *self.syscall_table.assumptions_used.borrow_mut() = snapshot.assumptions_used;
+        *self.syscall_table.pending_zkrs.borrow_mut() = snapshot.pending_zkrs;
+        *self.syscall_table.pending_keccaks.borrow_mut() = snapshot.pending_keccaks;
+        *journal.buf.borrow_mut() = snapshot.journal;
+        Ok(snapshot.checkpoint)
+    }
 }
 
 struct ContextAdapter<'a, 'b> {
@@ -287,16 +458,25 @@ impl<'a> NewSyscall for ExecutorImpl<'a> {
             .borrow_mut()
             .syscall(syscall, &mut ctx, into_guest)
     }
+
+    fn take_segment_flush(&self) -> bool {
+        self.syscall_table.flush_segment.take()
+    }
 }
 
-// Capture the journal output in a buffer that we can access afterwards.
-#[derive(Clone, Default)]
-struct Journal {
+// Capture the journal output in a buffer that we can access afterwards, passing it on to the
+// journal callback if there is one.
+#[derive(Clone)]
+struct Journal<'a> {
     buf: Rc<RefCell<Vec<u8>>>,
+    callback: Option<Rc<RefCell<dyn Write + 'a>>>,
 }
 
-impl Write for Journal {
+impl Write for Journal<'_> {
     fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        if let Some(callback) = &self.callback {
+            callback.borrow_mut().write_all(bytes)?;
+        }
         self.buf.borrow_mut().write(bytes)
     }
 
@@ -16,6 +16,7 @@
 
 mod args;
 mod cycle_count;
+mod flush_segment;
 mod fork;
 mod getenv;
 mod keccak;
@@ -29,7 +30,11 @@ mod random;
 mod slice_io;
 mod verify;
 
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
 
 use anyhow::{anyhow, Result};
 use enum_map::{Enum, EnumMap};
@@ -37,9 +42,9 @@ use risc0_circuit_rv32im::prove::emu::addr::ByteAddr;
 use risc0_zkp::core::digest::Digest;
 use risc0_zkvm_platform::syscall::{
     nr::{
-        SYS_ARGC, SYS_ARGV, SYS_CYCLE_COUNT, SYS_FORK, SYS_GETENV, SYS_KECCAK, SYS_LOG, SYS_PANIC,
-        SYS_PIPE, SYS_PROVE_KECCAK, SYS_PROVE_ZKR, SYS_RANDOM, SYS_READ, SYS_VERIFY_INTEGRITY,
-        SYS_WRITE,
+        SYS_ARGC, SYS_ARGV, SYS_CYCLE_COUNT, SYS_FLUSH_SEGMENT, SYS_FORK, SYS_GETENV, SYS_KECCAK,
+        SYS_LOG, SYS_PANIC, SYS_PIPE, SYS_PROVE_KECCAK, SYS_PROVE_ZKR, SYS_RANDOM, SYS_READ,
+        SYS_VERIFY_INTEGRITY, SYS_WRITE,
     },
     SyscallName, DIGEST_BYTES,
 };
@@ -53,10 +58,10 @@ use crate::{
 };
 
 use self::{
-    args::SysArgs, cycle_count::SysCycleCount, fork::SysFork, getenv::SysGetenv, keccak::SysKeccak,
-    log::SysLog, panic::SysPanic, pipe::SysPipe, posix_io::SysRead, posix_io::SysWrite,
-    prove_keccak::SysProveKeccak, prove_zkr::SysProveZkr, random::SysRandom, slice_io::SysSliceIo,
-    verify::SysVerify,
+    args::SysArgs, cycle_count::SysCycleCount, flush_segment::SysFlushSegment, fork::SysFork,
+    getenv::SysGetenv, keccak::SysKeccak, log::SysLog, panic::SysPanic, pipe::SysPipe,
+    posix_io::SysRead, posix_io::SysWrite, prove_keccak::SysProveKeccak, prove_zkr::SysProveZkr,
+    random::SysRandom, slice_io::SysSliceIo, verify::SysVerify,
 };
 
 /// A host-side implementation of a system call.
@@ -132,12 +137,15 @@ pub(crate) struct SyscallMetric {
 pub(crate) struct SyscallTable<'a> {
     pub(crate) inner: HashMap<String, Rc<RefCell<dyn Syscall + 'a>>>,
     pub(crate) posix_io: Rc<RefCell<PosixIo<'a>>>,
+    pub(crate) read_offsets: Rc<RefCell<BTreeMap<u32, u64>>>,
     pub(crate) assumptions: Rc<RefCell<AssumptionReceipts>>,
     pub(crate) assumptions_used: Rc<RefCell<AssumptionUsage>>,
     pub(crate) coprocessor: Option<CoprocessorCallbackRef<'a>>,
     pub(crate) pending_zkrs: Rc<RefCell<Vec<ProveZkrRequest>>>,
     pub(crate) pending_keccaks: Rc<RefCell<Vec<ProveKeccakRequest>>>,
     pub(crate) metrics: Rc<RefCell<EnumMap<SyscallKind, SyscallMetric>>>,
+    pub(crate) flush_segment: Rc<Cell<bool>>,
+    pub(crate) host_failed: Rc<Cell<bool>>,
 }
 
 impl<'a> SyscallTable<'a> {
@@ -145,12 +153,15 @@ impl<'a> SyscallTable<'a> {
         Self {
             inner: Default::default(),
             posix_io: env.posix_io.clone(),
+            read_offsets: Default::default(),
             assumptions: env.assumptions.clone(),
             assumptions_used: Default::default(),
             coprocessor: env.coprocessor.clone(),
             pending_zkrs: Default::default(),
             pending_keccaks: Default::default(),
             metrics: Default::default(),
+            flush_segment: Default::default(),
+            host_failed: Default::default(),
         }
     }
 
@@ -160,6 +171,7 @@ impl<'a> SyscallTable<'a> {
         this.with_syscall(SYS_ARGC, SysArgs(env.args.clone()))
             .with_syscall(SYS_ARGV, SysArgs(env.args.clone()))
             .with_syscall(SYS_CYCLE_COUNT, SysCycleCount)
+            .with_syscall(SYS_FLUSH_SEGMENT, SysFlushSegment)
             .with_syscall(SYS_FORK, SysFork)
             .with_syscall(SYS_GETENV, SysGetenv(env.env_vars.clone()))
             .with_syscall(SYS_KECCAK, SysKeccak)
@@ -191,6 +203,12 @@ impl<'a> SyscallTable<'a> {
         self
     }
 
+    /// Records a failure of the host while serving a syscall, e.g. an I/O error, so that it is
+    /// not reported as a fault of the guest.
+    pub(crate) fn host_result<T>(&self, result: Result<T>) -> Result<T> {
+        result.inspect_err(|_| self.host_failed.set(true))
+    }
+
     pub(crate) fn get_syscall(&self, name: &str) -> Option<&Rc<RefCell<(dyn Syscall + 'a)>>> {
         self.inner.get(name)
     }
@@ -50,7 +50,9 @@ impl Syscall for SysRead {
         let read_all = |mut buf: &mut [u8]| -> Result<usize> {
             let mut tot_nread = 0;
             while !buf.is_empty() {
-                let nread = reader.borrow_mut().read(buf)?;
+                let nread = ctx
+                    .syscall_table()
+                    .host_result(reader.borrow_mut().read(buf).map_err(Into::into))?;
                 if nread == 0 {
                     break;
                 }
@@ -77,6 +79,11 @@ impl Syscall for SysRead {
         let mut to_guest_end: [u8; WORD_SIZE] = [0; WORD_SIZE];
         let nread_end = read_all(&mut to_guest_end[0..unaligned_end])?;
         let nread_total = nread_main + nread_end;
+        *ctx.syscall_table()
+            .read_offsets
+            .borrow_mut()
+            .entry(fd)
+            .or_default() += nread_total as u64;
 
         let metric = &mut ctx.syscall_table().metrics.borrow_mut()[SyscallKind::Read];
         metric.count += 1;
@@ -103,7 +110,12 @@ impl Syscall for SysWrite {
 
         tracing::trace!("sys_write(fd: {fd}, bytes: {buf_len})");
 
-        writer.borrow_mut().write_all(from_guest_bytes.as_slice())?;
+        ctx.syscall_table().host_result(
+            writer
+                .borrow_mut()
+                .write_all(from_guest_bytes.as_slice())
+                .map_err(Into::into),
+        )?;
 
         let metric = &mut ctx.syscall_table().metrics.borrow_mut()[SyscallKind::Write];
         metric.count += 1;
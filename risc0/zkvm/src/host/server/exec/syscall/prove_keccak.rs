@@ -39,6 +39,16 @@ impl Syscall for SysProveKeccak {
         let input_len = ctx.load_register(REG_A7);
         let input: Vec<u8> = ctx.load_region(input_ptr, input_len * WORD_SIZE as u32)?;
 
+        let assumption = ctx
+            .syscall_table()
+            .assumptions
+            .borrow()
+            .find_assumption(&claim, &control_root)?;
+        if assumption.is_some() {
+            // This assumption is already known, no need to create another proof.
+            return Ok((0, 0));
+        }
+
         let proof_request = ProveKeccakRequest {
             claim_digest: claim,
             po2,
@@ -47,7 +57,8 @@ impl Syscall for SysProveKeccak {
         };
 
         if let Some(coprocessor) = &ctx.syscall_table().coprocessor {
-            coprocessor.borrow_mut().prove_keccak(proof_request)?;
+            ctx.syscall_table()
+                .host_result(coprocessor.borrow_mut().prove_keccak(proof_request))?;
         } else {
             ctx.syscall_table()
                 .pending_keccaks
@@ -62,7 +62,8 @@ impl Syscall for SysProveZkr {
         };
 
         if let Some(coprocessor) = &ctx.syscall_table().coprocessor {
-            coprocessor.borrow_mut().prove_zkr(proof_request)?;
+            ctx.syscall_table()
+                .host_result(coprocessor.borrow_mut().prove_zkr(proof_request))?;
         } else {
             get_registered_zkr(&control_id)?;
             ctx.syscall_table()
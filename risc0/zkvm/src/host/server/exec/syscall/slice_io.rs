@@ -65,7 +65,9 @@ impl<'a> Syscall for SysSliceIo<'a> {
                 // and save what it returns.
                 assert_eq!(to_guest.len(), 0);
                 let mut handler = self.handler.borrow_mut();
-                let result = handler.handle_io(syscall, from_guest.into())?;
+                let result = ctx
+                    .syscall_table()
+                    .host_result(handler.handle_io(syscall, from_guest.into()))?;
                 let len = result.len() as u32;
                 *stored_result = Some(result);
                 (len, 0)
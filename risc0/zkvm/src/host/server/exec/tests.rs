@@ -22,13 +22,17 @@ use std::{
 use anyhow::Result;
 use bytes::Bytes;
 use risc0_binfmt::{MemoryImage, Program};
-use risc0_zkp::digest;
+use risc0_zkp::{digest, MAX_CYCLES_PO2};
 use risc0_zkvm_methods::{
     multi_test::{MultiTestSpec, SYS_MULTI_TEST, SYS_MULTI_TEST_WORDS},
     BLST_ELF, HEAP_ELF, HELLO_COMMIT_ELF, MULTI_TEST_ELF, RAND_ELF, SLICE_IO_ELF, STANDARD_LIB_ELF,
     SYS_ARGS_ELF, SYS_ENV_ELF, ZKVM_527_ELF,
 };
-use risc0_zkvm_platform::{fileno, syscall::nr::SYS_RANDOM, PAGE_SIZE, WORD_SIZE};
+use risc0_zkvm_platform::{
+    fileno,
+    syscall::nr::{SYS_RANDOM, SYS_WRITE},
+    PAGE_SIZE, WORD_SIZE,
+};
 use sha2::{Digest as _, Sha256};
 use test_log::test;
 
@@ -42,7 +46,8 @@ use crate::{
     },
     serde::to_vec,
     sha::{Digest, Digestible},
-    ExecutorEnv, ExecutorImpl, ExitCode,
+    CycleLimitExceeded, ExecutorEnv, ExecutorImpl, ExitCode, GuestFault, NullSegmentRef,
+    SegmentDecision, SessionSnapshot, TraceEvent,
 };
 
 fn run_test(spec: MultiTestSpec) {
@@ -112,6 +117,62 @@ fn basic() {
     assert_eq!(segment.index, 0);
 }
 
+#[test]
+fn trace_events() {
+    let entry = 0x4000;
+    let insns = [
+        0x1234b137, // lui x2, 0x1234b000
+        0xf387e1b7, // lui x3, 0xf387e000
+        0x003100b3, // add x1, x2, x3
+        0x000055b7, // lui x11, 0x5
+        0x00000073, // ecall(halt)
+    ];
+    let image: BTreeMap<_, _> = (entry..).step_by(WORD_SIZE).zip(insns).collect();
+    let program = Program { entry, image };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+
+    let mut full = Vec::new();
+    let mut sampled = Vec::new();
+    {
+        let pc = |events: &mut Vec<(u64, u32, u32)>, event| {
+            if let TraceEvent::InstructionStart { cycle, pc, insn } = event {
+                events.push((cycle, pc, insn));
+            }
+            Ok(())
+        };
+        let env = ExecutorEnv::builder()
+            .trace_callback(|event| pc(&mut full, event))
+            .trace_callback_sampled(2, |event| pc(&mut sampled, event))
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::new(env, image).unwrap().run().unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+    }
+
+    let pcs = |events: &[(u64, u32, u32)]| events.iter().map(|event| event.1).collect::<Vec<_>>();
+    assert_eq!(pcs(&full), [0x4000, 0x4004, 0x4008, 0x400c, 0x4010]);
+    assert!(full.iter().zip(insns).all(|(event, insn)| event.2 == insn));
+    assert_eq!(pcs(&sampled), [0x4000, 0x4008, 0x4010]);
+}
+
+#[test]
+fn cycle_limit_exceeded() {
+    let program = Program {
+        entry: 0x4000,
+        image: BTreeMap::from([(0x4000, 0x0000006f)]), // j 0x4000
+    };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(14)
+        .session_limit(Some(100_000))
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::new(env, image).unwrap().run().err().unwrap();
+    let err = err.downcast::<CycleLimitExceeded>().unwrap();
+    assert_eq!(err.limit, 100_000);
+    assert!((100_000..100_010).contains(&err.cycles), "{err}");
+}
+
 #[test]
 fn system_split() {
     let entry = 0x4000;
@@ -157,6 +218,340 @@ fn system_split() {
     assert_eq!(segments[1].index, 1);
 }
 
+/// A program that counts to `0x49000` in a loop, taking about 600K cycles.
+fn counting_loop() -> MemoryImage {
+    let entry = 0x4000;
+    let program = Program {
+        entry,
+        image: [
+            0x000497b7, // lui a5, 0x49
+            0x00000713, // li a4, 0
+            0x00170713, // loop: addi a4, a4, 1
+            0xfef74ee3, // blt a4, a5, loop
+            0x000055b7, // lui a1, 0x00005000
+            0xc0058593, // addi a1, a1, -0x400
+            0x00000073, // ecall(halt)
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(idx, insn)| (entry + (idx * WORD_SIZE) as u32, insn))
+        .collect(),
+    };
+    MemoryImage::new(&program, PAGE_SIZE as u32).unwrap()
+}
+
+#[test]
+fn segment_po2_hint() {
+    let env = ExecutorEnv::builder().segment_po2_hint(18).build().unwrap();
+    let mut po2s = Vec::new();
+    let session = ExecutorImpl::new(env, counting_loop())
+        .unwrap()
+        .run_with_callback(|segment| {
+            po2s.push(segment.po2());
+            Ok(Box::new(NullSegmentRef))
+        })
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert!(po2s.len() > 1);
+    assert!(po2s.iter().all(|&po2| po2 == 18), "{po2s:?}");
+}
+
+#[test]
+fn segment_po2_hint_clamped_to_limit() {
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(16)
+        .segment_po2_hint(18)
+        .build()
+        .unwrap();
+    let mut po2s = Vec::new();
+    ExecutorImpl::new(env, counting_loop())
+        .unwrap()
+        .run_with_callback(|segment| {
+            po2s.push(segment.po2());
+            Ok(Box::new(NullSegmentRef))
+        })
+        .unwrap();
+    assert!(po2s.len() > 1);
+    assert!(po2s.iter().all(|&po2| po2 == 16), "{po2s:?}");
+}
+
+#[test]
+fn segment_policy() {
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(20)
+        .segment_policy(|segment| match segment.po2 {
+            18 => SegmentDecision::Split,
+            _ => SegmentDecision::Continue,
+        })
+        .build()
+        .unwrap();
+    let mut po2s = Vec::new();
+    let session = ExecutorImpl::new(env, counting_loop())
+        .unwrap()
+        .run_with_callback(|segment| {
+            po2s.push(segment.po2());
+            Ok(Box::new(NullSegmentRef))
+        })
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    // Every segment but the last is split at po2 18, well below the limit.
+    let (last, split) = po2s.split_last().unwrap();
+    assert!(!split.is_empty());
+    assert!(split.iter().all(|&po2| po2 == 18), "{po2s:?}");
+    assert!(*last <= 18);
+}
+
+#[test]
+fn flush_segment() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::FlushSegments {
+            flushes: 2,
+            iterations: 20_000,
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    // The guest is far smaller than the default segment limit, so each segment boundary is one
+    // the guest asked for.
+    assert!(session.segments.len() >= 3, "{}", session.segments.len());
+}
+
+#[test]
+fn segment_po2_hint_out_of_range() {
+    let env = ExecutorEnv::builder()
+        .segment_po2_hint(MAX_CYCLES_PO2 as u32 + 1)
+        .build()
+        .unwrap();
+    let Err(err) = ExecutorImpl::new(env, counting_loop()).unwrap().run() else {
+        panic!("expected an out of range po2 hint to be rejected");
+    };
+    assert!(err.to_string().contains("segment po2 hint"), "{err}");
+}
+
+/// A program that stores a word to each of `pages` consecutive pages starting at `0x10_0000`.
+fn store_pages(pages: u32) -> MemoryImage {
+    let entry = 0x4000;
+    let mut insns = vec![0x00100537]; // lui a0, 0x100
+    for _ in 0..pages {
+        insns.push(0x00052023); // sw x0, 0(a0)
+        insns.push(0x40050513); // addi a0, a0, 0x400
+    }
+    insns.extend([
+        0x00000513, // li a0, 0
+        0x000055b7, // lui a1, 0x00005000
+        0xc0058593, // addi a1, a1, -0x400
+        0x00000073, // ecall(halt)
+    ]);
+    let program = Program {
+        entry,
+        image: insns
+            .into_iter()
+            .enumerate()
+            .map(|(idx, insn)| (entry + (idx * WORD_SIZE) as u32, insn))
+            .collect(),
+    };
+    MemoryImage::new(&program, PAGE_SIZE as u32).unwrap()
+}
+
+#[test]
+fn paging_stats() {
+    let run = |pages| {
+        let env = ExecutorEnv::builder().build().unwrap();
+        let mut segments = Vec::new();
+        let session = ExecutorImpl::new(env, store_pages(pages))
+            .unwrap()
+            .run_with_callback(|segment| {
+                segments.push(segment.paging());
+                Ok(Box::new(NullSegmentRef))
+            })
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+        assert_eq!(segments, [session.stats().paging]);
+        session.paging
+    };
+
+    // The data pages all share a page table page, so storing to 4 more of them
+    // touches exactly 4 more pages, each of which is paged in and out once.
+    let (few, many) = (run(4), run(8));
+    assert_eq!(many.page_in_count - few.page_in_count, 4);
+    assert_eq!(many.page_out_count - few.page_out_count, 4);
+    assert_eq!(many.unique_pages_touched - few.unique_pages_touched, 4);
+    assert_eq!(few.unique_pages_touched, few.page_in_count);
+}
+
+/// A program that writes `journal` to the journal with `sys_write`, then runs `tail`.
+fn commit_then(journal: &[u8], tail: &[u32]) -> MemoryImage {
+    let entry = 0x4000;
+    let (name_addr, journal_addr) = (0x8000, 0x8100);
+    let mut insns = vec![
+        0x00200293,                                  // li t0, 2 (ecall::SOFTWARE)
+        0x00000513,                                  // li a0, 0
+        0x00000593,                                  // li a1, 0
+        0x00008637,                                  // lui a2, 0x8 (name_addr)
+        0x00000693 | (fileno::JOURNAL << 20),        // li a3, JOURNAL
+        0x00008737,                                  // lui a4, 0x8
+        0x10070713,                                  // addi a4, a4, 0x100 (journal_addr)
+        0x00000793 | ((journal.len() as u32) << 20), // li a5, len
+        0x00000073,                                  // ecall(software)
+    ];
+    insns.extend(tail);
+
+    let mut image: BTreeMap<u32, u32> = insns
+        .into_iter()
+        .enumerate()
+        .map(|(idx, insn)| (entry + (idx * WORD_SIZE) as u32, insn))
+        .collect();
+    let name = [SYS_WRITE.as_str().as_bytes(), &[0]].concat();
+    for (addr, bytes) in [(name_addr, name.as_slice()), (journal_addr, journal)] {
+        for (idx, chunk) in bytes.chunks(WORD_SIZE).enumerate() {
+            let mut word = [0u8; WORD_SIZE];
+            word[..chunk.len()].copy_from_slice(chunk);
+            image.insert(addr + (idx * WORD_SIZE) as u32, u32::from_le_bytes(word));
+        }
+    }
+    MemoryImage::new(&Program { entry, image }, PAGE_SIZE as u32).unwrap()
+}
+
+#[test]
+fn partial_journal() {
+    let journal = b"committed before the end";
+
+    // A fault after the commit stops execution, but the committed bytes are kept.
+    let image = commit_then(journal, &[0x00002023]); // sw x0, 0(x0)
+    let env = ExecutorEnv::builder().build().unwrap();
+    let Err(err) = ExecutorImpl::new(env, image).unwrap().run() else {
+        panic!("expected the store to address 0 to fault");
+    };
+    assert!(err.to_string().contains("StoreAccessFault"), "{err}");
+    let fault = err.downcast_ref::<GuestFault>().unwrap();
+    assert_eq!(fault.partial_journal, journal);
+    let source = std::error::Error::source(fault).unwrap();
+    assert!(source.to_string().contains("StoreAccessFault"), "{source}");
+
+    // A failure of the host is not a fault of the guest.
+    struct FailingWriter;
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("journal unavailable"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let image = commit_then(journal, &[0x00002023]); // sw x0, 0(x0)
+    let env = ExecutorEnv::builder()
+        .write_fd(fileno::JOURNAL, FailingWriter)
+        .build()
+        .unwrap();
+    let Err(err) = ExecutorImpl::new(env, image).unwrap().run() else {
+        panic!("expected the journal write to fail");
+    };
+    assert!(err.downcast_ref::<GuestFault>().is_none(), "{err}");
+    assert!(
+        format!("{err:#}").contains("journal unavailable"),
+        "{err:#}"
+    );
+
+    // Halting without setting an output drops the journal, but the committed bytes are kept.
+    let image = commit_then(
+        journal,
+        &[
+            0x00000293, // li t0, 0 (ecall::HALT)
+            0x00000513, // li a0, 0
+            0x000055b7, // lui a1, 0x00005000
+            0xc0058593, // addi a1, a1, -0x400
+            0x00000073, // ecall(halt)
+        ],
+    );
+    let env = ExecutorEnv::builder().build().unwrap();
+    let session = ExecutorImpl::new(env, image).unwrap().run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert!(session.journal.is_none());
+    assert_eq!(session.partial_journal(), journal);
+}
+
+#[test]
+fn resume_from_snapshot() {
+    let entry = 0x4000;
+    let mut image = BTreeMap::new();
+    let mut pc = entry;
+    for _ in 0..20_000 {
+        image.insert(pc, 0x1234b137); // lui x2, 0x1234b000
+        pc += WORD_SIZE as u32;
+    }
+    image.insert(pc, 0x000055b7); // lui a1, 0x00005000
+    pc += WORD_SIZE as u32;
+    image.insert(pc, 0xc0058593); // addi a1, a1, -0x400
+    pc += WORD_SIZE as u32;
+    image.insert(pc, 0x00000073); // ecall(halt)
+
+    let program = Program { entry, image };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(14) // 16K cycles
+        .enable_checkpoints(0)
+        .build()
+        .unwrap();
+    let mut segments = Vec::new();
+    let mut snapshots = Vec::new();
+    let session = ExecutorImpl::new(env, image)
+        .unwrap()
+        .run_with_callback(|segment| {
+            snapshots.extend(segment.snapshot().cloned());
+            segments.push(segment.clone());
+            Ok(Box::new(NullSegmentRef))
+        })
+        .unwrap();
+    assert!(segments.len() >= 3);
+    assert_eq!(snapshots.len(), segments.len() - 1);
+
+    // Resume halfway through, from a snapshot that went through encoding.
+    let snapshot = SessionSnapshot::decode(&snapshots[0].encode().unwrap()).unwrap();
+    assert_eq!(snapshot.segment_index(), 1);
+    assert!(snapshot.user_cycles() > 0);
+
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let mut resumed_segments = Vec::new();
+    let resumed = ExecutorImpl::from_snapshot(env, snapshot)
+        .unwrap()
+        .run_with_callback(|segment| {
+            resumed_segments.push(segment);
+            Ok(Box::new(NullSegmentRef))
+        })
+        .unwrap();
+
+    assert_eq!(resumed.exit_code, ExitCode::Halted(0));
+    assert_eq!(resumed.user_cycles, session.user_cycles);
+    assert_eq!(
+        resumed.claim().unwrap().digest(),
+        session.claim().unwrap().digest()
+    );
+    assert_eq!(resumed_segments.len(), segments.len() - 1);
+    for (resumed, original) in resumed_segments.iter().zip(&segments[1..]) {
+        assert_eq!(resumed.index, original.index);
+        assert_eq!(
+            resumed.inner.pre_state.digest(),
+            original.inner.pre_state.digest()
+        );
+        assert_eq!(
+            resumed.inner.post_state.digest(),
+            original.inner.post_state.digest()
+        );
+    }
+}
+
 #[test]
 fn libm_build() {
     run_test(MultiTestSpec::LibM);
@@ -775,6 +1170,29 @@ fn std_stdio() {
     assert_eq!(from_utf8(&stderr).unwrap(), EXPECTED_STDERR);
 }
 
+#[test]
+fn std_stdio_large() {
+    const PREFIX: &[u8] = b"Hello world on stdout!\n";
+
+    let input: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+    let mut stdout: Vec<u8> = Vec::new();
+    {
+        let env = ExecutorEnv::builder()
+            .env_var("TEST_MODE", "STDIO")
+            .stdin(input.as_slice())
+            .stdout(&mut stdout)
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+    }
+    assert_eq!(&stdout[..PREFIX.len()], PREFIX);
+    assert_eq!(&stdout[PREFIX.len()..], input.as_slice());
+}
+
 #[test]
 fn environment() {
     let env = ExecutorEnv::builder()
@@ -803,6 +1221,38 @@ ENV_VAR2=
     );
 }
 
+#[test]
+fn inherit_environment() {
+    std::env::set_var("RISC0_TEST_INHERITED_VAR", "inherited");
+    std::env::set_var("RISC0_TEST_OVERRIDDEN_VAR", "inherited");
+    let env = ExecutorEnv::builder()
+        .env_var("TEST_MODE", "ENV_VARS")
+        .inherit_env()
+        .env_var("RISC0_TEST_OVERRIDDEN_VAR", "explicit")
+        .read_fd(
+            fileno::STDIN,
+            Cursor::new(
+                r"RISC0_TEST_INHERITED_VAR
+RISC0_TEST_OVERRIDDEN_VAR
+RISC0_TEST_UNSET_VAR",
+            ),
+        )
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let actual = &session.journal.as_ref().unwrap().bytes;
+    assert_eq!(
+        from_utf8(actual).unwrap(),
+        r"RISC0_TEST_INHERITED_VAR=inherited
+RISC0_TEST_OVERRIDDEN_VAR=explicit
+!RISC0_TEST_UNSET_VAR
+"
+    );
+}
+
 #[test]
 fn args() {
     let test_cases: [&[String]; 3] = [
@@ -853,6 +1303,248 @@ fn buf_read() {
     assert_eq!(output, input);
 }
 
+#[test]
+fn read_typed_then_slice() {
+    let value: (u32, u64) = (7, u64::MAX - 1);
+    let words: Vec<u32> = (0..100).collect();
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadTypedThenSlice {
+            nwords: words.len() as u32,
+        })
+        .unwrap()
+        .write(&value)
+        .unwrap()
+        .write_slice(&words)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let actual: ((u32, u64), Vec<u32>) = session.journal.unwrap().decode().unwrap();
+    assert_eq!(actual, (value, words));
+}
+
+#[test]
+fn journal_callback() {
+    let chunks = vec![b"abcd".to_vec(), b"efghijkl".to_vec()];
+    let mut delivered = Vec::new();
+    let session = {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::EchoChunks {
+                chunks: chunks.clone(),
+            })
+            .unwrap()
+            .journal_callback(|bytes: &[u8]| {
+                delivered.push(bytes.to_vec());
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
+    };
+    assert_eq!(delivered, chunks);
+    assert_eq!(session.journal.unwrap().bytes, chunks.concat());
+}
+
+#[test]
+fn write_frame_from() {
+    use std::io::Read as _;
+
+    // Stream the frame from a reader that never holds the payload in memory.
+    let len = 1 << 20;
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadFrameDigest)
+        .unwrap()
+        .write_frame_from(std::io::repeat(0x5a).take(len as u64), len)
+        .write(&7u32)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let actual: (u32, Digest, u32) = session.journal.unwrap().decode().unwrap();
+    let expected = Digest::try_from(Sha256::digest(vec![0x5a; len as usize]).as_slice()).unwrap();
+    assert_eq!(actual, (len, expected, 7));
+}
+
+#[test]
+fn journal_frames() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::CommitFrames)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let mut frames = session.journal.unwrap().frames();
+    assert_eq!(frames.read::<u32>().unwrap(), 7);
+    assert_eq!(frames.read::<String>().unwrap(), "frames");
+    assert_eq!(frames.read::<(u8, u64)>().unwrap(), (1, 2));
+    frames.finish().unwrap();
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn write_with_postcard() {
+    use risc0_zkvm_methods::multi_test::CodecTestValue;
+
+    let value = CodecTestValue {
+        id: 42,
+        name: "postcard".into(),
+        tags: vec![1, 2, 3],
+    };
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadPostcard)
+        .unwrap()
+        .write_with::<crate::serde::Postcard, _>(&value)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let actual: CodecTestValue = session.journal.unwrap().decode().unwrap();
+    assert_eq!(actual, value);
+}
+
+#[test]
+fn write_frame_from_stdin() {
+    let read_stdin = |env: ExecutorEnv| {
+        let mut stdin = Vec::new();
+        env.posix_io
+            .borrow()
+            .get_reader(fileno::STDIN)
+            .unwrap()
+            .borrow_mut()
+            .read_to_end(&mut stdin)
+            .map(|_| stdin)
+    };
+
+    // The streamed frame is delivered in order with buffered input around it.
+    let env = ExecutorEnv::builder()
+        .write_slice(&[1u8, 2, 3, 4])
+        .write_frame_from(&b"frame"[..], 5)
+        .write_slice(&[5u8, 6, 7, 8])
+        .build()
+        .unwrap();
+    let mut expected = vec![1, 2, 3, 4];
+    expected.extend_from_slice(&5u32.to_le_bytes());
+    expected.extend_from_slice(b"frame");
+    expected.extend_from_slice(&[5, 6, 7, 8]);
+    assert_eq!(read_stdin(env).unwrap(), expected);
+
+    // Only the declared length is read, and a reader that ends early is an error.
+    let env = ExecutorEnv::builder()
+        .write_frame_from(&b"frame"[..], 3)
+        .build()
+        .unwrap();
+    assert_eq!(read_stdin(env).unwrap()[4..], b"fra"[..]);
+    let env = ExecutorEnv::builder()
+        .write_frame_from(&b"frame"[..], 8)
+        .build()
+        .unwrap();
+    let err = read_stdin(env).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn write_chunked() {
+    let read_stdin = |env: ExecutorEnv| {
+        let mut stdin = Vec::new();
+        env.posix_io
+            .borrow()
+            .get_reader(fileno::STDIN)
+            .unwrap()
+            .borrow_mut()
+            .read_to_end(&mut stdin)
+            .unwrap();
+        stdin
+    };
+
+    // Each chunk is framed, and only the last one is flagged as final.
+    let env = ExecutorEnv::builder()
+        .write_chunked(b"hello", 2)
+        .build()
+        .unwrap();
+    let mut expected = Vec::new();
+    for (header, chunk) in [(2u32, &b"he"[..]), (2, b"ll"), (1 | 1 << 31, b"o")] {
+        expected.extend_from_slice(&header.to_le_bytes());
+        expected.extend_from_slice(chunk);
+    }
+    assert_eq!(read_stdin(env), expected);
+
+    let env = ExecutorEnv::builder()
+        .write_chunked(&[], 2)
+        .build()
+        .unwrap();
+    assert_eq!(read_stdin(env), (1u32 << 31).to_le_bytes());
+}
+
+#[test]
+fn write_sized() {
+    let env = ExecutorEnv::builder()
+        .write_sized(&[1u32, 2, 3])
+        .build()
+        .unwrap();
+    let mut stdin = Vec::new();
+    env.posix_io
+        .borrow()
+        .get_reader(fileno::STDIN)
+        .unwrap()
+        .borrow_mut()
+        .read_to_end(&mut stdin)
+        .unwrap();
+    assert_eq!(stdin, bytemuck::cast_slice::<u32, u8>(&[3, 1, 2, 3]));
+}
+
+#[test]
+fn read_sized() {
+    let words: Vec<u32> = (0..1000).collect();
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadSizedDigest)
+        .unwrap()
+        .write_sized(&words)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let actual: (u32, Digest) = session.journal.unwrap().decode().unwrap();
+    let expected =
+        Digest::try_from(Sha256::digest(bytemuck::cast_slice::<u32, u8>(&words)).as_slice())
+            .unwrap();
+    assert_eq!(actual, (words.len() as u32, expected));
+}
+
+#[test]
+fn read_chunked() {
+    // The guest reassembles 1 MiB written in 64 KiB chunks.
+    let data: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadChunkedDigest)
+        .unwrap()
+        .write_chunked(&data, 64 * 1024)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let actual: (u32, Digest) = session.journal.unwrap().decode().unwrap();
+    let expected = Digest::try_from(Sha256::digest(&data).as_slice()).unwrap();
+    assert_eq!(actual, (data.len() as u32, expected));
+}
+
 #[test]
 fn commit_hello_world() {
     ExecutorImpl::from_elf(ExecutorEnv::default(), HELLO_COMMIT_ELF)
@@ -939,6 +1631,39 @@ fn fault() {
     assert!(err.to_string().contains("StoreAccessFault"));
 }
 
+#[cfg(feature = "unstable")]
+#[test]
+fn session_trace_diff() {
+    use crate::SessionTrace;
+
+    const INTERVAL: u64 = 1000;
+    let trace = |cycles| {
+        let mut trace = SessionTrace::new(INTERVAL);
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::BusyLoop { cycles })
+            .unwrap()
+            .trace_callback(&mut trace)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        trace
+    };
+
+    // The guests run the same loop until the shorter one exits after 50,000 cycles.
+    let short = trace(50_000);
+    let long = trace(100_000);
+    assert_eq!(short.diff(&trace(50_000)), None);
+    let divergence = short.diff(&long).unwrap();
+    assert!(
+        (50_000..50_000 + 2 * INTERVAL).contains(&divergence.cycle),
+        "diverged at cycle {}",
+        divergence.cycle
+    );
+}
+
 #[test]
 fn profiler() {
     let mut profiler = Profiler::new(MULTI_TEST_ELF, Some("multi_test.elf")).unwrap();
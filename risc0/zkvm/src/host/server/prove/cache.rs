@@ -0,0 +1,123 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pluggable caching of segment receipts, so that proving an identical segment again is a no-op.
+
+use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex};
+
+use anyhow::{Context, Result};
+use risc0_zkp::core::digest::Digest;
+
+use crate::{
+    sha::{self, Sha256},
+    Segment, SegmentReceipt,
+};
+
+/// A store of [SegmentReceipt]s, keyed by a digest of the segment proven and of the options it
+/// was proven with.
+///
+/// The cache set with [ProverOpts::with_segment_cache](crate::ProverOpts::with_segment_cache) is
+/// checked by [ProverServer::prove_segment](super::ProverServer::prove_segment) before proving a
+/// segment, and receives every segment receipt it proves.
+pub trait SegmentCache: Send + Sync {
+    /// Returns the receipt stored under `key`, or `None` if there is none.
+    fn get(&self, key: &Digest) -> Result<Option<SegmentReceipt>>;
+
+    /// Store `receipt` under `key`, replacing any receipt already there.
+    fn put(&self, key: &Digest, receipt: &SegmentReceipt) -> Result<()>;
+}
+
+/// A [SegmentCache] which holds receipts in the memory of this process.
+#[derive(Default)]
+pub struct InMemorySegmentCache {
+    receipts: Mutex<HashMap<Digest, SegmentReceipt>>,
+}
+
+impl InMemorySegmentCache {
+    /// Construct an empty [InMemorySegmentCache].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SegmentCache for InMemorySegmentCache {
+    fn get(&self, key: &Digest) -> Result<Option<SegmentReceipt>> {
+        Ok(self.receipts.lock().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &Digest, receipt: &SegmentReceipt) -> Result<()> {
+        self.receipts.lock().unwrap().insert(*key, receipt.clone());
+        Ok(())
+    }
+}
+
+/// A [SegmentCache] which holds receipts as files in a directory, so that they can be reused
+/// across processes.
+pub struct DiskSegmentCache {
+    dir: PathBuf,
+}
+
+impl DiskSegmentCache {
+    /// Construct a [DiskSegmentCache] in `dir`, which is created when the first receipt is stored.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &Digest) -> PathBuf {
+        self.dir.join(format!("{key}.receipt"))
+    }
+}
+
+impl SegmentCache for DiskSegmentCache {
+    fn get(&self, key: &Digest) -> Result<Option<SegmentReceipt>> {
+        let path = self.path(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&path).with_context(|| format!("failed to read {path:?}"))?;
+        let receipt = bincode::deserialize(&bytes)
+            .with_context(|| format!("failed to decode segment receipt {path:?}"))?;
+        Ok(Some(receipt))
+    }
+
+    fn put(&self, key: &Digest, receipt: &SegmentReceipt) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create {:?}", self.dir))?;
+        // Write to a temporary file first, so that a concurrent reader never sees a partial receipt.
+        let path = self.path(key);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bincode::serialize(receipt)?)
+            .with_context(|| format!("failed to write {tmp_path:?}"))?;
+        fs::rename(&tmp_path, &path).with_context(|| format!("failed to write {path:?}"))?;
+        Ok(())
+    }
+}
+
+/// The key of the receipt of `segment` when proven with `hashfn` and the given segment receipt
+/// verifier parameters.
+///
+/// The output of a segment is not proven by its seal, so it is left out of the key and filled in
+/// from the segment on a hit.
+pub(crate) fn segment_cache_key(
+    segment: &Segment,
+    hashfn: &str,
+    verifier_parameters: &Digest,
+) -> Result<Digest> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(hashfn.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(hashfn.as_bytes());
+    bytes.extend_from_slice(verifier_parameters.as_bytes());
+    bytes.extend_from_slice(&bincode::serialize(&segment.inner)?);
+    Ok(*sha::Impl::hash_bytes(&bytes))
+}
@@ -72,10 +72,7 @@ impl ProverServer for DevModeProver {
             session.journal.clone().unwrap_or_default().bytes,
         );
 
-        Ok(ProveInfo {
-            receipt,
-            stats: session.stats(),
-        })
+        Ok(ProveInfo::new(receipt, session.stats()))
     }
 
     /// Prove the specified ELF binary using the specified [VerifierContext].
@@ -14,6 +14,7 @@
 
 //! Run the zkVM guest and prove its results.
 
+pub(crate) mod cache;
 mod dev_mode;
 pub(crate) mod keccak;
 mod prover_impl;
@@ -26,6 +27,7 @@ use anyhow::{anyhow, bail, ensure, Result};
 use risc0_core::field::baby_bear::{BabyBear, Elem, ExtElem};
 use risc0_zkp::hal::{CircuitHal, Hal};
 
+pub use self::cache::{DiskSegmentCache, InMemorySegmentCache, SegmentCache};
 use self::{dev_mode::DevModeProver, prover_impl::ProverImpl};
 use crate::{
     host::prove_info::ProveInfo,
@@ -69,6 +71,9 @@ pub trait ProverServer {
     fn prove_session(&self, ctx: &VerifierContext, session: &Session) -> Result<ProveInfo>;
 
     /// Prove the specified [Segment].
+    ///
+    /// The local prover first looks the segment up in the [SegmentCache] set with
+    /// [ProverOpts::with_segment_cache], if any, and stores the receipt there once proven.
     fn prove_segment(&self, ctx: &VerifierContext, segment: &Segment) -> Result<SegmentReceipt>;
 
     /// Lift a [SegmentReceipt] into a [SuccinctReceipt]
@@ -242,5 +247,6 @@ pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
         return Ok(Rc::new(DevModeProver));
     }
 
+    opts.validate()?;
     Ok(Rc::new(ProverImpl::new(opts.clone())))
 }
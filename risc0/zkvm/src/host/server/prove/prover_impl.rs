@@ -17,7 +17,7 @@ use std::collections::HashMap;
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use risc0_circuit_rv32im::prove::segment_prover;
 
-use super::{keccak::prove_keccak, ProverServer};
+use super::{cache::segment_cache_key, keccak::prove_keccak, ProverServer};
 use crate::{
     host::{
         client::prove::ReceiptKind,
@@ -30,8 +30,9 @@ use crate::{
     },
     receipt_claim::{MaybePruned, Merge, Unknown},
     sha::Digestible,
-    Assumption, AssumptionReceipt, CompositeReceipt, InnerAssumptionReceipt, Output, ProverOpts,
-    Receipt, ReceiptClaim, Segment, Session, VerifierContext,
+    Assumption, AssumptionReceipt, CompositeReceipt, ExecutorEnv, ExecutorImpl,
+    InnerAssumptionReceipt, Output, ProverOpts, Receipt, ReceiptClaim, Segment, Session,
+    VerifierContext,
 };
 
 /// An implementation of a Prover that runs locally.
@@ -47,6 +48,21 @@ impl ProverImpl {
 }
 
 impl ProverServer for ProverImpl {
+    fn prove_with_ctx(
+        &self,
+        mut env: ExecutorEnv<'_>,
+        ctx: &VerifierContext,
+        elf: &[u8],
+    ) -> Result<ProveInfo> {
+        env.segment_limit_po2 = Some(self.opts.segment_limit_po2(env.segment_limit_po2));
+        env.segment_po2_hint = env
+            .segment_po2_hint
+            .map(|po2| self.opts.segment_limit_po2(Some(po2)));
+        let mut exec = ExecutorImpl::from_elf(env, elf)?;
+        let session = exec.run()?;
+        self.prove_session(ctx, &session)
+    }
+
     fn prove_session(&self, ctx: &VerifierContext, session: &Session) -> Result<ProveInfo> {
         tracing::debug!(
             "prove_session: exit_code = {:?}, journal = {:?}, segments: {}",
@@ -155,24 +171,33 @@ impl ProverServer for ProverImpl {
         )?;
 
         // Compress the receipt to the requested level.
-        let receipt = match self.opts.receipt_kind {
-            ReceiptKind::Composite => Receipt::new(
-                InnerReceipt::Composite(composite_receipt),
-                session.journal.clone().unwrap_or_default().bytes,
+        let (receipt, composite) = match self.opts.receipt_kind {
+            ReceiptKind::Composite => (
+                Receipt::new(
+                    InnerReceipt::Composite(composite_receipt),
+                    session.journal.clone().unwrap_or_default().bytes,
+                ),
+                None,
             ),
             ReceiptKind::Succinct => {
                 let succinct_receipt = self.composite_to_succinct(&composite_receipt)?;
-                Receipt::new(
-                    InnerReceipt::Succinct(succinct_receipt),
-                    session.journal.clone().unwrap_or_default().bytes,
+                (
+                    Receipt::new(
+                        InnerReceipt::Succinct(succinct_receipt),
+                        session.journal.clone().unwrap_or_default().bytes,
+                    ),
+                    self.opts.keep_composite.then_some(composite_receipt),
                 )
             }
             ReceiptKind::Groth16 => {
                 let succinct_receipt = self.composite_to_succinct(&composite_receipt)?;
                 let groth16_receipt = self.succinct_to_groth16(&succinct_receipt)?;
-                Receipt::new(
-                    InnerReceipt::Groth16(groth16_receipt),
-                    session.journal.clone().unwrap_or_default().bytes,
+                (
+                    Receipt::new(
+                        InnerReceipt::Groth16(groth16_receipt),
+                        session.journal.clone().unwrap_or_default().bytes,
+                    ),
+                    self.opts.keep_composite.then_some(composite_receipt),
                 )
             }
         };
@@ -184,6 +209,7 @@ impl ProverServer for ProverImpl {
         Ok(ProveInfo {
             receipt,
             stats: session.stats(),
+            composite,
         })
     }
 
@@ -195,12 +221,6 @@ impl ProverServer for ProverImpl {
             self.opts.max_segment_po2
         );
 
-        let segment_prover = segment_prover(&self.opts.hashfn)?;
-        let seal = segment_prover.prove_segment(&segment.inner)?;
-
-        let mut claim = decode_receipt_claim_from_seal(&seal)?;
-        claim.output = segment.output.clone().into();
-
         let verifier_parameters = ctx
             .segment_verifier_parameters
             .as_ref()
@@ -208,6 +228,40 @@ impl ProverServer for ProverImpl {
                 "segment receipt verifier parameters missing from context"
             ))?
             .digest();
+
+        // A cache that cannot be read or written only costs the work of proving the segment.
+        let cache = self.opts.segment_cache.clone().and_then(|cache| {
+            match segment_cache_key(segment, &self.opts.hashfn, &verifier_parameters) {
+                Ok(key) => Some((cache, key)),
+                Err(err) => {
+                    tracing::warn!("failed to compute segment cache key: {err:#}");
+                    None
+                }
+            }
+        });
+        if let Some((cache, key)) = &cache {
+            match cache.get(key) {
+                Ok(Some(mut receipt)) => {
+                    receipt.claim.output = segment.output.clone().into();
+                    match check_cached_receipt(ctx, segment, &receipt) {
+                        Ok(()) => {
+                            tracing::debug!("segment {} found in cache: {key}", segment.index);
+                            return Ok(receipt);
+                        }
+                        Err(err) => tracing::warn!("ignoring cached segment receipt: {err:#}"),
+                    }
+                }
+                Ok(None) => (),
+                Err(err) => tracing::warn!("failed to read segment cache: {err:#}"),
+            }
+        }
+
+        let segment_prover = segment_prover(&self.opts.hashfn)?;
+        let seal = segment_prover.prove_segment(&segment.inner)?;
+
+        let mut claim = decode_receipt_claim_from_seal(&seal)?;
+        claim.output = segment.output.clone().into();
+
         let receipt = SegmentReceipt {
             seal,
             index: segment.index,
@@ -217,6 +271,12 @@ impl ProverServer for ProverImpl {
         };
         receipt.verify_integrity_with_context(ctx)?;
 
+        if let Some((cache, key)) = &cache {
+            if let Err(err) = cache.put(key, &receipt) {
+                tracing::warn!("failed to write segment cache: {err:#}");
+            }
+        }
+
         Ok(receipt)
     }
 
@@ -256,6 +316,32 @@ impl ProverServer for ProverImpl {
     }
 }
 
+/// Check that a [SegmentReceipt] read from a segment cache is valid and proves `segment`.
+fn check_cached_receipt(
+    ctx: &VerifierContext,
+    segment: &Segment,
+    receipt: &SegmentReceipt,
+) -> Result<()> {
+    receipt.verify_integrity_with_context(ctx)?;
+    let claim = &receipt.claim;
+    ensure!(
+        claim.pre.digest() == segment.inner.pre_state.digest(),
+        "pre state does not match segment {}",
+        segment.index
+    );
+    ensure!(
+        claim.input.digest() == segment.inner.input_digest,
+        "input does not match segment {}",
+        segment.index
+    );
+    ensure!(
+        claim.exit_code == segment.inner.exit_code,
+        "exit code does not match segment {}",
+        segment.index
+    );
+    Ok(())
+}
+
 fn check_claims(
     session_claim: &ReceiptClaim,
     other_name: &str,
@@ -24,7 +24,9 @@ use super::get_prover_server;
 use crate::{
     host::server::testutils,
     serde::{from_slice, to_vec},
-    ExecutorEnv, ExecutorImpl, ExitCode, ProveInfo, ProverOpts, Receipt, Session, VerifierContext,
+    sha::Digestible,
+    ExecutorEnv, ExecutorImpl, ExitCode, ExitVerificationError, ProveInfo, ProverOpts, Receipt,
+    Session, VerifierContext,
 };
 
 fn prove_session_fast(session: &Session) -> Receipt {
@@ -63,11 +65,165 @@ fn prove_nothing_succinct() {
         .unwrap(); // ensure that we got a succinct receipt.
 }
 
+#[test]
+fn keep_composite() {
+    let prove = |opts: &ProverOpts| {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap();
+        get_prover_server(opts)
+            .unwrap()
+            .prove(env, MULTI_TEST_ELF)
+            .unwrap()
+    };
+
+    let info = prove(&ProverOpts::succinct().with_keep_composite(true));
+    let receipt = info.receipt;
+    receipt.inner.succinct().unwrap();
+    let composite = info.composite.unwrap();
+    composite
+        .verify_integrity_with_context(&VerifierContext::default())
+        .unwrap();
+    assert_eq!(
+        composite.claim().unwrap().digest(),
+        receipt.claim().unwrap().digest()
+    );
+
+    assert!(prove(&ProverOpts::succinct()).composite.is_none());
+}
+
 #[test]
 fn hashfn_poseidon2() {
     prove_nothing("poseidon2").unwrap();
 }
 
+#[test]
+fn prover_opts_po2_bounds() {
+    let opts = ProverOpts::fast().with_min_po2(16).with_segment_po2_max(15);
+    assert!(get_prover_server(&opts).is_err());
+
+    let opts = ProverOpts::fast().with_min_po2(14).with_segment_po2_max(16);
+    opts.validate().unwrap();
+    assert_eq!(opts.segment_limit_po2(None), 16);
+    assert_eq!(opts.segment_limit_po2(Some(13)), 14);
+    assert_eq!(opts.segment_limit_po2(Some(15)), 15);
+
+    let opts = opts.with_deterministic(true);
+    assert_eq!(opts.segment_limit_po2(None), 16);
+    assert_eq!(opts.segment_limit_po2(Some(15)), 16);
+
+    // The segment limit caps the po2 used for execution, even below the minimum.
+    let opts = opts.with_deterministic(false).with_segment_limit_po2(13);
+    opts.validate().unwrap();
+    assert_eq!(opts.segment_limit_po2(None), 13);
+    assert_eq!(opts.segment_limit_po2(Some(15)), 13);
+    assert_eq!(
+        opts.clone()
+            .with_deterministic(true)
+            .segment_limit_po2(None),
+        13
+    );
+    assert!(opts.with_segment_limit_po2(17).validate().is_err());
+}
+
+#[test]
+fn prover_opts_serde_defaults() {
+    // Options serialized before the segment and proving options were added still decode.
+    let mut value = serde_json::to_value(ProverOpts::fast()).unwrap();
+    let fields = value.as_object_mut().unwrap();
+    for field in [
+        "min_segment_po2",
+        "segment_limit_po2",
+        "timeout",
+        "deterministic",
+        "keep_composite",
+    ] {
+        fields.remove(field).unwrap();
+    }
+    let opts: ProverOpts = serde_json::from_value(value).unwrap();
+    opts.validate().unwrap();
+    assert_eq!(opts.min_segment_po2, risc0_zkp::MIN_CYCLES_PO2);
+    assert_eq!(opts.segment_limit_po2, None);
+    assert_eq!(opts.timeout, None);
+    assert!(!opts.deterministic);
+    assert!(!opts.keep_composite);
+}
+
+#[test]
+fn segment_limit_claims() {
+    let prove = |segment_limit_po2| {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::BusyLoop { cycles: 1 << 18 })
+            .unwrap()
+            .build()
+            .unwrap();
+        let opts = ProverOpts::succinct()
+            .with_segment_limit_po2(segment_limit_po2)
+            .with_keep_composite(true);
+        let info = get_prover_server(&opts)
+            .unwrap()
+            .prove(env, MULTI_TEST_ELF)
+            .unwrap();
+        info.receipt.verify(MULTI_TEST_ID).unwrap();
+        info
+    };
+
+    // The smaller limit splits the execution into more segments, but the receipts are for the
+    // same claim.
+    let small = prove(16);
+    let large = prove(20);
+    let segments = |info: &ProveInfo| info.composite.as_ref().unwrap().segments.len();
+    assert!(segments(&small) > segments(&large));
+    assert_eq!(
+        small.receipt.claim().unwrap().digest(),
+        large.receipt.claim().unwrap().digest()
+    );
+}
+
+#[test]
+fn deterministic_claims() {
+    let opts = ProverOpts::fast()
+        .with_segment_po2_max(15)
+        .with_deterministic(true);
+    let prove = |segment_limit_po2| {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::BusyLoop { cycles: 1 << 16 })
+            .unwrap()
+            .segment_limit_po2(segment_limit_po2)
+            .build()
+            .unwrap();
+        get_prover_server(&opts)
+            .unwrap()
+            .prove(env, MULTI_TEST_ELF)
+            .unwrap()
+            .receipt
+    };
+
+    // The segment limit requested by the env is ignored, so both runs split the execution into
+    // the same segments.
+    let first = prove(14);
+    let second = prove(15);
+    assert_eq!(
+        first.claim().unwrap().digest(),
+        second.claim().unwrap().digest()
+    );
+
+    let segment_claims = |receipt: &Receipt| -> Vec<Digest> {
+        receipt
+            .inner
+            .composite()
+            .unwrap()
+            .segments
+            .iter()
+            .map(|segment| segment.claim.digest())
+            .collect()
+    };
+    assert!(segment_claims(&first).len() > 1);
+    assert_eq!(segment_claims(&first), segment_claims(&second));
+}
+
 #[test]
 fn receipt_serde() {
     let receipt = prove_nothing("sha-256").unwrap().receipt;
@@ -77,6 +233,123 @@ fn receipt_serde() {
     decoded.verify(MULTI_TEST_ID).unwrap();
 }
 
+#[test]
+fn receipt_json() {
+    let opts = ProverOpts::succinct();
+    let composite = prove_nothing("poseidon2").unwrap().receipt;
+    let succinct = get_prover_server(&opts)
+        .unwrap()
+        .compress(&opts, &composite)
+        .unwrap();
+    for receipt in [composite, succinct] {
+        let decoded = Receipt::from_json(&receipt.to_json().unwrap()).unwrap();
+        assert_eq!(decoded, receipt);
+        decoded.verify(MULTI_TEST_ID).unwrap();
+    }
+}
+
+#[test]
+fn compose_keccak_receipts() {
+    let prove = |spec: &MultiTestSpec| {
+        let env = ExecutorEnv::builder().write(spec).unwrap().build().unwrap();
+        get_prover_server(&ProverOpts::fast())
+            .unwrap()
+            .prove(env, MULTI_TEST_ELF)
+            .unwrap()
+            .receipt
+    };
+    let receipts = [
+        prove(&MultiTestSpec::KeccakUpdate),
+        prove(&MultiTestSpec::KeccakUpdate2),
+    ];
+
+    let batch = Receipt::compose(&receipts).unwrap();
+    batch
+        .verify(&[MULTI_TEST_ID.into(), MULTI_TEST_ID.into()])
+        .unwrap();
+
+    let claims = batch.claims().unwrap();
+    assert_eq!(claims.len(), 2);
+    for (claim, receipt) in claims.iter().zip(&receipts) {
+        assert_eq!(claim.digest(), receipt.claim().unwrap().digest());
+    }
+
+    // Every receipt must match the image ID at the same position.
+    assert!(matches!(
+        batch
+            .verify(&[MULTI_TEST_ID.into(), Digest::ZERO])
+            .unwrap_err(),
+        VerificationError::ClaimDigestMismatch { .. }
+    ));
+    assert_eq!(
+        batch.verify(&[MULTI_TEST_ID.into()]).unwrap_err(),
+        VerificationError::ImageVerificationError
+    );
+}
+
+#[test]
+fn verify_batch_reports_index() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let receipt = get_prover_server(&ProverOpts::fast())
+        .unwrap()
+        .prove(env, MULTI_TEST_ELF)
+        .unwrap()
+        .receipt;
+
+    // The second receipt is checked against the wrong image ID.
+    let image_id = Digest::from(MULTI_TEST_ID);
+    let receipts = [
+        (receipt.clone(), image_id),
+        (receipt.clone(), Digest::ZERO),
+        (receipt, image_id),
+    ];
+    let err = crate::verify_batch(&receipts).unwrap_err();
+    assert_eq!(err.index, 1);
+    assert!(matches!(
+        err.source,
+        VerificationError::ClaimDigestMismatch { .. }
+    ));
+
+    let results = crate::verify_all(&receipts);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+
+    crate::verify_batch(&[receipts[0].clone(), receipts[2].clone()]).unwrap();
+}
+
+#[test]
+fn verify_claim() {
+    use risc0_zkvm_methods::{HELLO_COMMIT_ELF, HELLO_COMMIT_ID};
+
+    let receipt = get_prover_server(&ProverOpts::fast())
+        .unwrap()
+        .prove(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+        .unwrap()
+        .receipt;
+    let journal_digest = receipt.journal.digest();
+
+    // The journal bytes are not needed to verify against a known digest.
+    let receipt = Receipt::new(receipt.inner, vec![]);
+    receipt
+        .verify_claim(HELLO_COMMIT_ID, journal_digest)
+        .unwrap();
+
+    let mut wrong_digest = journal_digest;
+    wrong_digest.as_mut_bytes()[0] ^= 1;
+    assert!(matches!(
+        receipt
+            .verify_claim(HELLO_COMMIT_ID, wrong_digest)
+            .unwrap_err(),
+        VerificationError::ClaimDigestMismatch { .. }
+    ));
+}
+
 #[test]
 fn check_image_id() {
     let receipt = prove_nothing("sha-256").unwrap().receipt;
@@ -262,6 +535,102 @@ fn session_events() {
     assert!(on_post_prove_segment_flag.take());
 }
 
+#[test]
+fn segment_cache() {
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        host::server::prove::cache::segment_cache_key, InMemorySegmentCache, SegmentCache,
+        SegmentReceipt,
+    };
+
+    #[derive(Default)]
+    struct RecordingCache {
+        inner: InMemorySegmentCache,
+        hits: Mutex<HashSet<Digest>>,
+    }
+
+    impl SegmentCache for RecordingCache {
+        fn get(&self, key: &Digest) -> Result<Option<SegmentReceipt>> {
+            let receipt = self.inner.get(key)?;
+            if receipt.is_some() {
+                self.hits.lock().unwrap().insert(*key);
+            }
+            Ok(receipt)
+        }
+
+        fn put(&self, key: &Digest, receipt: &SegmentReceipt) -> Result<()> {
+            self.inner.put(key, receipt)
+        }
+    }
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let segment = session.segments[0].resolve().unwrap();
+
+    let cache = Arc::new(RecordingCache::default());
+    let opts = ProverOpts::fast().with_segment_cache(cache.clone());
+    let ctx = VerifierContext::default();
+    let key = segment_cache_key(
+        &segment,
+        &opts.hashfn,
+        &ctx.segment_verifier_parameters.as_ref().unwrap().digest(),
+    )
+    .unwrap();
+
+    let prover = get_prover_server(&opts).unwrap();
+    let first = prover.prove_segment(&ctx, &segment).unwrap();
+    assert!(!cache.hits.lock().unwrap().contains(&key));
+    let second = prover.prove_segment(&ctx, &segment).unwrap();
+
+    // Proving adds random noise to the trace, so an identical seal must come from the cache.
+    assert!(cache.hits.lock().unwrap().contains(&key));
+    assert_eq!(first.seal, second.seal);
+    second.verify_integrity_with_context(&ctx).unwrap();
+
+    // A cached receipt that does not verify is proven again and replaced.
+    let mut corrupt = first.clone();
+    corrupt.seal[0] ^= 1;
+    cache.put(&key, &corrupt).unwrap();
+    let third = prover.prove_segment(&ctx, &segment).unwrap();
+    assert_ne!(third.seal, corrupt.seal);
+    third.verify_integrity_with_context(&ctx).unwrap();
+    assert_eq!(cache.inner.get(&key).unwrap().unwrap().seal, third.seal);
+}
+
+#[test]
+fn disk_segment_cache() {
+    use crate::{DiskSegmentCache, ReceiptClaim, SegmentCache, SegmentReceipt};
+
+    let dir = tempfile::tempdir().unwrap();
+    let cache = DiskSegmentCache::new(dir.path().join("segments"));
+    let receipt = SegmentReceipt {
+        seal: vec![1, 2, 3],
+        index: 4,
+        hashfn: "sha-256".to_string(),
+        verifier_parameters: Digest::ZERO,
+        claim: ReceiptClaim::ok(Digest::ZERO, vec![5]),
+    };
+    let key = Digest::from([6; 32]);
+
+    assert!(cache.get(&key).unwrap().is_none());
+    cache.put(&key, &receipt).unwrap();
+    let cached = cache.get(&key).unwrap().unwrap();
+    assert_eq!(cached.seal, receipt.seal);
+    assert_eq!(cached.index, receipt.index);
+    assert_eq!(cached.claim, receipt.claim);
+}
+
 // These tests come from:
 // https://github.com/riscv-software-src/riscv-tests
 // They were built using the toolchain from:
@@ -403,6 +772,156 @@ fn pause_exit_nonzero() {
     prove_session_fast(&session);
 }
 
+#[test]
+fn verify_any() {
+    use risc0_zkvm_methods::{HELLO_COMMIT_ELF, HELLO_COMMIT_ID};
+
+    let receipt = get_prover_server(&ProverOpts::fast())
+        .unwrap()
+        .prove(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+        .unwrap()
+        .receipt;
+    let image_id = Digest::from(HELLO_COMMIT_ID);
+    let decoy = Digest::from(MULTI_TEST_ID);
+
+    assert_eq!(receipt.verify_any(&[decoy, image_id]).unwrap(), image_id);
+    assert_eq!(
+        receipt.verify_any(&[decoy, Digest::ZERO]),
+        Err(VerificationError::ImageVerificationError)
+    );
+    assert_eq!(
+        receipt.verify_any(&[]),
+        Err(VerificationError::ImageVerificationError)
+    );
+
+    let ctx = VerifierContext::default().with_allowed_image_ids(&[image_id]);
+    assert_eq!(
+        receipt
+            .verify_any_with_context(&ctx, &[decoy, image_id])
+            .unwrap(),
+        image_id
+    );
+    let ctx = VerifierContext::default().with_allowed_image_ids(&[decoy]);
+    assert_eq!(
+        receipt.verify_any_with_context(&ctx, &[decoy, image_id]),
+        Err(VerificationError::ImageIdNotAllowed { image_id })
+    );
+}
+
+fn prove_halt(exit_code: u8) -> Receipt {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Halt(exit_code))
+        .unwrap()
+        .build()
+        .unwrap();
+    let opts = ProverOpts::fast().with_prove_guest_errors(true);
+    get_prover_server(&opts)
+        .unwrap()
+        .prove(env, MULTI_TEST_ELF)
+        .unwrap()
+        .receipt
+}
+
+#[test]
+fn verify_with_exit_halted_ok() {
+    let receipt = prove_halt(0);
+    receipt
+        .verify_with_exit(MULTI_TEST_ID, ExitCode::Halted(0))
+        .unwrap();
+    assert_eq!(
+        receipt.verify_with_exit(Digest::ZERO, ExitCode::Halted(0)),
+        Err(ExitVerificationError::Verification(
+            VerificationError::ImageVerificationError
+        ))
+    );
+}
+
+#[test]
+fn verify_with_exit_halted_error() {
+    let receipt = prove_halt(1);
+    receipt
+        .verify_with_exit(MULTI_TEST_ID, ExitCode::Halted(1))
+        .unwrap();
+    assert_eq!(
+        receipt.verify_with_exit(MULTI_TEST_ID, ExitCode::Halted(0)),
+        Err(ExitVerificationError::UnexpectedExitCode {
+            expected: ExitCode::Halted(0),
+            actual: ExitCode::Halted(1),
+        })
+    );
+}
+
+#[test]
+fn verify_with_exit_with_context() {
+    let receipt = prove_halt(1);
+    let ctx = VerifierContext::default().with_allowed_image_ids(&[MULTI_TEST_ID.into()]);
+    receipt
+        .verify_with_exit_with_context(&ctx, MULTI_TEST_ID, ExitCode::Halted(1))
+        .unwrap();
+
+    let ctx = VerifierContext::default().with_allowed_image_ids(&[Digest::ZERO]);
+    assert_eq!(
+        receipt.verify_with_exit_with_context(&ctx, MULTI_TEST_ID, ExitCode::Halted(1)),
+        Err(ExitVerificationError::Verification(
+            VerificationError::ImageIdNotAllowed {
+                image_id: MULTI_TEST_ID.into()
+            }
+        ))
+    );
+}
+
+#[test]
+fn verify_with_exit_paused() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::PauseResume(0))
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let receipt = prove_session_fast(&exec.run().unwrap());
+    receipt
+        .verify_with_exit(MULTI_TEST_ID, ExitCode::Paused(0))
+        .unwrap();
+    assert_eq!(
+        receipt.verify_with_exit(MULTI_TEST_ID, ExitCode::Halted(0)),
+        Err(ExitVerificationError::UnexpectedExitCode {
+            expected: ExitCode::Halted(0),
+            actual: ExitCode::Paused(0),
+        })
+    );
+}
+
+#[test]
+fn verifier_context_profiles() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let receipt = prove_session_fast(&exec.run().unwrap());
+
+    receipt
+        .verify_with_context(&VerifierContext::v1_0(), MULTI_TEST_ID)
+        .unwrap();
+    receipt
+        .verify_with_context(&VerifierContext::builder().build(), MULTI_TEST_ID)
+        .unwrap();
+
+    // A composite receipt cannot be verified under a profile for succinct receipts.
+    assert_eq!(
+        receipt.verify_with_context(&VerifierContext::with_succinct_defaults(), MULTI_TEST_ID),
+        Err(VerificationError::VerifierParametersMissing)
+    );
+
+    // Nor under a profile that excludes the po2 of its segments.
+    let ctx = VerifierContext::builder().po2_range(0..=0).build();
+    assert!(matches!(
+        receipt.verify_with_context(&ctx, MULTI_TEST_ID),
+        Err(VerificationError::ControlVerificationError { .. })
+    ));
+}
+
 #[test]
 fn continuation() {
     const COUNT: usize = 2; // Number of total chunks to aim for.
@@ -621,13 +1140,15 @@ mod sys_verify {
 
     use super::get_prover_server;
     use crate::{
+        prove_keccak,
         receipt_claim::Unknown,
         recursion::{prove::zkr, test_zkr, MerkleGroup},
         register_zkr,
         serde::to_vec,
         sha::Digestible,
         Assumption, CoprocessorCallback, ExecutorEnv, ExecutorEnvBuilder, ExecutorImpl, ExitCode,
-        ProveKeccakRequest, ProveZkrRequest, ProverOpts, Receipt, SuccinctReceipt, RECURSION_PO2,
+        ProveKeccakRequest, ProveZkrRequest, ProverOpts, Receipt, RecordingCoprocessor,
+        ReplayingCoprocessor, SuccinctReceipt, RECURSION_PO2,
     };
 
     fn prove_hello_commit() -> Receipt {
@@ -1005,6 +1526,87 @@ mod sys_verify {
         assert!(coprocessor.borrow().zkr_requests.is_empty());
         assert!(coprocessor.borrow().keccak_requests.is_empty());
     }
+
+    #[test]
+    fn sys_prove_keccak_supplied() {
+        let spec = &MultiTestSpec::KeccakUpdate;
+
+        // Capture the keccak request made by the guest, and prove it out of band.
+        let coprocessor = Rc::new(RefCell::new(Coprocessor::new()));
+        let env = ExecutorEnv::builder()
+            .coprocessor_callback_ref(coprocessor.clone())
+            .write(&spec)
+            .unwrap()
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        let proof_request = coprocessor.borrow_mut().keccak_requests.remove(0);
+        let assumption_receipt = prove_keccak(&proof_request).unwrap();
+
+        // With the receipt supplied up-front, the coprocessor is not asked for it again.
+        let coprocessor = Rc::new(RefCell::new(Coprocessor::new()));
+        let env = ExecutorEnv::builder()
+            .coprocessor_callback_ref(coprocessor.clone())
+            .add_assumption(assumption_receipt)
+            .write(&spec)
+            .unwrap()
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert!(coprocessor.borrow().keccak_requests.is_empty());
+
+        // The supplied receipt resolves the assumption when proving.
+        let receipt = get_prover_server(&ProverOpts::succinct())
+            .unwrap()
+            .prove_session(&Default::default(), &session)
+            .unwrap()
+            .receipt;
+        receipt.verify(MULTI_TEST_ID).unwrap();
+    }
+
+    #[test]
+    fn sys_prove_keccak_replay() {
+        let spec = &MultiTestSpec::KeccakUpdate;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coprocessor.bin");
+        let prove = |env| {
+            get_prover_server(&ProverOpts::succinct())
+                .unwrap()
+                .prove(env, MULTI_TEST_ELF)
+                .unwrap()
+                .receipt
+        };
+
+        // Record the requests made by the guest during a run.
+        let env = ExecutorEnv::builder()
+            .coprocessor_callback(RecordingCoprocessor::new(Coprocessor::new(), &path).unwrap())
+            .write(&spec)
+            .unwrap()
+            .build()
+            .unwrap();
+        let recorded = prove(env);
+
+        // Replaying the recording produces the same receipt without the original coprocessor.
+        let env = ExecutorEnv::builder()
+            .coprocessor_callback(ReplayingCoprocessor::from_file(&path).unwrap())
+            .write(&spec)
+            .unwrap()
+            .build()
+            .unwrap();
+        let replayed = prove(env);
+        replayed.verify(MULTI_TEST_ID).unwrap();
+        assert_eq!(replayed.journal, recorded.journal);
+        assert_eq!(
+            replayed.claim().unwrap().digest(),
+            recorded.claim().unwrap().digest()
+        );
+    }
 }
 
 #[test]
@@ -15,25 +15,32 @@
 //! This module defines [Session] and [Segment] which provides a way to share
 //! execution traces between the execution phase and the proving phase.
 
-use std::{collections::BTreeSet, fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
 
 use anyhow::{ensure, Result};
 use enum_map::EnumMap;
 use risc0_binfmt::{MemoryImage, SystemState};
-use risc0_circuit_rv32im::prove::{emu::exec::EcallMetric, segment::Segment as CircuitSegment};
+use risc0_circuit_rv32im::prove::{
+    emu::exec::{EcallMetric, ExecutorCheckpoint},
+    segment::Segment as CircuitSegment,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     host::{
         client::env::{ProveKeccakRequest, ProveZkrRequest, SegmentPath},
-        prove_info::SessionStats,
+        prove_info::{PagingStats, SessionStats},
     },
     sha::Digest,
     Assumption, AssumptionReceipt, Assumptions, ExitCode, Journal, MaybePruned, Output,
     ReceiptClaim,
 };
 
-use super::exec::syscall::{SyscallKind, SyscallMetric};
+use super::exec::syscall::{AssumptionUsage, SyscallKind, SyscallMetric};
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub struct PageFaults {
@@ -61,6 +68,9 @@ pub struct Session {
     /// The data publicly committed by the guest program.
     pub journal: Option<Journal>,
 
+    /// The bytes committed to the journal, whether or not the session has an output.
+    pub(crate) partial_journal: Vec<u8>,
+
     /// The [ExitCode] of the session.
     pub exit_code: ExitCode,
 
@@ -87,6 +97,9 @@ pub struct Session {
     /// associated with continuations and padding up to the nearest power of 2.
     pub total_cycles: u64,
 
+    /// The pages moved in and out of segments over the whole execution.
+    pub paging: PagingStats,
+
     /// The system state of the initial [MemoryImage].
     pub pre_state: SystemState,
 
@@ -123,6 +136,8 @@ pub struct Segment {
 
     pub(crate) inner: CircuitSegment,
     pub(crate) output: Option<Output>,
+    #[serde(skip)]
+    pub(crate) snapshot: Option<Box<SessionSnapshot>>,
 }
 
 impl Segment {
@@ -132,6 +147,70 @@ impl Segment {
     pub fn po2(&self) -> usize {
         self.inner.po2
     }
+
+    /// The [SessionSnapshot] taken at the end of this [Segment], if any.
+    ///
+    /// Snapshots are only taken when checkpoints are enabled with
+    /// [ExecutorEnvBuilder::enable_checkpoints](crate::ExecutorEnvBuilder::enable_checkpoints).
+    /// They are not serialized with the segment, so a [SegmentRef] resolves to a segment without
+    /// one.
+    pub fn snapshot(&self) -> Option<&SessionSnapshot> {
+        self.snapshot.as_deref()
+    }
+
+    /// The pages moved in and out of this [Segment].
+    ///
+    /// Every page touched by a segment is paged in, so `unique_pages_touched`
+    /// is the same as `page_in_count`.
+    pub fn paging(&self) -> PagingStats {
+        let page_in_count = self.inner.page_in_count as u64;
+        PagingStats {
+            page_in_count,
+            page_out_count: self.inner.page_out_count as u64,
+            unique_pages_touched: page_in_count,
+        }
+    }
+}
+
+/// The state of an execution at a segment boundary, from which it can be
+/// resumed.
+///
+/// A snapshot captures the guest's registers, memory and program counter, along
+/// with the index of the next segment and the host-side state of the session
+/// (journal, consumed input, and assumptions). Resuming from a snapshot with an
+/// [ExecutorEnv](crate::ExecutorEnv) equivalent to the original one produces the
+/// same remaining segments, and therefore the same receipt claim, as an
+/// uninterrupted execution.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub(crate) checkpoint: ExecutorCheckpoint,
+    pub(crate) journal: Vec<u8>,
+    pub(crate) read_offsets: BTreeMap<u32, u64>,
+    pub(crate) assumptions_used: AssumptionUsage,
+    pub(crate) pending_zkrs: Vec<ProveZkrRequest>,
+    pub(crate) pending_keccaks: Vec<ProveKeccakRequest>,
+}
+
+impl SessionSnapshot {
+    /// The index of the first segment produced when resuming from this snapshot.
+    pub fn segment_index(&self) -> u32 {
+        self.checkpoint.segment_index as u32
+    }
+
+    /// The number of user cycles executed before this snapshot was taken.
+    pub fn user_cycles(&self) -> u64 {
+        self.checkpoint.user_cycles
+    }
+
+    /// Encode this snapshot so it can be persisted.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Decode a snapshot previously produced by [SessionSnapshot::encode].
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        Ok(bincode::deserialize(bytes)?)
+    }
 }
 
 /// A reference to a [Segment].
@@ -162,6 +241,7 @@ impl Session {
         segments: Vec<Box<dyn SegmentRef>>,
         input: Digest,
         journal: Option<Vec<u8>>,
+        partial_journal: Vec<u8>,
         exit_code: ExitCode,
         post_image: MemoryImage,
         assumptions: Vec<(Assumption, AssumptionReceipt)>,
@@ -169,6 +249,7 @@ impl Session {
         paging_cycles: u64,
         padding_cycles: u64,
         total_cycles: u64,
+        paging: PagingStats,
         pre_state: SystemState,
         post_state: SystemState,
         pending_zkrs: Vec<ProveZkrRequest>,
@@ -180,6 +261,7 @@ impl Session {
             segments,
             input,
             journal: journal.map(Journal::new),
+            partial_journal,
             exit_code,
             post_image,
             assumptions,
@@ -188,6 +270,7 @@ impl Session {
             paging_cycles,
             reserved_cycles: padding_cycles,
             total_cycles,
+            paging,
             pre_state,
             post_state,
             pending_zkrs,
@@ -197,6 +280,15 @@ impl Session {
         }
     }
 
+    /// The bytes committed to the journal by the guest.
+    ///
+    /// Unlike [Session::journal], these are kept even when the guest exits without setting an
+    /// output, e.g. if it halts without committing the journal digest. Execution which stops with a
+    /// fault returns the committed bytes in a [GuestFault](crate::GuestFault) instead.
+    pub fn partial_journal(&self) -> &[u8] {
+        &self.partial_journal
+    }
+
     /// Add a hook to be called during the proving phase.
     pub fn add_hook<E: SessionEvents + 'static>(&mut self, hook: E) {
         self.hooks.push(Box::new(hook));
@@ -320,6 +412,7 @@ impl Session {
             user_cycles: self.user_cycles,
             paging_cycles: self.paging_cycles,
             reserved_cycles: self.reserved_cycles,
+            paging: self.paging,
         }
     }
 }
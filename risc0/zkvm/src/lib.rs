@@ -51,12 +51,16 @@
 //!
 //! | Feature          | Target(s)         | Implies    | Description                                                                                                                                                  |
 //! | ---------------- | ----------------- | ---------- | ------------------------------------------------------------------------------------------------------------------------------------------------------------ |
+//! | async-client     | all except rv32im | client     | Enables an async variant of the client API, backed by Tokio.                                                                                                 |
 //! | client           | all except rv32im | std        | Enables the client API.                                                                                                                                      |
 //! | cuda             |                   | prove, std | Enables CUDA GPU acceleration for the prover. Requires CUDA toolkit to be installed.                                                                         |
 //! | disable-dev-mode | all except rv32im |            | Disables dev mode so that proving and verifying may not be faked. Used to prevent a misplaced `RISC0_DEV_MODE` from breaking security in production systems. |
 //! | metal            | macos             | prove, std | Deprecated - Metal GPU acceleration for the prover is now enabled by default on Apple Silicon.                                                               |
+//! | postcard         | all               |            | Enables the `Postcard` codec for `ExecutorEnvBuilder::write_with` and `env::read_with`.                                                                      |
 //! | prove            | all except rv32im | std        | Enables the prover, incompatible within the zkvm guest.                                                                                                      |
 //! | std              | all               |            | Support for the Rust stdlib.                                                                                                                                 |
+//! | tls              | all except rv32im | client     | Enables `ApiClient::connect` for proving with a remote server over TLS, with an optional bearer token.                                                       |
+//! | tracing          | all except rv32im | client     | Wraps client requests in `tracing` spans carrying fields such as the segment index, po2 and claim digest.                                                    |
 //!
 //! [`cargo risczero` tool]: https://crates.io/crates/cargo-risczero
 //! [dev-docs]: https://dev.risczero.com
@@ -101,24 +105,31 @@ pub use {
         api::server::Server as ApiServer,
         client::prove::local::LocalProver,
         recursion::{
-            prove::{prove_registered_zkr, prove_zkr, register_zkr},
+            prove::{prove_registered_zkr, prove_zkr, register_zkr, register_zkr_with_info},
             RECURSION_PO2,
         },
         server::{
-            exec::executor::ExecutorImpl,
-            prove::{get_prover_server, HalPair, ProverServer},
+            exec::executor::{ExecutorImpl, GuestFault},
+            prove::{
+                get_prover_server, DiskSegmentCache, HalPair, InMemorySegmentCache, ProverServer,
+                SegmentCache,
+            },
             session::{
                 FileSegmentRef, NullSegmentRef, Segment, SegmentRef, Session, SessionEvents,
-                SimpleSegmentRef,
+                SessionSnapshot, SimpleSegmentRef,
             },
         },
     },
-    risc0_circuit_rv32im::prove::engine::loader::Loader,
+    risc0_circuit_rv32im::prove::{emu::exec::CycleLimitExceeded, engine::loader::Loader},
     risc0_groth16::{
         docker::stark_to_snark, to_json as seal_to_json, ProofJson as Groth16ProofJson,
     },
 };
 
+#[cfg(not(target_os = "zkvm"))]
+#[cfg(feature = "tls")]
+pub use self::host::api::tls::{AuthToken, TlsConfig};
+
 #[cfg(not(target_os = "zkvm"))]
 #[cfg(feature = "bonsai")]
 pub use self::host::client::prove::bonsai::BonsaiProver;
@@ -128,24 +139,51 @@ pub use self::host::client::prove::bonsai::BonsaiProver;
 pub use {
     self::host::{
         api::{
-            client::Client as ApiClient, Asset, AssetRequest, Connector, RedisParams, SegmentInfo,
-            SessionInfo,
+            client::{Client as ApiClient, ClientBuilder as ApiClientBuilder},
+            keccak_po2_range, Asset, AssetRequest, AssetStore, CancellationToken, Cancelled,
+            Connector, Endpoint, EndpointSelection, GuestArtifact, InMemoryAssetStore, ProverError,
+            RedisParams, SegmentAction, SegmentInfo, ServerInfo, SessionInfo, StoreKey, TimedOut,
+            UnsupportedPo2, ZkrInfo,
         },
         client::{
-            env::{ExecutorEnv, ExecutorEnvBuilder},
+            env::{
+                ExecutorEnv, ExecutorEnvBuilder, JournalCallback, PartialSegment, SegmentDecision,
+            },
             prove::{
-                default_executor, default_prover, external::ExternalProver, Executor, Prover,
-                ProverOpts, ReceiptKind,
+                default_executor, default_prover, external::ExternalProver, Executor, HashFn,
+                Prover, ProverOpts, ReceiptKind,
             },
         },
     },
     risc0_circuit_rv32im::trace::{TraceCallback, TraceEvent},
 };
 
+#[cfg(not(target_os = "zkvm"))]
+#[cfg(feature = "async-client")]
+pub use self::host::api::async_client::AsyncClient as AsyncApiClient;
+
+#[cfg(not(target_os = "zkvm"))]
+#[cfg(feature = "client")]
+#[cfg(feature = "unstable")]
+pub use self::host::client::env::{
+    CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest, ProveZkrRequestBuilder,
+};
+
+#[cfg(not(target_os = "zkvm"))]
+#[cfg(feature = "client")]
+#[cfg(feature = "unstable")]
+#[cfg(not(feature = "disable-dev-mode"))]
+pub use self::host::client::mock::MockCoprocessor;
+
+#[cfg(not(target_os = "zkvm"))]
+#[cfg(feature = "client")]
+#[cfg(feature = "unstable")]
+pub use self::host::client::record::{RecordingCoprocessor, ReplayingCoprocessor};
+
 #[cfg(not(target_os = "zkvm"))]
 #[cfg(feature = "client")]
 #[cfg(feature = "unstable")]
-pub use self::host::client::env::{CoprocessorCallback, ProveKeccakRequest, ProveZkrRequest};
+pub use self::host::client::trace::{DivergencePoint, SessionTrace, TracePoint};
 
 #[cfg(not(target_os = "zkvm"))]
 #[cfg(feature = "prove")]
@@ -155,7 +193,7 @@ pub use self::host::server::prove::keccak::prove_keccak;
 #[cfg(not(target_os = "zkvm"))]
 pub use {
     self::host::{
-        prove_info::{ProveInfo, SessionStats},
+        prove_info::{LinearPricing, PagingStats, PricingModel, ProveInfo, SessionStats},
         recursion::{ALLOWED_CONTROL_IDS, ALLOWED_CONTROL_ROOT},
     },
     risc0_binfmt::compute_image_id,
@@ -164,10 +202,12 @@ pub use {
 };
 
 pub use receipt::{
-    AssumptionReceipt, CompositeReceipt, CompositeReceiptVerifierParameters, FakeReceipt,
-    InnerAssumptionReceipt, InnerReceipt, Journal, Receipt, ReceiptMetadata, SegmentReceipt,
-    SegmentReceiptVerifierParameters, SuccinctReceipt, SuccinctReceiptVerifierParameters,
-    VerifierContext, DEFAULT_MAX_PO2,
+    verify_all, verify_batch, AssumptionReceipt, BatchVerifyError, CompositeReceipt,
+    CompositeReceiptVerifierParameters, ExitVerificationError, FakeReceipt, Incompatibility,
+    InnerAssumptionReceipt, InnerReceipt, Journal, JournalReader, Receipt, ReceiptBatch,
+    ReceiptMetadata, ReceiptSummary, SegmentReceipt, SegmentReceiptVerifierParameters,
+    SuccinctReceipt, SuccinctReceiptVerifierParameters, VerifierContext, VerifierContextBuilder,
+    DEFAULT_MAX_PO2,
 };
 //#[cfg(any(not(target_os = "zkvm"), feature = "std"))]
 pub use receipt::{Groth16Receipt, Groth16ReceiptVerifierParameters};
@@ -14,16 +14,27 @@
 
 //! Manages the output and cryptographic data for a proven computation.
 
+pub(crate) mod batch;
 pub(crate) mod composite;
 pub(crate) mod groth16;
+#[cfg(feature = "std")]
+mod json;
 pub(crate) mod merkle;
 pub(crate) mod segment;
 pub(crate) mod succinct;
 
-use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
-use core::fmt::Debug;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Debug},
+    ops::RangeInclusive,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use borsh::{BorshDeserialize, BorshSerialize};
 use risc0_core::field::baby_bear::BabyBear;
 use risc0_zkp::{
@@ -36,22 +47,24 @@ use risc0_zkp::{
     },
     verify::VerificationError,
 };
+use risc0_zkvm_platform::WORD_SIZE;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 // Make succinct receipt available through this `receipt` module.
 use crate::{
     receipt_claim::Unknown,
-    serde::{from_slice, Error},
+    serde::{from_slice, from_slice_exact, Deserializer, Error},
     sha::{Digestible, Sha256},
-    Assumption, Assumptions, MaybePruned, Output, ReceiptClaim,
+    Assumption, Assumptions, ExitCode, MaybePruned, Output, ReceiptClaim,
 };
 
 pub use self::groth16::{Groth16Receipt, Groth16ReceiptVerifierParameters};
 
 pub use self::{
+    batch::{verify_all, verify_batch, BatchVerifyError, ReceiptBatch},
     composite::{CompositeReceipt, CompositeReceiptVerifierParameters},
     segment::{SegmentReceipt, SegmentReceiptVerifierParameters},
-    succinct::{SuccinctReceipt, SuccinctReceiptVerifierParameters},
+    succinct::{Incompatibility, SuccinctReceipt, SuccinctReceiptVerifierParameters},
 };
 
 /// A receipt attesting to the execution of a guest program.
@@ -150,7 +163,7 @@ impl Receipt {
     /// status code (i.e. `Halted(0)`), the image ID is as expected, and the journal has not been
     /// tampered with.
     pub fn verify(&self, image_id: impl Into<Digest>) -> Result<(), VerificationError> {
-        self.verify_with_context(&VerifierContext::default(), image_id)
+        self.verify_with_context(&VerifierContext::v1_0(), image_id)
     }
 
     /// Verify that this receipt proves a successful execution of the zkVM from the given
@@ -160,11 +173,49 @@ impl Receipt {
     /// [ReceiptClaim]. This method additionally ensures that the guest exited with a successful
     /// status code (i.e. `Halted(0)`), the image ID is as expected, and the journal has not been
     /// tampered with.
+    ///
+    /// The [VerifierContext] determines which receipts are accepted, including the recursion
+    /// control root, the allowed segment po2 range, the hash function suites, and optionally the
+    /// allowed image IDs. Pinning these allows a verifier to reject receipts produced by an
+    /// unexpected prover version or guest.
+    /// [Receipt::verify] is equivalent to calling this with [VerifierContext::v1_0].
     pub fn verify_with_context(
         &self,
         ctx: &VerifierContext,
         image_id: impl Into<Digest>,
     ) -> Result<(), VerificationError> {
+        self.verify_claim_with_context(ctx, image_id, self.journal.digest())
+    }
+
+    /// Verify that this receipt proves a successful execution of the zkVM from the given
+    /// `image_id`, committing to a journal with the given `journal_digest`.
+    ///
+    /// This is equivalent to [Receipt::verify], except that the journal attached to this receipt
+    /// is ignored. It is useful when journals are large and stored out-of-band, such that the
+    /// verifier only knows the digest of the expected journal.
+    pub fn verify_claim(
+        &self,
+        image_id: impl Into<Digest>,
+        journal_digest: Digest,
+    ) -> Result<(), VerificationError> {
+        self.verify_claim_with_context(&VerifierContext::default(), image_id, journal_digest)
+    }
+
+    /// Verify that this receipt proves a successful execution of the zkVM from the given
+    /// `image_id`, committing to a journal with the given `journal_digest`.
+    ///
+    /// See [Receipt::verify_claim] and [Receipt::verify_with_context].
+    pub fn verify_claim_with_context(
+        &self,
+        ctx: &VerifierContext,
+        image_id: impl Into<Digest>,
+        journal_digest: Digest,
+    ) -> Result<(), VerificationError> {
+        let image_id = image_id.into();
+        if !ctx.allowed_image_ids.is_empty() && !ctx.allowed_image_ids.contains(&image_id) {
+            return Err(VerificationError::ImageIdNotAllowed { image_id });
+        }
+
         if self.inner.verifier_parameters() != self.metadata.verifier_parameters {
             return Err(VerificationError::VerifierParametersMismatch {
                 expected: self.inner.verifier_parameters(),
@@ -172,13 +223,13 @@ impl Receipt {
             });
         }
 
-        tracing::debug!("Receipt::verify_with_context");
+        tracing::debug!("Receipt::verify_claim_with_context");
         self.inner.verify_integrity_with_context(ctx)?;
 
         // Check that the claim on the verified receipt matches what was expected. Since we have
         // constrained all field in the ReceiptClaim, we can directly construct the expected digest
         // and do not need to open the claim digest on the inner receipt.
-        let expected_claim = ReceiptClaim::ok(image_id, MaybePruned::Pruned(self.journal.digest()));
+        let expected_claim = ReceiptClaim::ok(image_id, MaybePruned::Pruned(journal_digest));
         if expected_claim.digest() != self.inner.claim()?.digest() {
             tracing::debug!(
                 "receipt claim does not match expected claim:\nreceipt: {:#?}\nexpected: {:#?}",
@@ -194,6 +245,107 @@ impl Receipt {
         Ok(())
     }
 
+    /// Verify that this receipt proves a successful execution of the zkVM from any one of the
+    /// given `image_ids`, returning the image ID that matched.
+    ///
+    /// This is equivalent to calling [Receipt::verify] with each image ID in turn, except that
+    /// the seal is only verified once. It is useful when more than one version of a guest is
+    /// accepted, e.g. while rolling out an upgrade. Returns
+    /// [VerificationError::ImageVerificationError] if the receipt is valid but its image ID is
+    /// not in the list.
+    pub fn verify_any(&self, image_ids: &[Digest]) -> Result<Digest, VerificationError> {
+        self.verify_any_with_context(&VerifierContext::default(), image_ids)
+    }
+
+    /// Verify that this receipt proves a successful execution of the zkVM from any one of the
+    /// given `image_ids`, returning the image ID that matched.
+    ///
+    /// See [Receipt::verify_any] and [Receipt::verify_with_context]. Returns
+    /// [VerificationError::ImageIdNotAllowed] if the image ID that matched is not allowed by `ctx`.
+    pub fn verify_any_with_context(
+        &self,
+        ctx: &VerifierContext,
+        image_ids: &[Digest],
+    ) -> Result<Digest, VerificationError> {
+        if self.inner.verifier_parameters() != self.metadata.verifier_parameters {
+            return Err(VerificationError::VerifierParametersMismatch {
+                expected: self.inner.verifier_parameters(),
+                received: self.metadata.verifier_parameters,
+            });
+        }
+
+        tracing::debug!("Receipt::verify_any_with_context");
+        self.inner.verify_integrity_with_context(ctx)?;
+
+        // As in verify_claim_with_context, the expected claim is fully determined by the image ID
+        // and the journal, so only its digest needs to be compared.
+        let journal_digest = self.journal.digest();
+        let claim_digest = self.inner.claim()?.digest();
+        let image_id = image_ids
+            .iter()
+            .find(|image_id| {
+                ReceiptClaim::ok(**image_id, MaybePruned::Pruned(journal_digest)).digest()
+                    == claim_digest
+            })
+            .copied()
+            .ok_or(VerificationError::ImageVerificationError)?;
+        if !ctx.allowed_image_ids.is_empty() && !ctx.allowed_image_ids.contains(&image_id) {
+            return Err(VerificationError::ImageIdNotAllowed { image_id });
+        }
+        Ok(image_id)
+    }
+
+    /// Verify that this receipt proves an execution of the zkVM from the given `image_id` that
+    /// ended with the `expected` exit code.
+    ///
+    /// [Receipt::verify] only accepts a guest that halted successfully (i.e. `Halted(0)`). This
+    /// also accepts guests that paused for a continuation or exited with an error, returning
+    /// [ExitVerificationError::UnexpectedExitCode] if the proven exit code is not `expected`.
+    /// As with [Receipt::verify], the journal must match the claim and the receipt must not have
+    /// unresolved assumptions.
+    pub fn verify_with_exit(
+        &self,
+        image_id: impl Into<Digest>,
+        expected: ExitCode,
+    ) -> Result<(), ExitVerificationError> {
+        self.verify_with_exit_with_context(&VerifierContext::default(), image_id, expected)
+    }
+
+    /// Verify that this receipt proves an execution of the zkVM from the given `image_id` that
+    /// ended with the `expected` exit code.
+    ///
+    /// See [Receipt::verify_with_exit] and [Receipt::verify_with_context].
+    pub fn verify_with_exit_with_context(
+        &self,
+        ctx: &VerifierContext,
+        image_id: impl Into<Digest>,
+        expected: ExitCode,
+    ) -> Result<(), ExitVerificationError> {
+        let image_id = image_id.into();
+        if !ctx.allowed_image_ids.is_empty() && !ctx.allowed_image_ids.contains(&image_id) {
+            return Err(VerificationError::ImageIdNotAllowed { image_id }.into());
+        }
+
+        self.verify_integrity_with_context(ctx)?;
+
+        // The claim has been opened by the integrity check above.
+        let claim = self
+            .claim()?
+            .value()
+            .map_err(|_| VerificationError::ReceiptFormatError)?;
+        if claim.pre.digest() != image_id {
+            return Err(VerificationError::ImageVerificationError.into());
+        }
+        if claim.exit_code != expected {
+            return Err(ExitVerificationError::UnexpectedExitCode {
+                expected,
+                actual: claim.exit_code,
+            });
+        }
+
+        Ok(())
+    }
+
     /// Verify the integrity of this receipt, ensuring the claim and journal
     /// are attested to by the seal.
     ///
@@ -252,15 +404,143 @@ impl Receipt {
         Ok(())
     }
 
+    /// Compose independent receipts, possibly of different guests, into a [ReceiptBatch] that
+    /// can be verified with a single call to [ReceiptBatch::verify].
+    ///
+    /// The order of `receipts` is preserved, and determines the order in which image IDs must be
+    /// given when verifying the batch. Returns an error if `receipts` is empty.
+    pub fn compose(receipts: &[Receipt]) -> Result<ReceiptBatch> {
+        if receipts.is_empty() {
+            anyhow::bail!("cannot compose an empty list of receipts");
+        }
+        Ok(ReceiptBatch {
+            receipts: receipts.to_vec(),
+        })
+    }
+
     /// Extract the [ReceiptClaim] from this receipt.
     pub fn claim(&self) -> Result<MaybePruned<ReceiptClaim>, VerificationError> {
         self.inner.claim()
     }
 
+    /// Summarize the claim of this receipt, e.g. for indexing receipts in a database.
+    ///
+    /// This only decodes the claim; it does not perform any cryptographic verification, so the
+    /// returned values must not be trusted until the receipt has been verified. Returns an error if
+    /// the claim is pruned.
+    ///
+    /// This is distinct from [Receipt::metadata], which carries interoperability information
+    /// about how the receipt was produced rather than what it claims.
+    pub fn summary(&self) -> Result<ReceiptSummary, VerificationError> {
+        let claim = self
+            .inner
+            .claim()?
+            .value()
+            .map_err(|_| VerificationError::ReceiptFormatError)?;
+        let num_assumptions = match claim.output {
+            MaybePruned::Value(None) => Some(0),
+            MaybePruned::Value(Some(output)) => match output.assumptions {
+                MaybePruned::Value(assumptions) => Some(assumptions.len()),
+                assumptions => assumptions.is_empty().then_some(0),
+            },
+            MaybePruned::Pruned(digest) => (digest == Digest::ZERO).then_some(0),
+        };
+        Ok(ReceiptSummary {
+            image_id: claim.pre.digest(),
+            journal_digest: self.journal.digest(),
+            exit_code: claim.exit_code,
+            num_assumptions,
+            receipt_kind: self.inner.kind_name(),
+            control_root: self.inner.control_root(),
+        })
+    }
+
+    /// Decode the journal into a value of type `T` by using the
+    /// [risc0 deserializer](crate::serde).
+    ///
+    /// Unlike [Journal::decode], this returns an error if the journal contains
+    /// any data beyond the serialized `T`.
+    pub fn decode_journal<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        from_slice_exact(&self.journal.bytes)
+    }
+
+    /// Read the journal one committed value at a time, e.g. when the guest made several calls to
+    /// `env::commit`.
+    ///
+    /// See [Journal::frames].
+    pub fn journal_frames(&self) -> JournalReader {
+        self.journal.frames()
+    }
+
     /// Total number of bytes used by the seals of this receipt.
     pub fn seal_size(&self) -> usize {
         self.inner.seal_size()
     }
+
+    /// Encode this receipt as the calldata of a call to
+    /// `verify(bytes seal, bytes32 imageId, bytes32 journalDigest)` on the RISC Zero verifier
+    /// contracts.
+    ///
+    /// The seal is prefixed with the first four bytes of the verifier parameters digest, which the
+    /// verifier router uses to select the verifier for the receipt. Only [Groth16Receipt]s can be
+    /// verified on-chain, so this returns an error for any other kind of receipt, which must first
+    /// be compressed to Groth16. It also returns an error if the claim is pruned, as the image ID
+    /// is then unknown.
+    pub fn to_evm_calldata(&self) -> Result<Vec<u8>> {
+        let InnerReceipt::Groth16(inner) = &self.inner else {
+            bail!(
+                "EVM calldata requires a groth16 receipt, not a {} receipt",
+                self.inner.kind_name()
+            );
+        };
+        let claim = inner
+            .claim
+            .as_value()
+            .map_err(|_| anyhow!("the claim of the receipt is pruned"))?;
+        let mut seal = [&inner.verifier_parameters.as_bytes()[..4], &inner.seal].concat();
+        let seal_len = seal.len();
+        seal.resize(seal_len.next_multiple_of(EVM_WORD_SIZE), 0);
+
+        // The head holds the offset of the dynamic `seal` argument followed by the static
+        // arguments, and the tail holds the length of the seal followed by the padded seal.
+        let mut calldata = EVM_VERIFY_SELECTOR.to_vec();
+        calldata.extend_from_slice(&evm_word(3 * EVM_WORD_SIZE));
+        calldata.extend_from_slice(claim.pre.digest().as_bytes());
+        calldata.extend_from_slice(self.journal.digest().as_bytes());
+        calldata.extend_from_slice(&evm_word(seal_len));
+        calldata.extend_from_slice(&seal);
+        Ok(calldata)
+    }
+
+    /// Serialize this receipt as JSON.
+    ///
+    /// Unlike the binary encodings, the JSON representation is self-describing, which makes it
+    /// suitable for storing receipts in databases or returning them from REST APIs. Digests are
+    /// encoded as hex strings.
+    #[cfg(feature = "std")]
+    pub fn to_json(&self) -> Result<String> {
+        Ok(json::to_string(self)?)
+    }
+
+    /// Deserialize a receipt from JSON produced by [Receipt::to_json].
+    ///
+    /// The receipt must still be verified before its claim can be trusted.
+    #[cfg(feature = "std")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(json::from_str(json)?)
+    }
+}
+
+/// The selector of `verify(bytes,bytes32,bytes32)`, the first four bytes of its Keccak-256 hash.
+const EVM_VERIFY_SELECTOR: [u8; 4] = [0xab, 0x75, 0x0e, 0x75];
+
+const EVM_WORD_SIZE: usize = 32;
+
+/// Encode an integer as a big-endian ABI word.
+fn evm_word(value: usize) -> [u8; EVM_WORD_SIZE] {
+    let mut word = [0; EVM_WORD_SIZE];
+    word[EVM_WORD_SIZE - 8..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
 }
 
 /// A record of the public commitments for a proven zkVM execution.
@@ -286,6 +566,60 @@ impl Journal {
     pub fn decode<T: DeserializeOwned>(&self) -> Result<T, Error> {
         from_slice(&self.bytes)
     }
+
+    /// Read the values committed to the journal one at a time, in the order the guest committed
+    /// them.
+    pub fn frames(&self) -> JournalReader {
+        JournalReader {
+            words: self
+                .bytes
+                .chunks(WORD_SIZE)
+                .map(|chunk| {
+                    let mut word = [0u8; WORD_SIZE];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    u32::from_le_bytes(word)
+                })
+                .collect(),
+            len: self.bytes.len(),
+            pos: 0,
+        }
+    }
+}
+
+/// Reads the values committed to a [Journal] one at a time with the
+/// [risc0 deserializer](crate::serde).
+///
+/// Call [JournalReader::finish] after the last expected read to check that nothing more was
+/// committed.
+#[derive(Clone, Debug)]
+pub struct JournalReader {
+    words: Vec<u32>,
+    len: usize,
+    pos: usize,
+}
+
+impl JournalReader {
+    /// Decode the next committed value as a `T`.
+    pub fn read<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+        let mut words = &self.words[self.pos..];
+        let value = T::deserialize(&mut Deserializer::new(&mut words))?;
+        let pos = self.words.len() - words.len();
+        // The last word may be padded past the end of the journal.
+        if pos * WORD_SIZE > self.len {
+            return Err(Error::DeserializeUnexpectedEnd);
+        }
+        self.pos = pos;
+        Ok(value)
+    }
+
+    /// Check that every committed value has been read, returning
+    /// [Error::DeserializeTrailingData] otherwise.
+    pub fn finish(self) -> Result<(), Error> {
+        if self.pos * WORD_SIZE < self.len {
+            return Err(Error::DeserializeTrailingData);
+        }
+        Ok(())
+    }
 }
 
 impl risc0_binfmt::Digestible for Journal {
@@ -300,6 +634,52 @@ impl AsRef<[u8]> for Journal {
     }
 }
 
+/// Error returned by [Receipt::verify_with_exit].
+#[derive(Debug, PartialEq)]
+pub enum ExitVerificationError {
+    /// The receipt failed verification.
+    Verification(VerificationError),
+
+    /// The receipt is valid, but proves an execution that ended with a different exit code.
+    UnexpectedExitCode {
+        /// The exit code that was expected.
+        expected: ExitCode,
+
+        /// The exit code proven by the receipt.
+        actual: ExitCode,
+    },
+}
+
+impl From<VerificationError> for ExitVerificationError {
+    fn from(err: VerificationError) -> Self {
+        Self::Verification(err)
+    }
+}
+
+impl fmt::Display for ExitVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Verification(err) => write!(f, "{err}"),
+            Self::UnexpectedExitCode { expected, actual } => {
+                write!(
+                    f,
+                    "unexpected exit code: expected {expected:?}, got {actual:?}"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExitVerificationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Verification(err) => Some(err),
+            Self::UnexpectedExitCode { .. } => None,
+        }
+    }
+}
+
 /// A lower level receipt, containing the cryptographic seal (i.e. zero-knowledge proof) and
 /// verification logic for a specific proof system and circuit. All inner receipt types are
 /// zero-knowledge proofs of execution for a RISC-V zkVM.
@@ -372,6 +752,16 @@ impl InnerReceipt {
         }
     }
 
+    /// Name of the kind of this receipt, e.g. `"succinct"`.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Self::Composite(_) => "composite",
+            Self::Groth16(_) => "groth16",
+            Self::Succinct(_) => "succinct",
+            Self::Fake(_) => "fake",
+        }
+    }
+
     /// Return the digest of the verifier parameters struct for the appropriate receipt verifier.
     pub fn verifier_parameters(&self) -> Digest {
         match self {
@@ -382,6 +772,16 @@ impl InnerReceipt {
         }
     }
 
+    /// The control root of a succinct receipt, as returned by [SuccinctReceipt::control_root].
+    ///
+    /// Returns `None` for other kinds of receipt, or if the hash function is not supported.
+    pub fn control_root(&self) -> Option<Digest> {
+        match self {
+            Self::Succinct(ref inner) => inner.control_root().ok(),
+            _ => None,
+        }
+    }
+
     /// Total number of bytes used by the seals of this receipt.
     pub fn seal_size(&self) -> usize {
         match self {
@@ -465,6 +865,33 @@ pub struct ReceiptMetadata {
     pub verifier_parameters: Digest,
 }
 
+/// A summary of the claim of a [Receipt], returned by [Receipt::summary].
+///
+/// The values are decoded from the claim without verifying the receipt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ReceiptSummary {
+    /// Image ID of the guest, i.e. the digest of its initial [SystemState](crate::SystemState).
+    pub image_id: Digest,
+
+    /// Digest of the journal.
+    pub journal_digest: Digest,
+
+    /// The [ExitCode] of the guest.
+    pub exit_code: ExitCode,
+
+    /// Number of assumptions the receipt is conditional on, or `None` if the assumptions list is
+    /// pruned and non-empty.
+    pub num_assumptions: Option<usize>,
+
+    /// Name of the kind of receipt, as returned by [InnerReceipt::kind_name].
+    pub receipt_kind: &'static str,
+
+    /// The control root a succinct receipt was produced against, as returned by
+    /// [InnerReceipt::control_root], or `None` for other kinds of receipt.
+    pub control_root: Option<Digest>,
+}
+
 /// An assumption attached to a guest execution as a result of calling
 /// `env::verify` or `env::verify_integrity`.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -687,6 +1114,11 @@ pub struct VerifierContext {
 
     /// Parameters for verification of [Groth16Receipt].
     pub groth16_verifier_parameters: Option<Groth16ReceiptVerifierParameters>,
+
+    /// Image IDs accepted by [Receipt::verify_with_context]. An empty list accepts any image ID.
+    ///
+    /// Set with [VerifierContext::with_allowed_image_ids].
+    allowed_image_ids: Vec<Digest>,
 }
 
 impl VerifierContext {
@@ -697,9 +1129,45 @@ impl VerifierContext {
             segment_verifier_parameters: None,
             succinct_verifier_parameters: None,
             groth16_verifier_parameters: None,
+            allowed_image_ids: Vec::new(),
+        }
+    }
+
+    /// The verification profile of the 1.0 release.
+    ///
+    /// Accepts composite, succinct, and Groth16 receipts for segments of up to [DEFAULT_MAX_PO2]
+    /// cycles, proven with any of the default hash suites. This is the profile used by
+    /// [Receipt::verify] and [VerifierContext::default].
+    pub fn v1_0() -> Self {
+        Self {
+            suites: Self::default_hash_suites(),
+            segment_verifier_parameters: Some(Default::default()),
+            succinct_verifier_parameters: Some(Default::default()),
+            groth16_verifier_parameters: Some(Default::default()),
+            allowed_image_ids: Vec::new(),
         }
     }
 
+    /// A profile that only accepts succinct receipts, verified with the default
+    /// [SuccinctReceiptVerifierParameters].
+    ///
+    /// Composite and Groth16 receipts are rejected with
+    /// [VerificationError::VerifierParametersMissing].
+    pub fn with_succinct_defaults() -> Self {
+        Self {
+            suites: Self::default_hash_suites(),
+            succinct_verifier_parameters: Some(Default::default()),
+            ..Self::empty()
+        }
+    }
+
+    /// Start building a [VerifierContext] with a custom po2 range or control root.
+    ///
+    /// With no options set, the builder produces the same profile as [VerifierContext::v1_0].
+    pub fn builder() -> VerifierContextBuilder {
+        VerifierContextBuilder::default()
+    }
+
     /// Return the mapping of hash suites used in the default [VerifierContext].
     pub fn default_hash_suites() -> BTreeMap<String, HashSuite<BabyBear>> {
         BTreeMap::from([
@@ -725,6 +1193,7 @@ impl VerifierContext {
             groth16_verifier_parameters: Some(Groth16ReceiptVerifierParameters::from_max_po2(
                 po2_max,
             )),
+            allowed_image_ids: Vec::new(),
         }
     }
 
@@ -768,6 +1237,16 @@ impl VerifierContext {
         self
     }
 
+    /// Return [VerifierContext] that only accepts receipts for one of the given image IDs.
+    ///
+    /// The image ID passed to [Receipt::verify_with_context] is checked against this list before
+    /// the seal is verified, so receipts for other guests are rejected cheaply with
+    /// [VerificationError::ImageIdNotAllowed]. An empty list accepts any image ID.
+    pub fn with_allowed_image_ids(mut self, image_ids: &[Digest]) -> Self {
+        self.allowed_image_ids = image_ids.to_vec();
+        self
+    }
+
     /// Parameters for verification of [CompositeReceipt].
     ///
     /// Made up of the verifier parameters for each other receipt type. Returns none if any of the
@@ -782,22 +1261,115 @@ impl VerifierContext {
 }
 
 impl Default for VerifierContext {
+    fn default() -> Self {
+        Self::v1_0()
+    }
+}
+
+/// A builder for a [VerifierContext] accepting a custom range of segment sizes or recursion
+/// control root.
+///
+/// See [VerifierContext::builder].
+#[derive(Clone, Debug)]
+pub struct VerifierContextBuilder {
+    po2_range: RangeInclusive<usize>,
+    control_root: Option<Digest>,
+    allowed_image_ids: Vec<Digest>,
+}
+
+impl Default for VerifierContextBuilder {
     fn default() -> Self {
         Self {
-            suites: Self::default_hash_suites(),
-            segment_verifier_parameters: Some(Default::default()),
-            succinct_verifier_parameters: Some(Default::default()),
-            groth16_verifier_parameters: Some(Default::default()),
+            po2_range: risc0_zkp::MIN_CYCLES_PO2..=DEFAULT_MAX_PO2,
+            control_root: None,
+            allowed_image_ids: Vec::new(),
+        }
+    }
+}
+
+impl VerifierContextBuilder {
+    /// Accept segment receipts with a cycle count, as a power of two (po2), in the given range.
+    ///
+    /// Unless a control root is set with [VerifierContextBuilder::control_root], succinct and
+    /// Groth16 receipts are accepted if they were lifted from segments of up to the end of the
+    /// range.
+    pub fn po2_range(mut self, po2_range: RangeInclusive<usize>) -> Self {
+        self.po2_range = po2_range;
+        self
+    }
+
+    /// Accept succinct and Groth16 receipts proven by recursion programs under the given control
+    /// root.
+    pub fn control_root(mut self, control_root: Digest) -> Self {
+        self.control_root = Some(control_root);
+        self
+    }
+
+    /// Only accept receipts for one of the given image IDs.
+    ///
+    /// See [VerifierContext::with_allowed_image_ids].
+    pub fn allowed_image_ids(mut self, image_ids: &[Digest]) -> Self {
+        self.allowed_image_ids = image_ids.to_vec();
+        self
+    }
+
+    /// Build the [VerifierContext].
+    pub fn build(self) -> VerifierContext {
+        let po2_max = *self.po2_range.end();
+        let segment = SegmentReceiptVerifierParameters {
+            control_ids: ["poseidon2", "sha-256", "blake2b"]
+                .into_iter()
+                .flat_map(|hash_name| {
+                    self.po2_range
+                        .clone()
+                        .filter_map(move |po2| risc0_circuit_rv32im::control_id(hash_name, po2))
+                })
+                .collect::<BTreeSet<_>>(),
+            ..SegmentReceiptVerifierParameters::default()
+        };
+        let (succinct, groth16) = if po2_max == DEFAULT_MAX_PO2 {
+            (
+                SuccinctReceiptVerifierParameters::default(),
+                Groth16ReceiptVerifierParameters::default(),
+            )
+        } else {
+            (
+                SuccinctReceiptVerifierParameters::from_max_po2(po2_max),
+                Groth16ReceiptVerifierParameters::from_max_po2(po2_max),
+            )
+        };
+        let (succinct, groth16) = match self.control_root {
+            Some(control_root) => (
+                SuccinctReceiptVerifierParameters {
+                    control_root,
+                    ..succinct
+                },
+                Groth16ReceiptVerifierParameters {
+                    control_root,
+                    ..groth16
+                },
+            ),
+            None => (succinct, groth16),
+        };
+        VerifierContext {
+            suites: VerifierContext::default_hash_suites(),
+            segment_verifier_parameters: Some(segment),
+            succinct_verifier_parameters: Some(succinct),
+            groth16_verifier_parameters: Some(groth16),
+            allowed_image_ids: self.allowed_image_ids,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{FakeReceipt, InnerReceipt, Receipt};
+    use super::{
+        FakeReceipt, Groth16Receipt, InnerReceipt, Journal, Receipt, VerifierContext,
+        DEFAULT_MAX_PO2,
+    };
     use crate::{
-        sha::{Digest, DIGEST_BYTES},
-        MaybePruned,
+        sha::{self, Digest, Digestible, Sha256, DIGEST_BYTES},
+        MaybePruned, ReceiptClaim,
     };
     use risc0_zkp::verify::VerificationError;
 
@@ -841,6 +1413,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn disallowed_image_id_should_error() {
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt {
+                claim: MaybePruned::Pruned(Digest::ZERO),
+            }),
+            vec![],
+        );
+        let allowed = Digest::from([1u8; DIGEST_BYTES]);
+        let ctx = VerifierContext::default().with_allowed_image_ids(&[allowed]);
+
+        // The fake receipt would fail seal verification, so getting this error shows the image ID
+        // was rejected before the seal was checked.
+        assert_eq!(
+            receipt
+                .verify_with_context(&ctx, Digest::ZERO)
+                .err()
+                .unwrap(),
+            VerificationError::ImageIdNotAllowed {
+                image_id: Digest::ZERO
+            }
+        );
+
+        // An allowed image ID, or an empty allowlist, proceeds to verify the seal.
+        assert_ne!(
+            receipt.verify_with_context(&ctx, allowed).err().unwrap(),
+            VerificationError::ImageIdNotAllowed { image_id: allowed }
+        );
+        let ctx = VerifierContext::default().with_allowed_image_ids(&[]);
+        assert_ne!(
+            receipt
+                .verify_with_context(&ctx, Digest::ZERO)
+                .err()
+                .unwrap(),
+            VerificationError::ImageIdNotAllowed {
+                image_id: Digest::ZERO
+            }
+        );
+    }
+
     #[test]
     fn borsh_serde() {
         use crate::ReceiptClaim;
@@ -857,4 +1469,212 @@ mod tests {
         let decoded: Receipt = borsh::from_slice(&encoded).unwrap();
         assert_eq!(receipt, decoded);
     }
+
+    #[test]
+    fn summary() {
+        use crate::{sha::Digestible, ExitCode, MaybePruned, ReceiptClaim};
+        use risc0_zkvm_methods::MULTI_TEST_ID;
+
+        let journal = vec![1, 2, 3];
+        let claim = ReceiptClaim::ok(MULTI_TEST_ID, journal.clone());
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt {
+                claim: MaybePruned::Value(claim.clone()),
+            }),
+            journal.clone(),
+        );
+        let summary = receipt.summary().unwrap();
+        assert_eq!(summary.image_id, Digest::from(MULTI_TEST_ID));
+        assert_eq!(summary.journal_digest, journal.digest());
+        assert_eq!(summary.exit_code, ExitCode::Halted(0));
+        assert_eq!(summary.num_assumptions, Some(0));
+        assert_eq!(summary.receipt_kind, "fake");
+
+        // The claim must be available to summarize the receipt.
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt {
+                claim: MaybePruned::Pruned(claim.digest()),
+            }),
+            journal,
+        );
+        assert_eq!(
+            receipt.summary().err().unwrap(),
+            VerificationError::ReceiptFormatError
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        use crate::ReceiptClaim;
+        use risc0_zkvm_methods::MULTI_TEST_ID;
+
+        let claim = ReceiptClaim::ok(MULTI_TEST_ID, vec![1, 2, 3]);
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt {
+                claim: MaybePruned::Value(claim),
+            }),
+            vec![1, 2, 3],
+        );
+        let json = receipt.to_json().unwrap();
+        assert!(json.contains(&Digest::from(MULTI_TEST_ID).to_string()));
+        assert_eq!(Receipt::from_json(&json).unwrap(), receipt);
+
+        // Outside of the receipt JSON, digests keep their derived encoding as an array of words,
+        // and receipt JSON written that way is still accepted.
+        let digest = Digest::from(MULTI_TEST_ID);
+        assert_eq!(
+            serde_json::to_value(digest).unwrap(),
+            serde_json::to_value(digest.as_words()).unwrap()
+        );
+        let words = serde_json::to_string(&receipt).unwrap();
+        assert_eq!(Receipt::from_json(&words).unwrap(), receipt);
+    }
+
+    #[test]
+    fn decode_journal() {
+        use crate::serde::{to_vec, Error};
+
+        let journal: Vec<u8> = bytemuck::cast_slice(&to_vec(&(7u32, 42u64)).unwrap()).to_vec();
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt {
+                claim: MaybePruned::Pruned(Digest::ZERO),
+            }),
+            journal,
+        );
+        assert_eq!(receipt.decode_journal::<(u32, u64)>().unwrap(), (7, 42));
+        assert_eq!(
+            receipt.decode_journal::<u32>().err().unwrap(),
+            Error::DeserializeTrailingData
+        );
+        assert_eq!(
+            receipt.decode_journal::<(u32, u64, u32)>().err().unwrap(),
+            Error::DeserializeUnexpectedEnd
+        );
+    }
+
+    #[test]
+    fn journal_frames() {
+        use crate::serde::{to_vec, Error};
+
+        let mut words = to_vec(&7u32).unwrap();
+        words.extend(to_vec(&String::from("frame")).unwrap());
+        words.extend(to_vec(&(1u8, 2u64)).unwrap());
+        let journal = Journal::new(bytemuck::cast_slice(&words).to_vec());
+
+        let mut frames = journal.frames();
+        assert_eq!(frames.read::<u32>().unwrap(), 7);
+        assert_eq!(frames.read::<String>().unwrap(), "frame");
+        let rest = frames.clone();
+        assert_eq!(frames.read::<(u8, u64)>().unwrap(), (1, 2));
+        frames.finish().unwrap();
+
+        assert_eq!(rest.finish().err().unwrap(), Error::DeserializeTrailingData);
+        let mut frames = Journal::new(journal.bytes[..journal.bytes.len() - 2].to_vec()).frames();
+        frames.read::<u32>().unwrap();
+        frames.read::<String>().unwrap();
+        assert_eq!(
+            frames.read::<(u8, u64)>().err().unwrap(),
+            Error::DeserializeUnexpectedEnd
+        );
+    }
+
+    #[test]
+    fn compose() {
+        use crate::{sha::Digestible, ReceiptClaim};
+        use risc0_zkvm_methods::{HELLO_COMMIT_ID, MULTI_TEST_ID};
+
+        assert!(Receipt::compose(&[]).is_err());
+
+        let receipts: Vec<Receipt> = [(MULTI_TEST_ID, vec![1]), (HELLO_COMMIT_ID, vec![2])]
+            .into_iter()
+            .map(|(image_id, journal)| {
+                let claim = ReceiptClaim::ok(image_id, journal.clone());
+                Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+            })
+            .collect();
+        let batch = Receipt::compose(&receipts).unwrap();
+        assert_eq!(batch.receipts, receipts);
+
+        let claims = batch.claims().unwrap();
+        assert_eq!(claims[0].digest(), receipts[0].claim().unwrap().digest());
+        assert_eq!(claims[1].digest(), receipts[1].claim().unwrap().digest());
+
+        // The number of image IDs is checked before any receipt is verified.
+        assert_eq!(
+            batch.verify(&[MULTI_TEST_ID.into()]).unwrap_err(),
+            VerificationError::ImageVerificationError
+        );
+    }
+
+    #[test]
+    fn verifier_context_profiles() {
+        use crate::sha::Digestible;
+
+        let params =
+            |ctx: &VerifierContext| ctx.composite_verifier_parameters().map(|p| p.digest());
+
+        let v1_0 = params(&VerifierContext::v1_0()).unwrap();
+        assert_eq!(params(&VerifierContext::default()), Some(v1_0));
+        assert_eq!(params(&VerifierContext::builder().build()), Some(v1_0));
+
+        // Any change to the po2 range or control root results in a different profile.
+        let narrow = VerifierContext::builder()
+            .po2_range(risc0_zkp::MIN_CYCLES_PO2..=DEFAULT_MAX_PO2 - 1)
+            .build();
+        assert_ne!(params(&narrow), Some(v1_0));
+        let rooted = VerifierContext::builder()
+            .control_root(Digest::ZERO)
+            .build();
+        assert_eq!(
+            rooted.succinct_verifier_parameters.unwrap().control_root,
+            Digest::ZERO
+        );
+
+        let succinct = VerifierContext::with_succinct_defaults();
+        assert!(succinct.segment_verifier_parameters.is_none());
+        assert!(succinct.groth16_verifier_parameters.is_none());
+        assert_eq!(
+            succinct.succinct_verifier_parameters,
+            VerifierContext::v1_0().succinct_verifier_parameters
+        );
+    }
+
+    #[test]
+    fn evm_calldata() {
+        let image_id = Digest::from([0x11; DIGEST_BYTES]);
+        let verifier_parameters = Digest::from([0x22; DIGEST_BYTES]);
+        let seal: Vec<u8> = (0..=255).collect();
+        let claim = ReceiptClaim::ok(image_id, b"journal".to_vec());
+        let groth16 = Groth16Receipt::new(seal.clone(), claim.into(), verifier_parameters);
+        let receipt = Receipt::new(InnerReceipt::Groth16(groth16), b"journal".to_vec());
+
+        let mut expected = vec![0xab, 0x75, 0x0e, 0x75];
+        // Offset of the seal.
+        expected.extend([0; 31]);
+        expected.push(0x60);
+        expected.extend(image_id.as_bytes());
+        expected.extend(sha::Impl::hash_bytes(b"journal").as_bytes());
+        // Length of the seal, then the selector of the verifier and the proof, padded to a whole
+        // number of words.
+        expected.extend([0; 30]);
+        expected.extend(260u16.to_be_bytes());
+        expected.extend([0x22; 4]);
+        expected.extend(&seal);
+        expected.extend([0; 28]);
+        assert_eq!(receipt.to_evm_calldata().unwrap(), expected);
+
+        let mut pruned = receipt.clone();
+        let InnerReceipt::Groth16(ref mut inner) = pruned.inner else {
+            unreachable!()
+        };
+        inner.claim = MaybePruned::Pruned(inner.claim.digest());
+        assert!(pruned.to_evm_calldata().is_err());
+
+        let fake = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt::new(ReceiptClaim::ok(image_id, vec![]))),
+            vec![],
+        );
+        let err = fake.to_evm_calldata().unwrap_err();
+        assert!(err.to_string().contains("groth16"), "{err}");
+    }
 }
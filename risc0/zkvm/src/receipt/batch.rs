@@ -0,0 +1,125 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use risc0_zkp::{core::digest::Digest, verify::VerificationError};
+use serde::{Deserialize, Serialize};
+
+use super::{Receipt, VerifierContext};
+use crate::{MaybePruned, ReceiptClaim};
+
+/// An ordered collection of [Receipt] structs that are verified together.
+///
+/// Each receipt in the batch attests to an independent execution, possibly of a different guest.
+/// A batch is created with [Receipt::compose] and verified with a single call to
+/// [ReceiptBatch::verify], which takes one image ID per receipt, in the same order as the
+/// receipts.
+#[derive(Clone, Debug, Deserialize, Serialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[non_exhaustive]
+pub struct ReceiptBatch {
+    /// The receipts in this batch, in the order they were composed.
+    pub receipts: Vec<Receipt>,
+}
+
+impl ReceiptBatch {
+    /// Verify that every receipt in this batch proves a successful execution of the zkVM from the
+    /// image ID at the same position in `image_ids`.
+    ///
+    /// Image IDs may differ between receipts. Returns
+    /// [VerificationError::ImageVerificationError] if the number of image IDs does not match the
+    /// number of receipts, and otherwise the first error returned by [Receipt::verify].
+    pub fn verify(&self, image_ids: &[Digest]) -> Result<(), VerificationError> {
+        self.verify_with_context(&VerifierContext::default(), image_ids)
+    }
+
+    /// Verify this batch using the given [VerifierContext].
+    ///
+    /// See [ReceiptBatch::verify] for the semantics of `image_ids`.
+    pub fn verify_with_context(
+        &self,
+        ctx: &VerifierContext,
+        image_ids: &[Digest],
+    ) -> Result<(), VerificationError> {
+        if image_ids.len() != self.receipts.len() {
+            tracing::debug!(
+                "expected {} image IDs, got {}",
+                self.receipts.len(),
+                image_ids.len()
+            );
+            return Err(VerificationError::ImageVerificationError);
+        }
+        for (receipt, image_id) in self.receipts.iter().zip(image_ids) {
+            receipt.verify_with_context(ctx, *image_id)?;
+        }
+        Ok(())
+    }
+
+    /// Extract the [ReceiptClaim] of each receipt in this batch, in order.
+    pub fn claims(&self) -> Result<Vec<MaybePruned<ReceiptClaim>>, VerificationError> {
+        self.receipts.iter().map(Receipt::claim).collect()
+    }
+}
+
+/// Verify each receipt against the image ID paired with it, stopping at the first failure.
+///
+/// Unlike [ReceiptBatch::verify], the returned [BatchVerifyError] identifies which receipt failed.
+/// Use [verify_all] to check every receipt instead.
+pub fn verify_batch(receipts: &[(Receipt, Digest)]) -> Result<(), BatchVerifyError> {
+    for (index, (receipt, image_id)) in receipts.iter().enumerate() {
+        receipt
+            .verify(*image_id)
+            .map_err(|source| BatchVerifyError { index, source })?;
+    }
+    Ok(())
+}
+
+/// Verify every receipt against the image ID paired with it, returning the result for each in
+/// order.
+pub fn verify_all(receipts: &[(Receipt, Digest)]) -> Vec<Result<(), VerificationError>> {
+    receipts
+        .iter()
+        .map(|(receipt, image_id)| receipt.verify(*image_id))
+        .collect()
+}
+
+/// Error returned by [verify_batch] for the first receipt that failed verification.
+#[derive(Debug, PartialEq)]
+pub struct BatchVerifyError {
+    /// Position of the failing receipt in the batch.
+    pub index: usize,
+
+    /// The error returned when verifying that receipt.
+    pub source: VerificationError,
+}
+
+impl fmt::Display for BatchVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "receipt {} failed verification: {}",
+            self.index, self.source
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BatchVerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
@@ -70,6 +70,27 @@ pub struct SegmentReceipt {
 }
 
 impl SegmentReceipt {
+    /// Verify this receipt as produced by a prover, independently of the rest of its session.
+    ///
+    /// In addition to [SegmentReceipt::verify_integrity_with_context], this checks that the
+    /// receipt was produced for the verifier parameters in `ctx`. This allows each segment of a
+    /// session to be checked as soon as it is proven, rather than only once the segments have
+    /// been joined.
+    pub fn verify(&self, ctx: &VerifierContext) -> Result<(), VerificationError> {
+        let params = ctx
+            .segment_verifier_parameters
+            .as_ref()
+            .ok_or(VerificationError::VerifierParametersMissing)?;
+        let expected = params.digest::<sha::Impl>();
+        if self.verifier_parameters != expected {
+            return Err(VerificationError::VerifierParametersMismatch {
+                expected,
+                received: self.verifier_parameters,
+            });
+        }
+        self.verify_integrity_with_context(ctx)
+    }
+
     /// Verify the integrity of this receipt, ensuring the claim is attested
     /// to by the seal.
     pub fn verify_integrity_with_context(
@@ -254,9 +275,12 @@ pub(crate) fn decode_receipt_claim_from_seal(
 
 #[cfg(test)]
 mod tests {
-    use super::SegmentReceiptVerifierParameters;
-    use crate::sha::Digestible;
-    use risc0_zkp::core::digest::digest;
+    use super::{SegmentReceipt, SegmentReceiptVerifierParameters};
+    use crate::{sha::Digestible, ReceiptClaim, VerifierContext};
+    use risc0_zkp::{
+        core::digest::{digest, Digest},
+        verify::VerificationError,
+    };
 
     // Check that the verifier parameters has a stable digest (and therefore a stable value). This
     // struct encodes parameters used in verification, and so this value should be updated if and
@@ -269,4 +293,22 @@ mod tests {
             digest!("52a27aff2de5a8206e3e88cb8dcb087c1193ede8efaf4889117bc68e704cf29a")
         );
     }
+
+    #[test]
+    fn verify_checks_verifier_parameters() {
+        let receipt = SegmentReceipt {
+            seal: vec![],
+            index: 0,
+            hashfn: "poseidon2".into(),
+            verifier_parameters: Digest::ZERO,
+            claim: ReceiptClaim::ok(Digest::ZERO, vec![]),
+        };
+        assert_eq!(
+            receipt.verify(&VerifierContext::default()),
+            Err(VerificationError::VerifierParametersMismatch {
+                expected: SegmentReceiptVerifierParameters::default().digest(),
+                received: Digest::ZERO,
+            })
+        );
+    }
 }
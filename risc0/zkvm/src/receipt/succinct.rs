@@ -19,7 +19,7 @@ use alloc::{
     string::{String, ToString},
     vec::Vec,
 };
-use core::fmt::Debug;
+use core::fmt::{self, Debug};
 
 use anyhow::bail;
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -45,7 +45,7 @@ use crate::{
         VerifierContext,
     },
     receipt_claim::{MaybePruned, Unknown},
-    sha,
+    sha, ReceiptClaim,
 };
 
 /// A succinct receipt, produced via recursion, proving the execution of the zkVM with a [STARK].
@@ -206,10 +206,22 @@ where
         core::mem::size_of_val(self.seal.as_slice())
     }
 
-    #[cfg(feature = "prove")]
-    pub(crate) fn control_root(&self) -> anyhow::Result<Digest> {
-        let hash_suite = hash_suite_from_name(&self.hashfn)
-            .ok_or_else(|| anyhow::anyhow!("unsupported hash function: {}", self.hashfn))?;
+    /// Digest of the claim proven by this receipt, whether or not the claim is pruned.
+    pub fn claim_digest(&self) -> Digest {
+        self.claim.digest::<sha::Impl>()
+    }
+
+    /// The control root this receipt was produced against, i.e. the root of the Merkle tree of
+    /// recursion programs that includes its [control ID](Self::control_id).
+    ///
+    /// Checking this against an approved control root, e.g. the one in
+    /// [SuccinctReceiptVerifierParameters], pins the receipt to a specific version of the
+    /// recursion circuit. It is only authenticated once the receipt has been verified.
+    ///
+    /// Returns an error if the receipt uses an unsupported hash function.
+    pub fn control_root(&self) -> Result<Digest, VerificationError> {
+        let hash_suite =
+            hash_suite_from_name(&self.hashfn).ok_or(VerificationError::InvalidHashSuite)?;
         Ok(self
             .control_inclusion_proof
             .root(&self.control_id, hash_suite.hashfn.as_ref()))
@@ -229,6 +241,89 @@ where
     }
 }
 
+impl SuccinctReceipt<ReceiptClaim> {
+    /// Check that this receipt can be compressed to a [Groth16Receipt](crate::Groth16Receipt)
+    /// that verifies with the default [Groth16ReceiptVerifierParameters](crate::Groth16ReceiptVerifierParameters),
+    /// e.g. on-chain.
+    ///
+    /// Compression is expensive and only fails once the work has been done, so this returns the
+    /// reason it would fail, or be rejected by the verifier, without proving anything. It does
+    /// not verify the receipt.
+    pub fn is_compressible(&self) -> Result<(), Incompatibility> {
+        if self.hashfn != "poseidon2" {
+            return Err(Incompatibility::WrongHashFn {
+                hashfn: self.hashfn.clone(),
+            });
+        }
+        let control_root = self
+            .control_root()
+            .map_err(|_| Incompatibility::WrongHashFn {
+                hashfn: self.hashfn.clone(),
+            })?;
+        if control_root != ALLOWED_CONTROL_ROOT {
+            return Err(Incompatibility::ControlRootNotAllowed { control_root });
+        }
+        let claim = self
+            .claim
+            .as_value()
+            .map_err(|_| Incompatibility::PrunedClaim)?;
+        let resolved = match &claim.output {
+            MaybePruned::Value(None) => true,
+            MaybePruned::Value(Some(output)) => output.assumptions.is_empty(),
+            MaybePruned::Pruned(digest) if *digest == Digest::ZERO => true,
+            MaybePruned::Pruned(_) => return Err(Incompatibility::PrunedClaim),
+        };
+        match resolved {
+            true => Ok(()),
+            false => Err(Incompatibility::UnresolvedAssumptions),
+        }
+    }
+}
+
+/// The reason a [SuccinctReceipt] cannot be compressed, as returned by
+/// [SuccinctReceipt::is_compressible].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Incompatibility {
+    /// The receipt was proven with a hash function other than Poseidon2.
+    WrongHashFn {
+        /// The hash function of the receipt.
+        hashfn: String,
+    },
+
+    /// The receipt was proven with recursion programs, e.g. lifting a segment with a po2 above
+    /// [DEFAULT_MAX_PO2](crate::DEFAULT_MAX_PO2), which the Groth16 verifier does not
+    /// allow.
+    ControlRootNotAllowed {
+        /// The control root of the receipt.
+        control_root: Digest,
+    },
+
+    /// The claim has assumptions, which must be resolved with their receipts first.
+    UnresolvedAssumptions,
+
+    /// The claim is pruned, so its assumptions cannot be checked.
+    PrunedClaim,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongHashFn { hashfn } => {
+                write!(f, "receipt uses hash function {hashfn}, not poseidon2")
+            }
+            Self::ControlRootNotAllowed { control_root } => {
+                write!(f, "receipt control root {control_root} is not allowed")
+            }
+            Self::UnresolvedAssumptions => write!(f, "receipt claim has unresolved assumptions"),
+            Self::PrunedClaim => write!(f, "receipt claim is pruned"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Incompatibility {}
+
 /// Constructs the set of allowed control IDs, given a maximum cycle count as a po2.
 pub(crate) fn allowed_control_ids(
     hash_name: impl AsRef<str> + 'static,
@@ -347,9 +442,12 @@ impl Default for SuccinctReceiptVerifierParameters {
 
 #[cfg(test)]
 mod tests {
-    use super::{allowed_control_root, SuccinctReceiptVerifierParameters, ALLOWED_CONTROL_ROOT};
-    use crate::{receipt::DEFAULT_MAX_PO2, sha::Digestible};
-    use risc0_zkp::core::digest::digest;
+    use super::{
+        allowed_control_root, MerkleProof, SuccinctReceipt, SuccinctReceiptVerifierParameters,
+        ALLOWED_CONTROL_ROOT,
+    };
+    use crate::{receipt::DEFAULT_MAX_PO2, sha::Digestible, MaybePruned, ReceiptClaim};
+    use risc0_zkp::core::digest::{digest, Digest};
 
     // Check that the verifier parameters has a stable digest (and therefore a stable value). This
     // struct encodes parameters used in verification, and so this value should be updated if and
@@ -383,4 +481,84 @@ mod tests {
             allowed_control_root("poseidon2", 25).unwrap(),
         );
     }
+
+    #[test]
+    fn claim_digest_ignores_pruning() {
+        let claim = ReceiptClaim::ok(Digest::ZERO, vec![1, 2, 3]);
+        let receipt = SuccinctReceipt {
+            seal: vec![],
+            control_id: Digest::ZERO,
+            claim: MaybePruned::Value(claim.clone()),
+            hashfn: "poseidon2".into(),
+            verifier_parameters: Digest::ZERO,
+            control_inclusion_proof: MerkleProof {
+                index: 0,
+                digests: vec![],
+            },
+        };
+        let pruned = receipt.clone().into_unknown();
+        assert!(matches!(pruned.claim, MaybePruned::Pruned(_)));
+        assert_eq!(receipt.claim_digest(), claim.digest());
+        assert_eq!(pruned.claim_digest(), claim.digest());
+    }
+
+    #[cfg(feature = "prove")]
+    #[test]
+    fn is_compressible() {
+        use risc0_zkp::core::hash::poseidon2::Poseidon2HashSuite;
+
+        use super::{allowed_control_ids, Incompatibility, MerkleGroup};
+        use crate::Assumption;
+
+        let control_ids: Vec<_> = allowed_control_ids("poseidon2", DEFAULT_MAX_PO2)
+            .unwrap()
+            .collect();
+        let control_id = control_ids[0];
+        let hashfn = Poseidon2HashSuite::new_suite().hashfn;
+        let receipt = SuccinctReceipt {
+            seal: vec![],
+            control_id,
+            claim: MaybePruned::Value(ReceiptClaim::ok(Digest::ZERO, vec![1, 2, 3])),
+            hashfn: "poseidon2".into(),
+            verifier_parameters: Digest::ZERO,
+            control_inclusion_proof: MerkleGroup::new(control_ids)
+                .unwrap()
+                .get_proof(&control_id, hashfn.as_ref())
+                .unwrap(),
+        };
+        assert_eq!(receipt.is_compressible(), Ok(()));
+
+        let mut conditional = receipt.clone();
+        let claim = conditional.claim.as_value_mut().unwrap();
+        let output = claim.output.as_value_mut().unwrap().as_mut().unwrap();
+        output.assumptions.add(
+            Assumption {
+                claim: Digest::ZERO,
+                control_root: Digest::ZERO,
+            }
+            .into(),
+        );
+        assert_eq!(
+            conditional.is_compressible(),
+            Err(Incompatibility::UnresolvedAssumptions)
+        );
+
+        let sha = SuccinctReceipt {
+            hashfn: "sha-256".into(),
+            ..receipt.clone()
+        };
+        assert!(matches!(
+            sha.is_compressible(),
+            Err(Incompatibility::WrongHashFn { .. })
+        ));
+
+        let unknown = SuccinctReceipt {
+            control_id: Digest::ZERO,
+            ..receipt
+        };
+        assert!(matches!(
+            unknown.is_compressible(),
+            Err(Incompatibility::ControlRootNotAllowed { .. })
+        ));
+    }
 }
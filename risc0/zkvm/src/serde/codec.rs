@@ -0,0 +1,70 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+
+use serde::{de::DeserializeOwned, ser::StdError, Serialize};
+
+/// A serialization format for data passed between the host and the guest.
+///
+/// The host writes a value with `ExecutorEnvBuilder::write_with::<C>` and the guest reads it back
+/// with [`env::read_with::<C, _>`](crate::guest::env::read_with), so that both sides agree on the
+/// format. Each value is sent as a length-prefixed frame holding the bytes produced by
+/// [Codec::encode], which allows the guest to read data encoded for another system without
+/// re-encoding it on the host.
+pub trait Codec {
+    /// The error returned when encoding or decoding fails.
+    type Error: StdError + Send + Sync + 'static;
+
+    /// Encode `value` as bytes.
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Self::Error>;
+
+    /// Decode a value from `bytes`, as produced by [Codec::encode].
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Self::Error>;
+}
+
+/// The [Codec] used by `ExecutorEnvBuilder::write` and [`env::read`](crate::guest::env::read),
+/// i.e. [to_vec](super::to_vec) and [from_slice](super::from_slice).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultCodec;
+
+impl Codec for DefaultCodec {
+    type Error = super::Error;
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Self::Error> {
+        Ok(bytemuck::cast_slice(&super::to_vec(value)?).to_vec())
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Self::Error> {
+        super::from_slice(bytes)
+    }
+}
+
+/// A [Codec] using the [postcard] wire format, a compact format common in embedded systems.
+#[cfg(feature = "postcard")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Codec for Postcard {
+    type Error = postcard::Error;
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Self::Error> {
+        postcard::to_allocvec(value)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Self::Error> {
+        postcard::from_bytes(bytes)
+    }
+}
@@ -73,16 +73,35 @@ impl WordRead for &[u32] {
 /// possible, such as if `slice` is not the serialized form of an object of type
 /// `T`.
 pub fn from_slice<T: DeserializeOwned, P: Pod>(slice: &[P]) -> Result<T> {
-    match bytemuck::try_cast_slice(slice) {
-        Ok(slice) => {
-            let mut deserializer = Deserializer::new(slice);
-            T::deserialize(&mut deserializer)
+    with_words(slice, |mut words| {
+        let mut deserializer = Deserializer::new(&mut words);
+        T::deserialize(&mut deserializer)
+    })
+}
+
+/// Deserialize a slice into the specified type, requiring that the entire
+/// slice is consumed.
+///
+/// Behaves like [from_slice], but returns [Error::DeserializeTrailingData] if
+/// any words are left over after `T` has been deserialized.
+pub fn from_slice_exact<T: DeserializeOwned, P: Pod>(slice: &[P]) -> Result<T> {
+    with_words(slice, |mut words| {
+        let mut deserializer = Deserializer::new(&mut words);
+        let value = T::deserialize(&mut deserializer)?;
+        if !words.is_empty() {
+            return Err(Error::DeserializeTrailingData);
         }
+        Ok(value)
+    })
+}
+
+fn with_words<P: Pod, T>(slice: &[P], f: impl FnOnce(&[u32]) -> Result<T>) -> Result<T> {
+    match bytemuck::try_cast_slice(slice) {
+        Ok(slice) => f(slice),
         // P is u8 or another value without word-alignment. Data must be copied.
         Err(bytemuck::PodCastError::TargetAlignmentGreaterAndInputNotAligned) => {
             let vec = bytemuck::allocation::pod_collect_to_vec::<P, u32>(slice);
-            let mut deserializer = Deserializer::new(vec.as_slice());
-            T::deserialize(&mut deserializer)
+            f(vec.as_slice())
         }
         Err(ref e) => panic!("failed to cast or read slice as [u32]: {}", e),
     }
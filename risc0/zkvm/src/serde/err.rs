@@ -30,6 +30,8 @@ pub enum Error {
     DeserializeBadUtf8,
     /// Unexpected end during deserialization
     DeserializeUnexpectedEnd,
+    /// Found unread data after deserialization
+    DeserializeTrailingData,
     /// Not supported
     NotSupported,
     /// The serialize buffer is full
@@ -48,6 +50,7 @@ impl Display for Error {
             Self::DeserializeBadOption => "Found an Option discriminant that wasn't 0 or 1",
             Self::DeserializeBadUtf8 => "Tried to parse invalid utf-8",
             Self::DeserializeUnexpectedEnd => "Unexpected end during deserialization",
+            Self::DeserializeTrailingData => "Found unread data after deserialization",
             Self::NotSupported => "Not supported",
             Self::SerializeBufferFull => "The serialize buffer is full",
         })
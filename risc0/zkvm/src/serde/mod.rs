@@ -38,17 +38,21 @@
 //! [`env::commit`]: ../guest/env/fn.commit.html
 //! [`env::read`]: ../guest/env/fn.read.html
 
+mod codec;
 mod deserializer;
 mod err;
 mod serializer;
 
-pub use deserializer::{from_slice, Deserializer, WordRead};
+#[cfg(feature = "postcard")]
+pub use codec::Postcard;
+pub use codec::{Codec, DefaultCodec};
+pub use deserializer::{from_slice, from_slice_exact, Deserializer, WordRead};
 pub use err::{Error, Result};
 pub use serializer::{to_vec, to_vec_with_capacity, Serializer, WordWrite};
 
 #[cfg(test)]
 mod tests {
-    use crate::serde::{from_slice, to_vec};
+    use crate::serde::{from_slice, from_slice_exact, to_vec, Codec, DefaultCodec, Error};
     use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
     use chrono::NaiveDate;
 
@@ -84,4 +88,40 @@ mod tests {
         let output: NaiveDate = from_slice(date_vec.as_slice()).unwrap();
         assert_eq!(input, output);
     }
+
+    #[test]
+    fn from_slice_exact_rejects_trailing_data() {
+        let data = to_vec(&(1u32, 2u32)).unwrap();
+        let output: (u32, u32) = from_slice_exact(data.as_slice()).unwrap();
+        assert_eq!(output, (1, 2));
+        assert_eq!(from_slice::<u32, _>(data.as_slice()).unwrap(), 1);
+        assert_eq!(
+            from_slice_exact::<u32, _>(data.as_slice()).err().unwrap(),
+            Error::DeserializeTrailingData
+        );
+    }
+
+    #[test]
+    fn codec_round_trip() {
+        let input: (u32, String, Vec<u8>) = (7, "codec".into(), vec![1, 2, 3]);
+        let bytes = DefaultCodec::encode(&input).unwrap();
+        assert_eq!(
+            bytes,
+            bytemuck::cast_slice::<_, u8>(&to_vec(&input).unwrap())
+        );
+        assert_eq!(
+            DefaultCodec::decode::<(u32, String, Vec<u8>)>(&bytes).unwrap(),
+            input
+        );
+
+        #[cfg(feature = "postcard")]
+        {
+            let bytes = super::Postcard::encode(&input).unwrap();
+            assert_eq!(bytes, postcard::to_allocvec(&input).unwrap());
+            assert_eq!(
+                super::Postcard::decode::<(u32, String, Vec<u8>)>(&bytes).unwrap(),
+                input
+            );
+        }
+    }
 }